@@ -0,0 +1,358 @@
+//! Generates the message types of `src/messages.rs` from the Kafka JSON message schemas vendored
+//! in `schemas/`.
+//!
+//! Every schema becomes a module named after the message (`FetchRequest` => `fetch_request`),
+//! holding one module per valid version (`fetch_request::v16`). Each version module declares the
+//! message struct and its nested structs with exactly the fields on the wire for that version,
+//! using the compact types in the flexible versions. Tagged fields aren't expanded into struct
+//! fields: they stay in the `_tagged_fields` section of the flexible versions.
+
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::{env, fs};
+
+use serde::Deserialize;
+
+const SCHEMA_DIR: &str = "schemas";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Schema {
+    name: String,
+    api_key: Option<i16>,
+    valid_versions: String,
+    flexible_versions: String,
+    #[serde(default)]
+    fields: Vec<FieldSpec>,
+    #[serde(default)]
+    common_structs: Vec<StructSpec>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FieldSpec {
+    name: String,
+    #[serde(rename = "type")]
+    ty: String,
+    versions: String,
+    nullable_versions: Option<String>,
+    tagged_versions: Option<String>,
+    flexible_versions: Option<String>,
+    about: Option<String>,
+    #[serde(default)]
+    fields: Vec<FieldSpec>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StructSpec {
+    name: String,
+    #[serde(default)]
+    fields: Vec<FieldSpec>,
+}
+
+/// Version range of a schema, such as `"0-12"`, `"9+"`, `"3"` or `"none"`
+#[derive(Clone, Copy)]
+struct Versions {
+    min: i16,
+    max: i16,
+}
+
+impl Versions {
+    const NONE: Versions = Versions { min: 0, max: -1 };
+
+    fn parse(versions: &str) -> Versions {
+        let versions = versions.trim();
+        let parse_version = |version: &str| {
+            version
+                .trim()
+                .parse::<i16>()
+                .unwrap_or_else(|_| panic!("Invalid version range {versions:?}"))
+        };
+        if versions == "none" {
+            Versions::NONE
+        } else if let Some(min) = versions.strip_suffix('+') {
+            Versions {
+                min: parse_version(min),
+                max: i16::MAX,
+            }
+        } else if let Some((min, max)) = versions.split_once('-') {
+            Versions {
+                min: parse_version(min),
+                max: parse_version(max),
+            }
+        } else {
+            let version = parse_version(versions);
+            Versions {
+                min: version,
+                max: version,
+            }
+        }
+    }
+
+    fn parse_optional(versions: Option<&String>) -> Versions {
+        versions.map_or(Versions::NONE, |versions| Versions::parse(versions))
+    }
+
+    fn contains(&self, version: i16) -> bool {
+        (self.min..=self.max).contains(&version)
+    }
+}
+
+/// Generates the structs of one version of a message
+struct VersionGenerator<'a> {
+    schema: &'a Schema,
+    version: i16,
+    flexible: bool,
+    generated: HashSet<String>,
+    out: String,
+}
+
+impl VersionGenerator<'_> {
+    fn generate_struct(&mut self, name: &str, about: Option<&str>, fields: &[FieldSpec]) {
+        if !self.generated.insert(name.to_string()) {
+            return;
+        }
+        let version = self.version;
+        let fields: Vec<&FieldSpec> = fields
+            .iter()
+            .filter(|field| Versions::parse(&field.versions).contains(version))
+            .collect();
+
+        let mut out = String::new();
+        writeln!(out).unwrap();
+        write_doc(&mut out, 8, about.unwrap_or(name));
+        writeln!(
+            out,
+            "        #[derive(Debug, Clone, ::kafka_derive::ByteParsable, ::kafka_derive::Serializable, ::kafka_derive::Size)]"
+        )
+        .unwrap();
+        writeln!(out, "        pub struct {name} {{").unwrap();
+        for field in fields.iter().filter(|field| !self.is_tagged(field)) {
+            if let Some(about) = &field.about {
+                write_doc(&mut out, 12, about);
+            }
+            writeln!(
+                out,
+                "            pub {}: {},",
+                field_name(&field.name),
+                self.rust_type(field, &field.ty)
+            )
+            .unwrap();
+        }
+        if self.flexible {
+            writeln!(out, "            /// The tagged fields of this version").unwrap();
+            writeln!(out, "            #[kafka(tagged_fields)]").unwrap();
+            writeln!(
+                out,
+                "            pub _tagged_fields: crate::tagged_fields_section::TaggedFieldsSection,"
+            )
+            .unwrap();
+        }
+        writeln!(out, "        }}").unwrap();
+        self.out.push_str(&out);
+
+        for field in fields {
+            let struct_name = field.ty.trim_start_matches("[]");
+            if !field.fields.is_empty() {
+                self.generate_struct(struct_name, field.about.as_deref(), &field.fields);
+            } else if let Some(common) = self
+                .schema
+                .common_structs
+                .iter()
+                .find(|common| common.name == struct_name)
+            {
+                self.generate_struct(&common.name, None, &common.fields);
+            }
+        }
+    }
+
+    fn is_tagged(&self, field: &FieldSpec) -> bool {
+        Versions::parse_optional(field.tagged_versions.as_ref()).contains(self.version)
+    }
+
+    fn rust_type(&self, field: &FieldSpec, ty: &str) -> String {
+        let version = self.version;
+        let nullable = Versions::parse_optional(field.nullable_versions.as_ref()).contains(version);
+        let flexible = self.flexible
+            && field
+                .flexible_versions
+                .as_ref()
+                .is_none_or(|versions| Versions::parse(versions).contains(version));
+        if let Some(element) = ty.strip_prefix("[]") {
+            // Nullability applies to the array, not its elements
+            let element = self.rust_type_of(field, element, flexible, false);
+            return if flexible {
+                format!("crate::types::compact_array::CompactArray<{element}>")
+            } else {
+                format!("crate::types::array::Array<{element}>")
+            };
+        }
+        self.rust_type_of(field, ty, flexible, nullable)
+    }
+
+    fn rust_type_of(&self, field: &FieldSpec, ty: &str, flexible: bool, nullable: bool) -> String {
+        let rust_type = match (ty, flexible, nullable) {
+            ("bool", _, _) => "bool",
+            ("int8", _, _) => "i8",
+            ("int16", _, _) => "i16",
+            ("uint16", _, _) => "u16",
+            ("int32", _, _) => "i32",
+            ("uint32", _, _) => "u32",
+            ("int64", _, _) => "i64",
+            ("float64", _, _) => "f64",
+            ("uuid", _, _) => "::uuid::Uuid",
+            ("string", true, true) => {
+                "crate::types::compact_nullable_string::CompactNullableString"
+            }
+            ("string", true, false) => "crate::types::compact_string::CompactString",
+            ("string", false, _) => "crate::types::nullable_string::NullableString",
+            ("bytes", true, _) => "crate::types::compact_nullable_bytes::CompactNullableBytes",
+            ("records", true, _) => "crate::types::compact_records::CompactRecords",
+            ("bytes" | "records", false, _) => "crate::types::nullable_bytes::NullableBytes",
+            (_, _, true) if ty.starts_with(char::is_uppercase) => {
+                return format!("crate::types::nullable_struct::NullableStruct<{ty}>")
+            }
+            (_, _, false) if ty.starts_with(char::is_uppercase) => ty,
+            _ => panic!(
+                "Unsupported type {ty:?} of field {} in {}",
+                field.name, self.schema.name
+            ),
+        };
+        rust_type.to_string()
+    }
+}
+
+fn generate(schema: &Schema) -> String {
+    let valid_versions = Versions::parse(&schema.valid_versions);
+    let flexible_versions = Versions::parse(&schema.flexible_versions);
+    let mut out = String::new();
+    writeln!(out).unwrap();
+    write_doc(
+        &mut out,
+        0,
+        &format!(
+            "{}, versions {} (flexible versions: {})",
+            schema.name, schema.valid_versions, schema.flexible_versions
+        ),
+    );
+    writeln!(out, "pub mod {} {{", snake_case(&schema.name)).unwrap();
+    if let Some(api_key) = schema.api_key {
+        writeln!(out, "    pub const API_KEY: i16 = {api_key};").unwrap();
+    }
+    writeln!(
+        out,
+        "    pub const MIN_VERSION: i16 = {};",
+        valid_versions.min
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "    pub const MAX_VERSION: i16 = {};",
+        valid_versions.max
+    )
+    .unwrap();
+    if flexible_versions.min <= flexible_versions.max {
+        writeln!(
+            out,
+            "    pub const FIRST_FLEXIBLE_VERSION: i16 = {};",
+            flexible_versions.min
+        )
+        .unwrap();
+    }
+    for version in valid_versions.min..=valid_versions.max {
+        let mut generator = VersionGenerator {
+            schema,
+            version,
+            flexible: flexible_versions.contains(version),
+            generated: HashSet::new(),
+            out: String::new(),
+        };
+        generator.generate_struct(
+            &schema.name,
+            Some(&format!("{} (Version: {version})", schema.name)),
+            &schema.fields,
+        );
+        writeln!(out).unwrap();
+        writeln!(out, "    pub mod v{version} {{").unwrap();
+        out.push_str(&generator.out);
+        writeln!(out, "    }}").unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+/// Parses a schema, which is JSON with `//` line comments
+fn parse_schema(path: &Path) -> Schema {
+    let contents = fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("Unable to read {}: {e}", path.display()));
+    let json: String = contents
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("//"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    serde_json::from_str(&json).unwrap_or_else(|e| panic!("Invalid schema {}: {e}", path.display()))
+}
+
+fn write_doc(out: &mut String, indent: usize, doc: &str) {
+    for line in doc.lines() {
+        writeln!(out, "{:indent$}/// {}", "", line.trim_end()).unwrap();
+    }
+}
+
+/// Converts a schema name (`ThrottleTimeMs`) to snake case (`throttle_time_ms`)
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let previous = chars[i - 1];
+            let next_is_lowercase = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if previous.is_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_uppercase() && next_is_lowercase)
+            {
+                snake.push('_');
+            }
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+fn field_name(name: &str) -> String {
+    let name = snake_case(name);
+    match name.as_str() {
+        "as" | "break" | "const" | "continue" | "crate" | "else" | "enum" | "extern" | "false"
+        | "fn" | "for" | "if" | "impl" | "in" | "let" | "loop" | "match" | "mod" | "move"
+        | "mut" | "pub" | "ref" | "return" | "static" | "struct" | "trait" | "true" | "type"
+        | "unsafe" | "use" | "where" | "while" | "async" | "await" | "dyn" => format!("r#{name}"),
+        _ => name,
+    }
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed={SCHEMA_DIR}");
+
+    let mut paths: Vec<_> = fs::read_dir(SCHEMA_DIR)
+        .expect("Unable to read the schema directory")
+        .map(|entry| entry.expect("Unable to read schema directory entry").path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .collect();
+    paths.sort();
+
+    let mut out =
+        String::from("// Generated by build.rs from the schemas in schemas/. Do not edit.\n");
+    for path in paths {
+        println!("cargo:rerun-if-changed={}", path.display());
+        out.push_str(&generate(&parse_schema(&path)));
+    }
+
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+    fs::write(Path::new(&out_dir).join("messages.rs"), out)
+        .expect("Unable to write the generated messages");
+}
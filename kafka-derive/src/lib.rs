@@ -0,0 +1,314 @@
+//! Derives for the wire codec traits (`ByteParsable`, `Serializable` and `Size`) of the Kafka
+//! broker crate.
+//!
+//! Fields are read and written in declaration order. The generated code refers to the traits
+//! through `crate::`, so the derives are only usable from within the broker crate itself.
+//!
+//! Field attributes:
+//!
+//! - `#[kafka(skip)]`: the field isn't on the wire. Parsing fills it with `Default::default()`.
+//! - `#[kafka(versions = "3+")]` (also `"3-7"` or `"3"`): the field is only on the wire for
+//!   those versions of the message. Parsing fills it with `Default::default()` otherwise. Needs
+//!   the struct-level `#[kafka(version = ...)]`.
+//! - `#[kafka(tagged_fields)]`: the field holds the message's tagged fields. It must be the
+//!   last field on the wire. It can be combined with `versions` to restrict it to the flexible
+//!   versions of the message.
+//!
+//! Struct attribute:
+//!
+//! - `#[kafka(version = <expr>)]`: the version of the message the struct represents, which
+//!   can be a literal or a const generic parameter.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    parse_macro_input, spanned::Spanned, Data, DeriveInput, Expr, Fields, Ident, LitStr, Type,
+};
+
+#[proc_macro_derive(ByteParsable, attributes(kafka))]
+pub fn derive_byte_parsable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input, byte_parsable)
+}
+
+#[proc_macro_derive(Serializable, attributes(kafka))]
+pub fn derive_serializable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input, serializable)
+}
+
+#[proc_macro_derive(Size, attributes(kafka))]
+pub fn derive_size(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input, size)
+}
+
+fn expand(input: &DeriveInput, derive: fn(&DeriveInput, &Message) -> TokenStream2) -> TokenStream {
+    match Message::from_input(input) {
+        Ok(message) => derive(input, &message).into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+struct Message {
+    version: Option<Expr>,
+    fields: Vec<Field>,
+}
+
+struct Field {
+    ident: Ident,
+    ty: Type,
+    skip: bool,
+    versions: Option<VersionRange>,
+}
+
+struct VersionRange {
+    min: i16,
+    max: Option<i16>,
+}
+
+impl Message {
+    fn from_input(input: &DeriveInput) -> syn::Result<Self> {
+        let mut version = None;
+        for attr in input
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("kafka"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("version") {
+                    version = Some(meta.value()?.parse::<Expr>()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported kafka attribute"))
+                }
+            })?;
+        }
+
+        let named_fields = match &input.data {
+            Data::Struct(data) => match &data.fields {
+                Fields::Named(fields) => &fields.named,
+                _ => return Err(unsupported(input)),
+            },
+            _ => return Err(unsupported(input)),
+        };
+
+        let mut fields = Vec::new();
+        let mut tagged_fields = None;
+        for named_field in named_fields {
+            let mut field = Field {
+                ident: named_field.ident.clone().expect("named field"),
+                ty: named_field.ty.clone(),
+                skip: false,
+                versions: None,
+            };
+            for attr in named_field
+                .attrs
+                .iter()
+                .filter(|attr| attr.path().is_ident("kafka"))
+            {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("skip") {
+                        field.skip = true;
+                    } else if meta.path.is_ident("versions") {
+                        let versions = meta.value()?.parse::<LitStr>()?;
+                        field.versions = Some(VersionRange::parse(&versions)?);
+                    } else if meta.path.is_ident("tagged_fields") {
+                        tagged_fields = Some(fields.len());
+                    } else {
+                        return Err(meta.error("unsupported kafka attribute"));
+                    }
+                    Ok(())
+                })?;
+            }
+            if field.versions.is_some() && version.is_none() {
+                return Err(syn::Error::new(
+                    named_field.span(),
+                    "versioned fields need #[kafka(version = ...)] on the struct",
+                ));
+            }
+            fields.push(field);
+        }
+
+        if let Some(index) = tagged_fields {
+            if fields[index + 1..].iter().any(|field| !field.skip) {
+                return Err(syn::Error::new(
+                    fields[index].ident.span(),
+                    "tagged fields must be the last field on the wire",
+                ));
+            }
+        }
+
+        Ok(Self { version, fields })
+    }
+
+    fn wire_fields(&self) -> impl Iterator<Item = &Field> {
+        self.fields.iter().filter(|field| !field.skip)
+    }
+
+    fn version_binding(&self) -> TokenStream2 {
+        match &self.version {
+            Some(version) if self.fields.iter().any(|field| field.versions.is_some()) => {
+                let version_ident = local("version");
+                quote! { let #version_ident: i16 = #version; }
+            }
+            _ => quote! {},
+        }
+    }
+}
+
+impl Field {
+    /// Condition under which the field is on the wire, if it depends on the version
+    fn condition(&self) -> Option<TokenStream2> {
+        self.versions.as_ref().map(|versions| {
+            let version = local("version");
+            let min = versions.min;
+            match versions.max {
+                Some(max) => quote! { (#min..=#max).contains(&#version) },
+                None => quote! { #version >= #min },
+            }
+        })
+    }
+}
+
+impl VersionRange {
+    fn parse(versions: &LitStr) -> syn::Result<Self> {
+        let value = versions.value();
+        let parse_version = |version: &str| {
+            version.trim().parse::<i16>().map_err(|_| {
+                syn::Error::new(
+                    versions.span(),
+                    "expected versions like \"3\", \"3+\" or \"3-7\"",
+                )
+            })
+        };
+        if let Some(min) = value.strip_suffix('+') {
+            Ok(Self {
+                min: parse_version(min)?,
+                max: None,
+            })
+        } else if let Some((min, max)) = value.split_once('-') {
+            Ok(Self {
+                min: parse_version(min)?,
+                max: Some(parse_version(max)?),
+            })
+        } else {
+            let version = parse_version(&value)?;
+            Ok(Self {
+                min: version,
+                max: Some(version),
+            })
+        }
+    }
+}
+
+/// Identifier for a variable of the generated code, which can't clash with field names
+fn local(name: &str) -> Ident {
+    Ident::new(name, Span::mixed_site())
+}
+
+fn unsupported(input: &DeriveInput) -> syn::Error {
+    syn::Error::new(
+        input.ident.span(),
+        "kafka codec traits can only be derived for structs with named fields",
+    )
+}
+
+fn byte_parsable(input: &DeriveInput, message: &Message) -> TokenStream2 {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let version_binding = message.version_binding();
+    let (bytes, offset) = (local("bytes"), local("offset"));
+    let parse_fields = message.fields.iter().map(|field| {
+        let ident = &field.ident;
+        let ty = &field.ty;
+        if field.skip {
+            return quote! { let #ident = ::core::default::Default::default(); };
+        }
+        let parse = quote! {
+            let #ident =
+                <#ty as crate::byte_parsable::ByteParsable<#ty>>::parse(#bytes, #offset)?;
+            #offset += crate::size::Size::size(&#ident);
+        };
+        match field.condition() {
+            Some(condition) => quote! {
+                let #ident = if #condition {
+                    #parse
+                    #ident
+                } else {
+                    ::core::default::Default::default()
+                };
+            },
+            None => parse,
+        }
+    });
+    let idents = message.fields.iter().map(|field| &field.ident);
+    quote! {
+        impl #impl_generics crate::byte_parsable::ByteParsable<Self> for #name #ty_generics
+        #where_clause
+        {
+            #[allow(unused_mut, unused_assignments, unused_variables)]
+            fn parse(
+                #bytes: &[u8],
+                #offset: usize,
+            ) -> ::core::result::Result<Self, crate::decode_error::DecodeError> {
+                let mut #offset = #offset;
+                #version_binding
+                #(#parse_fields)*
+                ::core::result::Result::Ok(Self { #(#idents),* })
+            }
+        }
+    }
+}
+
+fn serializable(input: &DeriveInput, message: &Message) -> TokenStream2 {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let version_binding = message.version_binding();
+    let bytes = local("bytes");
+    let serialize_fields = message.wire_fields().map(|field| {
+        let ident = &field.ident;
+        let serialize = quote! {
+            #bytes.extend_from_slice(&crate::serializable::Serializable::to_be_bytes(&self.#ident));
+        };
+        match field.condition() {
+            Some(condition) => quote! { if #condition { #serialize } },
+            None => serialize,
+        }
+    });
+    quote! {
+        impl #impl_generics crate::serializable::Serializable for #name #ty_generics
+        #where_clause
+        {
+            fn to_be_bytes(&self) -> ::std::vec::Vec<u8> {
+                #version_binding
+                let mut #bytes = ::std::vec::Vec::with_capacity(crate::size::Size::size(self));
+                #(#serialize_fields)*
+                #bytes
+            }
+        }
+    }
+}
+
+fn size(input: &DeriveInput, message: &Message) -> TokenStream2 {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let version_binding = message.version_binding();
+    let field_sizes = message.wire_fields().map(|field| {
+        let ident = &field.ident;
+        let size = quote! { crate::size::Size::size(&self.#ident) };
+        match field.condition() {
+            Some(condition) => quote! { + if #condition { #size } else { 0 } },
+            None => quote! { + #size },
+        }
+    });
+    quote! {
+        impl #impl_generics crate::size::Size for #name #ty_generics #where_clause {
+            fn size(&self) -> usize {
+                #version_binding
+                0 #(#field_sizes)*
+            }
+        }
+    }
+}
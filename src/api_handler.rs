@@ -0,0 +1,299 @@
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
+use tracing::info;
+
+use crate::api_versions::api_versions_api::ApiVersionsApi;
+use crate::byte_parsable::ByteParsable;
+use crate::decode_error::DecodeError;
+use crate::describe_topic_partitions::describe_topic_partitions_api::DescribeTopicPartitionsApi;
+use crate::fetch::fetch_api::FetchApi;
+use crate::headers::request_header_v2::RequestHeaderV2;
+use crate::list_offsets::list_offsets_api::ListOffsetsApi;
+use crate::messages::request_header::v1::RequestHeader as RequestHeaderV1;
+use crate::metadata::metadata_api::MetadataApi;
+use crate::metadata_image::MetadataImage;
+use crate::produce::produce_api::ProduceApi;
+use crate::size::Size;
+use crate::utils::config::ServerConfig;
+use crate::version_negotiation::{negotiate, Negotiation, RequestError};
+
+// Length of the message_size that precedes the request header
+const MESSAGE_SIZE_LENGTH: usize = size_of::<i32>();
+
+/// Handles the requests of one API
+pub trait ApiHandler: Send + Sync {
+    fn api_key(&self) -> i16;
+
+    /// Versions of the API the handler supports, which is what ApiVersions advertises
+    fn versions(&self) -> RangeInclusive<i16>;
+
+    /// First version of the API whose requests have a v2 header. The requests of the versions
+    /// before it have a v1 header, without tagged fields.
+    fn first_flexible_version(&self) -> i16;
+
+    /// Responds to a request in one of the supported versions. The request body starts at
+    /// `body_offset`, after the header. A body that can't be decoded is returned as an error,
+    /// on which the connection is closed, as not every response can carry it.
+    fn handle(
+        &self,
+        request_header: RequestHeaderV2,
+        buf: &[u8],
+        body_offset: usize,
+        context: &RequestContext,
+    ) -> Result<Vec<u8>, DecodeError>;
+
+    /// Responds to a request in a version outside the supported versions
+    fn unsupported_version(
+        &self,
+        request_header: RequestHeaderV2,
+        buf: &[u8],
+        body_offset: usize,
+        context: &RequestContext,
+    ) -> Vec<u8>;
+}
+
+/// What handlers get to respond to a request with
+pub struct RequestContext<'a> {
+    pub server_config: &'a ServerConfig,
+    pub metadata_image: &'a MetadataImage,
+    pub registry: &'a ApiRegistry,
+}
+
+/// The handlers of the APIs the broker serves, by API key
+#[derive(Default)]
+pub struct ApiRegistry {
+    handlers: BTreeMap<i16, Box<dyn ApiHandler>>,
+}
+
+impl ApiRegistry {
+    /// Registry with the handlers of the APIs the broker implements
+    pub fn builtin() -> Self {
+        let mut registry = Self::default();
+        registry.register(ProduceApi);
+        registry.register(FetchApi);
+        registry.register(ListOffsetsApi);
+        registry.register(MetadataApi);
+        registry.register(ApiVersionsApi);
+        registry.register(DescribeTopicPartitionsApi);
+        registry
+    }
+
+    /// Registers the handler of an API, replacing and returning any previous handler of it
+    pub fn register(&mut self, handler: impl ApiHandler + 'static) -> Option<Box<dyn ApiHandler>> {
+        self.handlers.insert(handler.api_key(), Box::new(handler))
+    }
+
+    pub fn handler(&self, api_key: i16) -> Option<&dyn ApiHandler> {
+        self.handlers.get(&api_key).map(Box::as_ref)
+    }
+
+    /// The handlers in API key order
+    pub fn handlers(&self) -> impl Iterator<Item = &dyn ApiHandler> {
+        self.handlers.values().map(Box::as_ref)
+    }
+
+    /// Dispatches a request to the handler of its API
+    pub fn respond(
+        &self,
+        request_bytes: &[u8],
+        server_config: &ServerConfig,
+        metadata_image: &MetadataImage,
+    ) -> Result<Vec<u8>, RequestError> {
+        let request_start_offset = 0;
+        let message_size = i32::parse(request_bytes, request_start_offset)?;
+        let request_header_v1 =
+            RequestHeaderV1::parse(request_bytes, request_start_offset + MESSAGE_SIZE_LENGTH)?;
+        let api_key = request_header_v1.request_api_key;
+        let api_version = request_header_v1.request_api_version;
+        let handler = self
+            .handler(api_key)
+            .ok_or(RequestError::UnknownApiKey(api_key))?;
+        // Handlers read the header of every version as a v2 one, a v1 header having no tagged
+        // fields
+        let (request_header, body_offset) = if api_version < handler.first_flexible_version() {
+            let body_offset = request_start_offset + MESSAGE_SIZE_LENGTH + request_header_v1.size();
+            (
+                RequestHeaderV2::from_v1(message_size, request_header_v1),
+                body_offset,
+            )
+        } else {
+            let request_header = RequestHeaderV2::parse(request_bytes, request_start_offset)?;
+            let body_offset = request_start_offset + request_header.size();
+            (request_header, body_offset)
+        };
+        let context = RequestContext {
+            server_config,
+            metadata_image,
+            registry: self,
+        };
+        let response_bytes = match negotiate(handler, api_version) {
+            Negotiation::Supported => {
+                handler.handle(request_header, request_bytes, body_offset, &context)?
+            }
+            Negotiation::UnsupportedVersion => {
+                info!("Unsupported version {api_version} of API key {api_key}");
+                handler.unsupported_version(request_header, request_bytes, body_offset, &context)
+            }
+        };
+        Ok(response_bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api_keys::{API_VERSIONS, FETCH, METADATA};
+
+    const ECHO: i16 = 1000;
+
+    /// Responds with the correlation id followed by the request body, or with the negated
+    /// correlation id for unsupported versions
+    struct EchoApi;
+
+    impl ApiHandler for EchoApi {
+        fn api_key(&self) -> i16 {
+            ECHO
+        }
+
+        fn versions(&self) -> RangeInclusive<i16> {
+            0..=1
+        }
+
+        fn first_flexible_version(&self) -> i16 {
+            1
+        }
+
+        fn handle(
+            &self,
+            request_header: RequestHeaderV2,
+            buf: &[u8],
+            body_offset: usize,
+            _context: &RequestContext,
+        ) -> Result<Vec<u8>, DecodeError> {
+            Ok([
+                &request_header.correlation_id.to_be_bytes()[..],
+                &buf[body_offset..],
+            ]
+            .concat())
+        }
+
+        fn unsupported_version(
+            &self,
+            request_header: RequestHeaderV2,
+            _buf: &[u8],
+            _body_offset: usize,
+            _context: &RequestContext,
+        ) -> Vec<u8> {
+            (-request_header.correlation_id).to_be_bytes().to_vec()
+        }
+    }
+
+    /// Request with a v1 header, as in the versions before the first flexible one
+    fn request_v1(api_key: i16, api_version: i16) -> Vec<u8> {
+        let mut bytes = vec![0x00, 0x00, 0x00, 0x0b];
+        bytes.extend_from_slice(&api_key.to_be_bytes());
+        bytes.extend_from_slice(&api_version.to_be_bytes());
+        bytes.extend_from_slice(&[
+            0x00, 0x00, 0x00, 0x07, // correlation_id: 7
+            0xff, 0xff, // client_id: null
+            0x2a, // body
+        ]);
+        bytes
+    }
+
+    fn request(api_key: i16, api_version: i16) -> Vec<u8> {
+        let mut bytes = vec![0x00, 0x00, 0x00, 0x0b];
+        bytes.extend_from_slice(&api_key.to_be_bytes());
+        bytes.extend_from_slice(&api_version.to_be_bytes());
+        bytes.extend_from_slice(&[
+            0x00, 0x00, 0x00, 0x07, // correlation_id: 7
+            0xff, 0xff, // client_id: null
+            0x00, // _tagged_fields
+        ]);
+        bytes
+    }
+
+    fn respond(registry: &ApiRegistry, request_bytes: &[u8]) -> Result<Vec<u8>, RequestError> {
+        registry.respond(
+            request_bytes,
+            &ServerConfig::default(),
+            &MetadataImage::default(),
+        )
+    }
+
+    #[test]
+    fn dispatches_to_registered_handler() {
+        let mut registry = ApiRegistry::builtin();
+        registry.register(EchoApi);
+
+        assert_eq!(
+            vec![0, 0, 0, 7],
+            respond(&registry, &request(ECHO, 1)).unwrap()
+        );
+        assert_eq!(
+            (-7i32).to_be_bytes().to_vec(),
+            respond(&registry, &request(ECHO, 2)).unwrap()
+        );
+    }
+
+    #[test]
+    fn reads_v1_header_of_versions_before_first_flexible_one() {
+        let mut registry = ApiRegistry::builtin();
+        registry.register(EchoApi);
+
+        assert_eq!(
+            vec![0, 0, 0, 7, 0x2a],
+            respond(&registry, &request_v1(ECHO, 0)).unwrap()
+        );
+    }
+
+    #[test]
+    fn reads_v1_header_of_builtin_non_flexible_versions() {
+        let registry = ApiRegistry::builtin();
+        let request_bytes = [
+            0x00, 0x00, 0x00, 0x0b, // message_size
+            0x00, 0x12, // request_api_key: 18
+            0x00, 0x00, // request_api_version: 0
+            0x00, 0x00, 0x00, 0x07, // correlation_id: 7
+            0x00, 0x01, b'x', // client_id: x
+        ];
+
+        let bytes = respond(&registry, &request_bytes).unwrap();
+
+        assert_eq!(
+            &[
+                0x00, 0x00, 0x00, 0x07, // correlation_id: 7
+                0x00, 0x00, // error_code: 0
+            ],
+            &bytes[4..10]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_api_key() {
+        let registry = ApiRegistry::builtin();
+
+        assert!(matches!(
+            respond(&registry, &request(ECHO, 0)),
+            Err(RequestError::UnknownApiKey(ECHO))
+        ));
+    }
+
+    #[test]
+    fn lists_handlers_in_api_key_order() {
+        let mut registry = ApiRegistry::default();
+        registry.register(EchoApi);
+        registry.register(ApiVersionsApi);
+        registry.register(MetadataApi);
+        registry.register(FetchApi);
+
+        assert_eq!(
+            vec![FETCH, METADATA, API_VERSIONS, ECHO],
+            registry
+                .handlers()
+                .map(|handler| handler.api_key())
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -1,100 +1,405 @@
 use core::error;
+use std::ops::RangeInclusive;
 
 use crate::{
-    api_keys::{API_VERSIONS, DESCRIBE_TOPIC_PARTITIONS, FETCH, PRODUCE},
+    api_handler::{ApiHandler, ApiRegistry, RequestContext},
+    api_keys::API_VERSIONS,
     api_response::{self, ApiResponse},
     api_versions::{
         self,
-        api_versions_response_v4::{ApiKey, ApiVersionsResponseV4},
+        api_versions_response_v4::{
+            ApiKey, ApiVersionsResponseV4, FinalizedFeatureKey, SupportedFeatureKey,
+        },
     },
-    describe_topic_partitions, error_codes, fetch,
+    byte_parsable::ByteParsable,
+    decode_error::DecodeError,
+    error_codes,
     headers::{request_header_v2::RequestHeaderV2, response_header_v0::ResponseHeaderV0},
-    produce,
+    messages::{
+        api_versions_request::FIRST_FLEXIBLE_VERSION,
+        api_versions_response::{v0, v1, v2},
+    },
+    metadata_image::MetadataImage,
+    serializable::Serializable,
+    size::Size,
     tagged_fields_section::TaggedFieldsSection,
-    types::compact_array::CompactArray,
+    types::{array::Array, compact_array::CompactArray},
 };
 
-const SUPPORTED_API_VERSIONS: [i16; 5] = [0, 1, 2, 3, 4];
+// Features are tagged fields, which only exist from the first flexible version
+const FIRST_VERSION_WITH_FEATURES: i16 = 3;
+// Name, min version and max version of the features the broker supports
+const SUPPORTED_FEATURES: [(&str, i16, i16); 1] = [("metadata.version", 1, 21)];
 
 pub struct ApiVersionsApi;
 
 impl ApiVersionsApi {
     pub fn respond(
         request_header: RequestHeaderV2,
+        metadata_image: &MetadataImage,
+        registry: &ApiRegistry,
     ) -> ApiResponse<ResponseHeaderV0, ApiVersionsResponseV4> {
-        let error_code = Self::check_supported_version(request_header.request_api_version);
-        let api_keys = Self::api_keys();
+        let api_keys = Self::api_keys(registry);
         let throttle_time_ms = 0;
-        let response = ApiVersionsResponseV4::new(
-            error_code,
+        let mut response = ApiVersionsResponseV4::new(
+            error_codes::NONE,
             api_keys,
             throttle_time_ms,
             TaggedFieldsSection::empty(),
         );
+        if request_header.request_api_version >= FIRST_VERSION_WITH_FEATURES {
+            Self::add_features(&mut response, metadata_image);
+        }
         api_response::v0(request_header.correlation_id, response)
     }
 
-    fn check_supported_version(version: i16) -> i16 {
-        if SUPPORTED_API_VERSIONS.contains(&version) {
-            error_codes::NONE
-        } else {
-            error_codes::UNSUPPORTED_VERSION
+    /// Responds to the versions before the first flexible one, whose bodies have neither compact
+    /// arrays nor tagged fields, and no throttle_time_ms in version 0
+    pub fn respond_non_flexible(
+        request_header: RequestHeaderV2,
+        registry: &ApiRegistry,
+    ) -> Vec<u8> {
+        let correlation_id = request_header.correlation_id;
+        let error_code = error_codes::NONE;
+        let throttle_time_ms = 0;
+        match request_header.request_api_version {
+            0 => Self::version_0_response(correlation_id, error_code, registry).to_be_bytes(),
+            1 => {
+                let api_keys = Self::non_flexible_api_keys(registry, |api_key| v1::ApiVersion {
+                    api_key: api_key.api_key,
+                    min_version: api_key.min_version,
+                    max_version: api_key.max_version,
+                });
+                let response = v1::ApiVersionsResponse {
+                    error_code,
+                    api_keys,
+                    throttle_time_ms,
+                };
+                api_response::v0(correlation_id, response).to_be_bytes()
+            }
+            _ => {
+                let api_keys = Self::non_flexible_api_keys(registry, |api_key| v2::ApiVersion {
+                    api_key: api_key.api_key,
+                    min_version: api_key.min_version,
+                    max_version: api_key.max_version,
+                });
+                let response = v2::ApiVersionsResponse {
+                    error_code,
+                    api_keys,
+                    throttle_time_ms,
+                };
+                api_response::v0(correlation_id, response).to_be_bytes()
+            }
+        }
+    }
+
+    /// Responds to a version the broker doesn't support with a version 0 response, which every
+    /// client can decode, listing the supported versions so the client can pick one of them
+    pub fn unsupported_version(
+        request_header: RequestHeaderV2,
+        registry: &ApiRegistry,
+    ) -> ApiResponse<ResponseHeaderV0, v0::ApiVersionsResponse> {
+        Self::version_0_response(
+            request_header.correlation_id,
+            error_codes::UNSUPPORTED_VERSION,
+            registry,
+        )
+    }
+
+    fn version_0_response(
+        correlation_id: i32,
+        error_code: i16,
+        registry: &ApiRegistry,
+    ) -> ApiResponse<ResponseHeaderV0, v0::ApiVersionsResponse> {
+        let api_keys = Self::non_flexible_api_keys(registry, |api_key| v0::ApiVersion {
+            api_key: api_key.api_key,
+            min_version: api_key.min_version,
+            max_version: api_key.max_version,
+        });
+        let response = v0::ApiVersionsResponse {
+            error_code,
+            api_keys,
+        };
+        api_response::v0(correlation_id, response)
+    }
+
+    /// The APIs of the registry in the api_keys entries of a non-flexible version
+    fn non_flexible_api_keys<T: Serializable + Size + ByteParsable<T> + Clone>(
+        registry: &ApiRegistry,
+        api_version: impl Fn(ApiKey) -> T,
+    ) -> Array<T> {
+        Self::api_keys(registry)
+            .into_iter()
+            .map(api_version)
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    fn add_features(response: &mut ApiVersionsResponseV4, metadata_image: &MetadataImage) {
+        response.set_supported_features(
+            SUPPORTED_FEATURES
+                .iter()
+                .map(|&(name, min_version, max_version)| {
+                    SupportedFeatureKey::new(name, min_version, max_version)
+                })
+                .collect::<Vec<_>>()
+                .into(),
+        );
+        let finalized_features = metadata_image
+            .feature_levels()
+            .into_iter()
+            .map(|(name, level)| {
+                FinalizedFeatureKey::new(&String::from_utf8_lossy(name), level, level)
+            })
+            .collect::<Vec<_>>();
+        // The finalized features are versioned by the offset of the metadata they come from
+        let epoch = match metadata_image.last_offset() {
+            Some(offset) if !finalized_features.is_empty() => offset,
+            _ => -1,
+        };
+        response.set_finalized_features(epoch, finalized_features.into());
+    }
+
+    /// The APIs of the registry with their supported versions
+    fn api_keys(registry: &ApiRegistry) -> CompactArray<ApiKey> {
+        registry
+            .handlers()
+            .map(|handler| {
+                let versions = handler.versions();
+                ApiKey::new(
+                    handler.api_key(),
+                    *versions.start(),
+                    *versions.end(),
+                    TaggedFieldsSection::empty(),
+                )
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+impl ApiHandler for ApiVersionsApi {
+    fn api_key(&self) -> i16 {
+        API_VERSIONS
+    }
+
+    fn versions(&self) -> RangeInclusive<i16> {
+        api_versions::MIN_VERSION..=api_versions::MAX_VERSION
+    }
+
+    fn first_flexible_version(&self) -> i16 {
+        FIRST_FLEXIBLE_VERSION
+    }
+
+    fn handle(
+        &self,
+        request_header: RequestHeaderV2,
+        _buf: &[u8],
+        _offset: usize,
+        context: &RequestContext,
+    ) -> Result<Vec<u8>, DecodeError> {
+        // The request body is empty before the first flexible version, and only holds the
+        // client software name and version from it, which aren't used
+        if request_header.request_api_version < FIRST_FLEXIBLE_VERSION {
+            return Ok(Self::respond_non_flexible(request_header, context.registry));
         }
+        Ok(Self::respond(request_header, context.metadata_image, context.registry).to_be_bytes())
     }
 
-    fn api_keys() -> CompactArray<ApiKey> {
-        [
-            ApiKey::new(
-                API_VERSIONS,
-                api_versions::MIN_VERSION,
-                api_versions::MAX_VERSION,
-                TaggedFieldsSection::empty(),
-            ),
-            ApiKey::new(
-                FETCH,
-                fetch::MIN_VERSION,
-                fetch::MAX_VERSION,
-                TaggedFieldsSection::empty(),
-            ),
-            ApiKey::new(
-                DESCRIBE_TOPIC_PARTITIONS,
-                describe_topic_partitions::MIN_VERSION,
-                describe_topic_partitions::MAX_VERSION,
-                TaggedFieldsSection::empty(),
-            ),
-            ApiKey::new(
-                PRODUCE,
-                produce::MIN_VERSION,
-                produce::MAX_VERSION,
-                TaggedFieldsSection::empty(),
-            ),
-        ]
-        .into()
+    fn unsupported_version(
+        &self,
+        request_header: RequestHeaderV2,
+        _buf: &[u8],
+        _offset: usize,
+        context: &RequestContext,
+    ) -> Vec<u8> {
+        Self::unsupported_version(request_header, context.registry).to_be_bytes()
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::api_versions::api_versions_response_v4::{
+        FINALIZED_FEATURES, FINALIZED_FEATURES_EPOCH, SUPPORTED_FEATURES,
+    };
+    use crate::byte_parsable::ByteParsable;
+    use crate::records::feature_level_record::FeatureLevelRecord;
+    use crate::records::record_batch::RecordValue;
+    use crate::types::{compact_string::CompactString, unsigned_varint::UnsignedVarint};
+    use crate::utils::config::ServerConfig;
     use parameterized::parameterized;
 
+    fn api_versions_response() -> ApiVersionsResponseV4 {
+        ApiVersionsResponseV4::new(
+            error_codes::NONE,
+            CompactArray::empty(),
+            0,
+            TaggedFieldsSection::empty(),
+        )
+    }
+
+    #[test]
+    fn reports_supported_and_finalized_features() {
+        let mut metadata_image = MetadataImage::default();
+        metadata_image.apply(RecordValue::FeatureLevel(FeatureLevelRecord {
+            frame_version: 1,
+            _type: 12,
+            version: 0,
+            name: CompactString::from("metadata.version"),
+            feature_level: 20,
+            tagged_fields_count: UnsignedVarint::new(0),
+        }));
+        let mut response = api_versions_response();
+
+        ApiVersionsApi::add_features(&mut response, &metadata_image);
+
+        assert_eq!(
+            Some(vec![SupportedFeatureKey::new("metadata.version", 1, 21)]),
+            response
+                ._tagged_fields
+                .get::<CompactArray<SupportedFeatureKey>>(SUPPORTED_FEATURES)
+                .unwrap()
+                .map(|features| features.into_iter().collect())
+        );
+        assert_eq!(
+            Some(vec![FinalizedFeatureKey::new("metadata.version", 20, 20)]),
+            response
+                ._tagged_fields
+                .get::<CompactArray<FinalizedFeatureKey>>(FINALIZED_FEATURES)
+                .unwrap()
+                .map(|features| features.into_iter().collect())
+        );
+    }
+
+    #[test]
+    fn reports_unknown_epoch_without_finalized_features() {
+        let mut response = api_versions_response();
+
+        ApiVersionsApi::add_features(&mut response, &MetadataImage::default());
+
+        assert_eq!(
+            Ok(Some(-1)),
+            response._tagged_fields.get::<i64>(FINALIZED_FEATURES_EPOCH)
+        );
+        assert_eq!(
+            Some(0),
+            response
+                ._tagged_fields
+                .get::<CompactArray<FinalizedFeatureKey>>(FINALIZED_FEATURES)
+                .unwrap()
+                .map(|features| features.len())
+        );
+    }
+
+    #[test]
+    fn lists_supported_versions_in_version_0_response_to_unsupported_version() {
+        let request_header = RequestHeaderV2::parse(
+            &[
+                0x00, 0x00, 0x00, 0x0f, // message_size
+                0x00, 0x12, // request_api_key: 18
+                0x00, 0x05, // request_api_version: 5
+                0x00, 0x00, 0x00, 0x07, // correlation_id: 7
+                0xff, 0xff, // client_id: null
+                0x00, // _tagged_fields
+            ],
+            0,
+        )
+        .unwrap();
+
+        let bytes = ApiVersionsApi::unsupported_version(request_header, &ApiRegistry::builtin())
+            .to_be_bytes();
+
+        assert_eq!(
+            &[
+                0x00, 0x00, 0x00, 0x07, // correlation_id: 7
+                0x00, 0x23, // error_code: 35
+                0x00, 0x00, 0x00, 0x06, // api_keys (INT32 length): 6
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x0b, // api_key: 0, versions 0-11
+            ],
+            &bytes[4..20]
+        );
+        assert_eq!(4 + 4 + 2 + 4 + 6 * 6, bytes.len());
+    }
+
+    /// Request with a v1 header before the first flexible version, and a v2 one from it
+    fn api_versions_request(api_version: i16) -> Vec<u8> {
+        let mut bytes = vec![
+            0x00, 0x00, 0x00, 0x0a, // message_size
+            0x00, 0x12, // request_api_key: 18
+        ];
+        bytes.extend_from_slice(&api_version.to_be_bytes());
+        bytes.extend_from_slice(&[
+            0x00, 0x00, 0x00, 0x07, // correlation_id: 7
+            0xff, 0xff, // client_id: null
+        ]);
+        if api_version >= FIRST_FLEXIBLE_VERSION {
+            bytes[3] += 1;
+            bytes.push(0x00); // _tagged_fields
+        }
+        bytes
+    }
+
+    fn respond(request_bytes: &[u8]) -> Vec<u8> {
+        ApiRegistry::builtin()
+            .respond(
+                request_bytes,
+                &ServerConfig::default(),
+                &MetadataImage::default(),
+            )
+            .unwrap()
+    }
+
     #[parameterized(
         version = {
             0, 1, 2, 3, 4
         }
     )]
     fn checks_supported_version(version: i16) {
+        let bytes = respond(&api_versions_request(version));
+
         assert_eq!(
-            error_codes::NONE,
-            ApiVersionsApi::check_supported_version(version)
+            &[
+                0x00, 0x00, 0x00, 0x07, // correlation_id: 7
+                0x00, 0x00, // error_code: 0
+            ],
+            &bytes[4..10]
+        );
+    }
+
+    #[parameterized(
+        version = {
+            0, 1, 2
+        }
+    )]
+    fn responds_to_non_flexible_version_without_tagged_fields(version: i16) {
+        let bytes = respond(&api_versions_request(version));
+
+        assert_eq!(
+            &[
+                0x00, 0x00, 0x00, 0x06, // api_keys (INT32 length): 6
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x0b, // api_key: 0, versions 0-11
+            ],
+            &bytes[10..20]
         );
+        // From version 1, the api_keys are followed by throttle_time_ms
+        let throttle_time_ms_size = if version == 0 { 0 } else { 4 };
+        assert_eq!(4 + 4 + 2 + 4 + 6 * 6 + throttle_time_ms_size, bytes.len());
     }
 
     #[test]
     fn checks_unsupported_version() {
+        let bytes = respond(&api_versions_request(6));
+
         assert_eq!(
-            error_codes::UNSUPPORTED_VERSION,
-            ApiVersionsApi::check_supported_version(6)
+            &[
+                0x00, 0x00, 0x00, 0x07, // correlation_id: 7
+                0x00, 0x23, // error_code: 35
+                0x00, 0x00, 0x00, 0x06, // api_keys (INT32 length): 6
+            ],
+            &bytes[4..14]
         );
+        // A version 0 body, without throttle_time_ms and tagged fields
+        assert_eq!(4 + 4 + 2 + 4 + 6 * 6, bytes.len());
     }
 }
@@ -1,17 +1,20 @@
-use std::array;
+use kafka_derive::{ByteParsable, Serializable, Size};
 
-use crate::byte_parsable::ByteParsable;
-use crate::error_codes::NONE;
-use crate::serializable::Serializable;
-use crate::size::Size;
 use crate::tagged_fields_section::TaggedFieldsSection;
 use crate::types::compact_array::CompactArray;
+use crate::types::compact_string::CompactString;
 
-#[derive(Debug)]
+// Tags of the tagged fields of the response
+pub const SUPPORTED_FEATURES: u32 = 0;
+pub const FINALIZED_FEATURES_EPOCH: u32 = 1;
+pub const FINALIZED_FEATURES: u32 = 2;
+
+#[derive(Debug, ByteParsable, Serializable, Size)]
 pub struct ApiVersionsResponseV4 {
     pub error_code: i16,
     pub api_keys: CompactArray<ApiKey>,
     pub throttle_time_ms: i32,
+    #[kafka(tagged_fields)]
     pub _tagged_fields: TaggedFieldsSection,
 }
 
@@ -29,34 +32,34 @@ impl ApiVersionsResponseV4 {
             _tagged_fields,
         }
     }
-}
 
-impl Size for ApiVersionsResponseV4 {
-    fn size(&self) -> usize {
-        self.error_code.size()
-            + self.api_keys.size()
-            + self.throttle_time_ms.size()
-            + self._tagged_fields.size()
+    pub fn set_supported_features(
+        &mut self,
+        supported_features: CompactArray<SupportedFeatureKey>,
+    ) {
+        self._tagged_fields
+            .set(SUPPORTED_FEATURES, &supported_features);
     }
-}
 
-impl Serializable for ApiVersionsResponseV4 {
-    fn to_be_bytes(&self) -> Vec<u8> {
-        let mut bytes: Vec<u8> = Vec::new();
-        bytes.extend_from_slice(&self.error_code.to_be_bytes());
-        bytes.extend_from_slice(&self.api_keys.to_be_bytes());
-        bytes.extend_from_slice(&self.throttle_time_ms.to_be_bytes());
-        bytes.extend_from_slice(&self._tagged_fields.to_be_bytes());
-        bytes
+    /// Sets the finalized features along with their epoch, which is only valid if >= 0
+    pub fn set_finalized_features(
+        &mut self,
+        epoch: i64,
+        finalized_features: CompactArray<FinalizedFeatureKey>,
+    ) {
+        self._tagged_fields.set(FINALIZED_FEATURES_EPOCH, &epoch);
+        self._tagged_fields
+            .set(FINALIZED_FEATURES, &finalized_features);
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
 pub struct ApiKey {
-    api_key: i16,
-    min_version: i16,
-    max_version: i16,
-    _tagged_fields: TaggedFieldsSection,
+    pub api_key: i16,
+    pub min_version: i16,
+    pub max_version: i16,
+    #[kafka(tagged_fields)]
+    pub _tagged_fields: TaggedFieldsSection,
 }
 
 impl ApiKey {
@@ -75,34 +78,53 @@ impl ApiKey {
     }
 }
 
-impl Size for ApiKey {
-    fn size(&self) -> usize {
-        self.api_key.size()
-            + self.min_version.size()
-            + self.max_version.size()
-            + self._tagged_fields.size()
-    }
+/// A feature supported by the broker, with the range of its supported versions
+#[derive(Debug, Clone, PartialEq, ByteParsable, Serializable, Size)]
+pub struct SupportedFeatureKey {
+    pub name: CompactString,
+    pub min_version: i16,
+    pub max_version: i16,
+    #[kafka(tagged_fields)]
+    pub _tagged_fields: TaggedFieldsSection,
 }
 
-impl ByteParsable<ApiKey> for ApiKey {
-    fn parse(_bytes: &[u8], _offset: usize) -> ApiKey {
-        todo!()
+impl SupportedFeatureKey {
+    pub fn new(name: &str, min_version: i16, max_version: i16) -> Self {
+        Self {
+            name: CompactString::from(name),
+            min_version,
+            max_version,
+            _tagged_fields: TaggedFieldsSection::empty(),
+        }
     }
 }
 
-impl Serializable for ApiKey {
-    fn to_be_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        bytes.extend_from_slice(&self.api_key.to_be_bytes());
-        bytes.extend_from_slice(&self.min_version.to_be_bytes());
-        bytes.extend_from_slice(&self.max_version.to_be_bytes());
-        bytes.extend_from_slice(&self._tagged_fields.to_be_bytes());
-        bytes
+/// A cluster-wide finalized feature, with the range of its finalized version levels
+#[derive(Debug, Clone, PartialEq, ByteParsable, Serializable, Size)]
+pub struct FinalizedFeatureKey {
+    pub name: CompactString,
+    pub max_version_level: i16,
+    pub min_version_level: i16,
+    #[kafka(tagged_fields)]
+    pub _tagged_fields: TaggedFieldsSection,
+}
+
+impl FinalizedFeatureKey {
+    pub fn new(name: &str, max_version_level: i16, min_version_level: i16) -> Self {
+        Self {
+            name: CompactString::from(name),
+            max_version_level,
+            min_version_level,
+            _tagged_fields: TaggedFieldsSection::empty(),
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::byte_parsable::ByteParsable;
+    use crate::serializable::Serializable;
+    use crate::size::Size;
     use crate::{
         api_response::{self, v0, ApiResponse},
         error_codes,
@@ -181,4 +203,56 @@ mod test {
 
         assert_eq!(expected_bytes, response.to_be_bytes());
     }
+
+    #[test]
+    fn serializes_features_as_tagged_fields() {
+        let mut response = ApiVersionsResponseV4::new(
+            error_codes::NONE,
+            CompactArray::empty(),
+            0,
+            TaggedFieldsSection::empty(),
+        );
+        response.set_supported_features(
+            vec![SupportedFeatureKey::new("metadata.version", 1, 21)].into(),
+        );
+        response.set_finalized_features(
+            3,
+            vec![FinalizedFeatureKey::new("metadata.version", 20, 20)].into(),
+        );
+
+        let bytes = response.to_be_bytes();
+
+        // error_code (2 bytes) + api_keys (1 byte) + throttle_time_ms (4 bytes)
+        assert_eq!(
+            &[
+                0x03, // number of tagged fields: 3
+                0x00, 0x17, // tag: 0 (supported_features), size: 23
+                0x02, // array length (1 + N): 2
+                0x11, // name length (1 + N): 17
+            ],
+            &bytes[7..12]
+        );
+        assert_eq!(bytes.len(), response.size());
+        let parsed = ApiVersionsResponseV4::parse(&bytes, 0).unwrap();
+        assert_eq!(
+            Ok(Some(3)),
+            parsed._tagged_fields.get::<i64>(FINALIZED_FEATURES_EPOCH)
+        );
+        assert_eq!(
+            Some(vec![FinalizedFeatureKey::new("metadata.version", 20, 20)]),
+            parsed
+                ._tagged_fields
+                .get::<CompactArray<FinalizedFeatureKey>>(FINALIZED_FEATURES)
+                .unwrap()
+                .map(|features| features.into_iter().collect())
+        );
+        assert_eq!(
+            Some(vec![SupportedFeatureKey::new("metadata.version", 1, 21)]),
+            parsed
+                ._tagged_fields
+                .get::<CompactArray<SupportedFeatureKey>>(SUPPORTED_FEATURES)
+                .unwrap()
+                .map(|features| features.into_iter().collect())
+        );
+    }
 }
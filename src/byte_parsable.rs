@@ -1,58 +1,151 @@
 use uuid::Uuid;
 
+use crate::decode_error::{take_bytes, DecodeError};
 use crate::serializable::Serializable;
 use crate::size::Size;
 
 pub trait ByteParsable<T> {
-    fn parse(bytes: &[u8], offset: usize) -> T;
+    fn parse(bytes: &[u8], offset: usize) -> Result<T, DecodeError>;
 }
 
 impl ByteParsable<i64> for i64 {
-    fn parse(bytes: &[u8], offset: usize) -> i64 {
-        i64::from_be_bytes(bytes[offset..offset + size_of::<i64>()].try_into().unwrap())
+    fn parse(bytes: &[u8], offset: usize) -> Result<i64, DecodeError> {
+        let bytes = take_bytes::<i64>(bytes, offset, size_of::<i64>())?;
+        Ok(i64::from_be_bytes(bytes.try_into().unwrap()))
     }
 }
 
 impl ByteParsable<i32> for i32 {
-    fn parse(bytes: &[u8], offset: usize) -> i32 {
-        i32::from_be_bytes(bytes[offset..offset + size_of::<i32>()].try_into().unwrap())
+    fn parse(bytes: &[u8], offset: usize) -> Result<i32, DecodeError> {
+        let bytes = take_bytes::<i32>(bytes, offset, size_of::<i32>())?;
+        Ok(i32::from_be_bytes(bytes.try_into().unwrap()))
     }
 }
 
 impl ByteParsable<i16> for i16 {
-    fn parse(bytes: &[u8], offset: usize) -> i16 {
-        i16::from_be_bytes(bytes[offset..offset + size_of::<i16>()].try_into().unwrap())
+    fn parse(bytes: &[u8], offset: usize) -> Result<i16, DecodeError> {
+        let bytes = take_bytes::<i16>(bytes, offset, size_of::<i16>())?;
+        Ok(i16::from_be_bytes(bytes.try_into().unwrap()))
     }
 }
 
 impl ByteParsable<i8> for i8 {
-    fn parse(bytes: &[u8], offset: usize) -> i8 {
-        i8::from_be_bytes(bytes[offset..offset + size_of::<i8>()].try_into().unwrap())
+    fn parse(bytes: &[u8], offset: usize) -> Result<i8, DecodeError> {
+        let bytes = take_bytes::<i8>(bytes, offset, size_of::<i8>())?;
+        Ok(i8::from_be_bytes(bytes.try_into().unwrap()))
+    }
+}
+
+impl ByteParsable<f64> for f64 {
+    fn parse(bytes: &[u8], offset: usize) -> Result<f64, DecodeError> {
+        let bytes = take_bytes::<f64>(bytes, offset, size_of::<f64>())?;
+        Ok(f64::from_be_bytes(bytes.try_into().unwrap()))
+    }
+}
+
+impl ByteParsable<bool> for bool {
+    fn parse(bytes: &[u8], offset: usize) -> Result<bool, DecodeError> {
+        let bytes = take_bytes::<bool>(bytes, offset, size_of::<bool>())?;
+        Ok(bytes[0] != 0)
     }
 }
 
 impl ByteParsable<Self> for u16 {
-    fn parse(bytes: &[u8], offset: usize) -> Self {
-        Self::from_be_bytes(
-            bytes[offset..offset + size_of::<Self>()]
-                .try_into()
-                .unwrap(),
-        )
+    fn parse(bytes: &[u8], offset: usize) -> Result<Self, DecodeError> {
+        let bytes = take_bytes::<Self>(bytes, offset, size_of::<Self>())?;
+        Ok(Self::from_be_bytes(bytes.try_into().unwrap()))
     }
 }
 
 impl ByteParsable<u32> for u32 {
-    fn parse(bytes: &[u8], offset: usize) -> u32 {
-        u32::from_be_bytes(bytes[offset..offset + size_of::<u32>()].try_into().unwrap())
+    fn parse(bytes: &[u8], offset: usize) -> Result<u32, DecodeError> {
+        let bytes = take_bytes::<u32>(bytes, offset, size_of::<u32>())?;
+        Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
     }
 }
 
 impl ByteParsable<Uuid> for Uuid {
-    fn parse(bytes: &[u8], offset: usize) -> Uuid {
-        Uuid::from_bytes(
-            bytes[offset..offset + size_of::<Uuid>()]
-                .try_into()
-                .unwrap(),
-        )
+    fn parse(bytes: &[u8], offset: usize) -> Result<Uuid, DecodeError> {
+        let bytes = take_bytes::<Uuid>(bytes, offset, size_of::<Uuid>())?;
+        Ok(Uuid::from_bytes(bytes.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use kafka_derive::{ByteParsable, Serializable, Size};
+
+    use super::*;
+    use crate::serializable::Serializable;
+    use crate::size::Size;
+    use crate::tagged_fields_section::TaggedFieldsSection;
+
+    #[derive(Debug, ByteParsable, Serializable, Size)]
+    #[kafka(version = V)]
+    struct Message<const V: i16> {
+        always: i32,
+        #[kafka(versions = "1+")]
+        added_in_v1: i16,
+        #[kafka(versions = "0")]
+        removed_in_v1: i8,
+        #[kafka(skip)]
+        not_on_the_wire: Vec<u8>,
+        #[kafka(tagged_fields, versions = "1+")]
+        _tagged_fields: TaggedFieldsSection,
+    }
+
+    #[test]
+    fn parses_big_endian_integers() {
+        let bytes = [0x00, 0x00, 0x01, 0x00];
+
+        assert_eq!(Ok(256), i32::parse(&bytes, 0));
+        assert_eq!(Ok(1), i16::parse(&bytes, 1));
+    }
+
+    #[test]
+    fn fails_on_truncated_input_instead_of_panicking() {
+        let bytes = [0x00, 0x01];
+
+        let error = i32::parse(&bytes, 0).unwrap_err();
+
+        assert_eq!(0, error.offset);
+        assert_eq!("i32", error.expected);
+    }
+
+    #[test]
+    fn derives_codec_for_fields_in_declaration_order() {
+        let bytes = [0x00, 0x00, 0x00, 0x07, 0x00, 0x03, 0x00];
+
+        let message = Message::<1>::parse(&bytes, 0).unwrap();
+
+        assert_eq!(7, message.always);
+        assert_eq!(3, message.added_in_v1);
+        assert_eq!(0, message.removed_in_v1);
+        assert!(message.not_on_the_wire.is_empty());
+        assert_eq!(bytes.len(), message.size());
+        assert_eq!(bytes.to_vec(), message.to_be_bytes());
+    }
+
+    #[test]
+    fn derives_codec_that_leaves_out_fields_of_other_versions() {
+        let bytes = [0x00, 0x00, 0x00, 0x07, 0x05];
+
+        let message = Message::<0>::parse(&bytes, 0).unwrap();
+
+        assert_eq!(0, message.added_in_v1);
+        assert_eq!(5, message.removed_in_v1);
+        assert_eq!(TaggedFieldsSection::empty(), message._tagged_fields);
+        assert_eq!(bytes.len(), message.size());
+        assert_eq!(bytes.to_vec(), message.to_be_bytes());
+    }
+
+    #[test]
+    fn derived_parse_reports_the_failing_field() {
+        let bytes = [0x00, 0x00, 0x00, 0x07, 0x00];
+
+        let error = Message::<1>::parse(&bytes, 0).unwrap_err();
+
+        assert_eq!(4, error.offset);
+        assert_eq!("i16", error.expected);
     }
 }
@@ -0,0 +1,100 @@
+use std::any::type_name;
+
+use thiserror::Error;
+
+/// Error returned when bytes can't be decoded into the expected protocol type
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("failed to decode {expected} at offset {offset}: {reason}")]
+pub struct DecodeError {
+    pub offset: usize,
+    pub expected: &'static str,
+    pub reason: DecodeErrorReason,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum DecodeErrorReason {
+    #[error("needed {needed} byte(s) but only {available} remain")]
+    UnexpectedEof { needed: usize, available: usize },
+    #[error("varint is longer than {0} bytes")]
+    VarintTooLong(usize),
+    #[error("value {0} is out of range")]
+    OutOfRange(i128),
+    #[error("invalid length {0}")]
+    InvalidLength(i64),
+    #[error("declared length of {declared} byte(s) but {actual} byte(s) were parsed")]
+    LengthMismatch { declared: usize, actual: usize },
+    #[error("invalid UTF-8")]
+    InvalidUtf8,
+    #[error("tag {0} isn't greater than the previous tag")]
+    UnorderedTag(u32),
+    #[error("stored CRC {stored:#010x} doesn't match the computed CRC {computed:#010x}")]
+    CrcMismatch { stored: u32, computed: u32 },
+    #[error("unknown compression codec {0}")]
+    UnknownCompression(i16),
+    #[error("failed to decompress records: {0}")]
+    Decompression(String),
+    #[error("bytes remain after the {0} record(s) declared")]
+    RecordsRemaining(i32),
+}
+
+impl DecodeError {
+    pub fn new<T: ?Sized>(offset: usize, reason: DecodeErrorReason) -> Self {
+        Self {
+            offset,
+            expected: type_name::<T>(),
+            reason,
+        }
+    }
+}
+
+/// Returns `length` bytes starting at `offset`, or an error naming `T` if the input is too short
+pub fn take_bytes<T: ?Sized>(
+    bytes: &[u8],
+    offset: usize,
+    length: usize,
+) -> Result<&[u8], DecodeError> {
+    let available = bytes.len().saturating_sub(offset);
+    if available < length {
+        return Err(DecodeError::new::<T>(
+            offset,
+            DecodeErrorReason::UnexpectedEof {
+                needed: length,
+                available,
+            },
+        ));
+    }
+    Ok(&bytes[offset..offset + length])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn takes_bytes_within_input() {
+        let bytes = [0x01, 0x02, 0x03];
+
+        assert_eq!(Ok(&bytes[1..3]), take_bytes::<i16>(&bytes, 1, 2));
+    }
+
+    #[test]
+    fn reports_offset_type_and_shortfall_when_input_too_short() {
+        let bytes = [0x01, 0x02, 0x03];
+
+        let error = take_bytes::<i32>(&bytes, 1, 4).unwrap_err();
+
+        assert_eq!(1, error.offset);
+        assert_eq!("i32", error.expected);
+        assert_eq!(
+            DecodeErrorReason::UnexpectedEof {
+                needed: 4,
+                available: 2
+            },
+            error.reason
+        );
+        assert_eq!(
+            "failed to decode i32 at offset 1: needed 4 byte(s) but only 2 remain",
+            error.to_string()
+        );
+    }
+}
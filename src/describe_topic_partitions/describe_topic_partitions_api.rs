@@ -1,9 +1,16 @@
+use std::ops::RangeInclusive;
+
+use tracing::warn;
 use uuid::Uuid;
 
 use crate::{
+    api_handler::{ApiHandler, RequestContext},
+    api_keys::DESCRIBE_TOPIC_PARTITIONS,
     api_response::{self, ApiResponse},
     byte_parsable::ByteParsable,
+    decode_error::DecodeError,
     describe_topic_partitions::{
+        self,
         describe_topic_partitions_request_v0::{
             DescribeTopicPartitionsRequestV0, Topic as RequestTopic,
         },
@@ -11,12 +18,13 @@ use crate::{
             DescribeTopicPartitionsResponseV0, Partition, Topic as ResponseTopic,
         },
     },
-    error_codes, get_record_batches_from_metadata_log,
+    error_codes,
     headers::{request_header_v2::RequestHeaderV2, response_header_v1::ResponseHeaderV1},
-    records::record_batch::{RecordBatch, RecordValue, SearchItem},
-    size::Size,
+    messages::describe_topic_partitions_request::FIRST_FLEXIBLE_VERSION,
+    metadata_image::MetadataImage,
+    serializable::Serializable,
     tagged_fields_section::TaggedFieldsSection,
-    types::{compact_array::CompactArray, compact_string::CompactString},
+    types::compact_array::CompactArray,
     utils::uuid::all_zeroes_uuid,
 };
 
@@ -26,12 +34,51 @@ impl DescribeTopicPartitionsApi {
     pub fn respond(
         request_header: RequestHeaderV2,
         buf: &[u8],
-        offset: usize,
-    ) -> ApiResponse<ResponseHeaderV1, DescribeTopicPartitionsResponseV0> {
+        body_offset: usize,
+        metadata_image: &MetadataImage,
+    ) -> Result<ApiResponse<ResponseHeaderV1, DescribeTopicPartitionsResponseV0>, DecodeError> {
         let describe_topic_partitions_request =
-            DescribeTopicPartitionsRequestV0::parse(buf, offset + request_header.size());
+            DescribeTopicPartitionsRequestV0::parse(buf, body_offset)?;
+        let topics = Self::topics(describe_topic_partitions_request.topics, metadata_image);
+        let throttle_time_ms = 0;
+        let next_cursor: i8 = -1;
+        let response = DescribeTopicPartitionsResponseV0::new(
+            throttle_time_ms,
+            topics,
+            next_cursor,
+            TaggedFieldsSection::empty(),
+        );
+        Ok(api_response::v1(request_header.correlation_id, response))
+    }
+
+    /// Responds with the error for every topic of the request, as far as it can be decoded
+    pub fn error_response(
+        request_header: RequestHeaderV2,
+        buf: &[u8],
+        body_offset: usize,
+        error_code: i16,
+    ) -> ApiResponse<ResponseHeaderV1, DescribeTopicPartitionsResponseV0> {
+        let topics = DescribeTopicPartitionsRequestV0::parse(buf, body_offset)
+            .map(|describe_topic_partitions_request| {
+                describe_topic_partitions_request
+                    .topics
+                    .into_iter()
+                    .map(|topic| {
+                        ResponseTopic::new(
+                            error_code,
+                            topic.name.into_compact_nullable_string(),
+                            all_zeroes_uuid(),
+                            false,
+                            CompactArray::empty(),
+                            0,
+                            TaggedFieldsSection::empty(),
+                        )
+                    })
+                    .collect::<Vec<ResponseTopic>>()
+                    .into()
+            })
+            .unwrap_or_else(|_| CompactArray::empty());
         let throttle_time_ms = 0;
-        let topics = Self::topics(describe_topic_partitions_request.topics);
         let next_cursor: i8 = -1;
         let response = DescribeTopicPartitionsResponseV0::new(
             throttle_time_ms,
@@ -42,19 +89,32 @@ impl DescribeTopicPartitionsApi {
         api_response::v1(request_header.correlation_id, response)
     }
 
-    fn topics(request_topics: CompactArray<RequestTopic>) -> CompactArray<ResponseTopic> {
+    fn topics(
+        request_topics: CompactArray<RequestTopic>,
+        metadata_image: &MetadataImage,
+    ) -> CompactArray<ResponseTopic> {
         request_topics
             .into_iter()
-            .map(Self::topic)
+            .map(|topic| Self::topic(topic, metadata_image))
             .collect::<Vec<ResponseTopic>>()
             .into()
     }
 
-    fn topic(topic: RequestTopic) -> ResponseTopic {
-        let record_values = Self::get_record_values(&topic.name);
-        let (error_code, topic_id) = Self::get_error_code_and_topic_id(&record_values);
+    fn topic(topic: RequestTopic, metadata_image: &MetadataImage) -> ResponseTopic {
+        let (error_code, topic_id, partitions) =
+            match metadata_image.topic_by_name(&topic.name.bytes) {
+                Some(topic_record) => (
+                    error_codes::NONE,
+                    topic_record.topic_uuid,
+                    Self::partitions(metadata_image, topic_record.topic_uuid),
+                ),
+                None => (
+                    error_codes::UNKNOWN_TOPIC_OR_PARTITION,
+                    all_zeroes_uuid(),
+                    CompactArray::empty(),
+                ),
+            };
         let is_internal = false;
-        let partitions = Self::partitions(record_values);
         let topic_authorized_operation = 0;
         ResponseTopic::new(
             error_code,
@@ -67,35 +127,53 @@ impl DescribeTopicPartitionsApi {
         )
     }
 
-    fn partitions(record_values: Vec<RecordValue>) -> CompactArray<Partition> {
-        record_values
+    fn partitions(metadata_image: &MetadataImage, topic_id: Uuid) -> CompactArray<Partition> {
+        metadata_image
+            .partitions(&topic_id)
             .into_iter()
-            .filter_map(|record_value| record_value.into_partition_record())
+            .cloned()
             .map(Partition::from_partition_record)
             .collect::<Vec<Partition>>()
             .into()
     }
+}
 
-    fn get_record_values(topic_name: &CompactString) -> Vec<RecordValue> {
-        let record_batches = get_record_batches_from_metadata_log();
-        record_batches
-            .iter()
-            .flat_map(|record_batch| {
-                record_batch.parse_record_values(SearchItem::TopicName(topic_name.clone()), false)
-            })
-            .collect()
+impl ApiHandler for DescribeTopicPartitionsApi {
+    fn api_key(&self) -> i16 {
+        DESCRIBE_TOPIC_PARTITIONS
+    }
+
+    fn versions(&self) -> RangeInclusive<i16> {
+        describe_topic_partitions::MIN_VERSION..=describe_topic_partitions::MAX_VERSION
+    }
+
+    fn first_flexible_version(&self) -> i16 {
+        FIRST_FLEXIBLE_VERSION
     }
 
-    fn get_error_code_and_topic_id(record_values: &[RecordValue]) -> (i16, Uuid) {
-        if record_values.is_empty() {
-            (error_codes::UNKNOWN_TOPIC_OR_PARTITION, all_zeroes_uuid())
-        } else if let RecordValue::Topic(record) = &record_values[0] {
-            (error_codes::NONE, record.topic_uuid)
-        // Should always be TopicRecord but could get topic_uuid from PartitionRecord
-        } else if let RecordValue::Partition(record) = &record_values[0] {
-            (error_codes::NONE, record.topic_uuid)
-        } else {
-            (error_codes::UNKNOWN_TOPIC_OR_PARTITION, all_zeroes_uuid())
-        }
+    fn handle(
+        &self,
+        request_header: RequestHeaderV2,
+        buf: &[u8],
+        body_offset: usize,
+        context: &RequestContext,
+    ) -> Result<Vec<u8>, DecodeError> {
+        Ok(Self::respond(request_header, buf, body_offset, context.metadata_image)?.to_be_bytes())
+    }
+
+    fn unsupported_version(
+        &self,
+        request_header: RequestHeaderV2,
+        buf: &[u8],
+        body_offset: usize,
+        _context: &RequestContext,
+    ) -> Vec<u8> {
+        Self::error_response(
+            request_header,
+            buf,
+            body_offset,
+            error_codes::UNSUPPORTED_VERSION,
+        )
+        .to_be_bytes()
     }
 }
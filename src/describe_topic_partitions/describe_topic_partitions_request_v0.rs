@@ -1,8 +1,10 @@
 use std::cmp::Ordering;
 
+use kafka_derive::{ByteParsable, Serializable, Size};
+
 use crate::{
     byte_parsable::ByteParsable,
-    serializable::Serializable,
+    decode_error::DecodeError,
     size::Size,
     tagged_fields_section::TaggedFieldsSection,
     types::{compact_array::CompactArray, compact_string::CompactString},
@@ -17,46 +19,39 @@ use crate::{
 ///   cursor => topic_name partition_index _tagged_fields
 ///     topic_name => COMPACT_STRING
 ///     partition_index => INT32
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serializable, Size)]
 pub struct DescribeTopicPartitionsRequestV0 {
     pub topics: CompactArray<Topic>,
     pub response_partition_limit: i32,
     pub cursor: Cursor,
+    #[kafka(tagged_fields)]
     pub _tagged_fields: TaggedFieldsSection,
 }
 
-impl Size for DescribeTopicPartitionsRequestV0 {
-    fn size(&self) -> usize {
-        self.topics.size()
-            + self.response_partition_limit.size()
-            + self.cursor.size()
-            + self._tagged_fields.size()
-    }
-}
-
 impl ByteParsable<DescribeTopicPartitionsRequestV0> for DescribeTopicPartitionsRequestV0 {
-    fn parse(bytes: &[u8], offset: usize) -> DescribeTopicPartitionsRequestV0 {
+    fn parse(bytes: &[u8], offset: usize) -> Result<DescribeTopicPartitionsRequestV0, DecodeError> {
         let mut offset: usize = offset;
-        let mut topics = CompactArray::<Topic>::parse(bytes, offset);
+        let mut topics = CompactArray::<Topic>::parse(bytes, offset)?;
         topics.sort_by(topic_name);
         offset += topics.size();
-        let response_partition_limit = i32::parse(bytes, offset);
+        let response_partition_limit = i32::parse(bytes, offset)?;
         offset += response_partition_limit.size();
-        let cursor = Cursor::parse(bytes, offset);
+        let cursor = Cursor::parse(bytes, offset)?;
         offset += cursor.size();
-        let _tagged_fields = TaggedFieldsSection::parse(bytes, offset);
-        Self {
+        let _tagged_fields = TaggedFieldsSection::parse(bytes, offset)?;
+        Ok(Self {
             topics,
             response_partition_limit,
             cursor,
             _tagged_fields,
-        }
+        })
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
 pub struct Topic {
     pub name: CompactString,
+    #[kafka(tagged_fields)]
     pub _tagged_fields: TaggedFieldsSection,
 }
 
@@ -64,52 +59,10 @@ pub fn topic_name(topic1: &Topic, topic2: &Topic) -> Ordering {
     topic1.name.cmp(&topic2.name)
 }
 
-impl Size for Topic {
-    fn size(&self) -> usize {
-        self.name.size() + self._tagged_fields.size()
-    }
-}
-
-impl ByteParsable<Topic> for Topic {
-    fn parse(bytes: &[u8], offset: usize) -> Self {
-        let mut offset: usize = offset;
-        let name = CompactString::parse(bytes, offset);
-        offset += name.size();
-        let _tagged_fields = TaggedFieldsSection::parse(bytes, offset);
-        Self {
-            name,
-            _tagged_fields,
-        }
-    }
-}
-
-impl Serializable for Topic {}
-
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
 pub struct Cursor {
     pub topic_name: CompactString,
     pub partition_index: i32,
+    #[kafka(tagged_fields)]
     pub _tagged_fields: TaggedFieldsSection,
 }
-
-impl Size for Cursor {
-    fn size(&self) -> usize {
-        self.topic_name.size() + self.partition_index.size() + self._tagged_fields.size()
-    }
-}
-
-impl ByteParsable<Cursor> for Cursor {
-    fn parse(bytes: &[u8], offset: usize) -> Self {
-        let mut offset: usize = offset;
-        let topic_name = CompactString::parse(bytes, offset);
-        offset += topic_name.size();
-        let partition_index = i32::parse(bytes, offset);
-        offset += partition_index.size();
-        let _tagged_fields = TaggedFieldsSection::parse(bytes, offset);
-        Self {
-            topic_name,
-            partition_index,
-            _tagged_fields,
-        }
-    }
-}
@@ -1,15 +1,10 @@
+use kafka_derive::{ByteParsable, Serializable, Size};
 use uuid::Uuid;
 
 use crate::types::compact_nullable_string::CompactNullableString;
 use crate::{
-    byte_parsable::ByteParsable,
-    error_codes,
-    headers::{self, response_header_v1::ResponseHeaderV1},
-    records::partition_record::PartitionRecord,
-    serializable::Serializable,
-    size::Size,
-    tagged_fields_section::TaggedFieldsSection,
-    types::{compact_array::CompactArray, compact_string::CompactString},
+    error_codes, records::partition_record::PartitionRecord,
+    tagged_fields_section::TaggedFieldsSection, types::compact_array::CompactArray,
 };
 
 // https://kafka.apache.org/41/design/protocol/#The_Messages_DescribeTopicPartitions
@@ -35,11 +30,12 @@ use crate::{
 ///   next_cursor => topic_name partition_index _tagged_fields
 ///     topic_name => COMPACT_STRING
 ///     partition_index => INT32
-#[derive(Debug)]
+#[derive(Debug, ByteParsable, Serializable, Size)]
 pub struct DescribeTopicPartitionsResponseV0 {
     pub throttle_time_ms: i32,
     pub topics: CompactArray<Topic>,
     pub next_cursor: i8, // Although, this type is not what is in the protocol spec (see above)
+    #[kafka(tagged_fields)]
     pub _tagged_fields: TaggedFieldsSection,
 }
 
@@ -59,26 +55,6 @@ impl DescribeTopicPartitionsResponseV0 {
     }
 }
 
-impl Size for DescribeTopicPartitionsResponseV0 {
-    fn size(&self) -> usize {
-        self.throttle_time_ms.size()
-            + self.topics.size()
-            + self.next_cursor.size()
-            + self._tagged_fields.size()
-    }
-}
-
-impl Serializable for DescribeTopicPartitionsResponseV0 {
-    fn to_be_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        bytes.extend_from_slice(&self.throttle_time_ms.to_be_bytes());
-        bytes.extend_from_slice(&self.topics.to_be_bytes());
-        bytes.extend_from_slice(&self.next_cursor.to_be_bytes());
-        bytes.extend_from_slice(&self._tagged_fields.to_be_bytes());
-        bytes
-    }
-}
-
 impl std::fmt::Display for DescribeTopicPartitionsResponseV0 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut debug_struct = f.debug_struct("DescribeTopicPartitionsResponseV0");
@@ -92,7 +68,7 @@ impl std::fmt::Display for DescribeTopicPartitionsResponseV0 {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
 pub struct Topic {
     pub error_code: i16,
     pub name: CompactNullableString,
@@ -100,6 +76,7 @@ pub struct Topic {
     pub is_internal: bool,
     pub partitions: CompactArray<Partition>,
     pub topic_authorized_operations: i32,
+    #[kafka(tagged_fields)]
     pub _tagged_fields: TaggedFieldsSection,
 }
 
@@ -125,39 +102,7 @@ impl Topic {
     }
 }
 
-impl Size for Topic {
-    fn size(&self) -> usize {
-        self.error_code.size()
-            + self.name.size()
-            + self.topic_id.size()
-            + self.is_internal.size()
-            + self.partitions.size()
-            + self.topic_authorized_operations.size()
-            + self._tagged_fields.size()
-    }
-}
-
-impl ByteParsable<Self> for Topic {
-    fn parse(_bytes: &[u8], _offset: usize) -> Self {
-        todo!()
-    }
-}
-
-impl Serializable for Topic {
-    fn to_be_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        bytes.extend_from_slice(&self.error_code.to_be_bytes());
-        bytes.extend_from_slice(&self.name.to_be_bytes());
-        bytes.extend_from_slice(&self.topic_id.to_be_bytes());
-        bytes.extend_from_slice(&self.is_internal.to_be_bytes());
-        bytes.extend_from_slice(&self.partitions.to_be_bytes());
-        bytes.extend_from_slice(&self.topic_authorized_operations.to_be_bytes());
-        bytes.extend_from_slice(&self._tagged_fields.to_be_bytes());
-        bytes
-    }
-}
-
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
 pub struct Partition {
     pub error_code: i16,
     pub partition_index: i32,
@@ -168,6 +113,7 @@ pub struct Partition {
     pub eligible_leader_replicas: CompactArray<i32>,
     pub last_known_elr: CompactArray<i32>,
     pub offline_replicas: CompactArray<i32>,
+    #[kafka(tagged_fields)]
     pub _tagged_fields: TaggedFieldsSection,
 }
 
@@ -214,41 +160,3 @@ impl Partition {
         )
     }
 }
-
-impl Size for Partition {
-    fn size(&self) -> usize {
-        self.error_code.size()
-            + self.partition_index.size()
-            + self.leader_id.size()
-            + self.leader_epoch.size()
-            + self.replica_nodes.size()
-            + self.isr_nodes.size()
-            + self.eligible_leader_replicas.size()
-            + self.last_known_elr.size()
-            + self.offline_replicas.size()
-            + self._tagged_fields.size()
-    }
-}
-
-impl ByteParsable<Self> for Partition {
-    fn parse(_bytes: &[u8], _offset: usize) -> Self {
-        todo!()
-    }
-}
-
-impl Serializable for Partition {
-    fn to_be_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        bytes.extend_from_slice(&self.error_code.to_be_bytes());
-        bytes.extend_from_slice(&self.partition_index.to_be_bytes());
-        bytes.extend_from_slice(&self.leader_id.to_be_bytes());
-        bytes.extend_from_slice(&self.leader_epoch.to_be_bytes());
-        bytes.extend_from_slice(&self.replica_nodes.to_be_bytes());
-        bytes.extend_from_slice(&self.isr_nodes.to_be_bytes());
-        bytes.extend_from_slice(&self.eligible_leader_replicas.to_be_bytes());
-        bytes.extend_from_slice(&self.last_known_elr.to_be_bytes());
-        bytes.extend_from_slice(&self.offline_replicas.to_be_bytes());
-        bytes.extend_from_slice(&self._tagged_fields.to_be_bytes());
-        bytes
-    }
-}
@@ -1,42 +1,117 @@
-use uuid::Uuid;
+use std::io;
+use std::path::PathBuf;
+
+use tracing::{error, warn};
+
+use std::ops::RangeInclusive;
 
 use crate::{
+    api_handler::{ApiHandler, RequestContext},
+    api_keys::FETCH,
     api_response::{self, ApiResponse},
     byte_parsable::ByteParsable,
+    decode_error::DecodeError,
     error_codes,
     fetch::{
-        fetch_request_v16::FetchRequestV16,
-        fetch_response_v16::FetchResponseV16,
+        self,
+        fetch_request::FetchRequest,
+        fetch_response::FetchResponse,
+        non_flexible,
         partition::{ResponsePartition, Transaction},
         topic::{RequestTopic, ResponseTopic},
     },
-    get_record_batches_from_log_file, get_record_batches_from_metadata_log,
-    headers::{request_header_v2::RequestHeaderV2, response_header_v1::ResponseHeaderV1},
+    headers::{
+        request_header_v2::RequestHeaderV2, response_header_v0::ResponseHeaderV0,
+        response_header_v1::ResponseHeaderV1,
+    },
+    messages::fetch_request::FIRST_FLEXIBLE_VERSION,
+    metadata_image::MetadataImage,
     records::{
-        record_batch::{RecordBatch, SearchItem},
+        legacy_message_set::{self, MAGIC_V0, MAGIC_V1},
+        record_batch::RecordBatch,
         topic_record::TopicRecord,
     },
     serializable::Serializable,
     size::Size,
+    storage::log::Log,
     tagged_fields_section::TaggedFieldsSection,
-    types::{compact_array::CompactArray, compact_records::CompactRecords},
+    types::{
+        array::Array, compact_array::CompactArray, compact_records::CompactRecords,
+        nullable_bytes::NullableBytes,
+    },
+    with_version,
 };
 
+// Version from which topics are identified by their id rather than their name
+const FIRST_VERSION_WITH_TOPIC_IDS: i16 = 13;
+// Versions from which consumers read v2 batches, and before that message sets of magic 1
+const FIRST_VERSION_WITH_RECORD_BATCHES: i16 = 4;
+const FIRST_VERSION_WITH_MAGIC_V1: i16 = 2;
+// Version from which requests limit the bytes of the whole response
+const FIRST_VERSION_WITH_MAX_BYTES: i16 = 3;
+// Version from which responses have a top-level error code
+const FIRST_VERSION_WITH_ERROR_CODE: i16 = 7;
+// Watermarks and log start offset of a partition that couldn't be read
+const UNKNOWN_OFFSET: i64 = -1;
+
+/// Every version is handled with the messages generic over their version, those of the
+/// flexible versions or those of the versions before them
 pub struct FetchApi;
 
+/// A partition to fetch, whatever the version of the request
+struct FetchPartition {
+    partition_index: i32,
+    fetch_offset: i64,
+    partition_max_bytes: i32,
+}
+
+/// A partition read for a Fetch response, whatever its version
+struct FetchedPartition {
+    partition_index: i32,
+    error_code: i16,
+    high_watermark: i64,
+    last_stable_offset: i64,
+    log_start_offset: i64,
+    /// The batches read, as the bytes they are stored as
+    records: Vec<u8>,
+}
+
+/// What's left of the request's max bytes as its partitions are read in order. Until a batch
+/// is included, the first batch read is included whatever its size, so that consumers get
+/// past batches larger than the limits, as in Kafka's fetches since KIP-74.
+struct FetchBytes {
+    remaining: usize,
+    batch_included: bool,
+}
+
 impl FetchApi {
-    pub fn respond(
+    pub fn respond<const V: i16>(
         request_header: RequestHeaderV2,
         buf: &[u8],
-        offset: usize,
-    ) -> ApiResponse<ResponseHeaderV1, FetchResponseV16> {
-        let fetch_request = FetchRequestV16::parse(buf, offset + request_header.size());
+        body_offset: usize,
+        metadata_image: &MetadataImage,
+        log_dirs: &[PathBuf],
+    ) -> ApiResponse<ResponseHeaderV1, FetchResponse<V>> {
+        let (error_code, responses) = match FetchRequest::<V>::parse(buf, body_offset) {
+            Ok(fetch_request) => (
+                error_codes::NONE,
+                Self::responses(
+                    fetch_request.topics,
+                    fetch_request.max_bytes,
+                    metadata_image,
+                    log_dirs,
+                ),
+            ),
+            Err(e) => {
+                warn!("Rejecting Fetch request: {e}");
+                (error_codes::INVALID_REQUEST, CompactArray::empty())
+            }
+        };
         let throttle_time_ms = 0;
         let session_id = 0;
-        let responses = Self::responses(fetch_request.topics);
-        let response = FetchResponseV16::new(
+        let response = FetchResponse::new(
             throttle_time_ms,
-            error_codes::NONE,
+            error_code,
             session_id,
             responses,
             TaggedFieldsSection::empty(),
@@ -44,90 +119,524 @@ impl FetchApi {
         api_response::v1(request_header.correlation_id, response)
     }
 
-    fn responses(topics: CompactArray<RequestTopic>) -> CompactArray<ResponseTopic> {
+    /// Responds to the versions before the first flexible one, with a v0 response header.
+    /// The versions before 7 have no top-level error code, so an undecodable request of them is
+    /// returned as an error.
+    pub fn respond_non_flexible<const V: i16>(
+        request_header: RequestHeaderV2,
+        buf: &[u8],
+        body_offset: usize,
+        metadata_image: &MetadataImage,
+        log_dirs: &[PathBuf],
+    ) -> Result<ApiResponse<ResponseHeaderV0, non_flexible::FetchResponse<V>>, DecodeError> {
+        let (error_code, responses) = match non_flexible::FetchRequest::<V>::parse(buf, body_offset)
+        {
+            Ok(fetch_request) => {
+                // The versions before 3 only limit the bytes of each partition
+                let max_bytes = if V >= FIRST_VERSION_WITH_MAX_BYTES {
+                    fetch_request.max_bytes
+                } else {
+                    i32::MAX
+                };
+                (
+                    error_codes::NONE,
+                    Self::non_flexible_responses(
+                        fetch_request.topics,
+                        max_bytes,
+                        metadata_image,
+                        log_dirs,
+                    ),
+                )
+            }
+            Err(e) if V >= FIRST_VERSION_WITH_ERROR_CODE => {
+                warn!("Rejecting Fetch request: {e}");
+                (error_codes::INVALID_REQUEST, Array::empty())
+            }
+            Err(e) => return Err(e),
+        };
+        let throttle_time_ms = 0;
+        let session_id = 0;
+        let response =
+            non_flexible::FetchResponse::new(throttle_time_ms, error_code, session_id, responses);
+        Ok(api_response::v0(request_header.correlation_id, response))
+    }
+
+    /// Responds with the error as the top-level error code
+    pub fn error_response(
+        request_header: RequestHeaderV2,
+        error_code: i16,
+    ) -> ApiResponse<ResponseHeaderV1, FetchResponse<{ fetch::MAX_VERSION }>> {
+        let throttle_time_ms = 0;
+        let session_id = 0;
+        let response = FetchResponse::new(
+            throttle_time_ms,
+            error_code,
+            session_id,
+            CompactArray::empty(),
+            TaggedFieldsSection::empty(),
+        );
+        api_response::v1(request_header.correlation_id, response)
+    }
+
+    fn responses<const V: i16>(
+        topics: CompactArray<RequestTopic<V>>,
+        max_bytes: i32,
+        metadata_image: &MetadataImage,
+        log_dirs: &[PathBuf],
+    ) -> CompactArray<ResponseTopic<V>> {
+        let mut fetch_bytes = FetchBytes::new(max_bytes);
         topics
             .into_iter()
-            .map(Self::response_topic)
-            .collect::<Vec<ResponseTopic>>()
+            .map(|topic| Self::response_topic(topic, &mut fetch_bytes, metadata_image, log_dirs))
+            .collect::<Vec<ResponseTopic<V>>>()
             .into()
     }
 
-    fn response_topic(topic: RequestTopic) -> ResponseTopic {
-        let partition_index = 0;
-        let topic_id = topic.topic_id;
-        let metadata_record_batches = get_record_batches_from_metadata_log();
-        let error_code = Self::check_topic_exists(topic_id, &metadata_record_batches);
-        let high_watermark = 0;
-        let last_stable_offset = 0;
-        let log_start_offset = 0;
-        let aborted_transactions = CompactArray::empty();
-        let preferred_read_replica = 0;
-        let records =
-            Self::get_records_from_data_log(topic_id, &metadata_record_batches, partition_index);
-        ResponseTopic::new(
-            topic_id,
-            [ResponsePartition {
-                partition_index,
-                error_code,
-                high_watermark,
-                last_stable_offset,
-                log_start_offset,
+    fn response_topic<const V: i16>(
+        topic: RequestTopic<V>,
+        fetch_bytes: &mut FetchBytes,
+        metadata_image: &MetadataImage,
+        log_dirs: &[PathBuf],
+    ) -> ResponseTopic<V> {
+        let (topic_record, unknown_topic_error_code) = if V >= FIRST_VERSION_WITH_TOPIC_IDS {
+            (
+                metadata_image.topic_by_id(&topic.topic_id),
+                error_codes::UNKNOWN_TOPIC_ID,
+            )
+        } else {
+            (
+                metadata_image.topic_by_name(&topic.topic.bytes),
+                error_codes::UNKNOWN_TOPIC_OR_PARTITION,
+            )
+        };
+        let partitions = Self::fetch_topic(
+            topic_record,
+            unknown_topic_error_code,
+            topic.partitions.iter().map(|partition| FetchPartition {
+                partition_index: partition.partition,
+                fetch_offset: partition.fetch_offset,
+                partition_max_bytes: partition.partition_max_bytes,
+            }),
+            fetch_bytes,
+            metadata_image,
+            log_dirs,
+        )
+        .into_iter()
+        .map(|fetched| {
+            let aborted_transactions: CompactArray<Transaction> = CompactArray::empty();
+            let preferred_read_replica = 0;
+            ResponsePartition {
+                partition_index: fetched.partition_index,
+                error_code: fetched.error_code,
+                high_watermark: fetched.high_watermark,
+                last_stable_offset: fetched.last_stable_offset,
+                log_start_offset: fetched.log_start_offset,
                 aborted_transactions,
                 preferred_read_replica,
-                records,
+                records: if fetched.error_code == error_codes::NONE {
+                    CompactRecords::from_bytes(fetched.records)
+                } else {
+                    CompactRecords::null()
+                },
                 _tagged_fields: TaggedFieldsSection::empty(),
-            }]
-            .into(),
+            }
+        })
+        .collect::<Vec<ResponsePartition>>()
+        .into();
+        ResponseTopic::new(
+            topic.topic,
+            topic.topic_id,
+            partitions,
             TaggedFieldsSection::empty(),
         )
     }
 
-    fn check_topic_exists(topic_id: Uuid, record_batches: &[RecordBatch]) -> i16 {
-        let topic_exists = Self::metadata_file_contains(topic_id, record_batches);
-        if topic_exists {
-            error_codes::NONE
-        } else {
-            error_codes::UNKNOWN_TOPIC_ID
+    fn non_flexible_responses<const V: i16>(
+        topics: Array<non_flexible::RequestTopic<V>>,
+        max_bytes: i32,
+        metadata_image: &MetadataImage,
+        log_dirs: &[PathBuf],
+    ) -> Array<non_flexible::ResponseTopic<V>> {
+        let mut fetch_bytes = FetchBytes::new(max_bytes);
+        topics
+            .into_iter()
+            .map(|topic| {
+                Self::non_flexible_response_topic(topic, &mut fetch_bytes, metadata_image, log_dirs)
+            })
+            .collect::<Vec<non_flexible::ResponseTopic<V>>>()
+            .into()
+    }
+
+    fn non_flexible_response_topic<const V: i16>(
+        topic: non_flexible::RequestTopic<V>,
+        fetch_bytes: &mut FetchBytes,
+        metadata_image: &MetadataImage,
+        log_dirs: &[PathBuf],
+    ) -> non_flexible::ResponseTopic<V> {
+        let partitions = Self::fetch_topic(
+            metadata_image.topic_by_name(topic.topic.bytes().unwrap_or_default()),
+            error_codes::UNKNOWN_TOPIC_OR_PARTITION,
+            topic.partitions.iter().map(|partition| FetchPartition {
+                partition_index: partition.partition,
+                fetch_offset: partition.fetch_offset,
+                partition_max_bytes: partition.partition_max_bytes,
+            }),
+            fetch_bytes,
+            metadata_image,
+            log_dirs,
+        )
+        .into_iter()
+        .map(|fetched| {
+            let preferred_read_replica = 0;
+            let (error_code, records) = match Self::records_bytes(V, fetched.records) {
+                Ok(records) => (fetched.error_code, records),
+                Err(e) => {
+                    error!(
+                        "Failed to convert records of partition {}: {e}",
+                        fetched.partition_index
+                    );
+                    (error_codes::CORRUPT_MESSAGE, Vec::new())
+                }
+            };
+            non_flexible::ResponsePartition {
+                partition_index: fetched.partition_index,
+                error_code,
+                high_watermark: fetched.high_watermark,
+                last_stable_offset: fetched.last_stable_offset,
+                log_start_offset: fetched.log_start_offset,
+                aborted_transactions: Array::empty(),
+                preferred_read_replica,
+                records: NullableBytes {
+                    bytes: Some(records),
+                },
+            }
+        })
+        .collect::<Vec<_>>()
+        .into();
+        non_flexible::ResponseTopic::new(topic.topic, partitions)
+    }
+
+    /// The records of the batches in the format the consumers of the version read: v2
+    /// batches from version 4 on, and down-converted message sets of magic 1 in versions 2
+    /// and 3 and of magic 0 before
+    fn records_bytes(api_version: i16, records: Vec<u8>) -> Result<Vec<u8>, DecodeError> {
+        match api_version {
+            FIRST_VERSION_WITH_RECORD_BATCHES.. => Ok(records),
+            FIRST_VERSION_WITH_MAGIC_V1.. => {
+                legacy_message_set::down_convert(&RecordBatch::from_bytes(&records)?, MAGIC_V1)
+            }
+            _ => legacy_message_set::down_convert(&RecordBatch::from_bytes(&records)?, MAGIC_V0),
         }
     }
 
-    fn metadata_file_contains(topic_id: Uuid, record_batches: &[RecordBatch]) -> bool {
-        record_batches.iter().any(|record_batch| {
-            !record_batch
-                .parse_record_values(SearchItem::TopicId(topic_id), true)
-                .is_empty()
+    /// Reads the requested partitions of the topic, in the order of the request
+    fn fetch_topic(
+        topic_record: Option<&TopicRecord>,
+        unknown_topic_error_code: i16,
+        partitions: impl IntoIterator<Item = FetchPartition>,
+        fetch_bytes: &mut FetchBytes,
+        metadata_image: &MetadataImage,
+        log_dirs: &[PathBuf],
+    ) -> Vec<FetchedPartition> {
+        partitions
+            .into_iter()
+            .map(|partition| {
+                let Some(topic_record) = topic_record else {
+                    return FetchedPartition::error(
+                        partition.partition_index,
+                        unknown_topic_error_code,
+                    );
+                };
+                if metadata_image
+                    .partition(&topic_record.topic_uuid, partition.partition_index)
+                    .is_none()
+                {
+                    return FetchedPartition::error(
+                        partition.partition_index,
+                        error_codes::UNKNOWN_TOPIC_OR_PARTITION,
+                    );
+                }
+                Self::read_partition(topic_record, &partition, fetch_bytes, log_dirs)
+                    .unwrap_or_else(|e| {
+                        error!(
+                            "Failed to read records of {}-{}: {e}",
+                            topic_record.topic_name, partition.partition_index
+                        );
+                        FetchedPartition::error(
+                            partition.partition_index,
+                            error_codes::KAFKA_STORAGE_ERROR,
+                        )
+                    })
+            })
+            .collect()
+    }
+
+    /// Reads the records of the partition from the fetch offset, within the bytes left to
+    /// fetch. Without replication the high watermark is the log end offset, and without
+    /// transactions the last stable offset is the high watermark. An offset before the log
+    /// start offset has been deleted by retention, and one past the high watermark hasn't been
+    /// written yet.
+    fn read_partition(
+        topic_record: &TopicRecord,
+        partition: &FetchPartition,
+        fetch_bytes: &mut FetchBytes,
+        log_dirs: &[PathBuf],
+    ) -> io::Result<FetchedPartition> {
+        let topic_name = topic_record.topic_name.to_string();
+        let log = Log::new(log_dirs, &topic_name, partition.partition_index);
+        let log_start_offset = log.log_start_offset()?;
+        let high_watermark = log.next_offset()?;
+        let (error_code, records) =
+            if (log_start_offset..=high_watermark).contains(&partition.fetch_offset) {
+                let records = log.read(
+                    partition.fetch_offset,
+                    fetch_bytes.limit(partition.partition_max_bytes),
+                    !fetch_bytes.batch_included,
+                )?;
+                fetch_bytes.consume(records.len());
+                (error_codes::NONE, records)
+            } else {
+                (error_codes::OFFSET_OUT_OF_RANGE, Vec::new())
+            };
+        Ok(FetchedPartition {
+            partition_index: partition.partition_index,
+            error_code,
+            high_watermark,
+            last_stable_offset: high_watermark,
+            log_start_offset,
+            records,
         })
     }
+}
 
-    fn get_records_from_data_log(
-        topic_id: Uuid,
-        metadata_record_batches: &[RecordBatch],
-        partition_index: i32,
-    ) -> CompactRecords {
-        if let Some(topic_record) = Self::get_topic_record(topic_id, metadata_record_batches) {
-            let topic_name = topic_record.topic_name.to_string();
-            let data_record_batches =
-                Self::get_record_batches_from_data_log(&topic_name, partition_index);
-            CompactRecords::from_record_batches(&data_record_batches)
-        } else {
-            CompactRecords::null()
+impl FetchedPartition {
+    /// A partition that couldn't be read, without offsets
+    fn error(partition_index: i32, error_code: i16) -> Self {
+        Self {
+            partition_index,
+            error_code,
+            high_watermark: UNKNOWN_OFFSET,
+            last_stable_offset: UNKNOWN_OFFSET,
+            log_start_offset: UNKNOWN_OFFSET,
+            records: Vec::new(),
         }
     }
+}
 
-    fn get_topic_record(topic_id: Uuid, record_batches: &[RecordBatch]) -> Option<TopicRecord> {
-        record_batches
+impl FetchBytes {
+    fn new(max_bytes: i32) -> Self {
+        Self {
+            remaining: max_bytes.max(0) as usize,
+            batch_included: false,
+        }
+    }
+
+    /// The bytes of batches a partition may read: its max bytes, within what's left of the
+    /// request's. The first batch is read whatever its size if none was included yet.
+    fn limit(&self, partition_max_bytes: i32) -> usize {
+        self.remaining.min(partition_max_bytes.max(0) as usize)
+    }
+
+    /// Counts the bytes of the batches a partition read
+    fn consume(&mut self, bytes: usize) {
+        self.remaining = self.remaining.saturating_sub(bytes);
+        self.batch_included |= bytes > 0;
+    }
+}
+
+impl ApiHandler for FetchApi {
+    fn api_key(&self) -> i16 {
+        FETCH
+    }
+
+    fn versions(&self) -> RangeInclusive<i16> {
+        fetch::MIN_VERSION..=fetch::MAX_VERSION
+    }
+
+    fn first_flexible_version(&self) -> i16 {
+        FIRST_FLEXIBLE_VERSION
+    }
+
+    fn handle(
+        &self,
+        request_header: RequestHeaderV2,
+        buf: &[u8],
+        body_offset: usize,
+        context: &RequestContext,
+    ) -> Result<Vec<u8>, DecodeError> {
+        let api_version = request_header.request_api_version;
+        let log_dirs = &context.server_config.log_dirs;
+        if api_version >= FIRST_FLEXIBLE_VERSION {
+            return Ok(with_version!(api_version, [12, 13, 14, 15, 16], |V| {
+                Self::respond::<V>(
+                    request_header,
+                    buf,
+                    body_offset,
+                    context.metadata_image,
+                    log_dirs,
+                )
+                .to_be_bytes()
+            }));
+        }
+        with_version!(api_version, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11], |V| {
+            Ok(Self::respond_non_flexible::<V>(
+                request_header,
+                buf,
+                body_offset,
+                context.metadata_image,
+                log_dirs,
+            )?
+            .to_be_bytes())
+        })
+    }
+
+    fn unsupported_version(
+        &self,
+        request_header: RequestHeaderV2,
+        _buf: &[u8],
+        _offset: usize,
+        _context: &RequestContext,
+    ) -> Vec<u8> {
+        Self::error_response(request_header, error_codes::UNSUPPORTED_VERSION).to_be_bytes()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::records::record_batch_builder::RecordBatchBuilder;
+    use crate::storage::log_config::LogConfig;
+    use crate::test_fixtures::{self, TempDir};
+
+    fn record_batch() -> RecordBatch {
+        RecordBatchBuilder::new(0)
+            .record(1000, None, Some(&[0x76; 100]), Vec::new())
+            .build()
+            .unwrap()
+    }
+
+    fn record_batches(count: usize) -> Vec<RecordBatch> {
+        (0..count).map(|_| record_batch()).collect()
+    }
+
+    /// Topic whose partition 0 holds 3 batches of a record each, in a new log directory removed
+    /// along with the returned guard
+    fn topic_with_3_batches() -> (TempDir, TopicRecord, Vec<PathBuf>) {
+        let log_dir = TempDir::new("fetch");
+        let log_dirs = vec![log_dir.path().to_path_buf()];
+        Log::new(&log_dirs, "fetch", 0)
+            .append(record_batches(3), &LogConfig::default())
+            .unwrap();
+        (
+            log_dir,
+            test_fixtures::topic_record("fetch", Uuid::new_v4()),
+            log_dirs,
+        )
+    }
+
+    fn fetch_partition(fetch_offset: i64, partition_max_bytes: i32) -> FetchPartition {
+        FetchPartition {
+            partition_index: 0,
+            fetch_offset,
+            partition_max_bytes,
+        }
+    }
+
+    fn base_offsets(records: &[u8]) -> Vec<i64> {
+        RecordBatch::from_bytes(records)
+            .unwrap()
             .iter()
-            .flat_map(|record_batch| {
-                record_batch.parse_record_values(SearchItem::TopicId(topic_id), true)
-            })
-            .filter_map(|record_value| record_value.to_topic_record())
-            .next()
+            .map(|record_batch| record_batch.base_offset)
+            .collect()
+    }
+
+    #[test]
+    fn reads_from_fetch_offset_up_to_partition_max_bytes() {
+        let (_log_dir, topic_record, log_dirs) = topic_with_3_batches();
+        let batch_size = record_batch().size() as i32;
+
+        let fetched = FetchApi::read_partition(
+            &topic_record,
+            &fetch_partition(1, 2 * batch_size - 1),
+            &mut FetchBytes::new(i32::MAX),
+            &log_dirs,
+        )
+        .unwrap();
+
+        assert_eq!(error_codes::NONE, fetched.error_code);
+        assert_eq!(vec![1], base_offsets(&fetched.records));
+        assert_eq!(
+            (3, 3, 0),
+            (
+                fetched.high_watermark,
+                fetched.last_stable_offset,
+                fetched.log_start_offset
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_fetch_offset_outside_log() {
+        let (_log_dir, topic_record, log_dirs) = topic_with_3_batches();
+        let mut fetch_bytes = FetchBytes::new(i32::MAX);
+        let mut read = |fetch_offset| {
+            let fetched = FetchApi::read_partition(
+                &topic_record,
+                &fetch_partition(fetch_offset, i32::MAX),
+                &mut fetch_bytes,
+                &log_dirs,
+            )
+            .unwrap();
+            (fetched.error_code, base_offsets(&fetched.records).len())
+        };
+
+        assert_eq!((error_codes::OFFSET_OUT_OF_RANGE, 0), read(-1));
+        assert_eq!((error_codes::OFFSET_OUT_OF_RANGE, 0), read(4));
+        assert_eq!((error_codes::NONE, 0), read(3));
     }
 
-    fn get_record_batches_from_data_log(
-        topic_name: &str,
-        partition_index: i32,
-    ) -> Vec<RecordBatch> {
-        get_record_batches_from_log_file(format!("{topic_name}-{partition_index}").as_str())
+    #[test]
+    fn shares_max_bytes_between_partitions() {
+        let (_log_dir, topic_record, log_dirs) = topic_with_3_batches();
+        let batch_size = record_batch().size() as i32;
+        let mut fetch_bytes = FetchBytes::new(2 * batch_size);
+        let mut read = |partition_max_bytes| {
+            let fetched = FetchApi::read_partition(
+                &topic_record,
+                &fetch_partition(0, partition_max_bytes),
+                &mut fetch_bytes,
+                &log_dirs,
+            )
+            .unwrap();
+            base_offsets(&fetched.records)
+        };
+
+        let first = read(i32::MAX);
+        let second = read(i32::MAX);
+
+        assert_eq!((vec![0, 1], vec![]), (first, second));
+    }
+
+    #[test]
+    fn includes_first_batch_larger_than_limits() {
+        let (_log_dir, topic_record, log_dirs) = topic_with_3_batches();
+        let mut fetch_bytes = FetchBytes::new(10);
+        let mut read = |partition_max_bytes| {
+            let fetched = FetchApi::read_partition(
+                &topic_record,
+                &fetch_partition(0, partition_max_bytes),
+                &mut fetch_bytes,
+                &log_dirs,
+            )
+            .unwrap();
+            base_offsets(&fetched.records)
+        };
+
+        let first = read(10);
+        let second = read(10);
+
+        assert_eq!((vec![0], vec![]), (first, second));
     }
 }
@@ -1,23 +1,21 @@
-use std::any::Any;
+use kafka_derive::{ByteParsable, Serializable, Size};
 
-use crate::byte_parsable::ByteParsable;
-use crate::fetch::partition::RequestPartition;
-use crate::fetch::topic::{self, ForgottenTopicsDatum, RequestTopic};
-use crate::headers::request_header_v2::RequestHeaderV2;
-use crate::size::Size;
+use crate::fetch::topic::{ForgottenTopicsDatum, RequestTopic};
 use crate::tagged_fields_section::TaggedFieldsSection;
 use crate::types::compact_array::CompactArray;
 use crate::types::compact_string::CompactString;
 
-/// Fetch Request (Version: 16) => max_wait_ms min_bytes max_bytes isolation_level session_id session_epoch [topics] [forgotten_topics_data] rack_id _tagged_fields
+/// Fetch Request (Versions: 12-16) => replica_id max_wait_ms min_bytes max_bytes isolation_level session_id session_epoch [topics] [forgotten_topics_data] rack_id _tagged_fields
+///   replica_id => INT32 (versions 12-14)
 ///   max_wait_ms => INT32
 ///   min_bytes => INT32
 ///   max_bytes => INT32
 ///   isolation_level => INT8
 ///   session_id => INT32
 ///   session_epoch => INT32
-///   topics => topic_id [partitions] _tagged_fields
-///     topic_id => UUID
+///   topics => topic topic_id [partitions] _tagged_fields
+///     topic => COMPACT_STRING (version 12)
+///     topic_id => UUID (versions 13+)
 ///     partitions => partition current_leader_epoch fetch_offset last_fetched_epoch log_start_offset partition_max_bytes _tagged_fields
 ///       partition => INT32
 ///       current_leader_epoch => INT32
@@ -25,85 +23,36 @@ use crate::types::compact_string::CompactString;
 ///       last_fetched_epoch => INT32
 ///       log_start_offset => INT64
 ///       partition_max_bytes => INT32
-///   forgotten_topics_data => topic_id [partitions] _tagged_fields
-///     topic_id => UUID
+///   forgotten_topics_data => topic topic_id [partitions] _tagged_fields
+///     topic => COMPACT_STRING (version 12)
+///     topic_id => UUID (versions 13+)
 ///     partitions => INT32
 ///   rack_id => COMPACT_STRING
-#[derive(Debug)]
-pub struct FetchRequestV16 {
+#[derive(Debug, ByteParsable, Serializable, Size)]
+#[kafka(version = V)]
+pub struct FetchRequest<const V: i16> {
+    #[kafka(versions = "12-14")]
+    pub replica_id: i32,
     pub max_wait_ms: i32,
     pub min_bytes: i32,
     pub max_bytes: i32,
     pub isolation_level: i8,
     pub session_id: i32,
     pub session_epoch: i32,
-    pub topics: CompactArray<RequestTopic>,
-    pub forgotten_topics_data: CompactArray<ForgottenTopicsDatum>,
+    pub topics: CompactArray<RequestTopic<V>>,
+    pub forgotten_topics_data: CompactArray<ForgottenTopicsDatum<V>>,
     pub rack_id: CompactString,
+    #[kafka(tagged_fields)]
     pub _tagged_fields: TaggedFieldsSection,
 }
 
-impl Size for FetchRequestV16 {
-    fn size(&self) -> usize {
-        self.max_wait_ms.size()
-            + self.min_bytes.size()
-            + self.max_bytes.size()
-            + self.isolation_level.size()
-            + self.session_id.size()
-            + self.session_epoch.size()
-            + self.topics.size()
-            + self.forgotten_topics_data.size()
-            + self.rack_id.size()
-            + self._tagged_fields.size()
-    }
-}
-
-impl ByteParsable<FetchRequestV16> for FetchRequestV16 {
-    fn parse(bytes: &[u8], offset: usize) -> Self {
-        let mut offset: usize = offset;
-        let max_wait_ms = i32::parse(bytes, offset);
-        offset += max_wait_ms.size();
-        let min_bytes = i32::parse(bytes, offset);
-        offset += min_bytes.size();
-        let max_bytes = i32::parse(bytes, offset);
-        offset += max_bytes.size();
-        let isolation_level = i8::parse(bytes, offset);
-        offset += isolation_level.size();
-        let session_id = i32::parse(bytes, offset);
-        offset += session_id.size();
-        let session_epoch = i32::parse(bytes, offset);
-        offset += session_epoch.size();
-        let topics = CompactArray::<RequestTopic>::parse(bytes, offset);
-        offset += topics.size();
-        let forgotten_topics_data = CompactArray::<ForgottenTopicsDatum>::parse(bytes, offset);
-        offset += forgotten_topics_data.size();
-        let rack_id = CompactString::parse(bytes, offset);
-        offset += rack_id.size();
-        let _tagged_fields = TaggedFieldsSection::parse(bytes, offset);
-
-        Self {
-            max_wait_ms,
-            min_bytes,
-            max_bytes,
-            isolation_level,
-            session_id,
-            session_epoch,
-            topics,
-            forgotten_topics_data,
-            rack_id,
-            _tagged_fields,
-        }
-    }
-}
-
 #[cfg(test)]
 mod test {
     use uuid::Uuid;
 
     use crate::{
-        byte_parsable::ByteParsable,
-        fetch::fetch_request_v16::{self, FetchRequestV16},
-        tagged_fields_section::TaggedFieldsSection,
+        byte_parsable::ByteParsable, decode_error::DecodeErrorReason,
+        fetch::fetch_request::FetchRequest, tagged_fields_section::TaggedFieldsSection,
     };
 
     #[test]
@@ -139,7 +88,7 @@ mod test {
             0x00, // _tagged_fields
         ];
 
-        let fetch_request = FetchRequestV16::parse(bytes, 24);
+        let fetch_request = FetchRequest::<16>::parse(bytes, 24).unwrap();
 
         assert_eq!(500, fetch_request.max_wait_ms);
         assert_eq!(1, fetch_request.min_bytes);
@@ -168,4 +117,27 @@ mod test {
         );
         assert_eq!(TaggedFieldsSection::empty(), fetch_request._tagged_fields);
     }
+
+    #[test]
+    fn fails_to_parse_truncated_fetch_request() {
+        let bytes: &[u8] = &[
+            0x00, 0x00, 0x01, 0xf4, // max_wait_ms: 500
+            0x00, 0x00, 0x00, 0x01, // min_bytes: 1
+            0x03, 0x20, 0x00, 0x00, // max_bytes: 52428800
+            0x00, // isolation_level: 0
+            0x00, 0x00, // session_id: truncated
+        ];
+
+        let error = FetchRequest::<16>::parse(bytes, 0).unwrap_err();
+
+        assert_eq!(13, error.offset);
+        assert_eq!("i32", error.expected);
+        assert_eq!(
+            DecodeErrorReason::UnexpectedEof {
+                needed: 4,
+                available: 2
+            },
+            error.reason
+        );
+    }
 }
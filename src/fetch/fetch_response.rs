@@ -1,16 +1,16 @@
+use kafka_derive::{ByteParsable, Serializable, Size};
+
 use crate::fetch::topic::ResponseTopic;
-use crate::headers::response_header_v1::ResponseHeaderV1;
-use crate::serializable::Serializable;
-use crate::size::Size;
 use crate::tagged_fields_section::TaggedFieldsSection;
 use crate::types::compact_array::CompactArray;
 
-/// Fetch Response (Version: 16) => throttle_time_ms error_code session_id [responses] _tagged_fields
+/// Fetch Response (Versions: 12-16) => throttle_time_ms error_code session_id [responses] _tagged_fields
 ///   throttle_time_ms => INT32
 ///   error_code => INT16
 ///   session_id => INT32
-///   responses => topic_id [partitions] _tagged_fields
-///     topic_id => UUID
+///   responses => topic topic_id [partitions] _tagged_fields
+///     topic => COMPACT_STRING (version 12)
+///     topic_id => UUID (versions 13+)
 ///     partitions => partition_index error_code high_watermark last_stable_offset log_start_offset [aborted_transactions] preferred_read_replica records _tagged_fields
 ///       partition_index => INT32
 ///       error_code => INT16
@@ -21,24 +21,25 @@ use crate::types::compact_array::CompactArray;
 ///         producer_id => INT64 first_offset => INT64
 ///       preferred_read_replica => INT32
 ///       records => COMPACT_RECORDS
-#[derive(Debug, Clone)]
-pub struct FetchResponseV16 {
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+pub struct FetchResponse<const V: i16> {
     throttle_time_ms: i32,
     error_code: i16,
     session_id: i32,
-    responses: CompactArray<ResponseTopic>,
+    responses: CompactArray<ResponseTopic<V>>,
+    #[kafka(tagged_fields)]
     _tagged_fields: TaggedFieldsSection,
 }
 
-impl FetchResponseV16 {
+impl<const V: i16> FetchResponse<V> {
     pub fn new(
         throttle_time_ms: i32,
         error_code: i16,
         session_id: i32,
-        responses: CompactArray<ResponseTopic>,
+        responses: CompactArray<ResponseTopic<V>>,
         _tagged_fields: TaggedFieldsSection,
-    ) -> FetchResponseV16 {
-        FetchResponseV16 {
+    ) -> Self {
+        Self {
             throttle_time_ms,
             error_code,
             session_id,
@@ -48,31 +49,10 @@ impl FetchResponseV16 {
     }
 }
 
-impl Size for FetchResponseV16 {
-    fn size(&self) -> usize {
-        self.throttle_time_ms.size()
-            + self.error_code.size()
-            + self.session_id.size()
-            + self.responses.size()
-            + self._tagged_fields.size()
-    }
-}
-
-impl Serializable for FetchResponseV16 {
-    fn to_be_bytes(&self) -> Vec<u8> {
-        let mut bytes: Vec<u8> = Vec::new();
-        bytes.extend_from_slice(&self.throttle_time_ms.to_be_bytes());
-        bytes.extend_from_slice(&self.error_code.to_be_bytes());
-        bytes.extend_from_slice(&self.session_id.to_be_bytes());
-        bytes.extend_from_slice(&self.responses.to_be_bytes());
-        bytes.extend_from_slice(&self._tagged_fields.to_be_bytes());
-        bytes
-    }
-}
-
 #[cfg(test)]
 mod test {
     use crate::api_response::{self, ApiResponse};
+    use crate::serializable::Serializable;
 
     use super::*;
 
@@ -81,7 +61,7 @@ mod test {
         let expected_size = (4 + 1) + 4 + 2 + 4 + (1 + 0) + 1;
 
         let correlation_id = 1519289319; // 4 + 1 (tag buffer) bytes
-        let response = FetchResponseV16::new(
+        let response = FetchResponse::<16>::new(
             0,                            // 4 bytes
             0,                            // 2 bytes
             0,                            // 4 bytes
@@ -113,7 +93,7 @@ mod test {
         ];
 
         let correlation_id = 0; // 4 + 1 (tag buffer) bytes
-        let response = FetchResponseV16::new(
+        let response = FetchResponse::<16>::new(
             0,                            // 4 bytes
             0,                            // 2 bytes
             0,                            // 4 bytes
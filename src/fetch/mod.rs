@@ -2,7 +2,8 @@ pub const MIN_VERSION: i16 = 0;
 pub const MAX_VERSION: i16 = 16;
 
 pub mod fetch_api;
-pub mod fetch_request_v16;
-pub mod fetch_response_v16;
+pub mod fetch_request;
+pub mod fetch_response;
+pub mod non_flexible;
 pub mod partition;
 pub mod topic;
@@ -0,0 +1,157 @@
+//! Fetch requests and responses of the versions before the first flexible one, generic over
+//! their version. They have the fields of their version on the wire, with int length prefixes
+//! and no tagged fields.
+
+use kafka_derive::{ByteParsable, Serializable, Size};
+
+use crate::types::{array::Array, nullable_bytes::NullableBytes, nullable_string::NullableString};
+
+// https://kafka.apache.org/41/design/protocol/#The_Messages_Fetch
+
+/// Fetch Request (Versions: 0-11) => replica_id max_wait_ms min_bytes max_bytes isolation_level session_id session_epoch [topics] [forgotten_topics_data] rack_id
+///   replica_id => INT32
+///   max_wait_ms => INT32
+///   min_bytes => INT32
+///   max_bytes => INT32 (versions 3+)
+///   isolation_level => INT8 (versions 4+)
+///   session_id => INT32 (versions 7+)
+///   session_epoch => INT32 (versions 7+)
+///   topics => topic [partitions]
+///     topic => STRING
+///     partitions => partition current_leader_epoch fetch_offset log_start_offset partition_max_bytes
+///       partition => INT32
+///       current_leader_epoch => INT32 (versions 9+)
+///       fetch_offset => INT64
+///       log_start_offset => INT64 (versions 5+)
+///       partition_max_bytes => INT32
+///   forgotten_topics_data => topic [partitions] (versions 7+)
+///     topic => STRING
+///     partitions => INT32
+///   rack_id => STRING (versions 11+)
+#[allow(dead_code)]
+#[derive(Debug, ByteParsable, Serializable, Size)]
+#[kafka(version = V)]
+pub struct FetchRequest<const V: i16> {
+    pub replica_id: i32,
+    pub max_wait_ms: i32,
+    pub min_bytes: i32,
+    #[kafka(versions = "3+")]
+    pub max_bytes: i32,
+    #[kafka(versions = "4+")]
+    pub isolation_level: i8,
+    #[kafka(versions = "7+")]
+    pub session_id: i32,
+    #[kafka(versions = "7+")]
+    pub session_epoch: i32,
+    pub topics: Array<RequestTopic<V>>,
+    #[kafka(versions = "7+")]
+    pub forgotten_topics_data: Array<ForgottenTopic>,
+    #[kafka(versions = "11+")]
+    pub rack_id: NullableString,
+}
+
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+pub struct RequestTopic<const V: i16> {
+    pub topic: NullableString,
+    pub partitions: Array<RequestPartition<V>>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+#[kafka(version = V)]
+pub struct RequestPartition<const V: i16> {
+    pub partition: i32,
+    #[kafka(versions = "9+")]
+    pub current_leader_epoch: i32,
+    pub fetch_offset: i64,
+    #[kafka(versions = "5+")]
+    pub log_start_offset: i64,
+    pub partition_max_bytes: i32,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+pub struct ForgottenTopic {
+    topic: NullableString,
+    partitions: Array<i32>,
+}
+
+/// Fetch Response (Versions: 0-11) => throttle_time_ms error_code session_id [responses]
+///   throttle_time_ms => INT32 (versions 1+)
+///   error_code => INT16 (versions 7+)
+///   session_id => INT32 (versions 7+)
+///   responses => topic [partitions]
+///     topic => STRING
+///     partitions => partition_index error_code high_watermark last_stable_offset log_start_offset [aborted_transactions] preferred_read_replica records
+///       partition_index => INT32
+///       error_code => INT16
+///       high_watermark => INT64
+///       last_stable_offset => INT64 (versions 4+)
+///       log_start_offset => INT64 (versions 5+)
+///       aborted_transactions => producer_id first_offset (versions 4+)
+///         producer_id => INT64
+///         first_offset => INT64
+///       preferred_read_replica => INT32 (versions 11+)
+///       records => RECORDS
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+#[kafka(version = V)]
+pub struct FetchResponse<const V: i16> {
+    #[kafka(versions = "1+")]
+    throttle_time_ms: i32,
+    #[kafka(versions = "7+")]
+    error_code: i16,
+    #[kafka(versions = "7+")]
+    session_id: i32,
+    responses: Array<ResponseTopic<V>>,
+}
+
+impl<const V: i16> FetchResponse<V> {
+    pub fn new(
+        throttle_time_ms: i32,
+        error_code: i16,
+        session_id: i32,
+        responses: Array<ResponseTopic<V>>,
+    ) -> Self {
+        Self {
+            throttle_time_ms,
+            error_code,
+            session_id,
+            responses,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+pub struct ResponseTopic<const V: i16> {
+    topic: NullableString,
+    partitions: Array<ResponsePartition<V>>,
+}
+
+impl<const V: i16> ResponseTopic<V> {
+    pub fn new(topic: NullableString, partitions: Array<ResponsePartition<V>>) -> Self {
+        Self { topic, partitions }
+    }
+}
+
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+#[kafka(version = V)]
+pub struct ResponsePartition<const V: i16> {
+    pub partition_index: i32,
+    pub error_code: i16,
+    pub high_watermark: i64,
+    #[kafka(versions = "4+")]
+    pub last_stable_offset: i64,
+    #[kafka(versions = "5+")]
+    pub log_start_offset: i64,
+    #[kafka(versions = "4+")]
+    pub aborted_transactions: Array<AbortedTransaction>,
+    #[kafka(versions = "11+")]
+    pub preferred_read_replica: i32,
+    pub records: NullableBytes,
+}
+
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+pub struct AbortedTransaction {
+    producer_id: i64,
+    first_offset: i64,
+}
@@ -1,14 +1,10 @@
-use std::io::Cursor;
+use kafka_derive::{ByteParsable, Serializable, Size};
 
-use crate::byte_parsable::ByteParsable;
-use crate::fetch::partition;
-use crate::serializable::Serializable;
-use crate::size::{self, Size};
 use crate::tagged_fields_section::TaggedFieldsSection;
 use crate::types::compact_array::CompactArray;
 use crate::types::compact_records::CompactRecords;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
 pub struct RequestPartition {
     pub partition: i32,
     pub current_leader_epoch: i32,
@@ -16,57 +12,12 @@ pub struct RequestPartition {
     pub last_fetched_epoch: i32,
     pub log_start_offset: i64,
     pub partition_max_bytes: i32,
+    #[kafka(tagged_fields)]
     pub _tagged_fields: TaggedFieldsSection,
 }
 
-impl Size for RequestPartition {
-    fn size(&self) -> usize {
-        self.partition.size()
-            + self.current_leader_epoch.size()
-            + self.fetch_offset.size()
-            + self.last_fetched_epoch.size()
-            + self.log_start_offset.size()
-            + self.partition_max_bytes.size()
-            + self._tagged_fields.size()
-    }
-}
-
-impl ByteParsable<RequestPartition> for RequestPartition {
-    fn parse(bytes: &[u8], offset: usize) -> Self {
-        let mut offset = offset;
-        let partition = i32::parse(bytes, offset);
-        offset += partition.size();
-        let current_leader_epoch = i32::parse(bytes, offset);
-        offset += current_leader_epoch.size();
-        let fetch_offset = i64::parse(bytes, offset);
-        offset += fetch_offset.size();
-        let last_fetched_epoch = i32::parse(bytes, offset);
-        offset += last_fetched_epoch.size();
-        let log_start_offset = i64::parse(bytes, offset);
-        offset += log_start_offset.size();
-        let partition_max_bytes = i32::parse(bytes, offset);
-        offset += partition_max_bytes.size();
-        let _tagged_fields = TaggedFieldsSection::parse(bytes, offset);
-        Self {
-            partition,
-            current_leader_epoch,
-            fetch_offset,
-            last_fetched_epoch,
-            log_start_offset,
-            partition_max_bytes,
-            _tagged_fields,
-        }
-    }
-}
-
-impl Serializable for RequestPartition {
-    fn to_be_bytes(&self) -> Vec<u8> {
-        todo!()
-    }
-}
-
 #[allow(dead_code)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
 pub struct ResponsePartition {
     pub partition_index: i32,
     pub error_code: i16,
@@ -76,76 +27,117 @@ pub struct ResponsePartition {
     pub aborted_transactions: CompactArray<Transaction>,
     pub preferred_read_replica: i32,
     pub records: CompactRecords,
+    #[kafka(tagged_fields)]
     pub _tagged_fields: TaggedFieldsSection,
 }
 
-impl Size for ResponsePartition {
-    fn size(&self) -> usize {
-        self.partition_index.size()
-            + self.error_code.size()
-            + self.high_watermark.size()
-            + self.last_stable_offset.size()
-            + self.log_start_offset.size()
-            + self.aborted_transactions.size()
-            + self.preferred_read_replica.size()
-            + self.records.size()
-            + self._tagged_fields.size()
+// Tags of the tagged fields of a response partition
+pub const DIVERGING_EPOCH: u32 = 0;
+pub const CURRENT_LEADER: u32 = 1;
+
+#[allow(dead_code)]
+impl ResponsePartition {
+    /// Sets the largest epoch and its end offset such that subsequent records are known to
+    /// diverge
+    pub fn set_diverging_epoch(&mut self, diverging_epoch: EpochEndOffset) {
+        self._tagged_fields.set(DIVERGING_EPOCH, &diverging_epoch);
     }
-}
 
-impl Serializable for ResponsePartition {
-    fn to_be_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        bytes.extend_from_slice(&self.partition_index.to_be_bytes());
-        bytes.extend_from_slice(&self.error_code.to_be_bytes());
-        bytes.extend_from_slice(&self.high_watermark.to_be_bytes());
-        bytes.extend_from_slice(&self.last_stable_offset.to_be_bytes());
-        bytes.extend_from_slice(&self.log_start_offset.to_be_bytes());
-        bytes.extend_from_slice(&self.aborted_transactions.to_be_bytes());
-        bytes.extend_from_slice(&self.preferred_read_replica.to_be_bytes());
-        bytes.extend_from_slice(&self.records.to_be_bytes());
-        bytes.extend_from_slice(&self._tagged_fields.to_be_bytes());
-        bytes
+    /// Sets the current leader of the partition, sent along with leadership errors
+    pub fn set_current_leader(&mut self, current_leader: LeaderIdAndEpoch) {
+        self._tagged_fields.set(CURRENT_LEADER, &current_leader);
     }
 }
 
-impl ByteParsable<ResponsePartition> for ResponsePartition {
-    fn parse(_bytes: &[u8], _offset: usize) -> ResponsePartition {
-        todo!()
-    }
+#[derive(Debug, Clone, PartialEq, ByteParsable, Serializable, Size)]
+pub struct EpochEndOffset {
+    pub epoch: i32,
+    pub end_offset: i64,
+    #[kafka(tagged_fields)]
+    pub _tagged_fields: TaggedFieldsSection,
 }
 
-#[derive(Debug, Clone)]
-pub struct Transaction {
-    producer_id: i64,
-    first_offset: i64,
-    _tagged_fields: TaggedFieldsSection,
+#[derive(Debug, Clone, PartialEq, ByteParsable, Serializable, Size)]
+pub struct LeaderIdAndEpoch {
+    pub leader_id: i32,
+    pub leader_epoch: i32,
+    #[kafka(tagged_fields)]
+    pub _tagged_fields: TaggedFieldsSection,
 }
 
-impl Size for Transaction {
-    fn size(&self) -> usize {
-        self.producer_id.size() + self.first_offset.size() + self._tagged_fields.size()
+#[allow(dead_code)]
+impl EpochEndOffset {
+    pub fn new(epoch: i32, end_offset: i64) -> Self {
+        Self {
+            epoch,
+            end_offset,
+            _tagged_fields: TaggedFieldsSection::empty(),
+        }
     }
 }
 
-impl ByteParsable<Transaction> for Transaction {
-    fn parse(_bytes: &[u8], _offset: usize) -> Transaction {
-        todo!()
+#[allow(dead_code)]
+impl LeaderIdAndEpoch {
+    pub fn new(leader_id: i32, leader_epoch: i32) -> Self {
+        Self {
+            leader_id,
+            leader_epoch,
+            _tagged_fields: TaggedFieldsSection::empty(),
+        }
     }
 }
 
-impl Serializable for Transaction {
-    fn to_be_bytes(&self) -> Vec<u8> {
-        todo!()
-    }
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+pub struct Transaction {
+    producer_id: i64,
+    first_offset: i64,
+    #[kafka(tagged_fields)]
+    _tagged_fields: TaggedFieldsSection,
 }
 
 #[cfg(test)]
 mod test {
-    use crate::error_codes::UNKNOWN_TOPIC_ID;
+    use crate::byte_parsable::ByteParsable;
+    use crate::error_codes::{NONE, UNKNOWN_TOPIC_ID};
+    use crate::serializable::Serializable;
+    use crate::size::Size;
 
     use super::*;
 
+    fn response_partition(error_code: i16) -> ResponsePartition {
+        ResponsePartition {
+            partition_index: 0,
+            error_code,
+            high_watermark: 0,
+            last_stable_offset: 0,
+            log_start_offset: 0,
+            aborted_transactions: CompactArray::empty(),
+            preferred_read_replica: -1,
+            records: CompactRecords::null(),
+            _tagged_fields: TaggedFieldsSection::empty(),
+        }
+    }
+
+    #[test]
+    fn round_trips_current_leader_and_diverging_epoch() {
+        let mut partition = response_partition(NONE);
+        partition.set_current_leader(LeaderIdAndEpoch::new(1, 5));
+        partition.set_diverging_epoch(EpochEndOffset::new(4, 100));
+
+        let bytes = partition.to_be_bytes();
+        let parsed = ResponsePartition::parse(&bytes, 0).unwrap();
+
+        assert_eq!(bytes.len(), partition.size());
+        assert_eq!(
+            Ok(Some(LeaderIdAndEpoch::new(1, 5))),
+            parsed._tagged_fields.get(CURRENT_LEADER)
+        );
+        assert_eq!(
+            Ok(Some(EpochEndOffset::new(4, 100))),
+            parsed._tagged_fields.get(DIVERGING_EPOCH)
+        );
+    }
+
     #[test]
     fn computes_message_size() {
         let expected_size = 4 + 2 + 8 + 8 + 8 + (1 + 0) + 4 + 1 + 1;
@@ -1,63 +1,49 @@
+use kafka_derive::{ByteParsable, Serializable, Size};
 use uuid::Uuid;
 
 use super::partition::{RequestPartition, ResponsePartition};
-use crate::byte_parsable::ByteParsable;
 use crate::serializable::Serializable;
-use crate::size::Size;
-use crate::tagged_fields_section::{self, TaggedFieldsSection};
+use crate::tagged_fields_section::TaggedFieldsSection;
 use crate::types::compact_array::CompactArray;
+use crate::types::compact_string::CompactString;
+
+// Topics are named up to version 12 and identified by their id from version 13 on
 
 #[allow(dead_code)]
-#[derive(Debug, Clone)]
-pub struct RequestTopic {
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+#[kafka(version = V)]
+pub struct RequestTopic<const V: i16> {
+    #[kafka(versions = "12")]
+    pub topic: CompactString,
+    #[kafka(versions = "13+")]
     pub topic_id: Uuid, // v4 128 bits (16 bytes) UUID
     pub partitions: CompactArray<RequestPartition>,
+    #[kafka(tagged_fields)]
     pub _tagged_fields: TaggedFieldsSection,
 }
 
-impl Size for RequestTopic {
-    fn size(&self) -> usize {
-        self.topic_id.size() + self.partitions.size() + self._tagged_fields.size()
-    }
-}
-
-impl ByteParsable<RequestTopic> for RequestTopic {
-    fn parse(bytes: &[u8], offset: usize) -> Self {
-        let mut offset = offset;
-        let topic_id = Uuid::parse(bytes, offset);
-        offset += topic_id.size();
-        let partitions = CompactArray::<RequestPartition>::parse(bytes, offset);
-        offset += partitions.size();
-        let _tagged_fields = TaggedFieldsSection::parse(bytes, offset);
-        Self {
-            topic_id,
-            partitions,
-            _tagged_fields,
-        }
-    }
-}
-
-impl Serializable for RequestTopic {
-    fn to_be_bytes(&self) -> Vec<u8> {
-        todo!()
-    }
-}
-
 #[allow(dead_code)]
-#[derive(Debug, Clone)]
-pub struct ResponseTopic {
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+#[kafka(version = V)]
+pub struct ResponseTopic<const V: i16> {
+    #[kafka(versions = "12")]
+    topic: CompactString,
+    #[kafka(versions = "13+")]
     topic_id: Uuid, // v4 128 bits (16 bytes) UUID
     partitions: CompactArray<ResponsePartition>,
+    #[kafka(tagged_fields)]
     _tagged_fields: TaggedFieldsSection,
 }
 
-impl ResponseTopic {
+impl<const V: i16> ResponseTopic<V> {
     pub fn new(
+        topic: CompactString,
         topic_id: Uuid,
         partitions: CompactArray<ResponsePartition>,
         _tagged_fields: TaggedFieldsSection,
     ) -> Self {
         Self {
+            topic,
             topic_id,
             partitions,
             _tagged_fields,
@@ -65,79 +51,36 @@ impl ResponseTopic {
     }
 }
 
-impl Size for ResponseTopic {
-    fn size(&self) -> usize {
-        self.topic_id.size() + self.partitions.size() + self._tagged_fields.size()
-    }
-}
-
-impl Serializable for ResponseTopic {
-    fn to_be_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        bytes.extend_from_slice(&self.topic_id.to_be_bytes());
-        bytes.extend_from_slice(&self.partitions.to_be_bytes());
-        bytes.extend_from_slice(&self._tagged_fields.to_be_bytes());
-        bytes
-    }
-}
-
 impl Serializable for Uuid {
     fn to_be_bytes(&self) -> Vec<u8> {
         self.as_bytes().to_vec()
     }
 }
 
-impl ByteParsable<ResponseTopic> for ResponseTopic {
-    fn parse(_bytes: &[u8], _offset: usize) -> ResponseTopic {
-        todo!()
-    }
-}
-
 #[allow(dead_code)]
-#[derive(Debug, Clone)]
-pub struct ForgottenTopicsDatum {
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+#[kafka(version = V)]
+pub struct ForgottenTopicsDatum<const V: i16> {
+    #[kafka(versions = "12")]
+    topic: CompactString,
+    #[kafka(versions = "13+")]
     topic_id: Uuid, // v4 128 bits (16 bytes) UUID
     partitions: CompactArray<i32>,
+    #[kafka(tagged_fields)]
     _tagged_fields: TaggedFieldsSection,
 }
 
-impl Size for ForgottenTopicsDatum {
-    fn size(&self) -> usize {
-        self.topic_id.size() + self.partitions.size() + self._tagged_fields.size()
-    }
-}
-
-impl ByteParsable<ForgottenTopicsDatum> for ForgottenTopicsDatum {
-    fn parse(bytes: &[u8], offset: usize) -> ForgottenTopicsDatum {
-        let mut offset = offset;
-        let topic_id = Uuid::parse(bytes, offset);
-        offset += topic_id.size();
-        let partitions = CompactArray::<i32>::parse(bytes, offset);
-        offset += partitions.size();
-        let _tagged_fields = TaggedFieldsSection::parse(bytes, offset);
-        Self {
-            topic_id,
-            partitions,
-            _tagged_fields,
-        }
-    }
-}
-
-impl Serializable for ForgottenTopicsDatum {
-    fn to_be_bytes(&self) -> Vec<u8> {
-        todo!()
-    }
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::size::Size;
 
     #[test]
     fn computes_message_size() {
         let expected_size = 16 + (1 + 0) + 1;
 
-        let topic = ResponseTopic::new(
+        let topic = ResponseTopic::<16>::new(
+            CompactString::from(""),      // 0 bytes
             Uuid::new_v4(),               // 16 bytes
             CompactArray::empty(),        // 1 byte
             TaggedFieldsSection::empty(), // 1 byte
@@ -0,0 +1,207 @@
+use std::io::{self, ErrorKind, Read};
+
+use thiserror::Error;
+use tracing::debug;
+
+use crate::byte_parsable::ByteParsable;
+use crate::decode_error::DecodeError;
+
+const MESSAGE_SIZE_LENGTH: usize = size_of::<i32>();
+const READ_CHUNK_SIZE: usize = 4096;
+
+#[derive(Debug, Error)]
+pub enum FrameError {
+    #[error("request of {size} byte(s) exceeds the maximum request size of {max_size} byte(s)")]
+    TooLarge { size: usize, max_size: usize },
+    #[error("invalid message_size {0}")]
+    InvalidSize(i32),
+    #[error("connection closed with {0} byte(s) of an incomplete request buffered")]
+    Truncated(usize),
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Splits a byte stream into requests using each request's `message_size` prefix.
+///
+/// Every frame returned contains exactly one request, including its `message_size`, no matter
+/// how the bytes were split across reads. With a non-blocking reader, `next_frame` fails with
+/// `ErrorKind::WouldBlock` when no complete request is available yet, keeping what it has read.
+pub struct FrameReader<R: Read> {
+    reader: R,
+    buf: Vec<u8>,
+    max_request_size: usize,
+}
+
+impl<R: Read> FrameReader<R> {
+    pub fn new(reader: R, max_request_size: usize) -> Self {
+        Self {
+            reader,
+            buf: Vec::new(),
+            max_request_size,
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Returns the next complete request, or `None` once the peer has closed the connection
+    /// between requests.
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>, FrameError> {
+        loop {
+            if let Some(frame) = self.take_buffered_frame()? {
+                return Ok(Some(frame));
+            }
+            let mut chunk = [0u8; READ_CHUNK_SIZE];
+            match self.reader.read(&mut chunk) {
+                Ok(0) if self.buf.is_empty() => return Ok(None),
+                Ok(0) => return Err(FrameError::Truncated(self.buf.len())),
+                Ok(n) => {
+                    debug!("Read {n} byte(s)");
+                    self.buf.extend_from_slice(&chunk[..n]);
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    fn take_buffered_frame(&mut self) -> Result<Option<Vec<u8>>, FrameError> {
+        if self.buf.len() < MESSAGE_SIZE_LENGTH {
+            return Ok(None);
+        }
+        let message_size = i32::parse(&self.buf, 0)?;
+        if message_size < 0 {
+            return Err(FrameError::InvalidSize(message_size));
+        }
+        let message_size = message_size as usize;
+        if message_size > self.max_request_size {
+            return Err(FrameError::TooLarge {
+                size: message_size,
+                max_size: self.max_request_size,
+            });
+        }
+        let frame_size = MESSAGE_SIZE_LENGTH + message_size;
+        if self.buf.len() < frame_size {
+            self.buf.reserve(frame_size - self.buf.len());
+            return Ok(None);
+        }
+        Ok(Some(self.buf.drain(..frame_size).collect()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+
+    use super::*;
+
+    const MAX_REQUEST_SIZE: usize = 1024;
+
+    /// Hands out the given chunks one read at a time, as a socket would
+    struct ChunkedReader {
+        chunks: VecDeque<Vec<u8>>,
+    }
+
+    impl ChunkedReader {
+        fn new(chunks: &[&[u8]]) -> Self {
+            Self {
+                chunks: chunks.iter().map(|chunk| chunk.to_vec()).collect(),
+            }
+        }
+    }
+
+    impl Read for ChunkedReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.chunks.pop_front() {
+                Some(chunk) => {
+                    buf[..chunk.len()].copy_from_slice(&chunk);
+                    Ok(chunk.len())
+                }
+                None => Ok(0),
+            }
+        }
+    }
+
+    fn frame(message: &[u8]) -> Vec<u8> {
+        let mut frame = (message.len() as i32).to_be_bytes().to_vec();
+        frame.extend_from_slice(message);
+        frame
+    }
+
+    #[test]
+    fn reads_single_request() {
+        let request = frame(&[0x00, 0x12, 0x00, 0x04]);
+        let mut frame_reader = FrameReader::new(request.as_slice(), MAX_REQUEST_SIZE);
+
+        assert_eq!(Some(request.clone()), frame_reader.next_frame().unwrap());
+        assert_eq!(None, frame_reader.next_frame().unwrap());
+    }
+
+    #[test]
+    fn reads_pipelined_requests_from_one_read() {
+        let first = frame(&[0x01, 0x02]);
+        let second = frame(&[0x03, 0x04, 0x05]);
+        let bytes = [first.clone(), second.clone()].concat();
+        let mut frame_reader = FrameReader::new(ChunkedReader::new(&[&bytes]), MAX_REQUEST_SIZE);
+
+        assert_eq!(Some(first), frame_reader.next_frame().unwrap());
+        assert_eq!(Some(second), frame_reader.next_frame().unwrap());
+        assert_eq!(None, frame_reader.next_frame().unwrap());
+    }
+
+    #[test]
+    fn reassembles_request_split_across_reads() {
+        let request = frame(&[0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let chunks: [&[u8]; 3] = [&request[..2], &request[2..7], &request[7..]];
+        let mut frame_reader = FrameReader::new(ChunkedReader::new(&chunks), MAX_REQUEST_SIZE);
+
+        assert_eq!(Some(request.clone()), frame_reader.next_frame().unwrap());
+    }
+
+    #[test]
+    fn reads_request_larger_than_read_chunk() {
+        let request = frame(&vec![0xab; READ_CHUNK_SIZE * 3]);
+        let mut frame_reader = FrameReader::new(request.as_slice(), usize::MAX);
+
+        assert_eq!(Some(request.clone()), frame_reader.next_frame().unwrap());
+    }
+
+    #[test]
+    fn rejects_request_over_max_size() {
+        let request = frame(&[0x00; MAX_REQUEST_SIZE + 1]);
+        let mut frame_reader = FrameReader::new(request.as_slice(), MAX_REQUEST_SIZE);
+
+        assert!(matches!(
+            frame_reader.next_frame(),
+            Err(FrameError::TooLarge {
+                size: 1025,
+                max_size: MAX_REQUEST_SIZE
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_negative_message_size() {
+        let bytes = (-1i32).to_be_bytes();
+        let mut frame_reader = FrameReader::new(bytes.as_slice(), MAX_REQUEST_SIZE);
+
+        assert!(matches!(
+            frame_reader.next_frame(),
+            Err(FrameError::InvalidSize(-1))
+        ));
+    }
+
+    #[test]
+    fn reports_connection_closed_mid_request() {
+        let request = frame(&[0x01, 0x02, 0x03]);
+        let mut frame_reader = FrameReader::new(&request[..5], MAX_REQUEST_SIZE);
+
+        assert!(matches!(
+            frame_reader.next_frame(),
+            Err(FrameError::Truncated(5))
+        ));
+    }
+}
@@ -1,6 +1,7 @@
-use crate::byte_parsable::ByteParsable;
-use crate::size::Size;
-use crate::tagged_fields_section::{self, TaggedFieldsSection, EMPTY};
+use kafka_derive::{ByteParsable, Serializable, Size};
+
+use crate::messages::request_header::v1::RequestHeader as RequestHeaderV1;
+use crate::tagged_fields_section::{self, TaggedFieldsSection};
 use crate::types::nullable_string::{self, NullableString};
 
 /// Request Header v2 => request_api_key request_api_version correlation_id client_id _tagged_fields
@@ -9,13 +10,14 @@ use crate::types::nullable_string::{self, NullableString};
 ///   correlation_id => INT32
 ///   client_id => NULLABLE_STRING
 #[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, ByteParsable, Serializable, Size)]
 pub struct RequestHeaderV2 {
     pub message_size: i32,
     pub request_api_key: i16,
     pub request_api_version: i16,
     pub correlation_id: i32,
     pub client_id: NullableString,
+    #[kafka(tagged_fields)]
     pub _tagged_fields: TaggedFieldsSection,
 }
 
@@ -28,41 +30,21 @@ impl RequestHeaderV2 {
             + nullable_string::LENGTH
             + tagged_fields_section::EMPTY
     }
-}
 
-impl Size for RequestHeaderV2 {
-    fn size(&self) -> usize {
-        self.message_size.size()
-            + self.request_api_key.size()
-            + self.request_api_version.size()
-            + self.correlation_id.size()
-            + self.client_id.size()
-            + self._tagged_fields.size()
+    /// Size of the shortest v1 header, the one of the requests before the first flexible version
+    pub fn min_v1_size() -> usize {
+        Self::min_size() - tagged_fields_section::EMPTY
     }
-}
-
-impl ByteParsable<RequestHeaderV2> for RequestHeaderV2 {
-    fn parse(bytes: &[u8], offset: usize) -> RequestHeaderV2 {
-        let mut offset = offset;
-        let message_size = i32::parse(bytes, offset);
-        offset += message_size.size();
-        let request_api_key = i16::parse(bytes, offset);
-        offset += request_api_key.size();
-        let request_api_version = i16::parse(bytes, offset);
-        offset += request_api_key.size();
-        let correlation_id = i32::parse(bytes, offset);
-        offset += correlation_id.size();
-        let client_id = NullableString::parse(bytes, offset);
-        offset += client_id.size();
-        let _tagged_fields = TaggedFieldsSection::parse(bytes, offset);
 
-        RequestHeaderV2 {
+    /// Header of a request sent with a v1 header, which has no tagged fields
+    pub fn from_v1(message_size: i32, request_header_v1: RequestHeaderV1) -> Self {
+        Self {
             message_size,
-            request_api_key,
-            request_api_version,
-            correlation_id,
-            client_id,
-            _tagged_fields,
+            request_api_key: request_header_v1.request_api_key,
+            request_api_version: request_header_v1.request_api_version,
+            correlation_id: request_header_v1.correlation_id,
+            client_id: request_header_v1.client_id,
+            _tagged_fields: TaggedFieldsSection::empty(),
         }
     }
 }
@@ -70,6 +52,7 @@ impl ByteParsable<RequestHeaderV2> for RequestHeaderV2 {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::byte_parsable::ByteParsable;
 
     // Header Bytes
     const MESSAGE_SIZE: usize = 4;
@@ -112,7 +95,7 @@ mod test {
             // 0xff, 0xff, // -1
             0x00, 0x09, 0x6b, 0x61, 0x66, 0x6b, 0x61, 0x2d, 0x63, 0x6c, 0x69, 0x00,
         ];
-        let request_header = RequestHeaderV2::parse(bytes, 0);
+        let request_header = RequestHeaderV2::parse(bytes, 0).unwrap();
         assert_eq!(request_header.message_size, 35);
         assert_eq!(request_header.request_api_key, 18);
         assert_eq!(request_header.request_api_version, 26442);
@@ -1,6 +1,6 @@
-use crate::{serializable::Serializable, size::Size};
+use kafka_derive::{ByteParsable, Serializable, Size};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
 pub struct ResponseHeaderV0 {
     pub correlation_id: i32,
 }
@@ -10,15 +10,3 @@ impl ResponseHeaderV0 {
         Self { correlation_id }
     }
 }
-
-impl Size for ResponseHeaderV0 {
-    fn size(&self) -> usize {
-        self.correlation_id.size()
-    }
-}
-
-impl Serializable for ResponseHeaderV0 {
-    fn to_be_bytes(&self) -> Vec<u8> {
-        self.correlation_id.to_be_bytes().to_vec()
-    }
-}
@@ -1,10 +1,11 @@
-use crate::serializable::Serializable;
-use crate::size::Size;
+use kafka_derive::{ByteParsable, Serializable, Size};
+
 use crate::tagged_fields_section::TaggedFieldsSection;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
 pub struct ResponseHeaderV1 {
     pub correlation_id: i32,
+    #[kafka(tagged_fields)]
     _tagged_fields: TaggedFieldsSection,
 }
 
@@ -16,18 +17,3 @@ impl ResponseHeaderV1 {
         }
     }
 }
-
-impl Size for ResponseHeaderV1 {
-    fn size(&self) -> usize {
-        self.correlation_id.size() + self._tagged_fields.size()
-    }
-}
-
-impl Serializable for ResponseHeaderV1 {
-    fn to_be_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        bytes.extend_from_slice(&self.correlation_id.to_be_bytes());
-        bytes.extend_from_slice(&self._tagged_fields.to_be_bytes());
-        bytes
-    }
-}
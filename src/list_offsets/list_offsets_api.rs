@@ -0,0 +1,514 @@
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+use tracing::{error, warn};
+
+use crate::{
+    api_handler::{ApiHandler, RequestContext},
+    api_keys::LIST_OFFSETS,
+    api_response::{self, ApiResponse},
+    byte_parsable::ByteParsable,
+    decode_error::DecodeError,
+    error_codes,
+    headers::{
+        request_header_v2::RequestHeaderV2, response_header_v0::ResponseHeaderV0,
+        response_header_v1::ResponseHeaderV1,
+    },
+    list_offsets::{self, non_flexible},
+    messages::{
+        list_offsets_request::v9::{ListOffsetsPartition, ListOffsetsRequest, ListOffsetsTopic},
+        list_offsets_request::FIRST_FLEXIBLE_VERSION,
+        list_offsets_response::v9::{
+            ListOffsetsPartitionResponse, ListOffsetsResponse, ListOffsetsTopicResponse,
+        },
+    },
+    metadata_image::MetadataImage,
+    records::partition_record::PartitionRecord,
+    serializable::Serializable,
+    storage::{log::Log, log_segment::TimestampAndOffset},
+    tagged_fields_section::TaggedFieldsSection,
+    types::{array::Array, compact_array::CompactArray},
+    with_version,
+};
+
+// Timestamps that stand for an offset rather than a point in time
+const LATEST_TIMESTAMP: i64 = -1;
+const EARLIEST_TIMESTAMP: i64 = -2;
+const MAX_TIMESTAMP: i64 = -3;
+const EARLIEST_LOCAL_TIMESTAMP: i64 = -4;
+const LATEST_TIERED_TIMESTAMP: i64 = -5;
+
+const NO_TIMESTAMP: i64 = -1;
+const NO_OFFSET: i64 = -1;
+const NO_LEADER_EPOCH: i32 = -1;
+
+/// Versions 6 to 9 have the same fields, so all of them are handled with the version 9 messages
+pub struct ListOffsetsApi;
+
+impl ListOffsetsApi {
+    pub fn respond(
+        request_header: RequestHeaderV2,
+        buf: &[u8],
+        body_offset: usize,
+        metadata_image: &MetadataImage,
+        log_dirs: &[PathBuf],
+    ) -> Result<ApiResponse<ResponseHeaderV1, ListOffsetsResponse>, DecodeError> {
+        let list_offsets_request = ListOffsetsRequest::parse(buf, body_offset)?;
+        let topics = Self::responses(list_offsets_request.topics, metadata_image, log_dirs);
+        Ok(Self::response(request_header, topics))
+    }
+
+    /// Responds with the error for every partition of the request, as far as it can be decoded
+    pub fn error_response(
+        request_header: RequestHeaderV2,
+        buf: &[u8],
+        body_offset: usize,
+        error_code: i16,
+    ) -> ApiResponse<ResponseHeaderV1, ListOffsetsResponse> {
+        let topics = ListOffsetsRequest::parse(buf, body_offset)
+            .map(|list_offsets_request| {
+                list_offsets_request
+                    .topics
+                    .into_iter()
+                    .map(|topic| Self::error_topic_response(topic, error_code))
+                    .collect::<Vec<ListOffsetsTopicResponse>>()
+                    .into()
+            })
+            .unwrap_or_else(|_| CompactArray::empty());
+        Self::response(request_header, topics)
+    }
+
+    /// Responds to a version before the first flexible one with the error for every partition of
+    /// the request, as far as it can be decoded, in the body of that version
+    pub fn non_flexible_error_response<const V: i16>(
+        request_header: RequestHeaderV2,
+        buf: &[u8],
+        body_offset: usize,
+        error_code: i16,
+    ) -> ApiResponse<ResponseHeaderV0, non_flexible::ListOffsetsResponse<V>> {
+        let topics = non_flexible::ListOffsetsRequest::<V>::parse(buf, body_offset)
+            .map(|list_offsets_request| {
+                list_offsets_request
+                    .topics
+                    .into_iter()
+                    .map(|topic| {
+                        let partitions = topic
+                            .partitions
+                            .into_iter()
+                            .map(|partition| {
+                                non_flexible::ResponsePartition::error(
+                                    partition.partition_index,
+                                    error_code,
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                            .into();
+                        non_flexible::ResponseTopic::new(topic.name, partitions)
+                    })
+                    .collect::<Vec<_>>()
+                    .into()
+            })
+            .unwrap_or_else(|_| Array::empty());
+        let throttle_time_ms = 0;
+        let response = non_flexible::ListOffsetsResponse::new(throttle_time_ms, topics);
+        api_response::v0(request_header.correlation_id, response)
+    }
+
+    fn response(
+        request_header: RequestHeaderV2,
+        topics: CompactArray<ListOffsetsTopicResponse>,
+    ) -> ApiResponse<ResponseHeaderV1, ListOffsetsResponse> {
+        let response = ListOffsetsResponse {
+            throttle_time_ms: 0,
+            topics,
+            _tagged_fields: TaggedFieldsSection::empty(),
+        };
+        api_response::v1(request_header.correlation_id, response)
+    }
+
+    fn error_topic_response(topic: ListOffsetsTopic, error_code: i16) -> ListOffsetsTopicResponse {
+        let partitions = topic
+            .partitions
+            .into_iter()
+            .map(|partition| Self::partition_response(&partition, error_code, None))
+            .collect::<Vec<ListOffsetsPartitionResponse>>()
+            .into();
+        ListOffsetsTopicResponse {
+            name: topic.name,
+            partitions,
+            _tagged_fields: TaggedFieldsSection::empty(),
+        }
+    }
+
+    fn responses(
+        topics: CompactArray<ListOffsetsTopic>,
+        metadata_image: &MetadataImage,
+        log_dirs: &[PathBuf],
+    ) -> CompactArray<ListOffsetsTopicResponse> {
+        topics
+            .into_iter()
+            .map(|topic| Self::topic_response(topic, metadata_image, log_dirs))
+            .collect::<Vec<ListOffsetsTopicResponse>>()
+            .into()
+    }
+
+    fn topic_response(
+        topic: ListOffsetsTopic,
+        metadata_image: &MetadataImage,
+        log_dirs: &[PathBuf],
+    ) -> ListOffsetsTopicResponse {
+        let topic_id = metadata_image
+            .topic_by_name(&topic.name.bytes)
+            .map(|topic_record| topic_record.topic_uuid);
+        let topic_name = topic.name.to_string();
+        let partitions = topic
+            .partitions
+            .into_iter()
+            .map(|partition| {
+                let partition_record = topic_id.and_then(|topic_id| {
+                    metadata_image.partition(&topic_id, partition.partition_index)
+                });
+                match partition_record {
+                    Some(partition_record) => {
+                        Self::list_offset(&topic_name, &partition, partition_record, log_dirs)
+                    }
+                    None => Self::partition_response(
+                        &partition,
+                        error_codes::UNKNOWN_TOPIC_OR_PARTITION,
+                        None,
+                    ),
+                }
+            })
+            .collect::<Vec<ListOffsetsPartitionResponse>>()
+            .into();
+        ListOffsetsTopicResponse {
+            name: topic.name,
+            partitions,
+            _tagged_fields: TaggedFieldsSection::empty(),
+        }
+    }
+
+    fn list_offset(
+        topic_name: &str,
+        partition: &ListOffsetsPartition,
+        partition_record: &PartitionRecord,
+        log_dirs: &[PathBuf],
+    ) -> ListOffsetsPartitionResponse {
+        let log = Log::new(log_dirs, topic_name, partition.partition_index);
+        match Self::lookup(&log, partition.timestamp, partition_record) {
+            Ok(timestamp_and_offset) => {
+                Self::partition_response(partition, error_codes::NONE, timestamp_and_offset)
+            }
+            Err(e) => {
+                error!(
+                    "Failed to list offsets of {topic_name}-{}: {e}",
+                    partition.partition_index
+                );
+                Self::partition_response(partition, error_codes::KAFKA_STORAGE_ERROR, None)
+            }
+        }
+    }
+
+    /// Finds the offset the timestamp of the request stands for. Without tiered storage the
+    /// local log is the whole log, and nothing is tiered.
+    fn lookup(
+        log: &Log,
+        timestamp: i64,
+        partition_record: &PartitionRecord,
+    ) -> io::Result<Option<TimestampAndOffset>> {
+        // Without an epoch cache, offsets that aren't of a record get the current leader epoch
+        let current = |offset| TimestampAndOffset {
+            timestamp: NO_TIMESTAMP,
+            offset,
+            leader_epoch: partition_record.leader_epoch,
+        };
+        match timestamp {
+            LATEST_TIMESTAMP => Ok(Some(current(log.next_offset()?))),
+            EARLIEST_TIMESTAMP | EARLIEST_LOCAL_TIMESTAMP => {
+                Ok(Some(current(log.log_start_offset()?)))
+            }
+            MAX_TIMESTAMP => log.max_timestamp_and_offset(),
+            LATEST_TIERED_TIMESTAMP => Ok(None),
+            timestamp => log.offset_for_timestamp(timestamp),
+        }
+    }
+
+    /// Response for the partition with the offset found, or with no offset if there is none
+    fn partition_response(
+        partition: &ListOffsetsPartition,
+        error_code: i16,
+        timestamp_and_offset: Option<TimestampAndOffset>,
+    ) -> ListOffsetsPartitionResponse {
+        let timestamp_and_offset = timestamp_and_offset.unwrap_or(TimestampAndOffset {
+            timestamp: NO_TIMESTAMP,
+            offset: NO_OFFSET,
+            leader_epoch: NO_LEADER_EPOCH,
+        });
+        ListOffsetsPartitionResponse {
+            partition_index: partition.partition_index,
+            error_code,
+            timestamp: timestamp_and_offset.timestamp,
+            offset: timestamp_and_offset.offset,
+            leader_epoch: timestamp_and_offset.leader_epoch,
+            _tagged_fields: TaggedFieldsSection::empty(),
+        }
+    }
+}
+
+impl ApiHandler for ListOffsetsApi {
+    fn api_key(&self) -> i16 {
+        LIST_OFFSETS
+    }
+
+    fn versions(&self) -> RangeInclusive<i16> {
+        list_offsets::MIN_VERSION..=list_offsets::MAX_VERSION
+    }
+
+    fn first_flexible_version(&self) -> i16 {
+        FIRST_FLEXIBLE_VERSION
+    }
+
+    fn handle(
+        &self,
+        request_header: RequestHeaderV2,
+        buf: &[u8],
+        body_offset: usize,
+        context: &RequestContext,
+    ) -> Result<Vec<u8>, DecodeError> {
+        Ok(Self::respond(
+            request_header,
+            buf,
+            body_offset,
+            context.metadata_image,
+            &context.server_config.log_dirs,
+        )?
+        .to_be_bytes())
+    }
+
+    fn unsupported_version(
+        &self,
+        request_header: RequestHeaderV2,
+        buf: &[u8],
+        body_offset: usize,
+        _context: &RequestContext,
+    ) -> Vec<u8> {
+        let api_version = request_header.request_api_version;
+        if (0..FIRST_FLEXIBLE_VERSION).contains(&api_version) {
+            return with_version!(api_version, [0, 1, 2, 3, 4, 5], |V| {
+                Self::non_flexible_error_response::<V>(
+                    request_header,
+                    buf,
+                    body_offset,
+                    error_codes::UNSUPPORTED_VERSION,
+                )
+                .to_be_bytes()
+            });
+        }
+        Self::error_response(
+            request_header,
+            buf,
+            body_offset,
+            error_codes::UNSUPPORTED_VERSION,
+        )
+        .to_be_bytes()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::api_handler::ApiRegistry;
+    use crate::records::record_batch_builder::RecordBatchBuilder;
+    use crate::size::Size;
+    use crate::storage::log_config::LogConfig;
+    use crate::test_fixtures::{self, TempDir};
+    use crate::types::compact_string::CompactString;
+    use crate::utils::config::ServerConfig;
+
+    const TOPIC_ID: Uuid = Uuid::from_u128(0x71a59a51_8968_4f8b_937e_e0d0100d856a);
+
+    /// Image holding topic "offsets" with its partition 0
+    fn metadata_image() -> MetadataImage {
+        test_fixtures::metadata_image("offsets", TOPIC_ID, 1, 4)
+    }
+
+    /// Partition 0 of "offsets" holding records with timestamps 1000, 3000 and 2000, in a new
+    /// log directory removed along with the returned guard
+    fn log_dirs_with_3_records() -> (TempDir, Vec<PathBuf>) {
+        let log_dir = TempDir::new("list-offsets");
+        let log_dirs = vec![log_dir.path().to_path_buf()];
+        let record_batches = [1000, 3000, 2000]
+            .into_iter()
+            .map(|timestamp| {
+                RecordBatchBuilder::new(0)
+                    .record(timestamp, None, Some(b"value"), Vec::new())
+                    .build()
+                    .unwrap()
+            })
+            .collect();
+        Log::new(&log_dirs, "offsets", 0)
+            .append(record_batches, &LogConfig::default())
+            .unwrap();
+        (log_dir, log_dirs)
+    }
+
+    /// Request for the offsets of "offsets" at the timestamps, a partition each
+    fn list_offsets_request(partition_timestamps: &[(i32, i64)]) -> Vec<u8> {
+        let request = ListOffsetsRequest {
+            replica_id: -1,
+            isolation_level: 0,
+            topics: vec![ListOffsetsTopic {
+                name: CompactString::from("offsets"),
+                partitions: partition_timestamps
+                    .iter()
+                    .map(|&(partition_index, timestamp)| ListOffsetsPartition {
+                        partition_index,
+                        current_leader_epoch: -1,
+                        timestamp,
+                        _tagged_fields: TaggedFieldsSection::empty(),
+                    })
+                    .collect::<Vec<ListOffsetsPartition>>()
+                    .into(),
+                _tagged_fields: TaggedFieldsSection::empty(),
+            }]
+            .into(),
+            _tagged_fields: TaggedFieldsSection::empty(),
+        };
+        let mut bytes = vec![
+            0x00, 0x00, 0x00, 0x00, // message_size, not read
+            0x00, 0x02, // request_api_key: 2
+            0x00, 0x09, // request_api_version: 9
+            0x00, 0x00, 0x00, 0x07, // correlation_id: 7
+            0xff, 0xff, // client_id: null
+            0x00, // _tagged_fields
+        ];
+        bytes.extend(request.to_be_bytes());
+        bytes
+    }
+
+    /// Timestamp, offset and leader epoch listed for each partition, with its error code
+    fn list_offsets(
+        partition_timestamps: &[(i32, i64)],
+        log_dirs: &[PathBuf],
+    ) -> Vec<(i16, i64, i64, i32)> {
+        let request_bytes = list_offsets_request(partition_timestamps);
+        let request_header = RequestHeaderV2::parse(&request_bytes, 0).unwrap();
+        let body_offset = request_header.size();
+
+        let bytes = ListOffsetsApi::respond(
+            request_header,
+            &request_bytes,
+            body_offset,
+            &metadata_image(),
+            log_dirs,
+        )
+        .unwrap()
+        .to_be_bytes();
+
+        // Skips the message_size, correlation_id and _tagged_fields of the response header
+        let response = ListOffsetsResponse::parse(&bytes, 9).unwrap();
+        response.topics[0]
+            .partitions
+            .clone()
+            .into_iter()
+            .map(|partition| {
+                (
+                    partition.error_code,
+                    partition.timestamp,
+                    partition.offset,
+                    partition.leader_epoch,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lists_offsets_standing_for_special_timestamps() {
+        let (_log_dir, log_dirs) = log_dirs_with_3_records();
+
+        let listed = list_offsets(
+            &[
+                (0, LATEST_TIMESTAMP),
+                (0, EARLIEST_TIMESTAMP),
+                (0, MAX_TIMESTAMP),
+                (0, LATEST_TIERED_TIMESTAMP),
+            ],
+            &log_dirs,
+        );
+
+        assert_eq!(
+            vec![
+                (error_codes::NONE, NO_TIMESTAMP, 3, 4),
+                (error_codes::NONE, NO_TIMESTAMP, 0, 4),
+                (error_codes::NONE, 3000, 1, 0),
+                (error_codes::NONE, NO_TIMESTAMP, NO_OFFSET, NO_LEADER_EPOCH),
+            ],
+            listed
+        );
+    }
+
+    #[test]
+    fn lists_first_offset_at_or_after_timestamp() {
+        let (_log_dir, log_dirs) = log_dirs_with_3_records();
+
+        let listed = list_offsets(&[(0, 1000), (0, 1500), (0, 3001)], &log_dirs);
+
+        assert_eq!(
+            vec![
+                (error_codes::NONE, 1000, 0, 0),
+                (error_codes::NONE, 3000, 1, 0),
+                (error_codes::NONE, NO_TIMESTAMP, NO_OFFSET, NO_LEADER_EPOCH),
+            ],
+            listed
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_partition() {
+        let (_log_dir, log_dirs) = log_dirs_with_3_records();
+
+        let listed = list_offsets(&[(1, LATEST_TIMESTAMP)], &log_dirs);
+
+        assert_eq!(
+            vec![(
+                error_codes::UNKNOWN_TOPIC_OR_PARTITION,
+                NO_TIMESTAMP,
+                NO_OFFSET,
+                NO_LEADER_EPOCH
+            )],
+            listed
+        );
+    }
+
+    #[test]
+    fn rejects_non_flexible_version_in_its_own_body() {
+        let mut request_bytes = vec![
+            0x00, 0x00, 0x00, 0x00, // message_size, not read
+            0x00, 0x02, // request_api_key: 2
+            0x00, 0x01, // request_api_version: 1
+            0x00, 0x00, 0x00, 0x07, // correlation_id: 7
+            0xff, 0xff, // client_id: null
+            0xff, 0xff, 0xff, 0xff, // replica_id: -1
+            0x00, 0x00, 0x00, 0x01, // topics (INT32 length): 1
+            0x00, 0x07, b'o', b'f', b'f', b's', b'e', b't', b's', // name: offsets
+            0x00, 0x00, 0x00, 0x01, // partitions (INT32 length): 1
+            0x00, 0x00, 0x00, 0x00, // partition_index: 0
+        ];
+        request_bytes.extend(LATEST_TIMESTAMP.to_be_bytes());
+
+        let bytes = ApiRegistry::builtin()
+            .respond(&request_bytes, &ServerConfig::default(), &metadata_image())
+            .unwrap();
+
+        // Skips the message_size and correlation_id of the v0 response header
+        assert_eq!(&[0x00, 0x00, 0x00, 0x07], &bytes[4..8]);
+        let response = non_flexible::ListOffsetsResponse::<1>::parse(&bytes, 8).unwrap();
+        assert_eq!(bytes.len() - 8, response.size());
+        let partition = &response.topics[0].partitions[0];
+        assert_eq!(0, partition.partition_index);
+        assert_eq!(error_codes::UNSUPPORTED_VERSION, partition.error_code);
+    }
+}
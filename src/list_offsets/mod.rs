@@ -0,0 +1,5 @@
+pub const MIN_VERSION: i16 = 6;
+pub const MAX_VERSION: i16 = 9;
+
+pub mod list_offsets_api;
+pub mod non_flexible;
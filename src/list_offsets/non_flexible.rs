@@ -0,0 +1,114 @@
+//! ListOffsets requests and responses of the versions before the first flexible one, generic
+//! over their version. They have the fields of their version on the wire, with int length
+//! prefixes and no tagged fields.
+
+use kafka_derive::{ByteParsable, Serializable, Size};
+
+use crate::types::{array::Array, nullable_string::NullableString};
+
+// https://kafka.apache.org/41/design/protocol/#The_Messages_ListOffsets
+
+/// ListOffsets Request (Versions: 0-5) => replica_id isolation_level [topics]
+///   replica_id => INT32
+///   isolation_level => INT8 (versions 2+)
+///   topics => name [partitions]
+///     name => STRING
+///     partitions => partition_index current_leader_epoch timestamp max_num_offsets
+///       partition_index => INT32
+///       current_leader_epoch => INT32 (versions 4+)
+///       timestamp => INT64
+///       max_num_offsets => INT32 (version 0)
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+#[kafka(version = V)]
+pub struct ListOffsetsRequest<const V: i16> {
+    replica_id: i32,
+    #[kafka(versions = "2+")]
+    isolation_level: i8,
+    pub topics: Array<RequestTopic<V>>,
+}
+
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+pub struct RequestTopic<const V: i16> {
+    pub name: NullableString,
+    pub partitions: Array<RequestPartition<V>>,
+}
+
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+#[kafka(version = V)]
+pub struct RequestPartition<const V: i16> {
+    pub partition_index: i32,
+    #[kafka(versions = "4+")]
+    current_leader_epoch: i32,
+    timestamp: i64,
+    #[kafka(versions = "0")]
+    max_num_offsets: i32,
+}
+
+/// ListOffsets Response (Versions: 0-5) => throttle_time_ms [topics]
+///   throttle_time_ms => INT32 (versions 2+)
+///   topics => name [partitions]
+///     name => STRING
+///     partitions => partition_index error_code [old_style_offsets] timestamp offset leader_epoch
+///       partition_index => INT32
+///       error_code => INT16
+///       old_style_offsets => INT64 (version 0)
+///       timestamp => INT64 (versions 1+)
+///       offset => INT64 (versions 1+)
+///       leader_epoch => INT32 (versions 4+)
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+#[kafka(version = V)]
+pub struct ListOffsetsResponse<const V: i16> {
+    #[kafka(versions = "2+")]
+    throttle_time_ms: i32,
+    pub topics: Array<ResponseTopic<V>>,
+}
+
+impl<const V: i16> ListOffsetsResponse<V> {
+    pub fn new(throttle_time_ms: i32, topics: Array<ResponseTopic<V>>) -> Self {
+        Self {
+            throttle_time_ms,
+            topics,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+pub struct ResponseTopic<const V: i16> {
+    pub name: NullableString,
+    pub partitions: Array<ResponsePartition<V>>,
+}
+
+impl<const V: i16> ResponseTopic<V> {
+    pub fn new(name: NullableString, partitions: Array<ResponsePartition<V>>) -> Self {
+        Self { name, partitions }
+    }
+}
+
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+#[kafka(version = V)]
+pub struct ResponsePartition<const V: i16> {
+    pub partition_index: i32,
+    pub error_code: i16,
+    #[kafka(versions = "0")]
+    old_style_offsets: Array<i64>,
+    #[kafka(versions = "1+")]
+    timestamp: i64,
+    #[kafka(versions = "1+")]
+    offset: i64,
+    #[kafka(versions = "4+")]
+    leader_epoch: i32,
+}
+
+impl<const V: i16> ResponsePartition<V> {
+    /// Partition response carrying only the error, without offsets
+    pub fn error(partition_index: i32, error_code: i16) -> Self {
+        Self {
+            partition_index,
+            error_code,
+            old_style_offsets: Array::empty(),
+            timestamp: -1,
+            offset: -1,
+            leader_epoch: -1,
+        }
+    }
+}
@@ -15,3 +15,18 @@ macro_rules! lazy_trace {
         }
     };
 }
+
+/// Evaluates `$body` with the const `$V` set to `$version`, which must be one of the listed
+/// versions, so that it can call code generic over the version of a message
+#[macro_export]
+macro_rules! with_version {
+    ($version:expr, [$($v:literal),+ $(,)?], |$V:ident| $body:expr) => {
+        match $version {
+            $($v => {
+                const $V: i16 = $v;
+                $body
+            })+
+            version => unreachable!("version {version} isn't one of {:?}", [$($v),+]),
+        }
+    };
+}
@@ -1,15 +1,22 @@
 #![allow(unused_imports)]
+// Tests spell out the sizes of the fields they expect, empty ones as `(1 + 0)`
+#![cfg_attr(test, allow(clippy::identity_op))]
 use std::fs::{self, File};
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 use std::net::TcpListener;
 use std::net::TcpStream;
+use std::path::PathBuf;
+use std::process;
+use std::sync::{Arc, PoisonError};
 use std::thread;
 
-use crate::api_keys::{API_VERSIONS, DESCRIBE_TOPIC_PARTITIONS, FETCH, PRODUCE};
+use crate::api_handler::ApiRegistry;
+use crate::api_keys::{API_VERSIONS, DESCRIBE_TOPIC_PARTITIONS, FETCH, METADATA, PRODUCE};
 use crate::api_response::ApiResponse;
 use crate::api_versions::api_versions_api::ApiVersionsApi;
 use crate::api_versions::api_versions_response_v4::{ApiKey, ApiVersionsResponseV4};
 use crate::byte_parsable::ByteParsable;
+use crate::decode_error::DecodeError;
 use crate::describe_topic_partitions::describe_topic_partitions_api::DescribeTopicPartitionsApi;
 use crate::describe_topic_partitions::describe_topic_partitions_request_v0::{
     self, topic_name, DescribeTopicPartitionsRequestV0,
@@ -18,152 +25,154 @@ use crate::describe_topic_partitions::describe_topic_partitions_response_v0::{
     DescribeTopicPartitionsResponseV0, Partition, Topic,
 };
 use crate::fetch::fetch_api::FetchApi;
-use crate::fetch::fetch_request_v16::FetchRequestV16;
-use crate::fetch::fetch_response_v16::FetchResponseV16;
+use crate::fetch::fetch_request::FetchRequest;
+use crate::fetch::fetch_response::FetchResponse;
 use crate::fetch::partition::{ResponsePartition, Transaction};
 use crate::fetch::topic::ResponseTopic;
+use crate::frame_reader::FrameReader;
 use crate::headers::request_header_v2::RequestHeaderV2;
-use crate::partial_parsable::PartialParsable;
+use crate::metadata::metadata_api::MetadataApi;
+use crate::metadata_image::{spawn_metadata_loader_task, MetadataImage, SharedMetadataImage};
+use crate::network::SocketServer;
 use crate::produce::produce_api::ProduceApi;
 use crate::produce::produce_request_v11::ProduceRequestV11;
 use crate::produce::produce_response_v11::{PartitionResponse, ProduceResponseV11, Response};
 use crate::records::metadata_record::{MetadataRecord, TOPIC};
-use crate::records::record_batch::{RecordBatch, RecordValue, SearchItem};
+use crate::records::record_batch::RecordBatch;
 use crate::records::topic_record::TopicRecord;
 use crate::serializable::Serializable;
 use crate::size::Size;
+use crate::storage::log::Log;
+use crate::storage::log_manager::LogManager;
 use crate::tagged_fields_section::TaggedFieldsSection;
 use crate::types::compact_array::CompactArray;
 use crate::types::compact_records::CompactRecords;
 use crate::types::compact_string::CompactString;
-use crate::utils::config::load_config;
+use crate::utils::config::{load_config, ServerConfig};
 use crate::utils::logging::init_logging;
+use crate::utils::signals::{block_shutdown_signals, wait_for_shutdown_signal};
 use crate::utils::uuid::all_zeroes_uuid;
+use crate::version_negotiation::RequestError;
 use itertools::Itertools;
-use tracing::{debug, info, trace};
+use tracing::{debug, error, info, trace, warn};
 use uuid::Uuid;
 
+mod api_handler;
 mod api_keys;
 mod api_response;
 mod api_versions;
 mod byte_parsable;
+mod decode_error;
 mod describe_topic_partitions;
 mod error_codes;
 mod fetch;
+mod frame_reader;
 mod headers;
+mod list_offsets;
 mod macros;
-mod partial_parsable;
+mod messages;
+mod metadata;
+mod metadata_image;
+mod network;
 mod produce;
 mod records;
 mod serializable;
 mod size;
+mod storage;
 mod tagged_fields_section;
+#[cfg(test)]
+mod test_fixtures;
 mod types;
 mod utils;
+mod version_negotiation;
 
 fn main() {
-    let config = load_config();
+    // Before any thread is spawned, so that they all inherit the mask and leave the signals to
+    // the shutdown thread
+    block_shutdown_signals()
+        .unwrap_or_else(|e| panic!("Unable to block the shutdown signals: {e}"));
+    let config = load_config().unwrap_or_else(|e| panic!("Unable to load the config: {e}"));
     init_logging(&config.log.level);
+    for key in &config.unknown_properties {
+        warn!("Ignoring unsupported property {key}");
+    }
     // You can use print statements as follows for debugging, they'll be visible when running tests.
     println!("Logs from your program will appear here!");
 
-    let listener = TcpListener::bind("127.0.0.1:9092").unwrap();
-
-    for stream in listener.incoming() {
-        // Uses 1:1 model of thread implementation (1 thread: 1 OS thread), so probably won't scale
-        thread::spawn(move || match stream {
-            Ok(mut _stream) => {
-                println!("\nAccepted new connection");
-
-                let mut buf = [0u8; 1024];
-                process_bytes_from_stream(&mut _stream, &mut buf);
-            }
-            Err(e) => {
-                println!("error: {}", e);
-            }
-        });
+    // The logs are recovered first, as the cluster metadata log is one of them
+    let log_manager = LogManager::new(&config.server);
+    log_manager
+        .load()
+        .unwrap_or_else(|e| panic!("Unable to load the partition logs: {e}"));
+    let metadata_image = MetadataImage::load(&config.server.log_dirs)
+        .unwrap_or_else(|e| panic!("Unable to load the cluster metadata log: {e}"))
+        .into_shared();
+    spawn_metadata_loader_task(metadata_image.clone(), &config.server.log_dirs)
+        .unwrap_or_else(|e| panic!("Unable to start the metadata loader task: {e}"));
+    log_manager
+        .spawn_recovery_point_checkpoint_task()
+        .unwrap_or_else(|e| panic!("Unable to start the recovery point checkpoint task: {e}"));
+    log_manager
+        .spawn_retention_task(metadata_image.clone())
+        .unwrap_or_else(|e| panic!("Unable to start the log retention task: {e}"));
+    log_manager
+        .spawn_cleaner_task(metadata_image.clone())
+        .unwrap_or_else(|e| panic!("Unable to start the log cleaner task: {e}"));
+    spawn_shutdown_task(log_manager)
+        .unwrap_or_else(|e| panic!("Unable to start the shutdown task: {e}"));
+    let registry = Arc::new(ApiRegistry::builtin());
+
+    let listeners = config
+        .server
+        .listeners
+        .iter()
+        .map(|listener| {
+            TcpListener::bind(listener.bind_address())
+                .unwrap_or_else(|e| panic!("Unable to bind listener {listener}: {e}"))
+        })
+        .collect();
+    let socket_server = SocketServer::start(listeners, config.server, registry, metadata_image)
+        .unwrap_or_else(|e| panic!("Unable to start the socket server: {e}"));
+    for local_addr in socket_server.local_addrs().unwrap_or_default() {
+        info!("Listening on {local_addr}");
     }
+    socket_server.serve();
 }
 
-fn process_bytes_from_stream(stream: &mut TcpStream, buf: &mut [u8]) -> usize {
-    debug!("Buffer length: {}", buf.len());
-    let mut total_bytes_read = 0;
-    let header_size = RequestHeaderV2::min_size();
-    loop {
-        match stream.read(&mut buf[total_bytes_read..]) {
-            Ok(0) => {
-                println!("Connection closed by peer");
-                break;
-            }
-            Ok(n) => {
-                debug!("Read {n} byte(s)");
-                let request_start_offset = total_bytes_read;
-                total_bytes_read += n;
-                if total_bytes_read >= header_size {
-                    let request_header = RequestHeaderV2::parse(buf, request_start_offset);
-                    let response_bytes = match request_header.request_api_key {
-                        API_VERSIONS => ApiVersionsApi::respond(request_header).to_be_bytes(),
-                        FETCH => FetchApi::respond(request_header, buf, request_start_offset)
-                            .to_be_bytes(),
-                        DESCRIBE_TOPIC_PARTITIONS => DescribeTopicPartitionsApi::respond(
-                            request_header,
-                            buf,
-                            request_start_offset,
-                        )
-                        .to_be_bytes(),
-                        PRODUCE => ProduceApi::respond(request_header, buf, request_start_offset)
-                            .to_be_bytes(),
-                        _ => Vec::new(),
-                    };
-
-                    debug!("Response size: {} byte(s)", &response_bytes.len());
-
-                    let response_bytes_sent = write_bytes_to_stream(stream, &response_bytes);
-
-                    debug!("Sent {response_bytes_sent} byte(s) for response");
+/// Starts the task shutting the broker down on SIGTERM or SIGINT: the logs are flushed and
+/// closed, so that the next startup skips their recovery, before the process exits
+fn spawn_shutdown_task(log_manager: LogManager) -> io::Result<thread::JoinHandle<()>> {
+    thread::Builder::new()
+        .name("shutdown".to_string())
+        .spawn(move || {
+            match wait_for_shutdown_signal() {
+                Ok(signal) => info!("Shutting down on signal {signal}"),
+                Err(e) => {
+                    error!("Failed to wait for the shutdown signals: {e}");
+                    return;
                 }
             }
-            Err(e) => {
-                println!("Failed to read: {}", e);
-                break;
-            }
-        }
-    }
-    debug!("Total bytes read: {}", total_bytes_read);
-    total_bytes_read
-}
-
-pub fn get_record_values_by_topic_name_from_metadata_log(
-    topic_name: &CompactString,
-) -> Vec<RecordValue> {
-    let record_batches = get_record_batches_from_metadata_log();
-    record_batches
-        .into_iter()
-        .flat_map(|record_batch| record_batch.find_record_values_by_topic_name(topic_name))
-        .collect_vec()
-}
-
-pub fn get_record_batches_from_metadata_log() -> Vec<RecordBatch> {
-    get_record_batches_from_log_file("__cluster_metadata-0")
+            let exit_code = match log_manager.shutdown() {
+                Ok(()) => 0,
+                Err(e) => {
+                    error!("Failed to shut down the logs cleanly: {e}");
+                    1
+                }
+            };
+            process::exit(exit_code);
+        })
 }
 
-pub fn get_record_batches_from_log_file(directory: &str) -> Vec<RecordBatch> {
-    let log_file_path = format!("/tmp/kraft-combined-logs/{directory}/00000000000000000000.log");
-    RecordBatch::from_file(&log_file_path)
+pub fn get_record_batches_from_metadata_log(log_dirs: &[PathBuf]) -> io::Result<Vec<RecordBatch>> {
+    get_record_batches_from_log_file(log_dirs, "__cluster_metadata", 0)
 }
 
-fn write_bytes_to_stream(_stream: &mut TcpStream, bytes: &[u8]) -> usize {
-    trace!("Writing the following bytes to stream: {:X?}", bytes);
-    match _stream.write(bytes) {
-        Ok(n) => {
-            trace!("Wrote {n} byte(s) successfully");
-            n
-        }
-        Err(e) => {
-            println!("Write failed: {}", e);
-            0
-        }
-    }
+pub fn get_record_batches_from_log_file(
+    log_dirs: &[PathBuf],
+    topic_name: &str,
+    partition_index: i32,
+) -> io::Result<Vec<RecordBatch>> {
+    Log::new(log_dirs, topic_name, partition_index).record_batches()
 }
 
 #[cfg(test)]
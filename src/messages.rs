@@ -0,0 +1,115 @@
+//! Message types generated by `build.rs` from the Kafka JSON message schemas in `schemas/`.
+//!
+//! Each message has a module per version, e.g. `fetch_request::v16::FetchRequest`, whose structs
+//! hold the fields on the wire for that version. Adding a schema to `schemas/` makes its message
+//! available here.
+#![allow(dead_code)]
+
+include!(concat!(env!("OUT_DIR"), "/messages.rs"));
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use crate::byte_parsable::ByteParsable;
+    use crate::serializable::Serializable;
+    use crate::size::Size;
+
+    use super::*;
+
+    #[test]
+    fn parses_flexible_version_with_compact_types() {
+        let bytes: &[u8] = &[
+            0x00, 0x00, 0x01, 0xf4, // max_wait_ms: 500
+            0x00, 0x00, 0x00, 0x01, // min_bytes: 1
+            0x03, 0x20, 0x00, 0x00, // max_bytes: 52428800
+            0x00, // isolation_level: 0
+            0x00, 0x00, 0x00, 0x00, // session_id: 0
+            0x00, 0x00, 0x00, 0x00, // session_epoch: 0
+            0x02, // topics (length: 1 + N): 2
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x60, 0x70, // topic_id: 24688
+            0x02, // partitions (length: 1 + N): 2
+            0x00, 0x00, 0x00, 0x00, // partition: 0
+            0xff, 0xff, 0xff, 0xff, // current_leader_epoch: -1
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x2a, // fetch_offset: 42
+            0xff, 0xff, 0xff, 0xff, // last_fetched_epoch: -1
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // log_start_offset: -1
+            0x00, 0x10, 0x00, 0x00, // partition_max_bytes: 1048576
+            0x00, // _tagged_fields
+            0x00, // _tagged_fields
+            0x01, // forgotten_topics_data (length: 1 + N): 1
+            0x01, // rack_id (length): 1
+            0x00, // _tagged_fields
+        ];
+
+        let request = fetch_request::v16::FetchRequest::parse(bytes, 0).unwrap();
+
+        assert_eq!(500, request.max_wait_ms);
+        assert_eq!(Uuid::from_u128(24688), request.topics[0].topic_id);
+        assert_eq!(42, request.topics[0].partitions[0].fetch_offset);
+        assert_eq!(0, request.forgotten_topics_data.len());
+        assert_eq!(bytes.len(), request.size());
+        assert_eq!(bytes.to_vec(), request.to_be_bytes());
+    }
+
+    #[test]
+    fn parses_non_flexible_version_with_int_length_prefixes() {
+        let bytes: &[u8] = &[
+            0xff, 0xff, 0xff, 0xff, // replica_id: -1
+            0x00, 0x00, 0x01, 0xf4, // max_wait_ms: 500
+            0x00, 0x00, 0x00, 0x01, // min_bytes: 1
+            0x00, 0x00, 0x00, 0x01, // topics (length): 1
+            0x00, 0x03, b'f', b'o', b'o', // topic: foo
+            0x00, 0x00, 0x00, 0x01, // partitions (length): 1
+            0x00, 0x00, 0x00, 0x02, // partition: 2
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x07, // fetch_offset: 7
+            0x00, 0x00, 0x10, 0x00, // partition_max_bytes: 4096
+        ];
+
+        let request = fetch_request::v0::FetchRequest::parse(bytes, 0).unwrap();
+
+        assert_eq!(-1, request.replica_id);
+        assert_eq!(
+            crate::types::nullable_string::NullableString::from("foo"),
+            request.topics[0].topic
+        );
+        assert_eq!(2, request.topics[0].partitions[0].partition);
+        assert_eq!(7, request.topics[0].partitions[0].fetch_offset);
+        assert_eq!(bytes.to_vec(), request.to_be_bytes());
+    }
+
+    #[test]
+    fn keeps_field_level_non_flexible_encoding() {
+        let bytes: &[u8] = &[
+            0x00, 0x01, // request_api_key: 1
+            0x00, 0x10, // request_api_version: 16
+            0x00, 0x00, 0x00, 0x07, // correlation_id: 7
+            0x00, 0x03, b'c', b'l', b'i', // client_id: cli (INT16 length)
+            0x00, // _tagged_fields
+        ];
+
+        let header = request_header::v2::RequestHeader::parse(bytes, 0).unwrap();
+
+        assert_eq!(16, header.request_api_version);
+        assert_eq!(7, header.correlation_id);
+        assert_eq!(bytes.len(), header.size());
+    }
+
+    #[test]
+    fn round_trips_null_struct() {
+        let bytes: &[u8] = &[
+            0x01, // topics (length: 1 + N): 0
+            0x00, 0x00, 0x07, 0xd0, // response_partition_limit: 2000
+            0xff, // cursor: null
+            0x00, // _tagged_fields
+        ];
+
+        let request =
+            describe_topic_partitions_request::v0::DescribeTopicPartitionsRequest::parse(bytes, 0)
+                .unwrap();
+
+        assert!(request.cursor.value.is_none());
+        assert_eq!(bytes.to_vec(), request.to_be_bytes());
+    }
+}
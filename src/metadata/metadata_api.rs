@@ -0,0 +1,474 @@
+use std::ops::RangeInclusive;
+
+use tracing::warn;
+
+use crate::{
+    api_handler::{ApiHandler, RequestContext},
+    api_keys::METADATA,
+    api_response::{self, ApiResponse},
+    byte_parsable::ByteParsable,
+    decode_error::DecodeError,
+    error_codes,
+    headers::{
+        request_header_v2::RequestHeaderV2, response_header_v0::ResponseHeaderV0,
+        response_header_v1::ResponseHeaderV1,
+    },
+    messages::metadata_request::FIRST_FLEXIBLE_VERSION,
+    metadata::{
+        self,
+        metadata_request_v12::{MetadataRequestV12, Topic as RequestTopic},
+        metadata_response_v12::{Broker, MetadataResponseV12, Partition, Topic as ResponseTopic},
+        non_flexible,
+    },
+    metadata_image::MetadataImage,
+    records::{partition_record::PartitionRecord, topic_record::TopicRecord},
+    serializable::Serializable,
+    tagged_fields_section::TaggedFieldsSection,
+    types::{
+        array::Array, compact_array::CompactArray, compact_nullable_string::CompactNullableString,
+        compact_string::CompactString,
+    },
+    utils::{config::ServerConfig, uuid::all_zeroes_uuid},
+    with_version,
+};
+
+// Sent when include_topic_authorized_operations is false
+const TOPIC_AUTHORIZED_OPERATIONS_OMITTED: i32 = i32::MIN;
+// Sent when include_cluster_authorized_operations is false
+const CLUSTER_AUTHORIZED_OPERATIONS_OMITTED: i32 = i32::MIN;
+
+pub struct MetadataApi;
+
+impl MetadataApi {
+    pub fn respond(
+        request_header: RequestHeaderV2,
+        buf: &[u8],
+        body_offset: usize,
+        server_config: &ServerConfig,
+        metadata_image: &MetadataImage,
+    ) -> Result<ApiResponse<ResponseHeaderV1, MetadataResponseV12>, DecodeError> {
+        let metadata_request = MetadataRequestV12::parse(buf, body_offset)?;
+        let topics = Self::topics(metadata_request.topics, metadata_image);
+        let throttle_time_ms = 0;
+        let brokers = [Self::broker(server_config)].into();
+        // The cluster id of meta.properties is not read, so none is reported
+        let cluster_id = CompactNullableString::null();
+        // Single-node cluster: this broker is also the controller
+        let controller_id = server_config.node_id;
+        let response = MetadataResponseV12::new(
+            throttle_time_ms,
+            brokers,
+            cluster_id,
+            controller_id,
+            topics,
+            TaggedFieldsSection::empty(),
+        );
+        Ok(api_response::v1(request_header.correlation_id, response))
+    }
+
+    /// Responds with the error for every topic of the request, as far as it can be decoded
+    pub fn error_response(
+        request_header: RequestHeaderV2,
+        buf: &[u8],
+        body_offset: usize,
+        error_code: i16,
+    ) -> ApiResponse<ResponseHeaderV1, MetadataResponseV12> {
+        let topics = MetadataRequestV12::parse(buf, body_offset)
+            .map(|metadata_request| {
+                metadata_request
+                    .topics
+                    .into_iter()
+                    .map(|request_topic| {
+                        ResponseTopic::new(
+                            error_code,
+                            request_topic.name,
+                            request_topic.topic_id,
+                            false,
+                            CompactArray::empty(),
+                            TOPIC_AUTHORIZED_OPERATIONS_OMITTED,
+                            TaggedFieldsSection::empty(),
+                        )
+                    })
+                    .collect::<Vec<ResponseTopic>>()
+                    .into()
+            })
+            .unwrap_or_else(|_| CompactArray::empty());
+        let throttle_time_ms = 0;
+        let controller_id = -1;
+        let response = MetadataResponseV12::new(
+            throttle_time_ms,
+            CompactArray::empty(),
+            CompactNullableString::null(),
+            controller_id,
+            topics,
+            TaggedFieldsSection::empty(),
+        );
+        api_response::v1(request_header.correlation_id, response)
+    }
+
+    /// Responds to a version before the first flexible one with the error for every topic of the
+    /// request, as far as it can be decoded, in the body of that version
+    pub fn non_flexible_error_response<const V: i16>(
+        request_header: RequestHeaderV2,
+        buf: &[u8],
+        body_offset: usize,
+        error_code: i16,
+    ) -> ApiResponse<ResponseHeaderV0, non_flexible::MetadataResponse<V>> {
+        let topics = non_flexible::MetadataRequest::<V>::parse(buf, body_offset)
+            .map(|metadata_request| {
+                metadata_request
+                    .topics
+                    .into_iter()
+                    .map(|request_topic| {
+                        non_flexible::ResponseTopic::error(
+                            error_code,
+                            request_topic.name,
+                            TOPIC_AUTHORIZED_OPERATIONS_OMITTED,
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .into()
+            })
+            .unwrap_or_else(|_| Array::empty());
+        let response = non_flexible::MetadataResponse::topics_only(
+            topics,
+            CLUSTER_AUTHORIZED_OPERATIONS_OMITTED,
+        );
+        api_response::v0(request_header.correlation_id, response)
+    }
+
+    fn broker(server_config: &ServerConfig) -> Broker {
+        let (host, port) = server_config
+            .advertised_listener()
+            .map_or(("", -1), |listener| {
+                (listener.host.as_str(), listener.port as i32)
+            });
+        Broker::new(
+            server_config.node_id,
+            CompactString::from(host),
+            port,
+            CompactNullableString::null(),
+            TaggedFieldsSection::empty(),
+        )
+    }
+
+    fn topics(
+        request_topics: CompactArray<RequestTopic>,
+        metadata_image: &MetadataImage,
+    ) -> CompactArray<ResponseTopic> {
+        if request_topics.is_null() {
+            return metadata_image
+                .topics()
+                .into_iter()
+                .map(|topic_record| Self::topic(topic_record, metadata_image))
+                .collect::<Vec<ResponseTopic>>()
+                .into();
+        }
+        request_topics
+            .into_iter()
+            .map(
+                |request_topic| match Self::find_topic(&request_topic, metadata_image) {
+                    Some(topic_record) => Self::topic(topic_record, metadata_image),
+                    None => Self::unknown_topic(request_topic),
+                },
+            )
+            .collect::<Vec<ResponseTopic>>()
+            .into()
+    }
+
+    fn find_topic<'a>(
+        request_topic: &RequestTopic,
+        metadata_image: &'a MetadataImage,
+    ) -> Option<&'a TopicRecord> {
+        match &request_topic.name.bytes {
+            Some(name) => metadata_image.topic_by_name(name),
+            None => metadata_image.topic_by_id(&request_topic.topic_id),
+        }
+    }
+
+    fn topic(topic_record: &TopicRecord, metadata_image: &MetadataImage) -> ResponseTopic {
+        let is_internal = false;
+        let partitions = metadata_image
+            .partitions(&topic_record.topic_uuid)
+            .into_iter()
+            .map(Self::partition)
+            .collect::<Vec<Partition>>()
+            .into();
+        ResponseTopic::new(
+            error_codes::NONE,
+            topic_record.topic_name.to_compact_nullable_string(),
+            topic_record.topic_uuid,
+            is_internal,
+            partitions,
+            TOPIC_AUTHORIZED_OPERATIONS_OMITTED,
+            TaggedFieldsSection::empty(),
+        )
+    }
+
+    fn unknown_topic(request_topic: RequestTopic) -> ResponseTopic {
+        // Topics requested by id (with a null name) are reported as an unknown topic id
+        let (error_code, topic_id) = match request_topic.name.bytes {
+            Some(_) => (error_codes::UNKNOWN_TOPIC_OR_PARTITION, all_zeroes_uuid()),
+            None => (error_codes::UNKNOWN_TOPIC_ID, request_topic.topic_id),
+        };
+        ResponseTopic::new(
+            error_code,
+            request_topic.name,
+            topic_id,
+            false,
+            CompactArray::empty(),
+            TOPIC_AUTHORIZED_OPERATIONS_OMITTED,
+            TaggedFieldsSection::empty(),
+        )
+    }
+
+    fn partition(partition_record: &PartitionRecord) -> Partition {
+        Partition::new(
+            error_codes::NONE,
+            partition_record.partition_id,
+            partition_record.leader,
+            partition_record.leader_epoch,
+            partition_record.replica_array.clone(),
+            partition_record.in_sync_replica_array.clone(),
+            CompactArray::empty(),
+            TaggedFieldsSection::empty(),
+        )
+    }
+}
+
+impl ApiHandler for MetadataApi {
+    fn api_key(&self) -> i16 {
+        METADATA
+    }
+
+    fn versions(&self) -> RangeInclusive<i16> {
+        metadata::MIN_VERSION..=metadata::MAX_VERSION
+    }
+
+    fn first_flexible_version(&self) -> i16 {
+        FIRST_FLEXIBLE_VERSION
+    }
+
+    fn handle(
+        &self,
+        request_header: RequestHeaderV2,
+        buf: &[u8],
+        body_offset: usize,
+        context: &RequestContext,
+    ) -> Result<Vec<u8>, DecodeError> {
+        Ok(Self::respond(
+            request_header,
+            buf,
+            body_offset,
+            context.server_config,
+            context.metadata_image,
+        )?
+        .to_be_bytes())
+    }
+
+    fn unsupported_version(
+        &self,
+        request_header: RequestHeaderV2,
+        buf: &[u8],
+        body_offset: usize,
+        _context: &RequestContext,
+    ) -> Vec<u8> {
+        let api_version = request_header.request_api_version;
+        if (0..FIRST_FLEXIBLE_VERSION).contains(&api_version) {
+            return with_version!(api_version, [0, 1, 2, 3, 4, 5, 6, 7, 8], |V| {
+                Self::non_flexible_error_response::<V>(
+                    request_header,
+                    buf,
+                    body_offset,
+                    error_codes::UNSUPPORTED_VERSION,
+                )
+                .to_be_bytes()
+            });
+        }
+        Self::error_response(
+            request_header,
+            buf,
+            body_offset,
+            error_codes::UNSUPPORTED_VERSION,
+        )
+        .to_be_bytes()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::api_handler::ApiRegistry;
+    use crate::size::Size;
+    use crate::test_fixtures;
+    use crate::types::compact_string::CompactString;
+    use crate::version_negotiation::RequestError;
+
+    const TOPIC_ID: Uuid = Uuid::from_u128(0x71a59a51_8968_4f8b_937e_e0d0100d856a);
+    const UNKNOWN_TOPIC_ID: Uuid = Uuid::from_u128(0x1);
+
+    /// Image holding topic "mango" with partitions 0 and 1, led by broker 1
+    fn metadata_image() -> MetadataImage {
+        test_fixtures::metadata_image("mango", TOPIC_ID, 2, 0)
+    }
+
+    fn request_topic(topic_id: Uuid, name: Option<&str>) -> RequestTopic {
+        RequestTopic {
+            topic_id,
+            name: name.map_or_else(CompactNullableString::null, |name| {
+                CompactString::from(name).to_compact_nullable_string()
+            }),
+            _tagged_fields: TaggedFieldsSection::empty(),
+        }
+    }
+
+    fn partition_indexes(topic: &ResponseTopic) -> Vec<i32> {
+        topic
+            .partitions
+            .clone()
+            .into_iter()
+            .map(|partition| partition.partition_index)
+            .collect()
+    }
+
+    #[test]
+    fn describes_known_topic_by_name_and_by_id() {
+        let metadata_image = metadata_image();
+        let request_topics = vec![
+            request_topic(Uuid::nil(), Some("mango")),
+            request_topic(TOPIC_ID, None),
+        ];
+
+        let topics = MetadataApi::topics(request_topics.into(), &metadata_image);
+
+        assert_eq!(2, topics.len());
+        for topic in topics.into_iter() {
+            assert_eq!(error_codes::NONE, topic.error_code);
+            assert_eq!("mango", topic.name.to_string());
+            assert_eq!(TOPIC_ID, topic.topic_id);
+            assert_eq!(vec![0, 1], partition_indexes(&topic));
+        }
+    }
+
+    #[test]
+    fn reports_unknown_topic_by_name_and_by_id() {
+        let metadata_image = metadata_image();
+        let request_topics = vec![
+            request_topic(Uuid::nil(), Some("unknown")),
+            request_topic(UNKNOWN_TOPIC_ID, None),
+        ];
+
+        let topics = MetadataApi::topics(request_topics.into(), &metadata_image);
+
+        assert_eq!(
+            error_codes::UNKNOWN_TOPIC_OR_PARTITION,
+            topics[0].error_code
+        );
+        assert_eq!("unknown", topics[0].name.to_string());
+        assert_eq!(all_zeroes_uuid(), topics[0].topic_id);
+        assert_eq!(error_codes::UNKNOWN_TOPIC_ID, topics[1].error_code);
+        assert_eq!(UNKNOWN_TOPIC_ID, topics[1].topic_id);
+        assert!(topics[1].name.bytes.is_none());
+        assert!(partition_indexes(&topics[0]).is_empty());
+    }
+
+    #[test]
+    fn lists_all_topics_when_topics_are_null() {
+        let metadata_image = metadata_image();
+
+        let topics = MetadataApi::topics(CompactArray::null(), &metadata_image);
+
+        assert_eq!(1, topics.len());
+        assert_eq!("mango", topics[0].name.to_string());
+        assert_eq!(vec![0, 1], partition_indexes(&topics[0]));
+    }
+
+    #[test]
+    fn responds_with_this_broker_as_controller() {
+        let request_bytes = [
+            0x00, 0x00, 0x00, 0x11, // message_size
+            0x00, 0x03, // request_api_key: 3
+            0x00, 0x0c, // request_api_version: 12
+            0x00, 0x00, 0x00, 0x07, // correlation_id: 7
+            0xff, 0xff, // client_id: null
+            0x00, // _tagged_fields
+            0x00, // topics: null
+            0x00, // allow_auto_topic_creation: false
+            0x00, // include_topic_authorized_operations: false
+            0x00, // _tagged_fields
+        ];
+        let request_header = RequestHeaderV2::parse(&request_bytes, 0).unwrap();
+        let body_offset = request_header.size();
+        let server_config = ServerConfig::default();
+
+        let bytes = MetadataApi::respond(
+            request_header,
+            &request_bytes,
+            body_offset,
+            &server_config,
+            &metadata_image(),
+        )
+        .unwrap()
+        .to_be_bytes();
+
+        assert_eq!(&[0x00, 0x00, 0x00, 0x07, 0x00], &bytes[4..9]);
+        let response = MetadataResponseV12::parse(&bytes, 9).unwrap();
+        assert_eq!(server_config.node_id, response.controller_id);
+        assert_eq!(1, response.brokers.len());
+        assert_eq!(server_config.node_id, response.brokers[0].node_id);
+        assert!(response.cluster_id.bytes.is_none());
+        assert_eq!(1, response.topics.len());
+    }
+
+    #[test]
+    fn rejects_truncated_request() {
+        let request_bytes = [
+            0x00, 0x00, 0x00, 0x0c, // message_size
+            0x00, 0x03, // request_api_key: 3
+            0x00, 0x0c, // request_api_version: 12
+            0x00, 0x00, 0x00, 0x07, // correlation_id: 7
+            0xff, 0xff, // client_id: null
+            0x00, // _tagged_fields
+            0x02, // topics (length: 1 + N): 2, without the topic
+        ];
+
+        assert!(matches!(
+            ApiRegistry::builtin().respond(
+                &request_bytes,
+                &ServerConfig::default(),
+                &metadata_image()
+            ),
+            Err(RequestError::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_non_flexible_version_in_its_own_body() {
+        let request_bytes = [
+            0x00, 0x00, 0x00, 0x15, // message_size
+            0x00, 0x03, // request_api_key: 3
+            0x00, 0x01, // request_api_version: 1
+            0x00, 0x00, 0x00, 0x07, // correlation_id: 7
+            0xff, 0xff, // client_id: null
+            0x00, 0x00, 0x00, 0x01, // topics (INT32 length): 1
+            0x00, 0x05, b'm', b'a', b'n', b'g', b'o', // name: mango
+        ];
+
+        let bytes = ApiRegistry::builtin()
+            .respond(&request_bytes, &ServerConfig::default(), &metadata_image())
+            .unwrap();
+
+        // Skips the message_size and correlation_id of the v0 response header
+        assert_eq!(&[0x00, 0x00, 0x00, 0x07], &bytes[4..8]);
+        let response = non_flexible::MetadataResponse::<1>::parse(&bytes, 8).unwrap();
+        assert_eq!(bytes.len() - 8, response.size());
+        assert_eq!(1, response.topics.len());
+        assert_eq!(
+            error_codes::UNSUPPORTED_VERSION,
+            response.topics[0].error_code
+        );
+        assert_eq!(Some(b"mango".as_slice()), response.topics[0].name.bytes());
+    }
+}
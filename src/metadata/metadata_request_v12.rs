@@ -0,0 +1,76 @@
+use kafka_derive::{ByteParsable, Serializable, Size};
+use uuid::Uuid;
+
+use crate::{
+    tagged_fields_section::TaggedFieldsSection,
+    types::{compact_array::CompactArray, compact_nullable_string::CompactNullableString},
+};
+
+// https://kafka.apache.org/41/design/protocol/#The_Messages_Metadata
+
+/// Metadata Request (Version: 12) => [topics] allow_auto_topic_creation include_topic_authorized_operations _tagged_fields
+///   topics => topic_id name _tagged_fields
+///     topic_id => UUID
+///     name => COMPACT_NULLABLE_STRING
+///   allow_auto_topic_creation => BOOLEAN
+///   include_topic_authorized_operations => BOOLEAN
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+pub struct MetadataRequestV12 {
+    /// Null when the client is asking for all topics
+    pub topics: CompactArray<Topic>,
+    pub allow_auto_topic_creation: bool,
+    pub include_topic_authorized_operations: bool,
+    #[kafka(tagged_fields)]
+    pub _tagged_fields: TaggedFieldsSection,
+}
+
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+pub struct Topic {
+    pub topic_id: Uuid,
+    pub name: CompactNullableString,
+    #[kafka(tagged_fields)]
+    pub _tagged_fields: TaggedFieldsSection,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{byte_parsable::ByteParsable, size::Size};
+
+    #[test]
+    fn parses_metadata_request() {
+        let bytes: &[u8] = &[
+            0x02, // topics (length: 1 + N): 2
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, // topic_id: all zeroes
+            0x04, 0x66, 0x6f, 0x6f, // name (length: 1 + N): "foo"
+            0x00, // _tagged_fields
+            0x01, // allow_auto_topic_creation: true
+            0x00, // include_topic_authorized_operations: false
+            0x00, // _tagged_fields
+        ];
+
+        let metadata_request = MetadataRequestV12::parse(bytes, 0).unwrap();
+
+        assert_eq!(1, metadata_request.topics.len());
+        assert_eq!(Uuid::nil(), metadata_request.topics[0].topic_id);
+        assert_eq!("foo", metadata_request.topics[0].name.to_string());
+        assert!(metadata_request.allow_auto_topic_creation);
+        assert!(!metadata_request.include_topic_authorized_operations);
+        assert_eq!(bytes.len(), metadata_request.size());
+    }
+
+    #[test]
+    fn parses_null_topics_as_all_topics() {
+        let bytes: &[u8] = &[
+            0x00, // topics: null
+            0x00, // allow_auto_topic_creation: false
+            0x00, // include_topic_authorized_operations: false
+            0x00, // _tagged_fields
+        ];
+
+        let metadata_request = MetadataRequestV12::parse(bytes, 0).unwrap();
+
+        assert!(metadata_request.topics.is_null());
+    }
+}
@@ -0,0 +1,248 @@
+use kafka_derive::{ByteParsable, Serializable, Size};
+use uuid::Uuid;
+
+use crate::{
+    tagged_fields_section::TaggedFieldsSection,
+    types::{
+        compact_array::CompactArray, compact_nullable_string::CompactNullableString,
+        compact_string::CompactString,
+    },
+};
+
+// https://kafka.apache.org/41/design/protocol/#The_Messages_Metadata
+
+/// Metadata Response (Version: 12) => throttle_time_ms [brokers] cluster_id controller_id [topics] _tagged_fields
+///   throttle_time_ms => INT32
+///   brokers => node_id host port rack _tagged_fields
+///     node_id => INT32
+///     host => COMPACT_STRING
+///     port => INT32
+///     rack => COMPACT_NULLABLE_STRING
+///   cluster_id => COMPACT_NULLABLE_STRING
+///   controller_id => INT32
+///   topics => error_code name topic_id is_internal [partitions] topic_authorized_operations _tagged_fields
+///     error_code => INT16
+///     name => COMPACT_NULLABLE_STRING
+///     topic_id => UUID
+///     is_internal => BOOLEAN
+///     partitions => error_code partition_index leader_id leader_epoch [replica_nodes] [isr_nodes] [offline_replicas] _tagged_fields
+///       error_code => INT16
+///       partition_index => INT32
+///       leader_id => INT32
+///       leader_epoch => INT32
+///       replica_nodes => INT32
+///       isr_nodes => INT32
+///       offline_replicas => INT32
+///     topic_authorized_operations => INT32
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+pub struct MetadataResponseV12 {
+    pub throttle_time_ms: i32,
+    pub brokers: CompactArray<Broker>,
+    pub cluster_id: CompactNullableString,
+    pub controller_id: i32,
+    pub topics: CompactArray<Topic>,
+    #[kafka(tagged_fields)]
+    pub _tagged_fields: TaggedFieldsSection,
+}
+
+impl MetadataResponseV12 {
+    pub fn new(
+        throttle_time_ms: i32,
+        brokers: CompactArray<Broker>,
+        cluster_id: CompactNullableString,
+        controller_id: i32,
+        topics: CompactArray<Topic>,
+        _tagged_fields: TaggedFieldsSection,
+    ) -> Self {
+        Self {
+            throttle_time_ms,
+            brokers,
+            cluster_id,
+            controller_id,
+            topics,
+            _tagged_fields,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+pub struct Broker {
+    pub node_id: i32,
+    pub host: CompactString,
+    pub port: i32,
+    pub rack: CompactNullableString,
+    #[kafka(tagged_fields)]
+    pub _tagged_fields: TaggedFieldsSection,
+}
+
+impl Broker {
+    pub fn new(
+        node_id: i32,
+        host: CompactString,
+        port: i32,
+        rack: CompactNullableString,
+        _tagged_fields: TaggedFieldsSection,
+    ) -> Self {
+        Self {
+            node_id,
+            host,
+            port,
+            rack,
+            _tagged_fields,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+pub struct Topic {
+    pub error_code: i16,
+    pub name: CompactNullableString,
+    pub topic_id: Uuid,
+    pub is_internal: bool,
+    pub partitions: CompactArray<Partition>,
+    pub topic_authorized_operations: i32,
+    #[kafka(tagged_fields)]
+    pub _tagged_fields: TaggedFieldsSection,
+}
+
+impl Topic {
+    pub fn new(
+        error_code: i16,
+        name: CompactNullableString,
+        topic_id: Uuid,
+        is_internal: bool,
+        partitions: CompactArray<Partition>,
+        topic_authorized_operations: i32,
+        _tagged_fields: TaggedFieldsSection,
+    ) -> Self {
+        Self {
+            error_code,
+            name,
+            topic_id,
+            is_internal,
+            partitions,
+            topic_authorized_operations,
+            _tagged_fields,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+pub struct Partition {
+    pub error_code: i16,
+    pub partition_index: i32,
+    pub leader_id: i32,
+    pub leader_epoch: i32,
+    pub replica_nodes: CompactArray<i32>,
+    pub isr_nodes: CompactArray<i32>,
+    pub offline_replicas: CompactArray<i32>,
+    #[kafka(tagged_fields)]
+    pub _tagged_fields: TaggedFieldsSection,
+}
+
+impl Partition {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        error_code: i16,
+        partition_index: i32,
+        leader_id: i32,
+        leader_epoch: i32,
+        replica_nodes: CompactArray<i32>,
+        isr_nodes: CompactArray<i32>,
+        offline_replicas: CompactArray<i32>,
+        _tagged_fields: TaggedFieldsSection,
+    ) -> Self {
+        Self {
+            error_code,
+            partition_index,
+            leader_id,
+            leader_epoch,
+            replica_nodes,
+            isr_nodes,
+            offline_replicas,
+            _tagged_fields,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::serializable::Serializable;
+    use crate::{api_response, error_codes};
+
+    use super::*;
+
+    #[test]
+    fn converts_to_bytes() {
+        let broker = Broker::new(
+            1,
+            CompactString::from("localhost"),
+            9092,
+            CompactNullableString::null(),
+            TaggedFieldsSection::empty(),
+        );
+        let partition = Partition::new(
+            error_codes::NONE,
+            0,
+            1,
+            0,
+            [1].into(),
+            [1].into(),
+            CompactArray::empty(),
+            TaggedFieldsSection::empty(),
+        );
+        let topic = Topic::new(
+            error_codes::NONE,
+            CompactString::from("foo").into_compact_nullable_string(),
+            Uuid::from_u128(1),
+            false,
+            [partition].into(),
+            i32::MIN,
+            TaggedFieldsSection::empty(),
+        );
+        let response = MetadataResponseV12::new(
+            0,
+            [broker].into(),
+            CompactNullableString::null(),
+            1,
+            [topic].into(),
+            TaggedFieldsSection::empty(),
+        );
+        let expected_bytes: &[u8] = &[
+            0x00, 0x00, 0x00, 0x5c, // message_size: 92
+            0x00, 0x00, 0x00, 0x07, // correlation_id: 7
+            0x00, // _tagged_fields
+            0x00, 0x00, 0x00, 0x00, // throttle_time_ms: 0
+            0x02, // brokers (length: 1 + N): 2
+            0x00, 0x00, 0x00, 0x01, // node_id: 1
+            0x0a, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 0x68, 0x6f, 0x73, 0x74, // host: "localhost"
+            0x00, 0x00, 0x23, 0x84, // port: 9092
+            0x00, // rack: null
+            0x00, // _tagged_fields
+            0x00, // cluster_id: null
+            0x00, 0x00, 0x00, 0x01, // controller_id: 1
+            0x02, // topics (length: 1 + N): 2
+            0x00, 0x00, // error_code: 0
+            0x04, 0x66, 0x6f, 0x6f, // name: "foo"
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x01, // topic_id
+            0x00, // is_internal: false
+            0x02, // partitions (length: 1 + N): 2
+            0x00, 0x00, // error_code: 0
+            0x00, 0x00, 0x00, 0x00, // partition_index: 0
+            0x00, 0x00, 0x00, 0x01, // leader_id: 1
+            0x00, 0x00, 0x00, 0x00, // leader_epoch: 0
+            0x02, 0x00, 0x00, 0x00, 0x01, // replica_nodes: [1]
+            0x02, 0x00, 0x00, 0x00, 0x01, // isr_nodes: [1]
+            0x01, // offline_replicas: []
+            0x00, // _tagged_fields
+            0x80, 0x00, 0x00, 0x00, // topic_authorized_operations: omitted
+            0x00, // _tagged_fields
+            0x00, // _tagged_fields
+        ];
+
+        let api_response = api_response::v1(7, response);
+
+        assert_eq!(expected_bytes, api_response.to_be_bytes());
+    }
+}
@@ -0,0 +1,10 @@
+/// Only v12 is served, the one version the request and response types are written for. Older
+/// versions differ in the header (non-flexible before v9) and in how topics are identified
+/// (no topic ids before v10), so their requests are answered with UNSUPPORTED_VERSION.
+pub const MIN_VERSION: i16 = 12;
+pub const MAX_VERSION: i16 = 12;
+
+pub mod metadata_api;
+pub mod metadata_request_v12;
+pub mod metadata_response_v12;
+pub mod non_flexible;
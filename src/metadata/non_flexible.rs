@@ -0,0 +1,133 @@
+//! Metadata requests and responses of the versions before the first flexible one, generic over
+//! their version. They have the fields of their version on the wire, with int length prefixes
+//! and no tagged fields.
+
+use kafka_derive::{ByteParsable, Serializable, Size};
+
+use crate::types::{array::Array, nullable_string::NullableString};
+
+// https://kafka.apache.org/41/design/protocol/#The_Messages_Metadata
+
+/// Metadata Request (Versions: 0-8) => [topics] allow_auto_topic_creation include_cluster_authorized_operations include_topic_authorized_operations
+///   topics => name
+///     name => STRING
+///   allow_auto_topic_creation => BOOLEAN (versions 4+)
+///   include_cluster_authorized_operations => BOOLEAN (versions 8+)
+///   include_topic_authorized_operations => BOOLEAN (versions 8+)
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+#[kafka(version = V)]
+pub struct MetadataRequest<const V: i16> {
+    pub topics: Array<RequestTopic>,
+    #[kafka(versions = "4+")]
+    allow_auto_topic_creation: bool,
+    #[kafka(versions = "8+")]
+    include_cluster_authorized_operations: bool,
+    #[kafka(versions = "8+")]
+    include_topic_authorized_operations: bool,
+}
+
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+pub struct RequestTopic {
+    pub name: NullableString,
+}
+
+/// Metadata Response (Versions: 0-8) => throttle_time_ms [brokers] cluster_id controller_id [topics] cluster_authorized_operations
+///   throttle_time_ms => INT32 (versions 3+)
+///   brokers => node_id host port rack
+///     node_id => INT32
+///     host => STRING
+///     port => INT32
+///     rack => NULLABLE_STRING (versions 1+)
+///   cluster_id => NULLABLE_STRING (versions 2+)
+///   controller_id => INT32 (versions 1+)
+///   topics => error_code name is_internal [partitions] topic_authorized_operations
+///     error_code => INT16
+///     name => STRING
+///     is_internal => BOOLEAN (versions 1+)
+///     partitions => error_code partition_index leader_id leader_epoch [replica_nodes] [isr_nodes] [offline_replicas]
+///       error_code => INT16
+///       partition_index => INT32
+///       leader_id => INT32
+///       leader_epoch => INT32 (versions 7+)
+///       replica_nodes => INT32
+///       isr_nodes => INT32
+///       offline_replicas => INT32 (versions 5+)
+///     topic_authorized_operations => INT32 (versions 8+)
+///   cluster_authorized_operations => INT32 (versions 8+)
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+#[kafka(version = V)]
+pub struct MetadataResponse<const V: i16> {
+    #[kafka(versions = "3+")]
+    throttle_time_ms: i32,
+    brokers: Array<Broker<V>>,
+    #[kafka(versions = "2+")]
+    cluster_id: NullableString,
+    #[kafka(versions = "1+")]
+    controller_id: i32,
+    pub topics: Array<ResponseTopic<V>>,
+    #[kafka(versions = "8+")]
+    cluster_authorized_operations: i32,
+}
+
+impl<const V: i16> MetadataResponse<V> {
+    /// Response without brokers, listing the topics only, as sent with an error for each of them
+    pub fn topics_only(topics: Array<ResponseTopic<V>>, authorized_operations: i32) -> Self {
+        Self {
+            throttle_time_ms: 0,
+            brokers: Array::empty(),
+            cluster_id: NullableString::null(),
+            controller_id: -1,
+            topics,
+            cluster_authorized_operations: authorized_operations,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+#[kafka(version = V)]
+pub struct Broker<const V: i16> {
+    node_id: i32,
+    host: NullableString,
+    port: i32,
+    #[kafka(versions = "1+")]
+    rack: NullableString,
+}
+
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+#[kafka(version = V)]
+pub struct ResponseTopic<const V: i16> {
+    pub error_code: i16,
+    pub name: NullableString,
+    #[kafka(versions = "1+")]
+    is_internal: bool,
+    partitions: Array<ResponsePartition<V>>,
+    #[kafka(versions = "8+")]
+    topic_authorized_operations: i32,
+}
+
+impl<const V: i16> ResponseTopic<V> {
+    /// Topic response carrying only the error, without partitions
+    pub fn error(error_code: i16, name: NullableString, authorized_operations: i32) -> Self {
+        Self {
+            error_code,
+            name,
+            is_internal: false,
+            partitions: Array::empty(),
+            topic_authorized_operations: authorized_operations,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+#[kafka(version = V)]
+pub struct ResponsePartition<const V: i16> {
+    error_code: i16,
+    partition_index: i32,
+    leader_id: i32,
+    #[kafka(versions = "7+")]
+    leader_epoch: i32,
+    replica_nodes: Array<i32>,
+    isr_nodes: Array<i32>,
+    #[kafka(versions = "5+")]
+    offline_replicas: Array<i32>,
+}
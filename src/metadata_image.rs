@@ -0,0 +1,401 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, ErrorKind};
+use std::path::PathBuf;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use tracing::error;
+use uuid::Uuid;
+
+use crate::decode_error::DecodeError;
+use crate::get_record_batches_from_metadata_log;
+use crate::records::config_record::{ConfigRecord, TOPIC_RESOURCE};
+use crate::records::feature_level_record::FeatureLevelRecord;
+use crate::records::partition_record::PartitionRecord;
+use crate::records::record_batch::{RecordBatch, RecordValue};
+use crate::records::topic_record::TopicRecord;
+use crate::storage::log::Log;
+
+pub type SharedMetadataImage = Arc<RwLock<MetadataImage>>;
+
+// Interval between reads of the batches appended to the cluster metadata log
+const METADATA_LOAD_INTERVAL: Duration = Duration::from_millis(100);
+
+/// In-memory view of the cluster metadata log, built by applying its records in order
+#[derive(Debug, Default)]
+pub struct MetadataImage {
+    topics_by_id: HashMap<Uuid, TopicRecord>,
+    topic_ids_by_name: HashMap<Vec<u8>, Uuid>,
+    // Partitions kept in index order for each topic id
+    partitions: HashMap<Uuid, BTreeMap<i32, PartitionRecord>>,
+    // Dynamic configs overriding the broker's defaults for each topic name
+    topic_configs: HashMap<Vec<u8>, HashMap<String, String>>,
+    feature_levels: HashMap<Vec<u8>, i16>,
+    // Offset of the last record applied to the image
+    last_offset: Option<i64>,
+}
+
+impl MetadataImage {
+    /// Builds the image from the `__cluster_metadata` log, kept up to date afterwards by
+    /// [`spawn_metadata_loader_task`]
+    pub fn load(log_dirs: &[PathBuf]) -> io::Result<Self> {
+        Self::from_record_batches(&get_record_batches_from_metadata_log(log_dirs)?)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+    }
+
+    pub fn from_record_batches(record_batches: &[RecordBatch]) -> Result<Self, DecodeError> {
+        let mut metadata_image = Self::default();
+        for record_batch in record_batches {
+            metadata_image.apply_record_batch(record_batch)?;
+        }
+        Ok(metadata_image)
+    }
+
+    pub fn into_shared(self) -> SharedMetadataImage {
+        Arc::new(RwLock::new(self))
+    }
+
+    pub fn apply_record_batch(&mut self, record_batch: &RecordBatch) -> Result<(), DecodeError> {
+        record_batch
+            .record_values()?
+            .into_iter()
+            .for_each(|record_value| self.apply(record_value));
+        self.last_offset = Some(record_batch.last_offset());
+        Ok(())
+    }
+
+    pub fn apply(&mut self, record_value: RecordValue) {
+        match record_value {
+            RecordValue::Topic(topic_record) => self.apply_topic_record(topic_record),
+            RecordValue::Partition(partition_record) => {
+                self.apply_partition_record(partition_record)
+            }
+            RecordValue::Config(config_record) => self.apply_config_record(config_record),
+            RecordValue::FeatureLevel(feature_level_record) => {
+                self.apply_feature_level_record(feature_level_record)
+            }
+        }
+    }
+
+    fn apply_topic_record(&mut self, topic_record: TopicRecord) {
+        self.topic_ids_by_name.insert(
+            topic_record.topic_name.bytes.clone(),
+            topic_record.topic_uuid,
+        );
+        self.topics_by_id
+            .insert(topic_record.topic_uuid, topic_record);
+    }
+
+    fn apply_partition_record(&mut self, partition_record: PartitionRecord) {
+        self.partitions
+            .entry(partition_record.topic_uuid)
+            .or_default()
+            .insert(partition_record.partition_id, partition_record);
+    }
+
+    fn apply_config_record(&mut self, config_record: ConfigRecord) {
+        if config_record.resource_type != TOPIC_RESOURCE {
+            return;
+        }
+        let name = String::from_utf8_lossy(&config_record.name.bytes).into_owned();
+        let topic_configs = self
+            .topic_configs
+            .entry(config_record.resource_name.bytes)
+            .or_default();
+        match config_record.value.bytes {
+            Some(value) => {
+                topic_configs.insert(name, String::from_utf8_lossy(&value).into_owned());
+            }
+            None => {
+                topic_configs.remove(&name);
+            }
+        }
+    }
+
+    fn apply_feature_level_record(&mut self, feature_level_record: FeatureLevelRecord) {
+        self.feature_levels.insert(
+            feature_level_record.name.bytes,
+            feature_level_record.feature_level,
+        );
+    }
+
+    pub fn topic_by_name(&self, topic_name: &[u8]) -> Option<&TopicRecord> {
+        self.topic_ids_by_name
+            .get(topic_name)
+            .and_then(|topic_id| self.topic_by_id(topic_id))
+    }
+
+    pub fn topic_by_id(&self, topic_id: &Uuid) -> Option<&TopicRecord> {
+        self.topics_by_id.get(topic_id)
+    }
+
+    /// All topics, ordered by name
+    pub fn topics(&self) -> Vec<&TopicRecord> {
+        let mut topics = self.topics_by_id.values().collect::<Vec<&TopicRecord>>();
+        topics.sort_by(|topic1, topic2| topic1.topic_name.cmp(&topic2.topic_name));
+        topics
+    }
+
+    pub fn partition(&self, topic_id: &Uuid, partition_index: i32) -> Option<&PartitionRecord> {
+        self.partitions
+            .get(topic_id)
+            .and_then(|partitions| partitions.get(&partition_index))
+    }
+
+    /// Partitions of the topic, ordered by partition index
+    pub fn partitions(&self, topic_id: &Uuid) -> Vec<&PartitionRecord> {
+        self.partitions
+            .get(topic_id)
+            .map(|partitions| partitions.values().collect())
+            .unwrap_or_default()
+    }
+
+    /// Configs set on the topic, such as `cleanup.policy`, by Kafka name
+    pub fn topic_configs(&self, topic_name: &[u8]) -> Option<&HashMap<String, String>> {
+        self.topic_configs.get(topic_name)
+    }
+
+    /// Features with a non-zero level, ordered by name
+    pub fn feature_levels(&self) -> Vec<(&[u8], i16)> {
+        let mut feature_levels = self
+            .feature_levels
+            .iter()
+            .filter(|(_, &level)| level > 0)
+            .map(|(name, &level)| (name.as_slice(), level))
+            .collect::<Vec<_>>();
+        feature_levels.sort();
+        feature_levels
+    }
+
+    /// Offset of the last record applied to the image, which versions the metadata it holds
+    pub fn last_offset(&self) -> Option<i64> {
+        self.last_offset
+    }
+}
+
+/// Applies the batches appended to the `__cluster_metadata` log since the last one applied to
+/// the image, returning how many were applied. The log is read before the write lock is taken,
+/// so requests are only held up while the new batches are applied.
+pub fn apply_appended_batches(
+    metadata_image: &SharedMetadataImage,
+    log: &Log,
+) -> io::Result<usize> {
+    let last_offset = metadata_image
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .last_offset();
+    let mut next_offset = last_offset.map_or(0, |last_offset| last_offset + 1);
+    let mut record_batches = Vec::new();
+    loop {
+        // The read starts at the batch holding the offset, which may already be applied
+        let read = RecordBatch::from_bytes(&log.read(next_offset, usize::MAX, true)?)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?
+            .into_iter()
+            .filter(|record_batch| record_batch.last_offset() >= next_offset)
+            .collect::<Vec<RecordBatch>>();
+        let Some(last) = read.last() else {
+            break;
+        };
+        next_offset = last.next_offset();
+        record_batches.extend(read);
+    }
+    if record_batches.is_empty() {
+        return Ok(0);
+    }
+
+    let mut metadata_image = metadata_image
+        .write()
+        .unwrap_or_else(PoisonError::into_inner);
+    let mut applied = 0;
+    for record_batch in &record_batches {
+        // Skips the batches applied by a concurrent call since the image was read
+        if metadata_image
+            .last_offset()
+            .is_some_and(|last_offset| record_batch.last_offset() <= last_offset)
+        {
+            continue;
+        }
+        metadata_image
+            .apply_record_batch(record_batch)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        applied += 1;
+    }
+    Ok(applied)
+}
+
+/// Starts the background task applying the batches appended to the `__cluster_metadata` log to
+/// the image, so topics and their configs created after startup are seen by the requests and
+/// the log retention and cleaner tasks
+pub fn spawn_metadata_loader_task(
+    metadata_image: SharedMetadataImage,
+    log_dirs: &[PathBuf],
+) -> io::Result<thread::JoinHandle<()>> {
+    let log = Log::new(log_dirs, "__cluster_metadata", 0);
+    thread::Builder::new()
+        .name("metadata-loader".to_string())
+        .spawn(move || loop {
+            thread::sleep(METADATA_LOAD_INTERVAL);
+            if let Err(e) = apply_appended_batches(&metadata_image, &log) {
+                error!("Failed to load the cluster metadata log: {e}");
+            }
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::byte_parsable::ByteParsable;
+    use crate::records::record_batch_builder::RecordBatchBuilder;
+    use crate::serializable::Serializable;
+    use crate::storage::log_config::LogConfig;
+    use crate::test_fixtures::{self, TempDir};
+    use crate::types::compact_nullable_string::CompactNullableString;
+    use crate::types::compact_string::CompactString;
+    use crate::types::unsigned_varint::UnsignedVarint;
+
+    const TOPIC_ID: Uuid = Uuid::from_u128(0x71a59a51_8968_4f8b_937e_e0d0100d856a);
+
+    /// Batch with the TopicRecord of "mango" and the PartitionRecord of its partition 0, at the
+    /// offsets following the FeatureLevelRecord batch of the fixture
+    fn topic_batch() -> RecordBatch {
+        let topic_record = test_fixtures::topic_record("mango", TOPIC_ID);
+        let partition_record = test_fixtures::partition_record(TOPIC_ID, 0, 0);
+        RecordBatchBuilder::new(2)
+            .record(1000, None, Some(&topic_record.to_be_bytes()), Vec::new())
+            .record(
+                1000,
+                None,
+                Some(&partition_record.to_be_bytes()),
+                Vec::new(),
+            )
+            .build()
+            .unwrap()
+    }
+
+    /// Batches of a __cluster_metadata log with the feature level and topic "mango"
+    fn record_batches() -> Vec<RecordBatch> {
+        vec![
+            RecordBatch::parse(&test_fixtures::RECORD_BATCH, 0).unwrap(),
+            topic_batch(),
+        ]
+    }
+
+    fn metadata_image() -> MetadataImage {
+        MetadataImage::from_record_batches(&record_batches()).unwrap()
+    }
+
+    #[test]
+    fn looks_up_topics_by_name_and_id() {
+        let metadata_image = metadata_image();
+
+        let topic_record = metadata_image.topic_by_name(b"mango").unwrap();
+
+        assert_eq!(TOPIC_ID, topic_record.topic_uuid);
+        assert_eq!(
+            b"mango".to_vec(),
+            metadata_image
+                .topic_by_id(&TOPIC_ID)
+                .unwrap()
+                .topic_name
+                .bytes
+        );
+        assert!(metadata_image.topic_by_name(b"unknown").is_none());
+        assert_eq!(1, metadata_image.topics().len());
+    }
+
+    #[test]
+    fn looks_up_partitions_by_topic_id_and_index() {
+        let metadata_image = metadata_image();
+
+        let partition_record = metadata_image.partition(&TOPIC_ID, 0).unwrap();
+
+        assert_eq!(1, partition_record.leader);
+        assert_eq!(1, metadata_image.partitions(&TOPIC_ID).len());
+        assert!(metadata_image.partition(&TOPIC_ID, 1).is_none());
+        assert!(metadata_image.partition(&Uuid::nil(), 0).is_none());
+    }
+
+    #[test]
+    fn tracks_feature_levels() {
+        let metadata_image = metadata_image();
+
+        assert_eq!(
+            vec![(b"metadata.version".as_slice(), 20)],
+            metadata_image.feature_levels()
+        );
+        assert_eq!(Some(3), metadata_image.last_offset());
+    }
+
+    #[test]
+    fn tracks_topic_configs() {
+        let mut metadata_image = metadata_image();
+        let config_record = |resource_type, value: Option<&str>| ConfigRecord {
+            frame_version: 1,
+            _type: 4,
+            version: 0,
+            resource_type,
+            resource_name: "mango".into(),
+            name: "cleanup.policy".into(),
+            value: value.map_or_else(CompactNullableString::null, |value| {
+                CompactString::from(value).into_compact_nullable_string()
+            }),
+            tagged_fields_count: UnsignedVarint::new(0),
+        };
+
+        metadata_image.apply(RecordValue::Config(config_record(
+            TOPIC_RESOURCE,
+            Some("compact"),
+        )));
+        metadata_image.apply(RecordValue::Config(config_record(4, Some("delete"))));
+        assert_eq!(
+            Some(&"compact".to_string()),
+            metadata_image
+                .topic_configs(b"mango")
+                .unwrap()
+                .get("cleanup.policy")
+        );
+
+        metadata_image.apply(RecordValue::Config(config_record(TOPIC_RESOURCE, None)));
+        assert!(metadata_image.topic_configs(b"mango").unwrap().is_empty());
+    }
+
+    #[test]
+    fn applies_records_incrementally() {
+        let mut metadata_image =
+            MetadataImage::from_record_batches(&record_batches()[..1]).unwrap();
+        assert!(metadata_image.topic_by_id(&TOPIC_ID).is_none());
+
+        metadata_image.apply_record_batch(&topic_batch()).unwrap();
+
+        assert!(metadata_image.topic_by_id(&TOPIC_ID).is_some());
+    }
+
+    #[test]
+    fn applies_batches_appended_to_the_metadata_log() {
+        let log_dir = TempDir::new("metadata");
+        let log_dirs = vec![log_dir.path().to_path_buf()];
+        let log = Log::new(&log_dirs, "__cluster_metadata", 0);
+        let mut record_batches = record_batches();
+        let topic_batch = record_batches.pop().unwrap();
+        log.append(record_batches, &LogConfig::default()).unwrap();
+        let metadata_image = MetadataImage::load(&log_dirs).unwrap().into_shared();
+        assert!(metadata_image
+            .read()
+            .unwrap()
+            .topic_by_id(&TOPIC_ID)
+            .is_none());
+
+        log.append(vec![topic_batch], &LogConfig::default())
+            .unwrap();
+
+        assert_eq!(1, apply_appended_batches(&metadata_image, &log).unwrap());
+        assert!(metadata_image
+            .read()
+            .unwrap()
+            .topic_by_id(&TOPIC_ID)
+            .is_some());
+        assert_eq!(Some(2), metadata_image.read().unwrap().last_offset());
+        assert_eq!(0, apply_appended_batches(&metadata_image, &log).unwrap());
+    }
+}
@@ -0,0 +1,257 @@
+//! Networking core, modelled on Kafka's SocketServer and KafkaRequestHandlerPool.
+//!
+//! An acceptor per listener hands each accepted connection to one of a fixed number of network threads,
+//! which multiplex their connections with non-blocking I/O. They frame the requests into a
+//! bounded request channel, from which a fixed pool of request handler threads takes them. A
+//! handler hands the response back to the connection's network thread, which writes it out.
+//! A connection has at most one request with the handlers at a time, so its responses are sent
+//! in the order of its requests.
+
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use tracing::error;
+
+use crate::api_handler::ApiRegistry;
+use crate::metadata_image::SharedMetadataImage;
+use crate::utils::config::ServerConfig;
+
+pub mod processor;
+pub mod request_channel;
+pub mod request_handler_pool;
+
+use processor::{Processor, ProcessorHandle};
+use request_channel::RequestChannel;
+use request_handler_pool::RequestHandlerPool;
+
+pub struct SocketServer {
+    listeners: Vec<TcpListener>,
+    processors: Vec<ProcessorHandle>,
+    // Network thread the next accepted connection goes to
+    next_processor: AtomicUsize,
+}
+
+impl SocketServer {
+    /// Starts the network threads and the request handler threads for the listeners' connections
+    pub fn start(
+        listeners: Vec<TcpListener>,
+        server_config: ServerConfig,
+        registry: Arc<ApiRegistry>,
+        metadata_image: SharedMetadataImage,
+    ) -> io::Result<Self> {
+        let request_channel = RequestChannel::new(server_config.queued_max_requests);
+        let processors = (0..server_config.num_network_threads.max(1))
+            .map(|id| {
+                let (processor, handle) = Processor::new(
+                    id,
+                    request_channel.clone(),
+                    server_config.socket_request_max_bytes,
+                )?;
+                processor.spawn()?;
+                Ok(handle)
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let num_io_threads = server_config.num_io_threads.max(1);
+        RequestHandlerPool::new(
+            request_channel,
+            processors.clone(),
+            server_config,
+            registry,
+            metadata_image,
+        )
+        .spawn(num_io_threads)?;
+        Ok(Self {
+            listeners,
+            processors,
+            next_processor: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn local_addrs(&self) -> io::Result<Vec<SocketAddr>> {
+        self.listeners.iter().map(TcpListener::local_addr).collect()
+    }
+
+    /// Accepts connections on every listener, handing them to the network threads in turn
+    pub fn serve(&self) {
+        thread::scope(|scope| {
+            for listener in &self.listeners {
+                scope.spawn(|| self.accept(listener));
+            }
+        });
+    }
+
+    fn accept(&self, listener: &TcpListener) {
+        for stream in listener.incoming() {
+            let stream = stream.and_then(|stream| {
+                stream.set_nonblocking(true)?;
+                stream.set_nodelay(true)?;
+                Ok(mio::net::TcpStream::from_std(stream))
+            });
+            match stream {
+                Ok(stream) => {
+                    let next_processor = self.next_processor.fetch_add(1, Ordering::Relaxed);
+                    self.processors[next_processor % self.processors.len()].accept(stream)
+                }
+                Err(e) => error!("Unable to accept connection: {e}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::ops::RangeInclusive;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::api_handler::{ApiHandler, RequestContext};
+    use crate::decode_error::DecodeError;
+    use crate::headers::request_header_v2::RequestHeaderV2;
+    use crate::metadata_image::MetadataImage;
+
+    const SLOW_ECHO: i16 = 1000;
+    const REQUESTS: i32 = 5;
+
+    /// Responds with the correlation id, taking longer for earlier requests
+    struct SlowEchoApi;
+
+    impl ApiHandler for SlowEchoApi {
+        fn api_key(&self) -> i16 {
+            SLOW_ECHO
+        }
+
+        fn versions(&self) -> RangeInclusive<i16> {
+            0..=0
+        }
+
+        fn first_flexible_version(&self) -> i16 {
+            1
+        }
+
+        fn handle(
+            &self,
+            request_header: RequestHeaderV2,
+            _buf: &[u8],
+            _offset: usize,
+            _context: &RequestContext,
+        ) -> Result<Vec<u8>, DecodeError> {
+            let correlation_id = request_header.correlation_id;
+            thread::sleep(Duration::from_millis(
+                10 * (REQUESTS - correlation_id) as u64,
+            ));
+            Ok([4i32.to_be_bytes(), correlation_id.to_be_bytes()].concat())
+        }
+
+        fn unsupported_version(
+            &self,
+            _request_header: RequestHeaderV2,
+            _buf: &[u8],
+            _offset: usize,
+            _context: &RequestContext,
+        ) -> Vec<u8> {
+            Vec::new()
+        }
+    }
+
+    fn start_server() -> SocketAddr {
+        let mut registry = ApiRegistry::default();
+        registry.register(SlowEchoApi);
+        let server_config = ServerConfig {
+            num_network_threads: 2,
+            num_io_threads: 4,
+            ..ServerConfig::default()
+        };
+        let server = SocketServer::start(
+            vec![TcpListener::bind("127.0.0.1:0").unwrap()],
+            server_config,
+            Arc::new(registry),
+            MetadataImage::default().into_shared(),
+        )
+        .unwrap();
+        let local_addr = server.local_addrs().unwrap()[0];
+        thread::spawn(move || server.serve());
+        local_addr
+    }
+
+    /// Request with the shortest header, a v1 one with a null client_id
+    fn request(api_key: i16, correlation_id: i32) -> Vec<u8> {
+        let mut bytes = vec![0x00, 0x00, 0x00, 0x0a];
+        bytes.extend_from_slice(&api_key.to_be_bytes());
+        bytes.extend_from_slice(&[0x00, 0x00]); // request_api_version: 0
+        bytes.extend_from_slice(&correlation_id.to_be_bytes());
+        bytes.extend_from_slice(&[0xff, 0xff]); // client_id: null
+        bytes
+    }
+
+    #[test]
+    fn responds_to_pipelined_requests_in_order() {
+        let mut stream = TcpStream::connect(start_server()).unwrap();
+        let requests: Vec<u8> = (0..REQUESTS)
+            .flat_map(|correlation_id| request(SLOW_ECHO, correlation_id))
+            .collect();
+
+        stream.write_all(&requests).unwrap();
+
+        let mut responses = vec![0u8; 8 * REQUESTS as usize];
+        stream.read_exact(&mut responses).unwrap();
+        let correlation_ids: Vec<i32> = responses
+            .chunks(8)
+            .map(|response| i32::from_be_bytes(response[4..].try_into().unwrap()))
+            .collect();
+        assert_eq!((0..REQUESTS).collect::<Vec<_>>(), correlation_ids);
+    }
+
+    #[test]
+    fn serves_concurrent_connections() {
+        let local_addr = start_server();
+        let mut streams: Vec<TcpStream> = (0..REQUESTS)
+            .map(|_| TcpStream::connect(local_addr).unwrap())
+            .collect();
+
+        for (correlation_id, stream) in streams.iter_mut().enumerate() {
+            stream
+                .write_all(&request(SLOW_ECHO, correlation_id as i32))
+                .unwrap();
+        }
+
+        for (correlation_id, stream) in streams.iter_mut().enumerate() {
+            let mut response = [0u8; 8];
+            stream.read_exact(&mut response).unwrap();
+            assert_eq!(
+                correlation_id as i32,
+                i32::from_be_bytes(response[4..].try_into().unwrap())
+            );
+        }
+    }
+
+    #[test]
+    fn closes_connection_on_request_shorter_than_a_header() {
+        let mut stream = TcpStream::connect(start_server()).unwrap();
+        let mut request = request(SLOW_ECHO, 7);
+        request[3] -= 1;
+        request.pop();
+
+        stream.write_all(&request).unwrap();
+
+        let mut bytes = Vec::new();
+        stream.read_to_end(&mut bytes).unwrap();
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn closes_connection_on_unknown_api_key() {
+        let mut stream = TcpStream::connect(start_server()).unwrap();
+
+        stream.write_all(&request(SLOW_ECHO + 1, 7)).unwrap();
+
+        let mut bytes = Vec::new();
+        stream.read_to_end(&mut bytes).unwrap();
+        assert!(bytes.is_empty());
+    }
+}
@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+use std::io::{self, ErrorKind, Write};
+use std::sync::Arc;
+use std::thread;
+
+use crossbeam_channel::{Receiver, Sender};
+use mio::net::TcpStream;
+use mio::{Events, Interest, Poll, Token, Waker};
+use tracing::{debug, error, trace, warn};
+
+use crate::frame_reader::{FrameError, FrameReader};
+use crate::headers::request_header_v2::RequestHeaderV2;
+use crate::network::request_channel::{Request, RequestChannel, Response, ResponseAction};
+
+const WAKER: Token = Token(0);
+const EVENTS_CAPACITY: usize = 1024;
+
+/// Handle through which the acceptor hands connections to a network thread, and the request
+/// handlers hand it responses. Either wakes the thread up.
+#[derive(Clone)]
+pub struct ProcessorHandle {
+    new_connections: Sender<TcpStream>,
+    responses: Sender<Response>,
+    waker: Arc<Waker>,
+}
+
+impl ProcessorHandle {
+    pub fn accept(&self, stream: TcpStream) {
+        if self.new_connections.send(stream).is_ok() {
+            self.wake();
+        }
+    }
+
+    pub fn respond(&self, response: Response) {
+        if self.responses.send(response).is_ok() {
+            self.wake();
+        }
+    }
+
+    fn wake(&self) {
+        if let Err(e) = self.waker.wake() {
+            error!("Unable to wake network thread: {e}");
+        }
+    }
+}
+
+/// A client connection of a network thread
+struct Connection {
+    frame_reader: FrameReader<TcpStream>,
+    /// Response bytes the socket hasn't accepted yet
+    pending_writes: Vec<u8>,
+    /// Whether a request of the connection is with the request handlers. No further request is
+    /// read until its response is queued, so responses go out in the order of the requests.
+    muted: bool,
+}
+
+/// Network thread, as Kafka's SocketServer processors. It multiplexes its connections with
+/// non-blocking I/O, framing requests into the request channel and writing back responses.
+pub struct Processor {
+    id: usize,
+    poll: Poll,
+    connections: HashMap<Token, Connection>,
+    next_token: usize,
+    new_connections: Receiver<TcpStream>,
+    responses: Receiver<Response>,
+    request_channel: RequestChannel,
+    max_request_size: usize,
+}
+
+impl Processor {
+    pub fn new(
+        id: usize,
+        request_channel: RequestChannel,
+        max_request_size: usize,
+    ) -> io::Result<(Self, ProcessorHandle)> {
+        let poll = Poll::new()?;
+        let waker = Arc::new(Waker::new(poll.registry(), WAKER)?);
+        let (new_connections_sender, new_connections) = crossbeam_channel::unbounded();
+        let (responses_sender, responses) = crossbeam_channel::unbounded();
+        let processor = Self {
+            id,
+            poll,
+            connections: HashMap::new(),
+            next_token: WAKER.0 + 1,
+            new_connections,
+            responses,
+            request_channel,
+            max_request_size,
+        };
+        let handle = ProcessorHandle {
+            new_connections: new_connections_sender,
+            responses: responses_sender,
+            waker,
+        };
+        Ok((processor, handle))
+    }
+
+    pub fn spawn(mut self) -> io::Result<thread::JoinHandle<()>> {
+        thread::Builder::new()
+            .name(format!("network-thread-{}", self.id))
+            .spawn(move || {
+                if let Err(e) = self.run() {
+                    error!("Network thread {} failed: {e}", self.id);
+                }
+            })
+    }
+
+    fn run(&mut self) -> io::Result<()> {
+        let mut events = Events::with_capacity(EVENTS_CAPACITY);
+        loop {
+            if let Err(e) = self.poll.poll(&mut events, None) {
+                if e.kind() == ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(e);
+            }
+            for event in events.iter() {
+                let token = event.token();
+                if token == WAKER {
+                    continue;
+                }
+                if event.is_writable() {
+                    self.flush(token);
+                }
+                if event.is_readable() || event.is_read_closed() {
+                    self.read_request(token);
+                }
+            }
+            self.register_new_connections();
+            self.process_responses();
+        }
+    }
+
+    fn register_new_connections(&mut self) {
+        while let Ok(mut stream) = self.new_connections.try_recv() {
+            let token = Token(self.next_token);
+            self.next_token += 1;
+            if let Err(e) = self
+                .poll
+                .registry()
+                .register(&mut stream, token, Interest::READABLE)
+            {
+                error!("Unable to register connection: {e}");
+                continue;
+            }
+            println!("\nAccepted new connection");
+            let connection = Connection {
+                frame_reader: FrameReader::new(stream, self.max_request_size),
+                pending_writes: Vec::new(),
+                muted: false,
+            };
+            self.connections.insert(token, connection);
+        }
+    }
+
+    fn process_responses(&mut self) {
+        while let Ok(response) = self.responses.try_recv() {
+            let token = response.connection;
+            let Some(connection) = self.connections.get_mut(&token) else {
+                // The connection closed while its request was being handled
+                continue;
+            };
+            match response.action {
+                ResponseAction::Send(response_bytes) => {
+                    debug!("Response size: {} byte(s)", response_bytes.len());
+                    connection.pending_writes.extend_from_slice(&response_bytes);
+                    connection.muted = false;
+                    self.flush(token);
+                    // Requests may have arrived while muted, without a new readiness event
+                    self.read_request(token);
+                }
+                ResponseAction::Close => self.close(token),
+            }
+        }
+    }
+
+    /// Reads the next request of the connection and queues it for the request handlers
+    fn read_request(&mut self, token: Token) {
+        let Some(connection) = self.connections.get_mut(&token) else {
+            return;
+        };
+        if connection.muted {
+            return;
+        }
+        match connection.frame_reader.next_frame() {
+            Ok(Some(request_bytes)) if request_bytes.len() < RequestHeaderV2::min_v1_size() => {
+                warn!(
+                    "Closing connection: request of {} byte(s) is too short for a request header",
+                    request_bytes.len()
+                );
+                self.close(token);
+            }
+            Ok(Some(request_bytes)) => {
+                debug!("Request size: {} byte(s)", request_bytes.len());
+                connection.muted = true;
+                self.request_channel.send_request(Request {
+                    processor_id: self.id,
+                    connection: token,
+                    bytes: request_bytes,
+                });
+            }
+            Ok(None) => {
+                println!("Connection closed by peer");
+                self.close(token);
+            }
+            Err(FrameError::Io(e)) if e.kind() == ErrorKind::WouldBlock => {}
+            Err(e) => {
+                println!("Closing connection: {}", e);
+                self.close(token);
+            }
+        }
+    }
+
+    /// Writes as much of the pending response bytes as the socket accepts, and waits for the
+    /// socket to become writable if some are left
+    fn flush(&mut self, token: Token) {
+        let Some(connection) = self.connections.get_mut(&token) else {
+            return;
+        };
+        let stream = connection.frame_reader.get_mut();
+        let mut written = 0;
+        let result = loop {
+            if written == connection.pending_writes.len() {
+                break Ok(());
+            }
+            match stream.write(&connection.pending_writes[written..]) {
+                Ok(0) => break Err(io::Error::from(ErrorKind::WriteZero)),
+                Ok(n) => written += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+        trace!("Wrote {written} byte(s)");
+        connection.pending_writes.drain(..written);
+        let interest = if connection.pending_writes.is_empty() {
+            Interest::READABLE
+        } else {
+            Interest::READABLE | Interest::WRITABLE
+        };
+        let result = result.and_then(|()| {
+            self.poll
+                .registry()
+                .reregister(connection.frame_reader.get_mut(), token, interest)
+        });
+        if let Err(e) = result {
+            println!("Write failed: {}", e);
+            self.close(token);
+        }
+    }
+
+    fn close(&mut self, token: Token) {
+        if let Some(mut connection) = self.connections.remove(&token) {
+            // Dropping the stream closes the connection
+            let _ = self
+                .poll
+                .registry()
+                .deregister(connection.frame_reader.get_mut());
+            debug!("Closed connection of network thread {}", self.id);
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use crossbeam_channel::{Receiver, Sender};
+use mio::Token;
+
+/// A complete request framed by a network thread, waiting for a request handler
+pub struct Request {
+    /// Network thread owning the connection, which the response goes back to
+    pub processor_id: usize,
+    pub connection: Token,
+    pub bytes: Vec<u8>,
+}
+
+pub enum ResponseAction {
+    Send(Vec<u8>),
+    /// There's no response the client could decode, so the connection gets closed
+    Close,
+}
+
+pub struct Response {
+    pub connection: Token,
+    pub action: ResponseAction,
+}
+
+/// Bounded queue of requests from the network threads to the request handler threads.
+///
+/// A network thread blocks when the queue is full, as Kafka's queued.max.requests, which stops
+/// it reading more requests until the handlers catch up.
+#[derive(Clone)]
+pub struct RequestChannel {
+    sender: Sender<Request>,
+    receiver: Receiver<Request>,
+}
+
+impl RequestChannel {
+    pub fn new(queued_max_requests: usize) -> Self {
+        let (sender, receiver) = crossbeam_channel::bounded(queued_max_requests);
+        Self { sender, receiver }
+    }
+
+    /// Queues the request, waiting while the queue is full
+    pub fn send_request(&self, request: Request) {
+        self.sender
+            .send(request)
+            .expect("the channel keeps a receiver, so it never disconnects")
+    }
+
+    /// Waits for the next request
+    pub fn receive_request(&self) -> Request {
+        self.receiver
+            .recv()
+            .expect("the channel keeps a sender, so it never disconnects")
+    }
+}
@@ -0,0 +1,80 @@
+use std::io;
+use std::sync::{Arc, PoisonError};
+use std::thread;
+
+use tracing::warn;
+
+use crate::api_handler::ApiRegistry;
+use crate::metadata_image::SharedMetadataImage;
+use crate::network::processor::ProcessorHandle;
+use crate::network::request_channel::{Request, RequestChannel, Response, ResponseAction};
+use crate::utils::config::ServerConfig;
+use crate::version_negotiation::RequestError;
+
+/// Fixed pool of request handler threads, as Kafka's KafkaRequestHandlerPool. Each takes
+/// requests off the request channel and hands the response to the connection's network thread.
+pub struct RequestHandlerPool {
+    request_channel: RequestChannel,
+    processors: Vec<ProcessorHandle>,
+    server_config: ServerConfig,
+    registry: Arc<ApiRegistry>,
+    metadata_image: SharedMetadataImage,
+}
+
+impl RequestHandlerPool {
+    pub fn new(
+        request_channel: RequestChannel,
+        processors: Vec<ProcessorHandle>,
+        server_config: ServerConfig,
+        registry: Arc<ApiRegistry>,
+        metadata_image: SharedMetadataImage,
+    ) -> Self {
+        Self {
+            request_channel,
+            processors,
+            server_config,
+            registry,
+            metadata_image,
+        }
+    }
+
+    pub fn spawn(self, num_threads: usize) -> io::Result<Vec<thread::JoinHandle<()>>> {
+        let pool = Arc::new(self);
+        (0..num_threads)
+            .map(|id| {
+                let pool = Arc::clone(&pool);
+                thread::Builder::new()
+                    .name(format!("request-handler-{id}"))
+                    .spawn(move || loop {
+                        let request = pool.request_channel.receive_request();
+                        pool.handle(request);
+                    })
+            })
+            .collect()
+    }
+
+    fn handle(&self, request: Request) {
+        let action = match self.respond(&request.bytes) {
+            Ok(response_bytes) => ResponseAction::Send(response_bytes),
+            Err(e) => {
+                // Without a request header or a known API key there's no response to
+                // send that the client could decode
+                warn!("Closing connection: {e}");
+                ResponseAction::Close
+            }
+        };
+        self.processors[request.processor_id].respond(Response {
+            connection: request.connection,
+            action,
+        });
+    }
+
+    fn respond(&self, request_bytes: &[u8]) -> Result<Vec<u8>, RequestError> {
+        let metadata_image = self
+            .metadata_image
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        self.registry
+            .respond(request_bytes, &self.server_config, &metadata_image)
+    }
+}
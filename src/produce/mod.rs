@@ -1,6 +1,7 @@
 pub const MIN_VERSION: i16 = 0;
 pub const MAX_VERSION: i16 = 11;
 
+pub mod non_flexible;
 pub mod produce_api;
 pub mod produce_request_v11;
 pub mod produce_response_v11;
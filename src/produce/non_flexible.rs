@@ -0,0 +1,128 @@
+//! Produce requests and responses of the versions before the first flexible one, generic over
+//! their version. They have the fields of their version on the wire, with int length prefixes
+//! and no tagged fields.
+
+use kafka_derive::{ByteParsable, Serializable, Size};
+
+use crate::types::{array::Array, nullable_bytes::NullableBytes, nullable_string::NullableString};
+
+// https://kafka.apache.org/41/design/protocol/#The_Messages_Produce
+
+/// Produce Request (Versions: 0-8) => transactional_id acks timeout_ms [topic_data]
+///   transactional_id => NULLABLE_STRING (versions 3+)
+///   acks => INT16
+///   timeout_ms => INT32
+///   topic_data => name [partition_data]
+///     name => STRING
+///     partition_data => index records
+///       index => INT32
+///       records => RECORDS
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+#[kafka(version = V)]
+pub struct ProduceRequest<const V: i16> {
+    #[kafka(versions = "3+")]
+    transactional_id: NullableString,
+    acks: i16,
+    timeout_ms: i32,
+    pub topic_data: Array<Topic>,
+}
+
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+pub struct Topic {
+    pub name: NullableString,
+    pub partition_data: Array<Partition>,
+}
+
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+pub struct Partition {
+    pub index: i32,
+    pub records: NullableBytes,
+}
+
+/// Produce Response (Versions: 0-8) => [responses] throttle_time_ms
+///   responses => name [partition_responses]
+///     name => STRING
+///     partition_responses => index error_code base_offset log_append_time_ms log_start_offset [record_errors] error_message
+///       index => INT32
+///       error_code => INT16
+///       base_offset => INT64
+///       log_append_time_ms => INT64 (versions 2+)
+///       log_start_offset => INT64 (versions 5+)
+///       record_errors => batch_index batch_index_error_message (versions 8+)
+///         batch_index => INT32
+///         batch_index_error_message => NULLABLE_STRING
+///       error_message => NULLABLE_STRING (versions 8+)
+///   throttle_time_ms => INT32 (versions 1+)
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+#[kafka(version = V)]
+pub struct ProduceResponse<const V: i16> {
+    responses: Array<Response<V>>,
+    #[kafka(versions = "1+")]
+    throttle_time_ms: i32,
+}
+
+impl<const V: i16> ProduceResponse<V> {
+    pub fn new(responses: Array<Response<V>>, throttle_time_ms: i32) -> Self {
+        Self {
+            responses,
+            throttle_time_ms,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+pub struct Response<const V: i16> {
+    name: NullableString,
+    partition_responses: Array<PartitionResponse<V>>,
+}
+
+impl<const V: i16> Response<V> {
+    pub fn new(name: NullableString, partition_responses: Array<PartitionResponse<V>>) -> Self {
+        Self {
+            name,
+            partition_responses,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+#[kafka(version = V)]
+pub struct PartitionResponse<const V: i16> {
+    index: i32,
+    error_code: i16,
+    base_offset: i64,
+    #[kafka(versions = "2+")]
+    log_append_time_ms: i64,
+    #[kafka(versions = "5+")]
+    log_start_offset: i64,
+    #[kafka(versions = "8+")]
+    record_errors: Array<RecordError>,
+    #[kafka(versions = "8+")]
+    error_message: NullableString,
+}
+
+impl<const V: i16> PartitionResponse<V> {
+    pub fn new(
+        index: i32,
+        error_code: i16,
+        base_offset: i64,
+        log_append_time_ms: i64,
+        log_start_offset: i64,
+    ) -> Self {
+        Self {
+            index,
+            error_code,
+            base_offset,
+            log_append_time_ms,
+            log_start_offset,
+            record_errors: Array::empty(),
+            error_message: NullableString::null(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+pub struct RecordError {
+    batch_index: i32,
+    batch_index_error_message: NullableString,
+}
@@ -1,112 +1,599 @@
+use std::io::{self, ErrorKind};
+use std::ops::RangeInclusive;
+
+use tracing::{error, warn};
+use uuid::Uuid;
+
 use crate::{
+    api_handler::{ApiHandler, RequestContext},
+    api_keys::PRODUCE,
     api_response::{self, ApiResponse},
     byte_parsable::ByteParsable,
-    error_codes, get_record_values_by_topic_name_from_metadata_log,
-    headers::{request_header_v2::RequestHeaderV2, response_header_v1::ResponseHeaderV1},
+    decode_error::DecodeError,
+    error_codes,
+    headers::{
+        request_header_v2::RequestHeaderV2, response_header_v0::ResponseHeaderV0,
+        response_header_v1::ResponseHeaderV1,
+    },
+    messages::produce_request::FIRST_FLEXIBLE_VERSION,
+    metadata_image::MetadataImage,
     produce::{
-        produce_request_v11::{Partition, ProduceRequestV11, Topic},
+        self, non_flexible,
+        produce_request_v11::{ProduceRequestV11, Topic},
         produce_response_v11::{PartitionResponse, ProduceResponseV11, Response},
     },
-    records::{partition_record, record_batch::RecordValue},
-    size::Size,
+    records::{legacy_message_set, record_batch::RecordBatch},
+    serializable::Serializable,
+    storage::{log::Log, log_config::LogConfig},
     tagged_fields_section::TaggedFieldsSection,
-    types::{compact_array::CompactArray, compact_nullable_string::CompactNullableString},
+    types::{
+        array::Array, compact_array::CompactArray, compact_nullable_string::CompactNullableString,
+    },
+    utils::config::ServerConfig,
+    with_version,
 };
 
+// Version from which produced records must be v2 batches, rather than legacy message sets
+const FIRST_VERSION_WITHOUT_MESSAGE_SETS: i16 = 3;
+
+/// The flexible versions, 9 to 11, have the same fields, so all of them are handled with the
+/// version 11 messages. The versions before them are handled with the messages generic over
+/// their version.
 pub struct ProduceApi;
 
 impl ProduceApi {
     pub fn respond(
         request_header: RequestHeaderV2,
         buf: &[u8],
-        offset: usize,
+        body_offset: usize,
+        metadata_image: &MetadataImage,
+        server_config: &ServerConfig,
+    ) -> Result<ApiResponse<ResponseHeaderV1, ProduceResponseV11>, DecodeError> {
+        let produce_request = ProduceRequestV11::parse(buf, body_offset)?;
+        let responses = Self::responses(
+            produce_request.topic_data,
+            request_header.request_api_version,
+            metadata_image,
+            server_config,
+        );
+        let throttle_time_ms = 0;
+        let response =
+            ProduceResponseV11::new(responses, throttle_time_ms, TaggedFieldsSection::empty());
+        Ok(api_response::v1(request_header.correlation_id, response))
+    }
+
+    /// Responds with the error for every partition of the request, as far as it can be decoded
+    pub fn error_response(
+        request_header: RequestHeaderV2,
+        buf: &[u8],
+        body_offset: usize,
+        error_code: i16,
     ) -> ApiResponse<ResponseHeaderV1, ProduceResponseV11> {
-        let produce_request = ProduceRequestV11::parse(buf, offset + request_header.size());
-        let responses = Self::responses(produce_request.topic_data);
+        let responses = ProduceRequestV11::parse(buf, body_offset)
+            .map(|produce_request| {
+                produce_request
+                    .topic_data
+                    .into_iter()
+                    .map(|topic| Self::error_topic_response(topic, error_code))
+                    .collect::<Vec<Response>>()
+                    .into()
+            })
+            .unwrap_or_else(|_| CompactArray::empty());
         let throttle_time_ms = 0;
         let response =
             ProduceResponseV11::new(responses, throttle_time_ms, TaggedFieldsSection::empty());
         api_response::v1(request_header.correlation_id, response)
     }
 
-    fn responses(topic_data: CompactArray<Topic>) -> CompactArray<Response> {
+    fn error_topic_response(topic: Topic, error_code: i16) -> Response {
+        let partition_responses = topic
+            .partition_data
+            .into_iter()
+            .map(|partition| {
+                PartitionResponse::new(
+                    partition.index,
+                    error_code,
+                    -1,
+                    -1,
+                    -1,
+                    CompactArray::empty(),
+                    CompactNullableString::null(),
+                    TaggedFieldsSection::empty(),
+                )
+            })
+            .collect::<Vec<PartitionResponse>>()
+            .into();
+        Response::new(
+            topic.name,
+            partition_responses,
+            TaggedFieldsSection::empty(),
+        )
+    }
+
+    fn responses(
+        topic_data: CompactArray<Topic>,
+        api_version: i16,
+        metadata_image: &MetadataImage,
+        server_config: &ServerConfig,
+    ) -> CompactArray<Response> {
         topic_data
             .into_iter()
-            .map(Self::response)
+            .map(|topic| Self::response(topic, api_version, metadata_image, server_config))
             .collect::<Vec<Response>>()
             .into()
     }
 
-    fn response(topic: Topic) -> Response {
-        let record_values = get_record_values_by_topic_name_from_metadata_log(&topic.name);
+    fn response(
+        topic: Topic,
+        api_version: i16,
+        metadata_image: &MetadataImage,
+        server_config: &ServerConfig,
+    ) -> Response {
+        let topic_log = TopicLog::new(&topic.name.bytes, metadata_image, server_config);
+        let partition_responses = topic
+            .partition_data
+            .into_iter()
+            .map(|partition| {
+                let (error_code, base_offset, log_append_time_ms, log_start_offset) = Self::produce(
+                    &topic_log,
+                    partition.index,
+                    partition.records.bytes(),
+                    api_version,
+                    metadata_image,
+                    server_config,
+                );
+                PartitionResponse::new(
+                    partition.index,
+                    error_code,
+                    base_offset,
+                    log_append_time_ms,
+                    log_start_offset,
+                    CompactArray::empty(),
+                    CompactNullableString::null(),
+                    TaggedFieldsSection::empty(),
+                )
+            })
+            .collect::<Vec<PartitionResponse>>()
+            .into();
         Response::new(
             topic.name,
-            Self::partition_responses(topic.partition_data, record_values),
+            partition_responses,
             TaggedFieldsSection::empty(),
         )
     }
 
-    fn partition_responses(
-        partition_data: CompactArray<Partition>,
-        record_values: Vec<RecordValue>,
-    ) -> CompactArray<PartitionResponse> {
-        partition_data
+    /// Responds to the versions before the first flexible one, with a v0 response header
+    pub fn respond_non_flexible<const V: i16>(
+        request_header: RequestHeaderV2,
+        buf: &[u8],
+        body_offset: usize,
+        metadata_image: &MetadataImage,
+        server_config: &ServerConfig,
+    ) -> Result<ApiResponse<ResponseHeaderV0, non_flexible::ProduceResponse<V>>, DecodeError> {
+        let produce_request = non_flexible::ProduceRequest::<V>::parse(buf, body_offset)?;
+        let responses = produce_request
+            .topic_data
             .into_iter()
-            .map(|partition| Self::partition_response(partition, &record_values))
-            .collect::<Vec<PartitionResponse>>()
-            .into()
+            .map(|topic| Self::non_flexible_response(topic, metadata_image, server_config))
+            .collect::<Vec<_>>()
+            .into();
+        let throttle_time_ms = 0;
+        let response = non_flexible::ProduceResponse::new(responses, throttle_time_ms);
+        Ok(api_response::v0(request_header.correlation_id, response))
     }
 
-    fn partition_response(
-        partition: Partition,
-        record_values: &[RecordValue],
-    ) -> PartitionResponse {
-        let (error_code, base_offset, log_start_offset) =
-            if Self::partition_exists(record_values, partition.index) {
-                (error_codes::NONE, 0, 0)
-            } else {
-                (error_codes::UNKNOWN_TOPIC_OR_PARTITION, -1i64, -1i64)
-            };
-        let log_append_time_ms = -1i64;
-        let record_errors = CompactArray::empty();
-        let error_message = CompactNullableString::null();
-        PartitionResponse::new(
-            partition.index,
-            error_code,
-            base_offset,
-            log_append_time_ms,
-            log_start_offset,
-            record_errors,
-            error_message,
-            TaggedFieldsSection::empty(),
-        )
+    fn non_flexible_response<const V: i16>(
+        topic: non_flexible::Topic,
+        metadata_image: &MetadataImage,
+        server_config: &ServerConfig,
+    ) -> non_flexible::Response<V> {
+        let topic_log = TopicLog::new(
+            topic.name.bytes().unwrap_or_default(),
+            metadata_image,
+            server_config,
+        );
+        let partition_responses = topic
+            .partition_data
+            .into_iter()
+            .map(|partition| {
+                let (error_code, base_offset, log_append_time_ms, log_start_offset) = Self::produce(
+                    &topic_log,
+                    partition.index,
+                    partition.records.bytes.as_deref(),
+                    V,
+                    metadata_image,
+                    server_config,
+                );
+                non_flexible::PartitionResponse::new(
+                    partition.index,
+                    error_code,
+                    base_offset,
+                    log_append_time_ms,
+                    log_start_offset,
+                )
+            })
+            .collect::<Vec<_>>()
+            .into();
+        non_flexible::Response::new(topic.name, partition_responses)
     }
 
-    fn partition_exists(record_values: &[RecordValue], partition_id: i32) -> bool {
-        let mut record_values = record_values.iter();
+    /// Appends the records produced to a partition of the topic. Returns the error code, base
+    /// offset, log append time and log start offset of the partition response.
+    fn produce(
+        topic_log: &TopicLog,
+        partition_index: i32,
+        records: Option<&[u8]>,
+        api_version: i16,
+        metadata_image: &MetadataImage,
+        server_config: &ServerConfig,
+    ) -> (i16, i64, i64, i64) {
+        let partition_exists = topic_log.id.is_some_and(|topic_id| {
+            metadata_image
+                .partition(&topic_id, partition_index)
+                .is_some()
+        });
+        if !partition_exists {
+            return (error_codes::UNKNOWN_TOPIC_OR_PARTITION, -1i64, -1i64, -1i64);
+        }
+        Self::append_records(
+            topic_log,
+            partition_index,
+            records.unwrap_or_default(),
+            api_version,
+            server_config,
+        )
+    }
 
-        // Assumes the partition records come after the topic record
-        let Some(topic_record) = record_values
-            .by_ref()
-            .find_map(|record_value| record_value.as_topic_record())
-        else {
-            return false;
+    /// Appends the partition's records, after checking that they decode and that the CRC of
+    /// each batch matches. The versions before 3 may produce a legacy message set, which is
+    /// up-converted to a v2 batch. Returns the error code, base offset, log append time and log
+    /// start offset of the partition response.
+    fn append_records(
+        topic_log: &TopicLog,
+        partition_index: i32,
+        records: &[u8],
+        api_version: i16,
+        server_config: &ServerConfig,
+    ) -> (i16, i64, i64, i64) {
+        let topic_name = &topic_log.name;
+        if legacy_message_set::is_legacy(records)
+            && api_version >= FIRST_VERSION_WITHOUT_MESSAGE_SETS
+        {
+            warn!(
+                "Rejecting message set for {topic_name}-{partition_index} in Produce v{api_version}"
+            );
+            return (error_codes::INVALID_RECORD, -1i64, -1i64, -1i64);
+        }
+        let record_batches = match Self::record_batches(records) {
+            Err(e) if e.kind() == ErrorKind::InvalidData => {
+                warn!("Rejecting records for {topic_name}-{partition_index}: {e}");
+                return (error_codes::CORRUPT_MESSAGE, -1i64, -1i64, -1i64);
+            }
+            record_batches => record_batches,
         };
-
-        for record_value in record_values {
-            if let RecordValue::Partition(partition_record) = record_value {
-                partition_record
-                    .directories_array
-                    .iter()
-                    .for_each(|d| println!("Directory id: {d}"));
-                if partition_record.partition_id == partition_id
-                    && partition_record.topic_uuid == topic_record.topic_uuid
-                {
-                    return true;
-                }
+        let log = Log::new(&server_config.log_dirs, topic_name, partition_index);
+        let appended = record_batches
+            .and_then(|record_batches| log.append(record_batches, &topic_log.config))
+            .and_then(|log_append_info| Ok((log_append_info, log.log_start_offset()?)));
+        match appended {
+            Ok((log_append_info, log_start_offset)) => (
+                error_codes::NONE,
+                log_append_info.base_offset,
+                log_append_info.log_append_time,
+                log_start_offset,
+            ),
+            Err(e) => {
+                error!("Failed to append records to {topic_name}-{partition_index}: {e}");
+                (error_codes::KAFKA_STORAGE_ERROR, -1i64, -1i64, -1i64)
             }
         }
+    }
+
+    /// The v2 batches of produced records, with the CRC of each checked. A legacy message set
+    /// is up-converted to a single batch. Records that fail to decode are `InvalidData`, while
+    /// the up-conversion only fails if compressing the batch does, as appending it can.
+    fn record_batches(records: &[u8]) -> io::Result<Vec<RecordBatch>> {
+        let invalid_data = |e: DecodeError| io::Error::new(ErrorKind::InvalidData, e);
+        if legacy_message_set::is_legacy(records) {
+            let (messages, compression) =
+                legacy_message_set::decompressed_messages(records).map_err(invalid_data)?;
+            return Ok(vec![legacy_message_set::up_convert(
+                &messages,
+                compression,
+            )?]);
+        }
+        let record_batches = RecordBatch::from_bytes(records).map_err(invalid_data)?;
+        for record_batch in &record_batches {
+            record_batch.validate_crc().map_err(invalid_data)?;
+            // Appended as they are, but the records are checked to decode first
+            record_batch.records().map_err(invalid_data)?;
+        }
+        Ok(record_batches)
+    }
+}
+
+/// A topic produced to, with what appending to the logs of its partitions needs
+struct TopicLog {
+    name: String,
+    /// Id of the topic, `None` if it doesn't exist
+    id: Option<Uuid>,
+    config: LogConfig,
+}
+
+impl TopicLog {
+    fn new(name: &[u8], metadata_image: &MetadataImage, server_config: &ServerConfig) -> Self {
+        Self {
+            name: String::from_utf8_lossy(name).into_owned(),
+            id: metadata_image
+                .topic_by_name(name)
+                .map(|topic_record| topic_record.topic_uuid),
+            config: LogConfig::for_topic(server_config, metadata_image.topic_configs(name)),
+        }
+    }
+}
+
+impl ApiHandler for ProduceApi {
+    fn api_key(&self) -> i16 {
+        PRODUCE
+    }
+
+    fn versions(&self) -> RangeInclusive<i16> {
+        produce::MIN_VERSION..=produce::MAX_VERSION
+    }
+
+    fn first_flexible_version(&self) -> i16 {
+        FIRST_FLEXIBLE_VERSION
+    }
+
+    fn handle(
+        &self,
+        request_header: RequestHeaderV2,
+        buf: &[u8],
+        body_offset: usize,
+        context: &RequestContext,
+    ) -> Result<Vec<u8>, DecodeError> {
+        let api_version = request_header.request_api_version;
+        if api_version >= FIRST_FLEXIBLE_VERSION {
+            return Ok(Self::respond(
+                request_header,
+                buf,
+                body_offset,
+                context.metadata_image,
+                context.server_config,
+            )?
+            .to_be_bytes());
+        }
+        with_version!(api_version, [0, 1, 2, 3, 4, 5, 6, 7, 8], |V| {
+            Ok(Self::respond_non_flexible::<V>(
+                request_header,
+                buf,
+                body_offset,
+                context.metadata_image,
+                context.server_config,
+            )?
+            .to_be_bytes())
+        })
+    }
+
+    fn unsupported_version(
+        &self,
+        request_header: RequestHeaderV2,
+        buf: &[u8],
+        body_offset: usize,
+        _context: &RequestContext,
+    ) -> Vec<u8> {
+        Self::error_response(
+            request_header,
+            buf,
+            body_offset,
+            error_codes::UNSUPPORTED_VERSION,
+        )
+        .to_be_bytes()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::*;
+    use crate::api_handler::ApiRegistry;
+    use crate::records::record_batch_builder::RecordBatchBuilder;
+    use crate::size::Size;
+    use crate::test_fixtures::{self, TempDir};
+    use crate::types::unsigned_varint::UnsignedVarint;
+    use crate::version_negotiation::RequestError;
+
+    const TOPIC_ID: Uuid = Uuid::from_u128(0x71a59a51_8968_4f8b_937e_e0d0100d856a);
+
+    /// Image holding topic "produce" with its partition 0
+    fn metadata_image() -> MetadataImage {
+        test_fixtures::metadata_image("produce", TOPIC_ID, 1, 0)
+    }
+
+    /// Broker config with a new log directory, removed along with the returned guard
+    fn server_config() -> (TempDir, ServerConfig) {
+        let log_dir = TempDir::new("produce");
+        let server_config = ServerConfig {
+            log_dirs: vec![log_dir.path().to_path_buf()],
+            ..ServerConfig::default()
+        };
+        (log_dir, server_config)
+    }
+
+    /// Batch of 2 records, as a producer sends it
+    fn record_batch_bytes() -> Vec<u8> {
+        RecordBatchBuilder::new(0)
+            .record(1000, None, Some(b"value1"), Vec::new())
+            .record(1001, None, Some(b"value2"), Vec::new())
+            .build()
+            .unwrap()
+            ._parsed_bytes
+    }
+
+    /// Produce v11 request of the records to a partition of "produce"
+    fn produce_request(partition_index: i32, records: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![
+            0x00, 0x00, 0x00, 0x00, // message_size, not read
+            0x00, 0x00, // request_api_key: 0
+            0x00, 0x0b, // request_api_version: 11
+            0x00, 0x00, 0x00, 0x07, // correlation_id: 7
+            0xff, 0xff, // client_id: null
+            0x00, // _tagged_fields
+            0x00, // transactional_id: null
+            0xff, 0xff, // acks: -1
+            0x00, 0x00, 0x75, 0x30, // timeout_ms: 30000
+            0x02, // topic_data (length: 1 + N): 2
+            0x08, b'p', b'r', b'o', b'd', b'u', b'c', b'e', // name: produce
+            0x02, // partition_data (length: 1 + N): 2
+        ];
+        bytes.extend(partition_index.to_be_bytes());
+        bytes.extend(UnsignedVarint::new(records.len() as u32 + 1).to_be_bytes());
+        bytes.extend(records);
+        bytes.extend([
+            0x00, // _tagged_fields of the partition
+            0x00, // _tagged_fields of the topic
+            0x00, // _tagged_fields
+        ]);
+        bytes
+    }
+
+    /// Error code, base offset and log start offset of the partition response
+    fn produce(request_bytes: &[u8], server_config: &ServerConfig) -> (i16, i64, i64) {
+        let request_header = RequestHeaderV2::parse(request_bytes, 0).unwrap();
+        let body_offset = request_header.size();
+
+        let bytes = ProduceApi::respond(
+            request_header,
+            request_bytes,
+            body_offset,
+            &metadata_image(),
+            server_config,
+        )
+        .unwrap()
+        .to_be_bytes();
+
+        // Skips the message_size, correlation_id and _tagged_fields of the response header
+        let response = ProduceResponseV11::parse(&bytes, 9).unwrap();
+        let partition_response = &response.responses[0].partition_responses[0];
+        (
+            partition_response.error_code,
+            partition_response.base_offset,
+            partition_response.log_start_offset,
+        )
+    }
+
+    #[test]
+    fn appends_batches_at_the_end_of_the_partition_log() {
+        let (_log_dir, server_config) = server_config();
+        let request_bytes = produce_request(0, &record_batch_bytes());
+
+        assert_eq!(
+            (error_codes::NONE, 0, 0),
+            produce(&request_bytes, &server_config)
+        );
+        assert_eq!(
+            (error_codes::NONE, 2, 0),
+            produce(&request_bytes, &server_config)
+        );
+
+        let log = Log::new(&server_config.log_dirs, "produce", 0);
+        assert_eq!(4, log.next_offset().unwrap());
+        assert_eq!(2, log.record_batches().unwrap().len());
+    }
+
+    #[test]
+    fn rejects_unknown_partition() {
+        let (_log_dir, server_config) = server_config();
+
+        assert_eq!(
+            (error_codes::UNKNOWN_TOPIC_OR_PARTITION, -1, -1),
+            produce(&produce_request(1, &record_batch_bytes()), &server_config)
+        );
+        assert_eq!(0, fs::read_dir(&server_config.log_dirs[0]).unwrap().count());
+    }
+
+    #[test]
+    fn rejects_batch_with_crc_mismatch() {
+        let (_log_dir, server_config) = server_config();
+        let mut records = record_batch_bytes();
+        // Corrupts the value of the last record
+        *records.last_mut().unwrap() ^= 0xff;
+
+        assert_eq!(
+            (error_codes::CORRUPT_MESSAGE, -1, -1),
+            produce(&produce_request(0, &records), &server_config)
+        );
+        assert!(Log::new(&server_config.log_dirs, "produce", 0)
+            .record_batches()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn rejects_truncated_request() {
+        let (_log_dir, server_config) = server_config();
+        let mut request_bytes = produce_request(0, &record_batch_bytes());
+        // Cuts the request within the records
+        request_bytes.truncate(request_bytes.len() - 10);
+
+        assert!(matches!(
+            ApiRegistry::builtin().respond(&request_bytes, &server_config, &metadata_image()),
+            Err(RequestError::Decode(_))
+        ));
+        assert_eq!(0, fs::read_dir(&server_config.log_dirs[0]).unwrap().count());
+    }
+
+    #[test]
+    fn rejects_message_set_from_version_3() {
+        let (_log_dir, server_config) = server_config();
+        let record_batch = RecordBatch::parse(&record_batch_bytes(), 0).unwrap();
+        let message_set = legacy_message_set::down_convert(&[record_batch], 1).unwrap();
+
+        assert_eq!(
+            (error_codes::INVALID_RECORD, -1, -1),
+            produce(&produce_request(0, &message_set), &server_config)
+        );
+    }
+
+    #[test]
+    fn up_converts_message_set_of_version_2() {
+        let (_log_dir, server_config) = server_config();
+        let record_batch = RecordBatch::parse(&record_batch_bytes(), 0).unwrap();
+        let message_set = legacy_message_set::down_convert(&[record_batch], 1).unwrap();
+        let mut request_bytes = vec![
+            0x00, 0x00, 0x00, 0x00, // message_size, not read
+            0x00, 0x00, // request_api_key: 0
+            0x00, 0x02, // request_api_version: 2
+            0x00, 0x00, 0x00, 0x07, // correlation_id: 7
+            0xff, 0xff, // client_id: null
+            0xff, 0xff, // acks: -1
+            0x00, 0x00, 0x75, 0x30, // timeout_ms: 30000
+            0x00, 0x00, 0x00, 0x01, // topic_data (length): 1
+            0x00, 0x07, b'p', b'r', b'o', b'd', b'u', b'c', b'e', // name: produce
+            0x00, 0x00, 0x00, 0x01, // partition_data (length): 1
+            0x00, 0x00, 0x00, 0x00, // index: 0
+        ];
+        request_bytes.extend((message_set.len() as i32).to_be_bytes());
+        request_bytes.extend(&message_set);
+
+        // Through the registry, which reads the v1 header of the version
+        let bytes = ApiRegistry::builtin()
+            .respond(&request_bytes, &server_config, &metadata_image())
+            .unwrap();
 
-        false
+        assert_eq!(
+            &[
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // index: 0, error_code: 0
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // base_offset: 0
+            ],
+            &bytes[4 + 4 + 4 + 2 + 7 + 4..][..14]
+        );
+        let record_batches = Log::new(&server_config.log_dirs, "produce", 0)
+            .record_batches()
+            .unwrap();
+        assert_eq!(1, record_batches.len());
+        assert_eq!(2, record_batches[0].magic);
+        assert_eq!(2, record_batches[0].records_count);
     }
 }
@@ -1,8 +1,6 @@
+use kafka_derive::{ByteParsable, Serializable, Size};
+
 use crate::{
-    byte_parsable::ByteParsable,
-    headers::response_header_v1::ResponseHeaderV1,
-    serializable::Serializable,
-    size::Size,
     tagged_fields_section::TaggedFieldsSection,
     types::{
         compact_array::CompactArray, compact_nullable_string::CompactNullableString,
@@ -26,11 +24,12 @@ use crate::{
 ///         batch_index_error_message => COMPACT_NULLABLE_STRING
 ///       error_message => COMPACT_NULLABLE_STRING
 ///   throttle_time_ms => INT32
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
 pub struct ProduceResponseV11 {
-    responses: CompactArray<Response>,
-    throttle_time_ms: i32,
-    _tagged_fields: TaggedFieldsSection,
+    pub responses: CompactArray<Response>,
+    pub throttle_time_ms: i32,
+    #[kafka(tagged_fields)]
+    pub _tagged_fields: TaggedFieldsSection,
 }
 
 impl ProduceResponseV11 {
@@ -47,27 +46,12 @@ impl ProduceResponseV11 {
     }
 }
 
-impl Size for ProduceResponseV11 {
-    fn size(&self) -> usize {
-        self.responses.size() + self.throttle_time_ms.size() + self._tagged_fields.size()
-    }
-}
-
-impl Serializable for ProduceResponseV11 {
-    fn to_be_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        bytes.extend_from_slice(&self.responses.to_be_bytes());
-        bytes.extend_from_slice(&self.throttle_time_ms.to_be_bytes());
-        bytes.extend_from_slice(&self._tagged_fields.to_be_bytes());
-        bytes
-    }
-}
-
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
 pub struct Response {
-    name: CompactString,
-    partition_responses: CompactArray<PartitionResponse>,
-    _tagged_fields: TaggedFieldsSection,
+    pub name: CompactString,
+    pub partition_responses: CompactArray<PartitionResponse>,
+    #[kafka(tagged_fields)]
+    pub _tagged_fields: TaggedFieldsSection,
 }
 
 impl Response {
@@ -84,38 +68,17 @@ impl Response {
     }
 }
 
-impl Size for Response {
-    fn size(&self) -> usize {
-        self.name.size() + self.partition_responses.size() + self._tagged_fields.size()
-    }
-}
-
-impl ByteParsable<Response> for Response {
-    fn parse(_bytes: &[u8], _offset: usize) -> Self {
-        todo!()
-    }
-}
-
-impl Serializable for Response {
-    fn to_be_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        bytes.extend_from_slice(&self.name.to_be_bytes());
-        bytes.extend_from_slice(&self.partition_responses.to_be_bytes());
-        bytes.extend_from_slice(&self._tagged_fields.to_be_bytes());
-        bytes
-    }
-}
-
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
 pub struct PartitionResponse {
-    index: i32,
-    error_code: i16,
-    base_offset: i64,
-    log_append_time_ms: i64,
-    log_start_offset: i64,
-    record_errors: CompactArray<RecordError>,
-    error_message: CompactNullableString,
-    _tagged_fields: TaggedFieldsSection,
+    pub index: i32,
+    pub error_code: i16,
+    pub base_offset: i64,
+    pub log_append_time_ms: i64,
+    pub log_start_offset: i64,
+    pub record_errors: CompactArray<RecordError>,
+    pub error_message: CompactNullableString,
+    #[kafka(tagged_fields)]
+    pub _tagged_fields: TaggedFieldsSection,
 }
 
 impl PartitionResponse {
@@ -143,65 +106,10 @@ impl PartitionResponse {
     }
 }
 
-impl Size for PartitionResponse {
-    fn size(&self) -> usize {
-        self.index.size()
-            + self.error_code.size()
-            + self.base_offset.size()
-            + self.log_append_time_ms.size()
-            + self.log_start_offset.size()
-            + self.record_errors.size()
-            + self.error_message.size()
-            + self._tagged_fields.size()
-    }
-}
-
-impl ByteParsable<PartitionResponse> for PartitionResponse {
-    fn parse(_bytes: &[u8], _offset: usize) -> PartitionResponse {
-        todo!()
-    }
-}
-
-impl Serializable for PartitionResponse {
-    fn to_be_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        bytes.extend_from_slice(&self.index.to_be_bytes());
-        bytes.extend_from_slice(&self.error_code.to_be_bytes());
-        bytes.extend_from_slice(&self.base_offset.to_be_bytes());
-        bytes.extend_from_slice(&self.log_append_time_ms.to_be_bytes());
-        bytes.extend_from_slice(&self.log_start_offset.to_be_bytes());
-        bytes.extend_from_slice(&self.record_errors.to_be_bytes());
-        bytes.extend_from_slice(&self.error_message.to_be_bytes());
-        bytes.extend_from_slice(&self._tagged_fields.to_be_bytes());
-        bytes
-    }
-}
-
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
 pub struct RecordError {
-    batch_index: i32,
-    batch_index_error_message: CompactNullableString,
-    _tagged_fields: TaggedFieldsSection,
-}
-
-impl Size for RecordError {
-    fn size(&self) -> usize {
-        self.batch_index.size() + self.batch_index_error_message.size() + self._tagged_fields.size()
-    }
-}
-
-impl ByteParsable<RecordError> for RecordError {
-    fn parse(_bytes: &[u8], _offset: usize) -> Self {
-        todo!()
-    }
-}
-
-impl Serializable for RecordError {
-    fn to_be_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        bytes.extend_from_slice(&self.batch_index.to_be_bytes());
-        bytes.extend_from_slice(&self.batch_index_error_message.to_be_bytes());
-        bytes.extend_from_slice(&self._tagged_fields.to_be_bytes());
-        bytes
-    }
+    pub batch_index: i32,
+    pub batch_index_error_message: CompactNullableString,
+    #[kafka(tagged_fields)]
+    pub _tagged_fields: TaggedFieldsSection,
 }
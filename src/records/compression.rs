@@ -0,0 +1,226 @@
+//! Codecs of compressed record batches. A batch's codec is in the lowest 3 bits of its
+//! attributes, and compresses the records that follow the batch header as a whole.
+
+use std::io::{self, Cursor, ErrorKind, Read, Write};
+use std::mem;
+
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+
+/// Bits of a batch's attributes holding its codec
+pub const CODEC_MASK: i16 = 0x07;
+// Header of the snappy framing of Java's xerial snappy-java, which Kafka's clients write: a
+// magic, then the version and the minimum compatible version
+const XERIAL_MAGIC: [u8; 8] = [0x82, b'S', b'N', b'A', b'P', b'P', b'Y', 0];
+const XERIAL_VERSION: i32 = 1;
+const XERIAL_HEADER_SIZE: usize = 16;
+// Size of the uncompressed blocks of the xerial framing, as snappy-java's default
+const XERIAL_BLOCK_SIZE: usize = 32 * 1024;
+// Compression level of zstd, as Kafka's default compression.zstd.level
+const ZSTD_LEVEL: i32 = 3;
+
+/// Codec of a record batch, as Kafka's CompressionType
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Snappy,
+    Lz4,
+    Zstd,
+}
+
+impl Compression {
+    /// The codec in a batch's attributes, or `None` if the bits don't name a known one
+    pub fn from_attributes(attributes: i16) -> Option<Self> {
+        match attributes & CODEC_MASK {
+            0 => Some(Self::None),
+            1 => Some(Self::Gzip),
+            2 => Some(Self::Snappy),
+            3 => Some(Self::Lz4),
+            4 => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    /// The attributes with their codec bits set to this codec
+    pub fn set_in(self, attributes: i16) -> i16 {
+        let id = match self {
+            Self::None => 0,
+            Self::Gzip => 1,
+            Self::Snappy => 2,
+            Self::Lz4 => 3,
+            Self::Zstd => 4,
+        };
+        (attributes & !CODEC_MASK) | id
+    }
+
+    pub fn compress(self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(bytes.to_vec()),
+            Self::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(bytes)?;
+                encoder.finish()
+            }
+            Self::Snappy => compress_xerial_snappy(bytes),
+            Self::Lz4 => {
+                let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+                encoder.write_all(bytes)?;
+                Ok(encoder.finish()?)
+            }
+            Self::Zstd => zstd::stream::encode_all(bytes, ZSTD_LEVEL),
+        }
+    }
+
+    /// Reader of the bytes compressed with the codec, decompressing them as they are read, so
+    /// that a reader stopping at the sizes a batch declares inflates no more than them
+    pub fn decoder<'a>(self, bytes: &'a [u8]) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Self::None => Box::new(bytes),
+            Self::Gzip => Box::new(MultiGzDecoder::new(bytes)),
+            Self::Snappy => Box::new(SnappyDecoder::new(bytes)),
+            Self::Lz4 => Box::new(lz4_flex::frame::FrameDecoder::new(bytes)),
+            Self::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(bytes)?),
+        })
+    }
+}
+
+/// Compresses in the xerial framing: its header, then each block compressed on its own and
+/// prefixed by its compressed length
+fn compress_xerial_snappy(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = snap::raw::Encoder::new();
+    let mut compressed = XERIAL_MAGIC.to_vec();
+    compressed.extend_from_slice(&XERIAL_VERSION.to_be_bytes());
+    compressed.extend_from_slice(&XERIAL_VERSION.to_be_bytes());
+    for block in bytes.chunks(XERIAL_BLOCK_SIZE) {
+        let compressed_block = encoder.compress_vec(block)?;
+        compressed.extend_from_slice(&(compressed_block.len() as i32).to_be_bytes());
+        compressed.extend_from_slice(&compressed_block);
+    }
+    Ok(compressed)
+}
+
+/// Reader decompressing snappy a block at a time: either the blocks of the xerial framing or,
+/// as librdkafka writes, a single raw snappy block
+struct SnappyDecoder<'a> {
+    decoder: snap::raw::Decoder,
+    xerial: bool,
+    /// The compressed blocks after the one being read
+    blocks: &'a [u8],
+    block: Cursor<Vec<u8>>,
+}
+
+impl<'a> SnappyDecoder<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        let xerial = bytes.starts_with(&XERIAL_MAGIC);
+        Self {
+            decoder: snap::raw::Decoder::new(),
+            xerial,
+            blocks: if xerial {
+                bytes.get(XERIAL_HEADER_SIZE..).unwrap_or_default()
+            } else {
+                bytes
+            },
+            block: Cursor::default(),
+        }
+    }
+
+    /// The next compressed block, `None` after the last one
+    fn next_block(&mut self) -> io::Result<Option<&'a [u8]>> {
+        if self.blocks.is_empty() {
+            return Ok(None);
+        }
+        if !self.xerial {
+            return Ok(Some(mem::take(&mut self.blocks)));
+        }
+        let (length, rest) = self
+            .blocks
+            .split_first_chunk::<4>()
+            .ok_or_else(|| invalid_xerial_block(self.blocks.len()))?;
+        let length = i32::from_be_bytes(*length);
+        let block = usize::try_from(length)
+            .ok()
+            .and_then(|length| rest.get(..length))
+            .ok_or_else(|| invalid_xerial_block(length as usize))?;
+        self.blocks = &rest[block.len()..];
+        Ok(Some(block))
+    }
+}
+
+impl Read for SnappyDecoder<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.block.position() as usize == self.block.get_ref().len() {
+            let Some(block) = self.next_block()? else {
+                return Ok(0);
+            };
+            self.block = Cursor::new(self.decoder.decompress_vec(block)?);
+        }
+        self.block.read(buf)
+    }
+}
+
+fn invalid_xerial_block(length: usize) -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidData,
+        format!("truncated xerial snappy block of {length} byte(s)"),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn decompress(codec: Compression, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        let mut decompressed = Vec::new();
+        codec.decoder(bytes)?.read_to_end(&mut decompressed)?;
+        Ok(decompressed)
+    }
+
+    const CODECS: [Compression; 5] = [
+        Compression::None,
+        Compression::Gzip,
+        Compression::Snappy,
+        Compression::Lz4,
+        Compression::Zstd,
+    ];
+
+    #[test]
+    fn decompresses_what_each_codec_compresses() {
+        let bytes = b"mango ".repeat(10_000);
+
+        for codec in CODECS {
+            let compressed = codec.compress(&bytes).unwrap();
+
+            assert_eq!(bytes, decompress(codec, &compressed).unwrap(), "{codec:?}");
+            assert_eq!(
+                Some(codec),
+                Compression::from_attributes(codec.set_in(0x18))
+            );
+            assert_eq!(0x18, codec.set_in(0x18) & !CODEC_MASK);
+        }
+    }
+
+    #[test]
+    fn frames_snappy_as_xerial() {
+        let bytes = b"mango ".repeat(10_000);
+
+        let compressed = Compression::Snappy.compress(&bytes).unwrap();
+
+        assert!(compressed.starts_with(&XERIAL_MAGIC));
+        let raw = snap::raw::Encoder::new().compress_vec(&bytes).unwrap();
+        assert_eq!(bytes, decompress(Compression::Snappy, &raw).unwrap());
+        assert_eq!(
+            ErrorKind::InvalidData,
+            decompress(Compression::Snappy, &compressed[..compressed.len() - 1])
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_codecs() {
+        assert_eq!(None, Compression::from_attributes(0x05));
+        assert_eq!(Some(Compression::Lz4), Compression::from_attributes(0x0b));
+    }
+}
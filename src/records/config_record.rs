@@ -0,0 +1,21 @@
+use kafka_derive::{ByteParsable, Serializable, Size};
+
+use crate::types::compact_nullable_string::CompactNullableString;
+use crate::types::compact_string::CompactString;
+use crate::types::unsigned_varint::UnsignedVarint;
+
+/// Resource type of the configs of a topic
+pub const TOPIC_RESOURCE: i8 = 2;
+
+/// A dynamic config of a resource, such as a topic's `cleanup.policy`. A null value deletes it.
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
+pub struct ConfigRecord {
+    pub frame_version: i8,
+    pub _type: i8,
+    pub version: i8,
+    pub resource_type: i8,
+    pub resource_name: CompactString,
+    pub name: CompactString,
+    pub value: CompactNullableString,
+    pub tagged_fields_count: UnsignedVarint,
+}
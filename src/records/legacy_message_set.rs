@@ -0,0 +1,541 @@
+//! Message sets of the legacy message formats that v2 record batches replaced, as Kafka's
+//! LegacyRecord: magic 0, and magic 1 which added a timestamp to each message. Every message
+//! has its own offset, size and CRC-32. A compressed message, the wrapper, holds a whole
+//! message set compressed as its value.
+//!
+//! The broker stores v2 batches only: legacy message sets are up-converted when produced and
+//! v2 batches are down-converted for the consumers that only read legacy message sets.
+
+use std::io::{self, Read};
+
+use crate::byte_parsable::ByteParsable;
+use crate::decode_error::{DecodeError, DecodeErrorReason};
+use crate::records::compression::Compression;
+use crate::records::record_batch::{non_negative_length, RecordBatch};
+use crate::records::record_batch_builder::RecordBatchBuilder;
+use crate::serializable::Serializable;
+use crate::size::Size;
+use crate::types::nullable_bytes::NullableBytes;
+
+pub const MAGIC_V0: i8 = 0;
+pub const MAGIC_V1: i8 = 1;
+// Bits of the attributes holding the codec of a wrapper message
+const CODEC_MASK: i8 = 0x07;
+// Attribute bit of magic 1 messages whose timestamp the broker set on append
+const LOG_APPEND_TIME: i8 = 0x08;
+// Timestamp of magic 0 messages, which have none
+const NO_TIMESTAMP: i64 = -1;
+// Positions in a message of its CRC and of its magic, the first byte the CRC covers. The magic
+// is at the same position as in v2 batches, which tells the formats apart.
+const CRC_POSITION: usize = 12;
+const MAGIC_POSITION: usize = 16;
+
+/// Message of the magic 0 or 1 format
+#[derive(Debug, Clone, PartialEq)]
+pub struct LegacyMessage {
+    pub offset: i64,
+    pub crc: u32,
+    pub magic: i8,
+    pub attributes: i8,
+    /// Timestamp of a magic 1 message, -1 for magic 0 messages
+    pub timestamp: i64,
+    pub key: NullableBytes,
+    pub value: NullableBytes,
+}
+
+impl LegacyMessage {
+    /// An uncompressed message, computing its CRC
+    pub fn new(
+        offset: i64,
+        magic: i8,
+        timestamp: i64,
+        key: Option<Vec<u8>>,
+        value: Option<Vec<u8>>,
+    ) -> Self {
+        let mut message = Self {
+            offset,
+            crc: 0,
+            magic,
+            attributes: 0,
+            timestamp: if magic == MAGIC_V0 {
+                NO_TIMESTAMP
+            } else {
+                timestamp
+            },
+            key: NullableBytes { bytes: key },
+            value: NullableBytes { bytes: value },
+        };
+        message.crc = message.compute_crc();
+        message
+    }
+
+    /// Codec of the message set a wrapper message holds as its value, `Compression::None` for
+    /// the messages that aren't wrappers
+    pub fn compression(&self) -> Result<Compression, DecodeError> {
+        // The codecs of the legacy formats are numbered as those of v2 batches, up to lz4
+        match Compression::from_attributes((self.attributes & CODEC_MASK).into()) {
+            Some(compression) if compression != Compression::Zstd => Ok(compression),
+            _ => Err(DecodeError::new::<Self>(
+                MAGIC_POSITION + 1,
+                DecodeErrorReason::UnknownCompression((self.attributes & CODEC_MASK).into()),
+            )),
+        }
+    }
+
+    /// Checks the CRC the message was received with, as a corrupt message fails to match
+    pub fn validate_crc(&self) -> Result<(), DecodeError> {
+        let computed = self.compute_crc();
+        if computed != self.crc {
+            return Err(DecodeError::new::<Self>(
+                CRC_POSITION,
+                DecodeErrorReason::CrcMismatch {
+                    stored: self.crc,
+                    computed,
+                },
+            ));
+        }
+        Ok(())
+    }
+
+    /// CRC-32 (IEEE) of the message from its magic to the end of its value
+    fn compute_crc(&self) -> u32 {
+        crc32fast::hash(&self.to_be_bytes()[MAGIC_POSITION..])
+    }
+
+    /// Size of the message after its offset and size fields, which its size field holds
+    fn message_size(&self) -> usize {
+        self.crc.size()
+            + self.magic.size()
+            + self.attributes.size()
+            + if self.magic == MAGIC_V0 {
+                0
+            } else {
+                self.timestamp.size()
+            }
+            + self.key.size()
+            + self.value.size()
+    }
+
+    /// The messages of a wrapper message, decompressed from its value, or the message itself
+    /// if it isn't a wrapper. The messages inside a wrapper are uncompressed, and those of a
+    /// wrapper whose timestamp the broker set on append take its timestamp.
+    fn flatten(self) -> Result<Vec<LegacyMessage>, DecodeError> {
+        let compression = self.compression()?;
+        if compression == Compression::None {
+            return Ok(vec![self]);
+        }
+        let decompression_error = |e: io::Error| {
+            DecodeError::new::<Self>(
+                MAGIC_POSITION,
+                DecodeErrorReason::Decompression(e.to_string()),
+            )
+        };
+        let value = self.value.bytes.as_deref().unwrap_or_default();
+        let mut decoder = compression.decoder(value).map_err(decompression_error)?;
+        let mut messages = Vec::new();
+        let mut message_bytes = Vec::new();
+        // The messages are read one at a time, each up to the size it declares, so that the value
+        // isn't inflated past them
+        loop {
+            message_bytes.clear();
+            // The offset and size fields, which come before the CRC
+            decoder
+                .by_ref()
+                .take(CRC_POSITION as u64)
+                .read_to_end(&mut message_bytes)
+                .map_err(decompression_error)?;
+            if message_bytes.is_empty() {
+                break;
+            }
+            let size_position = CRC_POSITION - size_of::<i32>();
+            let message_size = non_negative_length::<Self>(
+                i32::parse(&message_bytes, size_position)?,
+                size_position,
+            )?;
+            decoder
+                .by_ref()
+                .take(message_size as u64)
+                .read_to_end(&mut message_bytes)
+                .map_err(decompression_error)?;
+            let message = LegacyMessage::parse(&message_bytes, 0)?;
+            message.validate_crc()?;
+            messages.push(message);
+        }
+        if self.attributes & LOG_APPEND_TIME != 0 {
+            for message in &mut messages {
+                message.timestamp = self.timestamp;
+            }
+        }
+        Ok(messages)
+    }
+}
+
+impl Size for LegacyMessage {
+    fn size(&self) -> usize {
+        self.offset.size() + size_of::<i32>() + self.message_size()
+    }
+}
+
+impl Serializable for LegacyMessage {
+    fn to_be_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.size());
+        bytes.extend(self.offset.to_be_bytes());
+        bytes.extend((self.message_size() as i32).to_be_bytes());
+        bytes.extend(self.crc.to_be_bytes());
+        bytes.extend(self.magic.to_be_bytes());
+        bytes.extend(self.attributes.to_be_bytes());
+        if self.magic != MAGIC_V0 {
+            bytes.extend(self.timestamp.to_be_bytes());
+        }
+        bytes.extend(self.key.to_be_bytes());
+        bytes.extend(self.value.to_be_bytes());
+        bytes
+    }
+}
+
+impl ByteParsable<LegacyMessage> for LegacyMessage {
+    fn parse(bytes: &[u8], offset: usize) -> Result<Self, DecodeError> {
+        let initial_offset = offset;
+        let mut offset = offset;
+        let message_offset = i64::parse(bytes, offset)?;
+        offset += message_offset.size();
+        let message_size = i32::parse(bytes, offset)?;
+        // The size counts the bytes after the offset and size fields
+        let declared = non_negative_length::<Self>(message_size, offset)?
+            .checked_add(message_offset.size() + message_size.size())
+            .ok_or_else(|| {
+                DecodeError::new::<Self>(
+                    offset,
+                    DecodeErrorReason::InvalidLength(message_size.into()),
+                )
+            })?;
+        offset += message_size.size();
+        let crc = u32::parse(bytes, offset)?;
+        offset += crc.size();
+        let magic = i8::parse(bytes, offset)?;
+        if magic != MAGIC_V0 && magic != MAGIC_V1 {
+            return Err(DecodeError::new::<Self>(
+                offset,
+                DecodeErrorReason::OutOfRange(magic.into()),
+            ));
+        }
+        offset += magic.size();
+        let attributes = i8::parse(bytes, offset)?;
+        offset += attributes.size();
+        let timestamp = if magic == MAGIC_V0 {
+            NO_TIMESTAMP
+        } else {
+            let timestamp = i64::parse(bytes, offset)?;
+            offset += timestamp.size();
+            timestamp
+        };
+        let key = NullableBytes::parse(bytes, offset)?;
+        offset += key.size();
+        let value = NullableBytes::parse(bytes, offset)?;
+        offset += value.size();
+        let message = Self {
+            offset: message_offset,
+            crc,
+            magic,
+            attributes,
+            timestamp,
+            key,
+            value,
+        };
+        if offset - initial_offset != declared {
+            return Err(DecodeError::new::<Self>(
+                initial_offset,
+                DecodeErrorReason::LengthMismatch {
+                    declared,
+                    actual: offset - initial_offset,
+                },
+            ));
+        }
+        Ok(message)
+    }
+}
+
+/// Whether produced records are a legacy message set rather than v2 batches
+pub fn is_legacy(records: &[u8]) -> bool {
+    records
+        .get(MAGIC_POSITION)
+        .is_some_and(|&magic| magic as i8 == MAGIC_V0 || magic as i8 == MAGIC_V1)
+}
+
+/// Parses a message set, checking the CRC of each message
+pub fn parse_message_set(bytes: &[u8]) -> Result<Vec<LegacyMessage>, DecodeError> {
+    let mut messages = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let message = LegacyMessage::parse(bytes, offset)?;
+        message.validate_crc().map_err(|e| DecodeError {
+            offset: offset + e.offset,
+            ..e
+        })?;
+        offset += message.size();
+        messages.push(message);
+    }
+    Ok(messages)
+}
+
+/// The messages of a produced message set, with those of its wrappers decompressed, and the
+/// codec of its wrappers
+pub fn decompressed_messages(
+    bytes: &[u8],
+) -> Result<(Vec<LegacyMessage>, Compression), DecodeError> {
+    let mut compression = Compression::None;
+    let mut messages = Vec::new();
+    for message in parse_message_set(bytes)? {
+        if message.compression()? != Compression::None {
+            compression = message.compression()?;
+        }
+        messages.extend(message.flatten()?);
+    }
+    Ok((messages, compression))
+}
+
+/// Up-converts the messages of a produced message set to a v2 batch of the same records,
+/// compressed with the codec, as Kafka converts the message sets of old producers on append.
+/// Offsets are assigned on append, so those of the messages are dropped.
+pub fn up_convert(messages: &[LegacyMessage], compression: Compression) -> io::Result<RecordBatch> {
+    messages
+        .iter()
+        .fold(
+            RecordBatchBuilder::new(0).compression(compression),
+            |builder, message| {
+                builder.record(
+                    message.timestamp,
+                    message.key.bytes.as_deref(),
+                    message.value.bytes.as_deref(),
+                    Vec::new(),
+                )
+            },
+        )
+        .build()
+}
+
+/// Down-converts v2 batches to an uncompressed message set of the magic, for the consumers that
+/// only read that format. Record headers have no equivalent and are dropped, as are control
+/// batches. The messages are left uncompressed, so consumers get the records of zstd batches,
+/// which the legacy formats can't compress, too.
+pub fn down_convert(record_batches: &[RecordBatch], magic: i8) -> Result<Vec<u8>, DecodeError> {
+    let mut bytes = Vec::new();
+    for record_batch in record_batches
+        .iter()
+        .filter(|record_batch| !record_batch.is_control())
+    {
+        for record in record_batch.records()? {
+            let mut message = LegacyMessage::new(
+                record_batch.record_offset(&record),
+                magic,
+                record_batch.record_timestamp(&record),
+                record.key,
+                record.value,
+            );
+            if magic == MAGIC_V1 && record_batch.has_log_append_time() {
+                message.attributes |= LOG_APPEND_TIME;
+                message.crc = message.compute_crc();
+            }
+            bytes.extend(message.to_be_bytes());
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decode_error::DecodeErrorReason;
+
+    // Message set of 2 magic 0 messages, as written by a 0.9 producer
+    const MESSAGE_SET_V0: [u8; 63] = [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x13, 0x87, 0xa7, 0x7a,
+        0xb2, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x05, 0x68, 0x65, 0x6c, 0x6c,
+        0x6f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x14, 0xa8, 0xaa,
+        0x1c, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x6b, 0x00, 0x00, 0x00, 0x05, 0x77, 0x6f,
+        0x72, 0x6c, 0x64,
+    ];
+
+    // Magic 1 message with a key, as written by a 0.10 producer
+    const MESSAGE_SET_V1: [u8; 37] = [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x19, 0x3e, 0x73, 0x16,
+        0x74, 0x01, 0x00, 0x00, 0x00, 0x01, 0x8b, 0xcf, 0xe5, 0x68, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x6b, 0x00, 0x00, 0x00, 0x02, 0x76, 0x31,
+    ];
+
+    // Gzip wrapper message holding 2 magic 1 messages, whose offset is that of the last one
+    const GZIP_WRAPPER_V1: [u8; 92] = [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x50, 0x52, 0xd9, 0x3d,
+        0x1e, 0x01, 0x01, 0x00, 0x00, 0x01, 0x8b, 0xcf, 0xe5, 0x68, 0x01, 0xff, 0xff, 0xff, 0xff,
+        0x00, 0x00, 0x00, 0x3a, 0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x63,
+        0x60, 0x80, 0x03, 0x71, 0xa7, 0x2d, 0xeb, 0x99, 0x18, 0x81, 0x0c, 0xc6, 0xee, 0xf3, 0x4f,
+        0x33, 0x18, 0xfe, 0x03, 0x01, 0x88, 0x93, 0x08, 0x95, 0x06, 0xc9, 0x88, 0x9b, 0xd4, 0x4f,
+        0x6d, 0x83, 0x2b, 0x61, 0x84, 0x29, 0x49, 0x02, 0x00, 0x87, 0xa5, 0x1a, 0x0e, 0x46, 0x00,
+        0x00, 0x00,
+    ];
+
+    #[test]
+    fn parses_magic_0_message_set() {
+        let messages = parse_message_set(&MESSAGE_SET_V0).unwrap();
+
+        assert_eq!(
+            vec![
+                LegacyMessage::new(0, MAGIC_V0, NO_TIMESTAMP, None, Some(b"hello".to_vec())),
+                LegacyMessage::new(
+                    1,
+                    MAGIC_V0,
+                    NO_TIMESTAMP,
+                    Some(b"k".to_vec()),
+                    Some(b"world".to_vec())
+                ),
+            ],
+            messages
+        );
+        assert_eq!(
+            MESSAGE_SET_V0.to_vec(),
+            messages
+                .iter()
+                .flat_map(LegacyMessage::to_be_bytes)
+                .collect::<Vec<u8>>()
+        );
+    }
+
+    #[test]
+    fn parses_magic_1_message_with_timestamp() {
+        let message = LegacyMessage::parse(&MESSAGE_SET_V1, 0).unwrap();
+
+        assert_eq!(MAGIC_V1, message.magic);
+        assert_eq!(1_700_000_000_000, message.timestamp);
+        assert_eq!(Some(b"k".to_vec()), message.key.bytes);
+        assert_eq!(Some(b"v1".to_vec()), message.value.bytes);
+        assert!(message.validate_crc().is_ok());
+        assert_eq!(MESSAGE_SET_V1.len(), message.size());
+    }
+
+    #[test]
+    fn decompresses_messages_of_wrapper() {
+        let (messages, compression) = decompressed_messages(&GZIP_WRAPPER_V1).unwrap();
+
+        assert_eq!(Compression::Gzip, compression);
+        assert_eq!(
+            vec![
+                LegacyMessage::new(0, MAGIC_V1, 1_700_000_000_000, None, Some(b"a".to_vec())),
+                LegacyMessage::new(1, MAGIC_V1, 1_700_000_000_001, None, Some(b"b".to_vec())),
+            ],
+            messages
+        );
+    }
+
+    #[test]
+    fn rejects_message_of_negative_size() {
+        let mut bytes = MESSAGE_SET_V0;
+        bytes[8..12].copy_from_slice(&(-1i32).to_be_bytes());
+
+        let error = parse_message_set(&bytes).unwrap_err();
+
+        assert_eq!(8, error.offset);
+        assert_eq!(DecodeErrorReason::InvalidLength(-1), error.reason);
+    }
+
+    #[test]
+    fn rejects_message_that_fails_its_crc() {
+        let mut bytes = MESSAGE_SET_V0;
+        bytes[60] = 0x4c;
+
+        let error = parse_message_set(&bytes).unwrap_err();
+
+        assert_eq!(31 + CRC_POSITION, error.offset);
+        assert_eq!(
+            DecodeErrorReason::CrcMismatch {
+                stored: 0xa8aa1cff,
+                computed: crc32fast::hash(&bytes[31 + MAGIC_POSITION..]),
+            },
+            error.reason
+        );
+    }
+
+    #[test]
+    fn tells_legacy_message_sets_from_record_batches() {
+        let record_batch = RecordBatchBuilder::new(0)
+            .record(0, None, Some(b"v"), Vec::new())
+            .build()
+            .unwrap();
+
+        assert!(is_legacy(&MESSAGE_SET_V0));
+        assert!(is_legacy(&MESSAGE_SET_V1));
+        assert!(!is_legacy(&record_batch.to_be_bytes()));
+    }
+
+    #[test]
+    fn up_converts_messages_to_compressed_batch() {
+        let (messages, compression) = decompressed_messages(&GZIP_WRAPPER_V1).unwrap();
+
+        let record_batch = up_convert(&messages, compression).unwrap();
+
+        let parsed = RecordBatch::parse(&record_batch.to_be_bytes(), 0).unwrap();
+        assert!(parsed.validate_crc().is_ok());
+        assert_eq!(Compression::Gzip, parsed.compression());
+        let records: Vec<(i64, i64, Option<Vec<u8>>)> = parsed
+            .records()
+            .unwrap()
+            .iter()
+            .map(|record| {
+                (
+                    parsed.record_offset(record),
+                    parsed.record_timestamp(record),
+                    record.value.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (0, 1_700_000_000_000, Some(b"a".to_vec())),
+                (1, 1_700_000_000_001, Some(b"b".to_vec())),
+            ],
+            records
+        );
+    }
+
+    #[test]
+    fn down_converts_batches_without_control_batches() {
+        let record_batches = [
+            RecordBatchBuilder::new(5)
+                .compression(Compression::Zstd)
+                .record(1000, Some(b"k"), Some(b"v1"), Vec::new())
+                .record(2000, None, Some(b"v2"), Vec::new())
+                .build()
+                .unwrap(),
+            RecordBatchBuilder::new(7)
+                .control()
+                .record(
+                    3000,
+                    Some(&[0, 0, 0, 0]),
+                    Some(&[0, 0, 0, 0, 0, 0]),
+                    Vec::new(),
+                )
+                .build()
+                .unwrap(),
+        ];
+
+        assert_eq!(
+            vec![
+                LegacyMessage::new(5, MAGIC_V1, 1000, Some(b"k".to_vec()), Some(b"v1".to_vec())),
+                LegacyMessage::new(6, MAGIC_V1, 2000, None, Some(b"v2".to_vec())),
+            ],
+            parse_message_set(&down_convert(&record_batches, MAGIC_V1).unwrap()).unwrap()
+        );
+        assert_eq!(
+            vec![
+                LegacyMessage::new(
+                    5,
+                    MAGIC_V0,
+                    NO_TIMESTAMP,
+                    Some(b"k".to_vec()),
+                    Some(b"v1".to_vec())
+                ),
+                LegacyMessage::new(6, MAGIC_V0, NO_TIMESTAMP, None, Some(b"v2".to_vec())),
+            ],
+            parse_message_set(&down_convert(&record_batches, MAGIC_V0).unwrap()).unwrap()
+        );
+    }
+}
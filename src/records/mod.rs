@@ -1,5 +1,9 @@
+pub mod compression;
+pub mod config_record;
 pub mod feature_level_record;
+pub mod legacy_message_set;
 pub mod metadata_record;
 pub mod partition_record;
 pub mod record_batch;
+pub mod record_batch_builder;
 pub mod topic_record;
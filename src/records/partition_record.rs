@@ -1,12 +1,10 @@
+use kafka_derive::{ByteParsable, Serializable, Size};
 use uuid::Uuid;
 
 use crate::types::compact_array::CompactArray;
-use crate::{
-    byte_parsable::ByteParsable, partial_parsable::PartialParsable,
-    records::metadata_record::MetadataRecord, size::Size, types::unsigned_varint::UnsignedVarint,
-};
+use crate::types::unsigned_varint::UnsignedVarint;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
 pub struct PartitionRecord {
     pub frame_version: i8,
     pub _type: i8,
@@ -23,65 +21,3 @@ pub struct PartitionRecord {
     pub directories_array: CompactArray<Uuid>, // Array of UUIDs
     pub tagged_fields_count: UnsignedVarint,
 }
-
-impl Size for PartitionRecord {
-    fn size(&self) -> usize {
-        self.frame_version.size()
-            + self._type.size()
-            + self.version.size()
-            + self.partition_id.size()
-            + self.topic_uuid.size()
-            + self.replica_array.size()
-            + self.in_sync_replica_array.size()
-            + self.removing_replica_array.size()
-            + self.adding_replica_array.size()
-            + self.leader.size()
-            + self.leader_epoch.size()
-            + self.partition_epoch.size()
-            + self.directories_array.size()
-            + self.tagged_fields_count.size()
-    }
-}
-
-impl PartialParsable<Self, MetadataRecord> for PartitionRecord {
-    fn parse(bytes: &[u8], offset: usize, metadata_record: MetadataRecord) -> Self {
-        let mut offset = offset;
-        let partition_id = i32::parse(bytes, offset);
-        offset += partition_id.size();
-        let topic_uuid = Uuid::parse(bytes, offset);
-        offset += topic_uuid.size();
-        let replica_array = CompactArray::parse(bytes, offset);
-        offset += replica_array.size();
-        let in_sync_replica_array = CompactArray::parse(bytes, offset);
-        offset += in_sync_replica_array.size();
-        let removing_replica_array = CompactArray::parse(bytes, offset);
-        offset += removing_replica_array.size();
-        let adding_replica_array = CompactArray::parse(bytes, offset);
-        offset += adding_replica_array.size();
-        let leader = i32::parse(bytes, offset);
-        offset += leader.size();
-        let leader_epoch = i32::parse(bytes, offset);
-        offset += leader_epoch.size();
-        let partition_epoch = i32::parse(bytes, offset);
-        offset += partition_epoch.size();
-        let directories_array = CompactArray::parse(bytes, offset);
-        offset += directories_array.size();
-        let tagged_fields_count = UnsignedVarint::parse(bytes, offset);
-        Self {
-            frame_version: metadata_record.frame_version,
-            _type: metadata_record._type,
-            version: metadata_record.version,
-            partition_id,
-            topic_uuid,
-            replica_array,
-            in_sync_replica_array,
-            removing_replica_array,
-            adding_replica_array,
-            leader,
-            leader_epoch,
-            partition_epoch,
-            directories_array,
-            tagged_fields_count,
-        }
-    }
-}
@@ -1,19 +1,41 @@
-use std::fs::{self, File};
-use std::io::Read;
+use std::io::{self, BufRead, BufReader, ErrorKind, Read};
 
-use uuid::Uuid;
-
-use crate::partial_parsable::PartialParsable;
-use crate::records::metadata_record::{MetadataRecord, PARTITION, TOPIC};
+use crate::decode_error::{take_bytes, DecodeError, DecodeErrorReason};
+use crate::records::compression::{Compression, CODEC_MASK};
+use crate::records::config_record::ConfigRecord;
+use crate::records::feature_level_record::FeatureLevelRecord;
+use crate::records::metadata_record::{MetadataRecord, CONFIG, FEATURE_LEVEL, PARTITION, TOPIC};
 use crate::records::partition_record::PartitionRecord;
 use crate::records::topic_record::TopicRecord;
-use crate::types::compact_string::CompactString;
+use crate::serializable::Serializable;
 use crate::types::signed_varint::SignedVarint;
-use crate::types::unsigned_varint::UnsignedVarint;
 use crate::types::varlong::Varlong;
-use crate::utils::file;
 use crate::{byte_parsable::ByteParsable, size::Size};
 
+/// Magic of the v2 batch format, the only one a batch may have: older messages are in the
+/// legacy message set format
+pub const MAGIC: i8 = 2;
+// A record's length is a zigzag varint of an i32, which takes at most 5 bytes
+const MAX_RECORD_LENGTH_SIZE: u64 = 5;
+// Attribute bit set when the broker assigned the batch's max_timestamp on append, which is then
+// the timestamp of every record in it
+const LOG_APPEND_TIME: i16 = 0x08;
+/// Attribute bit of batches holding control records, such as the markers the broker writes to
+/// commit or abort transactions
+pub const CONTROL: i16 = 0x20;
+// Positions in the batch of the fields that rewriting it changes. The CRC covers the bytes from
+// the attributes to the end of the batch, and the records follow the header.
+const BATCH_LENGTH_POSITION: usize = 8;
+const CRC_POSITION: usize = 17;
+const ATTRIBUTES_POSITION: usize = 21;
+const LAST_OFFSET_DELTA_POSITION: usize = 23;
+const MAX_TIMESTAMP_POSITION: usize = 35;
+const RECORDS_COUNT_POSITION: usize = 57;
+const HEADER_SIZE: usize = 61;
+/// Bytes of a batch's header up to its `last_offset_delta`, which tell where the batch ends and
+/// the offsets it spans
+pub const SPAN_HEADER_SIZE: usize = LAST_OFFSET_DELTA_POSITION + 4;
+
 #[derive(Debug)]
 pub struct RecordBatch {
     pub base_offset: i64,
@@ -29,131 +51,350 @@ pub struct RecordBatch {
     pub producer_epoch: i16,
     pub base_sequence: i32,
     pub records_count: i32,
-    pub records: Vec<Record>,
-    // Store bytes parsed
+    // Store bytes parsed, the records staying encoded, compressed for a compressed batch, until
+    // they are asked for
     pub _parsed_bytes: Vec<u8>,
 }
 
 impl RecordBatch {
-    pub fn from_file(path: &str) -> Vec<RecordBatch> {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Vec<RecordBatch>, DecodeError> {
         let mut record_batches = Vec::new();
-
-        let buf = file::read_file(path)
-            .unwrap_or_else(|_| panic!("An error occurred trying to read file: {path}"));
         let mut offset = 0;
-        while offset < buf.len() {
-            let record_batch = RecordBatch::parse(&buf, offset);
+        while offset < bytes.len() {
+            let record_batch = RecordBatch::parse(bytes, offset)?;
             offset += record_batch.size();
             record_batches.push(record_batch);
         }
+        Ok(record_batches)
+    }
 
-        record_batches
+    /// The last offset and the size of the batch whose header the bytes start with, read from
+    /// its first `SPAN_HEADER_SIZE` bytes without the rest of the batch
+    pub fn span(header: &[u8]) -> Result<(i64, usize), DecodeError> {
+        let base_offset = i64::parse(header, 0)?;
+        let batch_length = i32::parse(header, BATCH_LENGTH_POSITION)?;
+        let last_offset_delta = i32::parse(header, LAST_OFFSET_DELTA_POSITION)?;
+        let batch_length = non_negative_length::<Self>(batch_length, BATCH_LENGTH_POSITION)?;
+        Ok((
+            base_offset + last_offset_delta as i64,
+            base_offset.size() + size_of::<i32>() + batch_length,
+        ))
     }
 
     pub fn expected_length(&self) -> usize {
         self.base_offset.size() + self.batch_length.size() + self.batch_length as usize
     }
 
-    pub fn parse_record_values(
-        &self,
-        search_item: SearchItem,
-        topic_record_only: bool,
-    ) -> Vec<RecordValue> {
+    /// Offset of the last record of the batch
+    pub fn last_offset(&self) -> i64 {
+        self.base_offset + self.last_offset_delta as i64
+    }
+
+    /// Offset that the first record after this batch will be assigned
+    pub fn next_offset(&self) -> i64 {
+        self.last_offset() + 1
+    }
+
+    /// Timestamp of one of the records of the batch
+    pub fn record_timestamp(&self, record: &Record) -> i64 {
+        if self.has_log_append_time() {
+            self.max_timestamp
+        } else {
+            self.base_timestamp + record.timestamp_delta.value
+        }
+    }
+
+    /// Whether the broker set the timestamp of the batch's records on append
+    pub fn has_log_append_time(&self) -> bool {
+        self.attributes & LOG_APPEND_TIME != 0
+    }
+
+    pub fn is_control(&self) -> bool {
+        self.attributes & CONTROL != 0
+    }
+
+    /// Offset of one of the records of the batch
+    pub fn record_offset(&self, record: &Record) -> i64 {
+        self.base_offset + record.offset_delta.value as i64
+    }
+
+    /// Codec the records of the batch are compressed with
+    pub fn compression(&self) -> Compression {
+        // Parsing rejects batches with unknown codecs
+        Compression::from_attributes(self.attributes).unwrap_or_default()
+    }
+
+    /// Keeps the records for which `keep`, given their offset, returns true. When records are
+    /// removed the batch is rewritten around the rest, compressed with the same codec, with
+    /// its length, `last_offset_delta`, `max_timestamp`, records count and CRC recomputed. The
+    /// base offset and timestamp stay, so the records kept have the same offsets and
+    /// timestamps as before.
+    pub fn retain_records(&mut self, mut keep: impl FnMut(i64, &Record) -> bool) -> io::Result<()> {
+        let mut records_bytes = Vec::new();
+        let mut records = Vec::new();
+        self.for_each_record(|record, record_bytes| {
+            if keep(self.base_offset + record.offset_delta.value as i64, &record) {
+                records_bytes.extend_from_slice(record_bytes);
+                records.push(record);
+            }
+        })
+        .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        if self.records_count as usize == records.len() {
+            return Ok(());
+        }
+        if let Some(last_record) = records.last() {
+            self.last_offset_delta = last_record.offset_delta.value;
+        }
+        if self.attributes & LOG_APPEND_TIME == 0 {
+            if let Some(max_timestamp) = records
+                .iter()
+                .map(|record| self.base_timestamp + record.timestamp_delta.value)
+                .max()
+            {
+                self.max_timestamp = max_timestamp;
+            }
+        }
+        self.records_count = records.len() as i32;
+        let bytes = &mut self._parsed_bytes;
+        bytes[LAST_OFFSET_DELTA_POSITION..LAST_OFFSET_DELTA_POSITION + 4]
+            .copy_from_slice(&self.last_offset_delta.to_be_bytes());
+        bytes[MAX_TIMESTAMP_POSITION..MAX_TIMESTAMP_POSITION + 8]
+            .copy_from_slice(&self.max_timestamp.to_be_bytes());
+        bytes[RECORDS_COUNT_POSITION..HEADER_SIZE]
+            .copy_from_slice(&self.records_count.to_be_bytes());
+        self.set_records_bytes(&records_bytes, self.compression())
+    }
+
+    /// Compresses the records of the batch with another codec, as topics whose
+    /// `compression.type` isn't `producer` store produced batches, and regenerates the CRC
+    pub fn recompress(&mut self, compression: Compression) -> io::Result<()> {
+        if compression == self.compression() {
+            return Ok(());
+        }
+        let mut records_bytes = Vec::new();
+        self.for_each_record(|_, record_bytes| records_bytes.extend_from_slice(record_bytes))
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        self.set_records_bytes(&records_bytes, compression)
+    }
+
+    /// The records of the batch, decoded from the bytes following its header, which are
+    /// decompressed first if the batch is compressed
+    pub fn records(&self) -> Result<Vec<Record>, DecodeError> {
+        let mut records = Vec::new();
+        self.for_each_record(|record, _| records.push(record))?;
+        Ok(records)
+    }
+
+    /// Decodes the records, passing each with its bytes to `f`. The records are read one at a
+    /// time, each up to the length it declares, and reading stops at the count the batch
+    /// declares, so that a compressed batch isn't inflated past them.
+    fn for_each_record(&self, mut f: impl FnMut(Record, &[u8])) -> Result<(), DecodeError> {
+        let decompression_error = |e: io::Error| {
+            DecodeError::new::<Self>(HEADER_SIZE, DecodeErrorReason::Decompression(e.to_string()))
+        };
+        let mut decoder = BufReader::new(
+            self.compression()
+                .decoder(&self._parsed_bytes[HEADER_SIZE..])
+                .map_err(decompression_error)?,
+        );
+        let mut record_bytes = Vec::new();
+        for _ in 0..self.records_count {
+            record_bytes.clear();
+            // A record or length cut short fails to parse below
+            read_varint_bytes(&mut decoder, &mut record_bytes).map_err(decompression_error)?;
+            let length = SignedVarint::parse(&record_bytes, 0)?;
+            let length = non_negative_length::<Record>(length.value, 0)?;
+            decoder
+                .by_ref()
+                .take(length as u64)
+                .read_to_end(&mut record_bytes)
+                .map_err(decompression_error)?;
+            f(Record::parse(&record_bytes, 0)?, &record_bytes);
+        }
+        if decoder.read(&mut [0]).map_err(decompression_error)? != 0 {
+            return Err(DecodeError::new::<Self>(
+                HEADER_SIZE,
+                DecodeErrorReason::RecordsRemaining(self.records_count),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Replaces the records following the header with the uncompressed records bytes,
+    /// compressed with the codec, updating the attributes, length and CRC to match
+    pub fn set_records_bytes(
+        &mut self,
+        records_bytes: &[u8],
+        compression: Compression,
+    ) -> io::Result<()> {
+        let records_bytes = compression.compress(records_bytes)?;
+        self.attributes = compression.set_in(self.attributes);
+        // The length counts the bytes after the length field
+        self.batch_length = (HEADER_SIZE + records_bytes.len() - BATCH_LENGTH_POSITION - 4) as i32;
+        let bytes = &mut self._parsed_bytes;
+        bytes.truncate(HEADER_SIZE);
+        bytes.extend_from_slice(&records_bytes);
+        bytes[BATCH_LENGTH_POSITION..BATCH_LENGTH_POSITION + 4]
+            .copy_from_slice(&self.batch_length.to_be_bytes());
+        bytes[ATTRIBUTES_POSITION..LAST_OFFSET_DELTA_POSITION]
+            .copy_from_slice(&self.attributes.to_be_bytes());
+        self.update_crc();
+        Ok(())
+    }
+
+    /// Sets the time the broker appended the batch as the timestamp of its records, as topics
+    /// with `message.timestamp.type=LogAppendTime` do, and regenerates the CRC
+    pub fn set_log_append_time(&mut self, timestamp: i64) {
+        self.attributes |= LOG_APPEND_TIME;
+        self.max_timestamp = timestamp;
+        let bytes = &mut self._parsed_bytes;
+        bytes[ATTRIBUTES_POSITION..LAST_OFFSET_DELTA_POSITION]
+            .copy_from_slice(&self.attributes.to_be_bytes());
+        bytes[MAX_TIMESTAMP_POSITION..MAX_TIMESTAMP_POSITION + 8]
+            .copy_from_slice(&timestamp.to_be_bytes());
+        self.update_crc();
+    }
+
+    /// Checks the CRC the batch was received or stored with, as a corrupt batch fails to match
+    pub fn validate_crc(&self) -> Result<(), DecodeError> {
+        let computed = self.compute_crc();
+        if computed != self.crc {
+            return Err(DecodeError::new::<Self>(
+                CRC_POSITION,
+                DecodeErrorReason::CrcMismatch {
+                    stored: self.crc,
+                    computed,
+                },
+            ));
+        }
+        Ok(())
+    }
+
+    /// CRC-32C (Castagnoli) of the batch from its attributes to the end of its records, as
+    /// Kafka checksums v2 batches
+    fn compute_crc(&self) -> u32 {
+        crc32c::crc32c(&self._parsed_bytes[ATTRIBUTES_POSITION..])
+    }
+
+    fn update_crc(&mut self) {
+        self.crc = self.compute_crc();
+        self._parsed_bytes[CRC_POSITION..ATTRIBUTES_POSITION]
+            .copy_from_slice(&self.crc.to_be_bytes());
+    }
+
+    pub fn set_base_offset(&mut self, base_offset: i64) {
+        self.base_offset = base_offset;
+        // base_offset is not covered by the CRC, so the parsed bytes can be patched in place
+        // and the CRC stays valid
+        self._parsed_bytes[..base_offset.size()].copy_from_slice(&base_offset.to_be_bytes());
+    }
+
+    /// Parses the values of the records in the batch as cluster metadata records, skipping
+    /// record types that aren't used
+    pub fn record_values(&self) -> Result<Vec<RecordValue>, DecodeError> {
         let mut record_values = Vec::new();
-        for record in &self.records {
-            let mut offset: usize = 0;
-            let metadata_record = MetadataRecord::parse(&record.value, offset);
-            offset += metadata_record.size();
+        for record in &self.records()? {
+            let Some(value) = &record.value else {
+                continue;
+            };
+            // Each record type parses its own copy of the metadata record header
+            let metadata_record = MetadataRecord::parse(value, 0)?;
             match metadata_record._type {
                 TOPIC => {
-                    let topic_record = TopicRecord::parse(&record.value, offset, metadata_record);
-                    if search_item.found_in(&topic_record) {
-                        record_values.push(RecordValue::Topic(topic_record));
-                    } else {
-                        break;
-                    }
-                    if topic_record_only {
-                        break;
-                    }
+                    let topic_record = TopicRecord::parse(value, 0)?;
+                    record_values.push(RecordValue::Topic(topic_record));
                 }
-                // Based on topic records always existing and before a partition record if one is in
-                // the batch
                 PARTITION => {
-                    let partition_record =
-                        PartitionRecord::parse(&record.value, offset, metadata_record);
+                    let partition_record = PartitionRecord::parse(value, 0)?;
                     record_values.push(RecordValue::Partition(partition_record));
                 }
+                CONFIG => {
+                    let config_record = ConfigRecord::parse(value, 0)?;
+                    record_values.push(RecordValue::Config(config_record));
+                }
+                FEATURE_LEVEL => {
+                    let feature_level_record = FeatureLevelRecord::parse(value, 0)?;
+                    record_values.push(RecordValue::FeatureLevel(feature_level_record));
+                }
                 _ => {}
             }
         }
-        record_values
-    }
-
-    pub fn find_record_values_by_topic_name(&self, topic_name: &CompactString) -> Vec<RecordValue> {
-        self.parse_record_values(SearchItem::TopicName(topic_name.clone()), false)
+        Ok(record_values)
     }
 }
 
 impl Size for RecordBatch {
     fn size(&self) -> usize {
-        self.base_offset.size()
-            + self.batch_length.size()
-            + self.partition_leader_epoch.size()
-            + self.magic.size()
-            + self.crc.size()
-            + self.attributes.size()
-            + self.last_offset_delta.size()
-            + self.base_timestamp.size()
-            + self.max_timestamp.size()
-            + self.producer_id.size()
-            + self.producer_epoch.size()
-            + self.base_sequence.size()
-            + self.records_count.size()
-            + self.records.size()
+        // The records of a compressed batch take less than their parsed size
+        self._parsed_bytes.len()
+    }
+}
+
+impl Serializable for RecordBatch {
+    fn to_be_bytes(&self) -> Vec<u8> {
+        self._parsed_bytes.clone()
     }
 }
 
 impl ByteParsable<RecordBatch> for RecordBatch {
-    fn parse(bytes: &[u8], offset: usize) -> Self {
+    fn parse(bytes: &[u8], offset: usize) -> Result<Self, DecodeError> {
         let initial_offset: usize = offset;
         let mut offset: usize = offset;
-        let base_offset = i64::parse(bytes, offset);
+        let base_offset = i64::parse(bytes, offset)?;
         offset += base_offset.size();
-        let batch_length = i32::parse(bytes, offset);
+        let batch_length = i32::parse(bytes, offset)?;
         offset += batch_length.size();
-        let partition_leader_epoch = i32::parse(bytes, offset);
+        let partition_leader_epoch = i32::parse(bytes, offset)?;
         offset += partition_leader_epoch.size();
-        let magic = i8::parse(bytes, offset);
+        let magic = i8::parse(bytes, offset)?;
+        if magic != MAGIC {
+            return Err(DecodeError::new::<Self>(
+                offset,
+                DecodeErrorReason::OutOfRange(magic.into()),
+            ));
+        }
         offset += magic.size();
-        let crc = u32::parse(bytes, offset);
+        let crc = u32::parse(bytes, offset)?;
         offset += crc.size();
-        let attributes = i16::parse(bytes, offset);
+        let attributes = i16::parse(bytes, offset)?;
+        if Compression::from_attributes(attributes).is_none() {
+            return Err(DecodeError::new::<Self>(
+                offset,
+                DecodeErrorReason::UnknownCompression(attributes & CODEC_MASK),
+            ));
+        }
         offset += attributes.size();
-        let last_offset_delta = i32::parse(bytes, offset);
+        let last_offset_delta = i32::parse(bytes, offset)?;
         offset += last_offset_delta.size();
-        let base_timestamp = i64::parse(bytes, offset);
+        let base_timestamp = i64::parse(bytes, offset)?;
         offset += base_timestamp.size();
-        let max_timestamp = i64::parse(bytes, offset);
+        let max_timestamp = i64::parse(bytes, offset)?;
         offset += max_timestamp.size();
-        let producer_id = i64::parse(bytes, offset);
+        let producer_id = i64::parse(bytes, offset)?;
         offset += producer_id.size();
-        let producer_epoch = i16::parse(bytes, offset);
+        let producer_epoch = i16::parse(bytes, offset)?;
         offset += producer_epoch.size();
-        let base_sequence = i32::parse(bytes, offset);
+        let base_sequence = i32::parse(bytes, offset)?;
         offset += base_sequence.size();
-        let records_count = i32::parse(bytes, offset);
+        let records_count = i32::parse(bytes, offset)?;
+        non_negative_length::<Self>(records_count, offset)?;
         offset += records_count.size();
-        let mut records = Vec::with_capacity(records_count as usize);
-        for _ in 0..records_count {
-            let record = Record::parse(bytes, offset);
-            offset += record.size();
-            records.push(record);
+        let expected_batch_size = base_offset.size()
+            + batch_length.size()
+            + non_negative_length::<Self>(batch_length, initial_offset + BATCH_LENGTH_POSITION)?;
+        if expected_batch_size < offset - initial_offset {
+            return Err(DecodeError::new::<Self>(
+                initial_offset,
+                DecodeErrorReason::LengthMismatch {
+                    declared: expected_batch_size,
+                    actual: offset - initial_offset,
+                },
+            ));
         }
-        let parsed_bytes_count = offset - initial_offset;
-        let expected_batch_size = base_offset.size() + batch_length.size() + batch_length as usize;
-        assert_eq!(parsed_bytes_count, expected_batch_size);
-        let _parsed_bytes = bytes[initial_offset..offset].to_vec();
-        Self {
+        // The records are only decoded when asked for
+        let _parsed_bytes =
+            take_bytes::<Self>(bytes, initial_offset, expected_batch_size)?.to_vec();
+        Ok(Self {
             base_offset,
             batch_length,
             partition_leader_epoch,
@@ -167,9 +408,8 @@ impl ByteParsable<RecordBatch> for RecordBatch {
             producer_epoch,
             base_sequence,
             records_count,
-            records,
             _parsed_bytes,
-        }
+        })
     }
 }
 
@@ -182,11 +422,42 @@ pub struct Record {
     pub key_length: SignedVarint,
     pub key: Option<Vec<u8>>,
     pub value_length: SignedVarint,
-    pub value: Vec<u8>,
+    pub value: Option<Vec<u8>>,
     pub headers_count: SignedVarint,
     pub headers: Option<Vec<Header>>,
 }
 
+impl Record {
+    /// A record at the deltas from its batch's base offset and timestamp, computing the
+    /// lengths of its key, value and itself
+    pub fn new(
+        offset_delta: i32,
+        timestamp_delta: i64,
+        key: Option<Vec<u8>>,
+        value: Option<Vec<u8>>,
+        headers: Vec<Header>,
+    ) -> Self {
+        let nullable_length = |bytes: &Option<Vec<u8>>| {
+            SignedVarint::new(bytes.as_ref().map_or(-1, |b| b.len() as i32))
+        };
+        let mut record = Self {
+            length: SignedVarint::new(0),
+            attributes: 0,
+            timestamp_delta: Varlong::new(timestamp_delta),
+            offset_delta: SignedVarint::new(offset_delta),
+            key_length: nullable_length(&key),
+            key,
+            value_length: nullable_length(&value),
+            value,
+            headers_count: SignedVarint::new(headers.len() as i32),
+            headers: (!headers.is_empty()).then_some(headers),
+        };
+        // The length counts the bytes after the length field
+        record.length = SignedVarint::new((record.size() - record.length.size()) as i32);
+        record
+    }
+}
+
 impl Size for Record {
     fn size(&self) -> usize {
         self.length.size()
@@ -202,48 +473,79 @@ impl Size for Record {
     }
 }
 
+impl Serializable for Record {
+    fn to_be_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.size());
+        bytes.extend(self.length.to_be_bytes());
+        bytes.extend(self.attributes.to_be_bytes());
+        bytes.extend(self.timestamp_delta.to_be_bytes());
+        bytes.extend(self.offset_delta.to_be_bytes());
+        bytes.extend(self.key_length.to_be_bytes());
+        bytes.extend(self.key.to_be_bytes());
+        bytes.extend(self.value_length.to_be_bytes());
+        bytes.extend(self.value.to_be_bytes());
+        bytes.extend(self.headers_count.to_be_bytes());
+        bytes.extend(self.headers.to_be_bytes());
+        bytes
+    }
+}
+
 impl ByteParsable<Record> for Record {
-    fn parse(bytes: &[u8], offset: usize) -> Self {
+    fn parse(bytes: &[u8], offset: usize) -> Result<Self, DecodeError> {
         let initial_offset: usize = offset;
         let mut offset: usize = offset;
-        let length = SignedVarint::parse(bytes, offset);
+        let length = SignedVarint::parse(bytes, offset)?;
         offset += length.size();
-        let attributes = i8::parse(bytes, offset);
+        let attributes = i8::parse(bytes, offset)?;
         offset += attributes.size();
-        let timestamp_delta = Varlong::parse(bytes, offset);
+        let timestamp_delta = Varlong::parse(bytes, offset)?;
         offset += timestamp_delta.size();
-        let offset_delta = SignedVarint::parse(bytes, offset);
+        let offset_delta = SignedVarint::parse(bytes, offset)?;
         offset += offset_delta.size();
-        let key_length = SignedVarint::parse(bytes, offset);
+        let key_length = SignedVarint::parse(bytes, offset)?;
         offset += key_length.size();
         let key = if key_length.value == -1 {
             None
         } else {
-            Some(bytes[offset..offset + key_length.value as usize].to_vec())
+            let key_length = non_negative_length::<Self>(key_length.value, offset)?;
+            Some(take_bytes::<Self>(bytes, offset, key_length)?.to_vec())
         };
         offset += key.size();
-        let value_length = SignedVarint::parse(bytes, offset);
+        let value_length = SignedVarint::parse(bytes, offset)?;
         offset += value_length.size();
-        let value = bytes[offset..offset + value_length.value as usize].to_vec();
-        offset += value.len();
-        let headers_count = SignedVarint::parse(bytes, offset);
+        // A null value marks the record as a tombstone for its key
+        let value = if value_length.value == -1 {
+            None
+        } else {
+            let value_length = non_negative_length::<Self>(value_length.value, offset)?;
+            Some(take_bytes::<Self>(bytes, offset, value_length)?.to_vec())
+        };
+        offset += value.size();
+        let headers_count = SignedVarint::parse(bytes, offset)?;
         offset += headers_count.size();
         let headers = if headers_count.value == 0 {
             None
         } else {
             let mut headers = Vec::new();
-            for _ in 0..headers_count.value {
-                let header = Header::parse(bytes, offset);
+            for _ in 0..non_negative_length::<Self>(headers_count.value, offset)? {
+                let header = Header::parse(bytes, offset)?;
                 offset += header.size();
                 headers.push(header);
             }
             Some(headers)
         };
-        assert_eq!(
-            offset - initial_offset,
-            length.size() + length.value as usize
-        );
-        Self {
+        let expected_record_size =
+            length.size() + non_negative_length::<Self>(length.value, offset)?;
+        if offset - initial_offset != expected_record_size {
+            return Err(DecodeError::new::<Self>(
+                initial_offset,
+                DecodeErrorReason::LengthMismatch {
+                    declared: expected_record_size,
+                    actual: offset - initial_offset,
+                },
+            ));
+        }
+        Ok(Self {
             length,
             attributes,
             timestamp_delta,
@@ -254,16 +556,36 @@ impl ByteParsable<Record> for Record {
             value,
             headers_count,
             headers,
-        }
+        })
     }
 }
 
-#[derive(Clone, Debug)]
+/// Header of a record: a string key with a value that may be null
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Header {
-    header_key_length: SignedVarint,
-    header_key: String,
-    header_value_length: SignedVarint,
-    value: Vec<u8>,
+    pub header_key_length: SignedVarint,
+    pub header_key: String,
+    pub header_value_length: SignedVarint,
+    pub value: Option<Vec<u8>>,
+}
+
+impl Header {
+    pub fn new(key: &str, value: Option<&[u8]>) -> Self {
+        Self {
+            header_key_length: SignedVarint::new(key.len() as i32),
+            header_key: key.to_string(),
+            header_value_length: SignedVarint::new(value.map_or(-1, |value| value.len() as i32)),
+            value: value.map(<[u8]>::to_vec),
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.header_key
+    }
+
+    pub fn value(&self) -> Option<&[u8]> {
+        self.value.as_deref()
+    }
 }
 
 impl Size for Header {
@@ -275,80 +597,282 @@ impl Size for Header {
     }
 }
 
+impl Serializable for Header {
+    fn to_be_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.size());
+        bytes.extend(self.header_key_length.to_be_bytes());
+        bytes.extend(self.header_key.as_bytes());
+        bytes.extend(self.header_value_length.to_be_bytes());
+        bytes.extend(self.value.to_be_bytes());
+        bytes
+    }
+}
+
 impl ByteParsable<Header> for Header {
-    fn parse(bytes: &[u8], offset: usize) -> Self {
+    fn parse(bytes: &[u8], offset: usize) -> Result<Self, DecodeError> {
         let mut offset: usize = offset;
-        let header_key_length = SignedVarint::parse(bytes, offset);
+        let header_key_length = SignedVarint::parse(bytes, offset)?;
         offset += header_key_length.size();
-        let header_key =
-            match String::from_utf8(bytes[offset..header_key_length.value as usize].to_vec()) {
-                Ok(s) => s,
-                Err(e) => panic!("Invalid UTF-8: {}", e),
-            };
-        offset += header_key_length.size();
-        let header_value_length = SignedVarint::parse(bytes, offset);
+        let header_key_bytes = take_bytes::<Self>(
+            bytes,
+            offset,
+            non_negative_length::<Self>(header_key_length.value, offset)?,
+        )?;
+        let header_key = String::from_utf8(header_key_bytes.to_vec())
+            .map_err(|_| DecodeError::new::<Self>(offset, DecodeErrorReason::InvalidUtf8))?;
+        offset += header_key.size();
+        let header_value_length = SignedVarint::parse(bytes, offset)?;
         offset += header_value_length.size();
-        let value = bytes[offset..offset + header_value_length.value as usize].to_vec();
-        Self {
+        let value = if header_value_length.value == -1 {
+            None
+        } else {
+            let value_length = non_negative_length::<Self>(header_value_length.value, offset)?;
+            Some(take_bytes::<Self>(bytes, offset, value_length)?.to_vec())
+        };
+        Ok(Self {
             header_key_length,
             header_key,
             header_value_length,
             value,
-        }
+        })
     }
 }
 
-pub enum SearchItem {
-    TopicId(Uuid),
-    TopicName(CompactString),
-}
-
-impl SearchItem {
-    fn found_in(&self, topic_record: &TopicRecord) -> bool {
-        match self {
-            Self::TopicId(id) => id == &topic_record.topic_uuid,
-            Self::TopicName(name) => name == &topic_record.topic_name,
+/// Reads the bytes of a record's length varint, up to the first without the continuation bit
+fn read_varint_bytes(reader: &mut impl BufRead, bytes: &mut Vec<u8>) -> io::Result<()> {
+    for byte in reader.take(MAX_RECORD_LENGTH_SIZE).bytes() {
+        let byte = byte?;
+        bytes.push(byte);
+        if byte & 0x80 == 0 {
+            break;
         }
     }
+    Ok(())
+}
+
+/// Converts a length read off the wire, rejecting negative values
+pub(crate) fn non_negative_length<T: ?Sized>(
+    length: i32,
+    offset: usize,
+) -> Result<usize, DecodeError> {
+    usize::try_from(length)
+        .map_err(|_| DecodeError::new::<T>(offset, DecodeErrorReason::InvalidLength(length.into())))
 }
 
 pub enum RecordValue {
     Topic(TopicRecord),
     Partition(PartitionRecord),
+    Config(ConfigRecord),
+    FeatureLevel(FeatureLevelRecord),
 }
 
-impl RecordValue {
-    pub fn to_topic_record(&self) -> Option<TopicRecord> {
-        if let RecordValue::Topic(record) = self {
-            Some(record.clone())
-        } else {
-            None
-        }
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_fixtures::RECORD_BATCH;
+
+    #[test]
+    fn computes_crc_as_kafka_does() {
+        let record_batch = RecordBatch::parse(&RECORD_BATCH, 0).unwrap();
+
+        assert_eq!(0xb069457c, record_batch.crc);
+        assert_eq!(record_batch.crc, record_batch.compute_crc());
     }
 
-    #[allow(clippy::needless_borrow)]
-    pub fn as_topic_record(&self) -> Option<&TopicRecord> {
-        if let RecordValue::Topic(record) = self {
-            Some(&record)
-        } else {
-            None
-        }
+    #[test]
+    fn rejects_batch_whose_crc_does_not_match() {
+        let mut bytes = RECORD_BATCH;
+        bytes[RECORD_BATCH.len() - 2] ^= 0x01;
+
+        let error = RecordBatch::parse(&bytes, 0)
+            .unwrap()
+            .validate_crc()
+            .unwrap_err();
+
+        assert_eq!(
+            DecodeErrorReason::CrcMismatch {
+                stored: 0xb069457c,
+                computed: crc32c::crc32c(&bytes[ATTRIBUTES_POSITION..])
+            },
+            error.reason
+        );
     }
 
-    #[allow(dead_code)]
-    pub fn to_partition_record(&self) -> Option<PartitionRecord> {
-        if let RecordValue::Partition(record) = self {
-            Some(record.clone())
-        } else {
-            None
-        }
+    #[test]
+    fn regenerates_crc_for_log_append_time() {
+        let mut record_batch = RecordBatch::parse(&RECORD_BATCH, 0).unwrap();
+
+        record_batch.set_log_append_time(1_700_000_000_000);
+
+        let record_batch = RecordBatch::parse(&record_batch.to_be_bytes(), 0).unwrap();
+        assert!(record_batch.validate_crc().is_ok());
+        assert_eq!(1_700_000_000_000, record_batch.max_timestamp);
+        assert_eq!(
+            1_700_000_000_000,
+            record_batch.record_timestamp(&record_batch.records().unwrap()[0])
+        );
     }
 
-    pub fn into_partition_record(self) -> Option<PartitionRecord> {
-        if let RecordValue::Partition(record) = self {
-            Some(record)
-        } else {
-            None
+    #[test]
+    fn rewrites_batch_only_when_records_are_removed() {
+        let mut record_batch = RecordBatch::parse(&RECORD_BATCH, 0).unwrap();
+
+        record_batch.retain_records(|_, _| true).unwrap();
+        assert_eq!(RECORD_BATCH.to_vec(), record_batch.to_be_bytes());
+
+        record_batch.retain_records(|_, _| false).unwrap();
+        assert_eq!(0, record_batch.records_count);
+        assert_eq!(HEADER_SIZE, record_batch.to_be_bytes().len());
+        assert_eq!(record_batch.expected_length(), record_batch.size());
+        assert_eq!(record_batch.crc, record_batch.compute_crc());
+    }
+
+    #[test]
+    fn decompresses_records_of_compressed_batches() {
+        for compression in [
+            Compression::Gzip,
+            Compression::Snappy,
+            Compression::Lz4,
+            Compression::Zstd,
+        ] {
+            let mut record_batch = RecordBatch::parse(&RECORD_BATCH, 0).unwrap();
+
+            record_batch.recompress(compression).unwrap();
+
+            let bytes = record_batch.to_be_bytes();
+            let mut record_batch = RecordBatch::parse(&bytes, 0).unwrap();
+            assert_eq!(compression, record_batch.compression());
+            assert!(record_batch.validate_crc().is_ok());
+            assert_eq!(bytes.len(), record_batch.size());
+            assert_eq!(record_batch.expected_length(), record_batch.size());
+            assert_eq!(
+                RecordBatch::parse(&RECORD_BATCH, 0)
+                    .unwrap()
+                    .records()
+                    .unwrap()[0]
+                    .value,
+                record_batch.records().unwrap()[0].value
+            );
+            record_batch.recompress(Compression::None).unwrap();
+            assert_eq!(RECORD_BATCH.to_vec(), record_batch.to_be_bytes());
         }
     }
+
+    #[test]
+    fn rejects_batch_with_unknown_codec() {
+        let mut bytes = RECORD_BATCH;
+        bytes[ATTRIBUTES_POSITION + 1] = 0x06;
+
+        let error = RecordBatch::parse(&bytes, 0).unwrap_err();
+
+        assert_eq!(DecodeErrorReason::UnknownCompression(6), error.reason);
+        assert_eq!(ATTRIBUTES_POSITION, error.offset);
+    }
+
+    #[test]
+    fn rejects_batch_of_legacy_magic() {
+        let mut bytes = RECORD_BATCH;
+        bytes[16] = 0x01;
+
+        let error = RecordBatch::parse(&bytes, 0).unwrap_err();
+
+        assert_eq!(DecodeErrorReason::OutOfRange(1), error.reason);
+        assert_eq!(16, error.offset);
+    }
+
+    #[test]
+    fn decodes_records_only_when_asked_for() {
+        let mut bytes = RECORD_BATCH;
+        bytes[RECORDS_COUNT_POSITION..HEADER_SIZE].copy_from_slice(&2i32.to_be_bytes());
+
+        let record_batch = RecordBatch::parse(&bytes, 0).unwrap();
+
+        assert_eq!(
+            DecodeErrorReason::UnexpectedEof {
+                needed: 1,
+                available: 0
+            },
+            record_batch.records().unwrap_err().reason
+        );
+    }
+
+    #[test]
+    fn stops_decompressing_at_declared_records_count() {
+        let mut record_batch = RecordBatch::parse(&RECORD_BATCH, 0).unwrap();
+        record_batch.recompress(Compression::Gzip).unwrap();
+        let mut bytes = record_batch.to_be_bytes();
+        bytes[RECORDS_COUNT_POSITION..HEADER_SIZE].copy_from_slice(&0i32.to_be_bytes());
+
+        let record_batch = RecordBatch::parse(&bytes, 0).unwrap();
+
+        assert_eq!(
+            DecodeErrorReason::RecordsRemaining(0),
+            record_batch.records().unwrap_err().reason
+        );
+    }
+
+    #[test]
+    fn parses_records_with_multiple_headers() {
+        let varint = |value: i32| SignedVarint::new(value).to_be_bytes();
+        let mut record_bytes = vec![0x00]; // attributes
+        record_bytes.extend(varint(0)); // timestamp_delta
+        record_bytes.extend(varint(0)); // offset_delta
+        record_bytes.extend([varint(1), b"k".to_vec()].concat());
+        record_bytes.extend([varint(1), b"v".to_vec()].concat());
+        record_bytes.extend(varint(3)); // headers_count
+        record_bytes.extend([varint(8), b"trace-id".to_vec(), varint(3), b"abc".to_vec()].concat());
+        record_bytes.extend([varint(4), b"none".to_vec(), varint(-1)].concat());
+        record_bytes.extend([varint(5), b"empty".to_vec(), varint(0)].concat());
+        let bytes = [varint(record_bytes.len() as i32), record_bytes].concat();
+
+        let record = Record::parse(&bytes, 0).unwrap();
+
+        assert_eq!(bytes.len(), record.size());
+        let headers: Vec<(&str, Option<&[u8]>)> = record
+            .headers
+            .iter()
+            .flatten()
+            .map(|header| (header.key(), header.value()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("trace-id", Some(&b"abc"[..])),
+                ("none", None),
+                ("empty", Some(&b""[..]))
+            ],
+            headers
+        );
+        assert_eq!(bytes, record.to_be_bytes());
+    }
+
+    #[test]
+    fn serializes_headers_it_is_built_with() {
+        let headers = vec![
+            Header::new("content-type", Some(b"json")),
+            Header::new("tombstone", None),
+        ];
+        let record = Record::new(0, 0, None, Some(b"{}".to_vec()), headers.clone());
+
+        let parsed = Record::parse(&record.to_be_bytes(), 0).unwrap();
+
+        assert_eq!(Some(headers), parsed.headers);
+        assert_eq!(record.size(), parsed.size());
+        assert_eq!(2, parsed.headers_count.value);
+    }
+
+    #[test]
+    fn rejects_header_key_longer_than_record() {
+        let varint = |value: i32| SignedVarint::new(value).to_be_bytes();
+        let bytes = [varint(20), b"trace-id".to_vec()].concat();
+
+        let error = Header::parse(&bytes, 0).unwrap_err();
+
+        assert_eq!(
+            DecodeErrorReason::UnexpectedEof {
+                needed: 20,
+                available: 8
+            },
+            error.reason
+        );
+    }
 }
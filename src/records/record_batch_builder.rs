@@ -0,0 +1,249 @@
+//! Building v2 record batches from keys, values, headers and timestamps, as Kafka's
+//! MemoryRecordsBuilder. The builder works out what the wire format derives from the records:
+//! their varint lengths and deltas, the batch's `last_offset_delta` and `max_timestamp`, its
+//! length and its CRC.
+
+use std::io;
+
+use crate::records::compression::Compression;
+use crate::records::record_batch::{Header, Record, RecordBatch, CONTROL, MAGIC};
+use crate::serializable::Serializable;
+
+// Producer id, epoch and sequence of batches from producers that aren't idempotent
+const NO_PRODUCER_ID: i64 = -1;
+const NO_PRODUCER_EPOCH: i16 = -1;
+const NO_SEQUENCE: i32 = -1;
+// Timestamp of a batch without records
+const NO_TIMESTAMP: i64 = -1;
+
+#[derive(Debug, Clone)]
+pub struct RecordBatchBuilder {
+    base_offset: i64,
+    partition_leader_epoch: i32,
+    attributes: i16,
+    compression: Compression,
+    records: Vec<BuilderRecord>,
+}
+
+#[derive(Debug, Clone)]
+struct BuilderRecord {
+    timestamp: i64,
+    key: Option<Vec<u8>>,
+    value: Option<Vec<u8>>,
+    headers: Vec<Header>,
+}
+
+impl RecordBatchBuilder {
+    /// An uncompressed batch from a producer that isn't idempotent, whose records take the
+    /// offsets from `base_offset` on
+    pub fn new(base_offset: i64) -> Self {
+        Self {
+            base_offset,
+            partition_leader_epoch: 0,
+            attributes: 0,
+            compression: Compression::None,
+            records: Vec::new(),
+        }
+    }
+
+    /// Sets the leader epoch the broker stamps on appended batches, which only tests reproducing
+    /// batches of a Kafka log need
+    #[cfg(test)]
+    pub fn partition_leader_epoch(mut self, partition_leader_epoch: i32) -> Self {
+        self.partition_leader_epoch = partition_leader_epoch;
+        self
+    }
+
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Marks the batch as holding control records, such as the markers the broker writes to
+    /// commit or abort transactions. The broker writes none, so only tests build them.
+    #[cfg(test)]
+    pub fn control(mut self) -> Self {
+        self.attributes |= CONTROL;
+        self
+    }
+
+    /// Adds a record at the next offset. A record without a value is a tombstone of its key.
+    pub fn record(
+        mut self,
+        timestamp: i64,
+        key: Option<&[u8]>,
+        value: Option<&[u8]>,
+        headers: Vec<Header>,
+    ) -> Self {
+        self.records.push(BuilderRecord {
+            timestamp,
+            key: key.map(<[u8]>::to_vec),
+            value: value.map(<[u8]>::to_vec),
+            headers,
+        });
+        self
+    }
+
+    /// The batch, with the records compressed. Timestamps are deltas from the first record's,
+    /// as Kafka's producers write them. A batch without records has a `last_offset_delta` of
+    /// -1, so it takes no offsets.
+    pub fn build(self) -> io::Result<RecordBatch> {
+        let base_timestamp = self
+            .records
+            .first()
+            .map_or(NO_TIMESTAMP, |record| record.timestamp);
+        let max_timestamp = self
+            .records
+            .iter()
+            .map(|record| record.timestamp)
+            .max()
+            .unwrap_or(NO_TIMESTAMP);
+        let records: Vec<Record> = self
+            .records
+            .into_iter()
+            .enumerate()
+            .map(|(offset_delta, record)| {
+                Record::new(
+                    offset_delta as i32,
+                    record.timestamp - base_timestamp,
+                    record.key,
+                    record.value,
+                    record.headers,
+                )
+            })
+            .collect();
+        let records_bytes: Vec<u8> = records.iter().flat_map(Record::to_be_bytes).collect();
+        let mut record_batch = RecordBatch {
+            base_offset: self.base_offset,
+            // The length and CRC are set along with the records
+            batch_length: 0,
+            partition_leader_epoch: self.partition_leader_epoch,
+            magic: MAGIC,
+            crc: 0,
+            attributes: self.attributes,
+            last_offset_delta: records.len() as i32 - 1,
+            base_timestamp,
+            max_timestamp,
+            producer_id: NO_PRODUCER_ID,
+            producer_epoch: NO_PRODUCER_EPOCH,
+            base_sequence: NO_SEQUENCE,
+            records_count: records.len() as i32,
+            _parsed_bytes: Vec::new(),
+        };
+        let header_fields: [&dyn Serializable; 13] = [
+            &record_batch.base_offset,
+            &record_batch.batch_length,
+            &record_batch.partition_leader_epoch,
+            &record_batch.magic,
+            &record_batch.crc,
+            &record_batch.attributes,
+            &record_batch.last_offset_delta,
+            &record_batch.base_timestamp,
+            &record_batch.max_timestamp,
+            &record_batch.producer_id,
+            &record_batch.producer_epoch,
+            &record_batch.base_sequence,
+            &record_batch.records_count,
+        ];
+        record_batch._parsed_bytes = header_fields
+            .iter()
+            .flat_map(|field| field.to_be_bytes())
+            .collect();
+        record_batch.set_records_bytes(&records_bytes, self.compression)?;
+        Ok(record_batch)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::byte_parsable::ByteParsable;
+    use crate::size::Size;
+    use crate::test_fixtures::RECORD_BATCH;
+
+    #[test]
+    fn builds_batch_as_kafka_writes_it() {
+        let record_batch = RecordBatchBuilder::new(1)
+            .partition_leader_epoch(1)
+            .record(
+                0x0191e05af818,
+                None,
+                Some(&RECORD_BATCH[67..90]),
+                Vec::new(),
+            )
+            .build()
+            .unwrap();
+
+        assert_eq!(RECORD_BATCH.to_vec(), record_batch.to_be_bytes());
+        assert_eq!(RECORD_BATCH.len(), record_batch.size());
+    }
+
+    #[test]
+    fn derives_deltas_and_lengths_from_records() {
+        let large_value = vec![0x6d; 20_000];
+        let record_batch = RecordBatchBuilder::new(40)
+            .record(
+                2000,
+                Some(b"a"),
+                Some(b"1"),
+                vec![Header::new("h1", Some(b"x")), Header::new("h2", None)],
+            )
+            .record(1000, Some(b"b"), None, Vec::new())
+            .record(3000, None, Some(&large_value), Vec::new())
+            .build()
+            .unwrap();
+
+        let parsed = RecordBatch::parse(&record_batch.to_be_bytes(), 0).unwrap();
+        assert!(parsed.validate_crc().is_ok());
+        assert_eq!(parsed.expected_length(), parsed.size());
+        assert_eq!(42, parsed.last_offset());
+        assert_eq!((2000, 3000), (parsed.base_timestamp, parsed.max_timestamp));
+        assert_eq!(
+            (NO_PRODUCER_ID, NO_PRODUCER_EPOCH, NO_SEQUENCE),
+            (
+                parsed.producer_id,
+                parsed.producer_epoch,
+                parsed.base_sequence
+            )
+        );
+        let records = parsed.records().unwrap();
+        let offsets_and_timestamps: Vec<(i64, i64)> = records
+            .iter()
+            .map(|record| {
+                (
+                    parsed.record_offset(record),
+                    parsed.record_timestamp(record),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![(40, 2000), (41, 1000), (42, 3000)],
+            offsets_and_timestamps
+        );
+        assert_eq!(
+            Some(vec![Header::new("h1", Some(b"x")), Header::new("h2", None)]),
+            records[0].headers
+        );
+        assert_eq!(None, records[1].value);
+        assert_eq!(None, records[2].key);
+        assert_eq!(Some(large_value), records[2].value);
+    }
+
+    #[test]
+    fn compresses_records_with_codec() {
+        let record_batch = RecordBatchBuilder::new(0)
+            .compression(Compression::Zstd)
+            .control()
+            .record(1000, Some(b"k"), Some(&[0x76; 1000]), Vec::new())
+            .build()
+            .unwrap();
+
+        let bytes = record_batch.to_be_bytes();
+        assert!(bytes.len() < 1000);
+        let parsed = RecordBatch::parse(&bytes, 0).unwrap();
+        assert!(parsed.validate_crc().is_ok());
+        assert_eq!(Compression::Zstd, parsed.compression());
+        assert_eq!(CONTROL, parsed.attributes & CONTROL);
+        assert_eq!(Some(vec![0x76; 1000]), parsed.records().unwrap()[0].value);
+    }
+}
@@ -36,12 +36,24 @@ impl Serializable for u8 {
     }
 }
 
+impl Serializable for u16 {
+    fn to_be_bytes(&self) -> Vec<u8> {
+        u16::to_be_bytes(*self).to_vec()
+    }
+}
+
 impl Serializable for u32 {
     fn to_be_bytes(&self) -> Vec<u8> {
         u32::to_be_bytes(*self).to_vec()
     }
 }
 
+impl Serializable for f64 {
+    fn to_be_bytes(&self) -> Vec<u8> {
+        f64::to_be_bytes(*self).to_vec()
+    }
+}
+
 impl Serializable for bool {
     fn to_be_bytes(&self) -> Vec<u8> {
         vec![*self as u8]
@@ -52,6 +52,12 @@ impl Size for u32 {
     }
 }
 
+impl Size for f64 {
+    fn size(&self) -> usize {
+        size_of::<f64>()
+    }
+}
+
 impl Size for bool {
     fn size(&self) -> usize {
         size_of::<bool>()
@@ -0,0 +1,294 @@
+//! Sparse indexes of a log segment, in the format of Kafka's `.index` and `.timeindex` files.
+//!
+//! Entries are fixed size and big-endian, with offsets stored relative to the segment's base
+//! offset. An offset index entry maps the last offset of a batch to the batch's position in the
+//! segment file; a time index entry holds the largest timestamp in the segment up to a batch,
+//! with the last offset of the batch that has it.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+/// Offset index entry: a batch's last offset and the position of the batch in the segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OffsetPosition {
+    pub offset: i64,
+    pub position: u32,
+}
+
+/// Time index entry: a timestamp and the last offset of the batch it is reached in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimestampOffset {
+    pub timestamp: i64,
+    pub offset: i64,
+}
+
+pub trait IndexEntry: Sized + Copy {
+    const SIZE: usize;
+
+    fn parse(bytes: &[u8], base_offset: i64) -> Self;
+
+    fn to_be_bytes(&self, base_offset: i64) -> Vec<u8>;
+}
+
+impl IndexEntry for OffsetPosition {
+    const SIZE: usize = 8;
+
+    fn parse(bytes: &[u8], base_offset: i64) -> Self {
+        Self {
+            offset: base_offset + i32::from_be_bytes(bytes[0..4].try_into().unwrap()) as i64,
+            position: u32::from_be_bytes(bytes[4..8].try_into().unwrap()),
+        }
+    }
+
+    fn to_be_bytes(&self, base_offset: i64) -> Vec<u8> {
+        let relative_offset = (self.offset - base_offset) as i32;
+        [relative_offset.to_be_bytes(), self.position.to_be_bytes()].concat()
+    }
+}
+
+impl IndexEntry for TimestampOffset {
+    const SIZE: usize = 12;
+
+    fn parse(bytes: &[u8], base_offset: i64) -> Self {
+        Self {
+            timestamp: i64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            offset: base_offset + i32::from_be_bytes(bytes[8..12].try_into().unwrap()) as i64,
+        }
+    }
+
+    fn to_be_bytes(&self, base_offset: i64) -> Vec<u8> {
+        let relative_offset = (self.offset - base_offset) as i32;
+        [
+            &self.timestamp.to_be_bytes()[..],
+            &relative_offset.to_be_bytes()[..],
+        ]
+        .concat()
+    }
+}
+
+/// Index file of a segment, whose entries are ordered by offset
+#[derive(Debug, Clone)]
+pub struct Index<E> {
+    path: PathBuf,
+    base_offset: i64,
+    entry: PhantomData<E>,
+}
+
+pub type OffsetIndex = Index<OffsetPosition>;
+pub type TimeIndex = Index<TimestampOffset>;
+
+impl<E: IndexEntry> Index<E> {
+    pub fn new(path: PathBuf, base_offset: i64) -> Self {
+        Self {
+            path,
+            base_offset,
+            entry: PhantomData,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads the entries of the index. A missing index has none, and a trailing partial entry
+    /// is ignored.
+    pub fn entries(&self) -> io::Result<Vec<E>> {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(bytes
+            .chunks_exact(E::SIZE)
+            .map(|entry| E::parse(entry, self.base_offset))
+            .collect())
+    }
+
+    pub fn last_entry(&self) -> io::Result<Option<E>> {
+        Ok(self.entries()?.pop())
+    }
+
+    /// Appends the entries, creating the index if it doesn't exist yet
+    pub fn append(&self, entries: &[E]) -> io::Result<()> {
+        let bytes: Vec<u8> = entries
+            .iter()
+            .flat_map(|entry| entry.to_be_bytes(self.base_offset))
+            .collect();
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(&bytes)
+    }
+
+    /// Replaces the entries of the index
+    pub fn reset(&self, entries: &[E]) -> io::Result<()> {
+        fs::write(&self.path, [])?;
+        self.append(entries)
+    }
+
+    /// Reads the entries, failing with `InvalidData` unless the file holds whole entries that
+    /// `in_order` accepts pairwise
+    fn checked_entries(&self, in_order: impl Fn(&E, &E) -> bool) -> io::Result<Vec<E>> {
+        let size = fs::metadata(&self.path)?.len();
+        if size % E::SIZE as u64 != 0 {
+            return Err(corrupt_index(
+                &self.path,
+                format!("size {size} isn't a multiple of the entry size {}", E::SIZE),
+            ));
+        }
+        let entries = self.entries()?;
+        if let Some(index) = entries
+            .windows(2)
+            .position(|pair| !in_order(&pair[0], &pair[1]))
+        {
+            return Err(corrupt_index(
+                &self.path,
+                format!("entry {} is out of order", index + 1),
+            ));
+        }
+        Ok(entries)
+    }
+}
+
+impl OffsetIndex {
+    /// The entry with the largest offset at or before the given one
+    pub fn lookup(&self, offset: i64) -> io::Result<Option<OffsetPosition>> {
+        let entries = self.entries()?;
+        let count = entries.partition_point(|entry| entry.offset <= offset);
+        Ok(count.checked_sub(1).map(|index| entries[index]))
+    }
+
+    /// Checks that the index is whole, ordered and within the segment of the given size
+    pub fn sanity_check(&self, segment_size: u64) -> io::Result<()> {
+        let entries = self.checked_entries(|previous, entry| {
+            previous.offset < entry.offset && previous.position < entry.position
+        })?;
+        match entries.first().zip(entries.last()) {
+            Some((first, _)) if first.offset < self.base_offset => Err(corrupt_index(
+                &self.path,
+                format!("offset {} is before the segment", first.offset),
+            )),
+            Some((_, last)) if last.position as u64 >= segment_size => Err(corrupt_index(
+                &self.path,
+                format!("position {} is past the segment", last.position),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl TimeIndex {
+    /// The entry with the largest timestamp before the given one
+    pub fn lookup_before(&self, timestamp: i64) -> io::Result<Option<TimestampOffset>> {
+        let entries = self.entries()?;
+        let count = entries.partition_point(|entry| entry.timestamp < timestamp);
+        Ok(count.checked_sub(1).map(|index| entries[index]))
+    }
+
+    /// Checks that the index is whole, ordered and doesn't reach past the next offset of the
+    /// segment
+    pub fn sanity_check(&self, next_offset: i64) -> io::Result<()> {
+        let entries = self.checked_entries(|previous, entry| {
+            previous.timestamp < entry.timestamp && previous.offset <= entry.offset
+        })?;
+        match entries.first().zip(entries.last()) {
+            Some((first, _)) if first.offset < self.base_offset => Err(corrupt_index(
+                &self.path,
+                format!("offset {} is before the segment", first.offset),
+            )),
+            Some((_, last)) if last.offset >= next_offset => Err(corrupt_index(
+                &self.path,
+                format!("offset {} is past the segment", last.offset),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn corrupt_index(path: &Path, reason: String) -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidData,
+        format!("corrupt index {}: {reason}", path.display()),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_fixtures::TempDir;
+
+    #[test]
+    fn stores_offsets_relative_to_base_offset() {
+        let directory = TempDir::new("index");
+        let index = OffsetIndex::new(directory.path().join("00000000000000000100.index"), 100);
+        let entry = OffsetPosition {
+            offset: 105,
+            position: 4200,
+        };
+
+        index.append(&[entry]).unwrap();
+
+        assert_eq!(
+            vec![0, 0, 0, 5, 0, 0, 0x10, 0x68],
+            fs::read(index.path()).unwrap()
+        );
+        assert_eq!(vec![entry], index.entries().unwrap());
+    }
+
+    #[test]
+    fn looks_up_entries_at_or_before_offset_and_before_timestamp() {
+        let directory = TempDir::new("index");
+        let offset_index = OffsetIndex::new(directory.path().join("00000000000000000000.index"), 0);
+        let time_index = TimeIndex::new(directory.path().join("00000000000000000000.timeindex"), 0);
+        let at = |offset, position| OffsetPosition { offset, position };
+        let when = |timestamp, offset| TimestampOffset { timestamp, offset };
+        offset_index.append(&[at(10, 4100), at(20, 8300)]).unwrap();
+        time_index
+            .append(&[when(1000, 10), when(2000, 20)])
+            .unwrap();
+
+        assert_eq!(None, offset_index.lookup(9).unwrap());
+        assert_eq!(Some(at(10, 4100)), offset_index.lookup(10).unwrap());
+        assert_eq!(Some(at(20, 8300)), offset_index.lookup(25).unwrap());
+        assert_eq!(None, time_index.lookup_before(1000).unwrap());
+        assert_eq!(
+            Some(when(1000, 10)),
+            time_index.lookup_before(2000).unwrap()
+        );
+        assert_eq!(
+            Some(when(2000, 20)),
+            time_index.lookup_before(5000).unwrap()
+        );
+    }
+
+    #[test]
+    fn sanity_check_rejects_partial_and_out_of_range_entries() {
+        let directory = TempDir::new("index");
+        let index = OffsetIndex::new(directory.path().join("00000000000000000000.index"), 0);
+        index
+            .append(&[OffsetPosition {
+                offset: 3,
+                position: 4100,
+            }])
+            .unwrap();
+
+        assert!(index.sanity_check(5000).is_ok());
+        assert_eq!(
+            ErrorKind::InvalidData,
+            index.sanity_check(4000).unwrap_err().kind()
+        );
+        OpenOptions::new()
+            .append(true)
+            .open(index.path())
+            .unwrap()
+            .write_all(&[0])
+            .unwrap();
+        assert_eq!(
+            ErrorKind::InvalidData,
+            index.sanity_check(5000).unwrap_err().kind()
+        );
+    }
+}
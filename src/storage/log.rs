@@ -0,0 +1,639 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tracing::warn;
+
+use crate::records::record_batch::RecordBatch;
+use crate::storage::log_cleaner::Cleaner;
+use crate::storage::log_config::{LogConfig, TimestampType};
+use crate::storage::log_segment::{LogSegment, TimestampAndOffset};
+use crate::storage::partition_directory;
+
+// Lock of each partition directory, shared by the handles of its log, guarding whether the log
+// is closed. Changes to a log are serialised so concurrent producers can't be assigned
+// overlapping offsets, while the other partitions are appended to in parallel.
+static PARTITION_LOCKS: Mutex<BTreeMap<PathBuf, Arc<Mutex<bool>>>> = Mutex::new(BTreeMap::new());
+
+/// Outcome of an append, as Kafka's LogAppendInfo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogAppendInfo {
+    /// Offset assigned to the first record
+    pub base_offset: i64,
+    /// Time set as the timestamp of the records, or -1 if they keep their create time
+    pub log_append_time: i64,
+}
+
+/// Log of a single topic partition, stored in `<topic>-<partition>/` under one of the log
+/// directories as segments named by their base offset. Batches are appended to the last
+/// segment, the active one, which is rolled over to a new segment as it fills up.
+#[derive(Debug, Clone)]
+pub struct Log {
+    directory: PathBuf,
+    lock: Arc<Mutex<bool>>,
+}
+
+impl Log {
+    pub fn new(log_dirs: &[PathBuf], topic_name: &str, partition_index: i32) -> Self {
+        Self::in_directory(partition_directory(log_dirs, topic_name, partition_index))
+    }
+
+    pub fn in_directory(directory: PathBuf) -> Self {
+        let lock = PARTITION_LOCKS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(directory.clone())
+            .or_default()
+            .clone();
+        Self { directory, lock }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// The segments in the log directory, in offset order
+    pub fn segments(&self) -> io::Result<Vec<LogSegment>> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut base_offsets = Vec::new();
+        for entry in entries {
+            if let Some(base_offset) = entry?
+                .file_name()
+                .to_str()
+                .and_then(LogSegment::parse_base_offset)
+            {
+                base_offsets.push(base_offset);
+            }
+        }
+        base_offsets.sort_unstable();
+        Ok(base_offsets
+            .into_iter()
+            .map(|base_offset| LogSegment::new(&self.directory, base_offset))
+            .collect())
+    }
+
+    /// Reads the record batches in the log. A segment that can't be decoded is reported as an
+    /// `InvalidData` error.
+    pub fn record_batches(&self) -> io::Result<Vec<RecordBatch>> {
+        let mut record_batches = Vec::new();
+        for segment in self.segments()? {
+            record_batches.extend(segment.record_batches()?);
+        }
+        Ok(record_batches)
+    }
+
+    /// Reads the bytes of the batches from the one holding the offset, in its segment or the
+    /// next segment with batches past the offset, up to `max_bytes` as [`LogSegment::read`]
+    /// does. Like Kafka's reads, a read doesn't span segments; the rest of the log is read
+    /// from the offset that follows.
+    pub fn read(&self, offset: i64, max_bytes: usize, min_one_batch: bool) -> io::Result<Vec<u8>> {
+        let segments = self.segments()?;
+        // The segment holding the offset is the last one starting at or before it
+        let first = segments
+            .partition_point(|segment| segment.base_offset() <= offset)
+            .saturating_sub(1);
+        for segment in &segments[first..] {
+            if let Some(bytes) = segment.read(offset, max_bytes, min_one_batch)? {
+                return Ok(bytes);
+            }
+        }
+        Ok(Vec::new())
+    }
+
+    /// Earliest offset of the log, the base offset of its oldest segment. It moves forward as
+    /// retention deletes segments.
+    pub fn log_start_offset(&self) -> io::Result<i64> {
+        Ok(self.segments()?.first().map_or(0, LogSegment::base_offset))
+    }
+
+    /// Offset the next appended record will be assigned (the log end offset)
+    pub fn next_offset(&self) -> io::Result<i64> {
+        match self.segments()?.last() {
+            Some(segment) => Ok(segment.next_offset()?.unwrap_or(segment.base_offset())),
+            None => Ok(0),
+        }
+    }
+
+    /// The first record with a timestamp at or after the given one
+    pub fn offset_for_timestamp(&self, timestamp: i64) -> io::Result<Option<TimestampAndOffset>> {
+        for segment in self.segments()? {
+            if let Some(timestamp_and_offset) = segment.find_offset_by_timestamp(timestamp)? {
+                return Ok(Some(timestamp_and_offset));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The record with the largest timestamp, the first of them if several share it
+    pub fn max_timestamp_and_offset(&self) -> io::Result<Option<TimestampAndOffset>> {
+        let mut max: Option<TimestampAndOffset> = None;
+        for segment in self.segments()? {
+            if let Some(segment_max) = segment.max_timestamp_and_offset()? {
+                if max.is_none_or(|max| segment_max.timestamp > max.timestamp) {
+                    max = Some(segment_max);
+                }
+            }
+        }
+        Ok(max)
+    }
+
+    /// Assigns offsets to the record batches, rewriting their base offsets, and appends them to
+    /// the active segment, first rolling it if the batches don't belong in it. With the
+    /// LogAppendTime timestamp type, the batches are stamped with the current time.
+    pub fn append(
+        &self,
+        mut record_batches: Vec<RecordBatch>,
+        config: &LogConfig,
+    ) -> io::Result<LogAppendInfo> {
+        let _guard = self.lock()?;
+        let base_offset = self.next_offset()?;
+        let log_append_time = match config.message_timestamp_type {
+            TimestampType::CreateTime => -1,
+            TimestampType::LogAppendTime => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since_epoch| since_epoch.as_millis() as i64),
+        };
+        let mut next_offset = base_offset;
+        for record_batch in &mut record_batches {
+            record_batch.set_base_offset(next_offset);
+            record_batch.recompress(config.compression_type.target(record_batch.compression()))?;
+            if log_append_time >= 0 {
+                record_batch.set_log_append_time(log_append_time);
+            }
+            next_offset = record_batch.next_offset();
+        }
+        fs::create_dir_all(&self.directory)?;
+        let segment = match self.segments()?.pop() {
+            Some(active_segment) if active_segment.should_roll(config, &record_batches)? => {
+                active_segment.on_become_inactive()?;
+                LogSegment::new(&self.directory, base_offset)
+            }
+            Some(active_segment) => active_segment,
+            None => LogSegment::new(&self.directory, base_offset),
+        };
+        segment.append(&record_batches)?;
+        Ok(LogAppendInfo {
+            base_offset,
+            log_append_time,
+        })
+    }
+
+    /// Deletes the oldest segments past the retention limits, as Kafka's retention: a segment
+    /// goes if its largest timestamp is more than `retention.ms` before `now_ms`, or if the log
+    /// without it is still at least `retention.bytes`. Deletion stops at the first segment
+    /// that stays. If every segment goes, an empty segment at the log end offset takes over
+    /// first, so the log carries on from there. Logs without the delete cleanup policy are
+    /// kept whole. Returns the number of segments deleted.
+    pub fn delete_old_segments(&self, config: &LogConfig, now_ms: i64) -> io::Result<usize> {
+        if !config.cleanup_policy.delete {
+            return Ok(0);
+        }
+        let _guard = self.lock()?;
+        let segments = self.segments()?;
+        let sizes = segments
+            .iter()
+            .map(LogSegment::size)
+            .collect::<io::Result<Vec<u64>>>()?;
+        let mut remaining_bytes: u64 = sizes.iter().sum();
+        let mut deletable = 0;
+        for (segment, &size) in segments.iter().zip(&sizes) {
+            let is_last_and_empty = deletable == segments.len() - 1 && size == 0;
+            let expired = config.retention_ms >= 0
+                && now_ms - segment.largest_timestamp()? > config.retention_ms;
+            let oversized = config.retention_bytes >= 0
+                && remaining_bytes - size >= config.retention_bytes as u64;
+            if is_last_and_empty || !(expired || oversized) {
+                break;
+            }
+            remaining_bytes -= size;
+            deletable += 1;
+        }
+        if deletable > 0 && deletable == segments.len() {
+            let next_offset = self.next_offset()?;
+            LogSegment::new(&self.directory, next_offset).create()?;
+        }
+        for segment in &segments[..deletable] {
+            segment.delete()?;
+        }
+        Ok(deletable)
+    }
+
+    /// Compacts the segments before the active one, keeping the latest record of each key.
+    /// Appends only go to the active segment, so the others are cleaned without holding the
+    /// lock, which is only taken to swap each cleaned segment in. Returns the number of
+    /// records removed.
+    pub fn compact(&self, config: &LogConfig, now_ms: i64) -> io::Result<usize> {
+        let mut segments = self.segments()?;
+        // The active segment is still appended to, so it is cleaned once it is rolled
+        segments.pop();
+        let cleaner = Cleaner::new(&segments, config, now_ms)?;
+        let mut removed = 0;
+        for segment in &segments {
+            let Some((cleaned, segment_removed)) = cleaner.clean_segment(segment)? else {
+                continue;
+            };
+            let _guard = self.lock()?;
+            if segment.replace_with(&cleaned)? {
+                removed += segment_removed;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Recovers the log at startup, from the recovery point up to which it is known to have
+    /// been flushed to disk. The segments from the one holding the recovery point are
+    /// validated; at the first invalid batch the log is truncated and the segments after it
+    /// are deleted. The segments before only have their indexes checked.
+    pub fn recover(&self, recovery_point: i64) -> io::Result<()> {
+        let _guard = self.lock()?;
+        let segments = self.segments()?;
+        let first = segments
+            .partition_point(|segment| segment.base_offset() <= recovery_point)
+            .saturating_sub(1);
+        segments[..first].iter().try_for_each(LogSegment::load)?;
+        for (index, segment) in segments.iter().enumerate().skip(first) {
+            if segment.recover()? {
+                for later_segment in &segments[index + 1..] {
+                    warn!(
+                        "Deleting segment {} after the truncated one",
+                        later_segment.log_path().display()
+                    );
+                    later_segment.delete()?;
+                }
+                break;
+            }
+            if index + 1 < segments.len() {
+                segment.on_become_inactive()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads the log at startup after an orderly shutdown, when it was fully flushed to disk:
+    /// the segments only have their indexes checked.
+    pub fn load(&self) -> io::Result<()> {
+        let _guard = self.lock()?;
+        self.segments()?.iter().try_for_each(LogSegment::load)
+    }
+
+    /// Flushes the log to disk, returning the offset it is flushed up to, which becomes its
+    /// recovery point. The segments before the active one are flushed as they are rolled.
+    pub fn flush(&self) -> io::Result<i64> {
+        let _guard = self.lock()?;
+        self.flush_active_segment()
+    }
+
+    /// Flushes the log on an orderly shutdown and closes it, so that it stays as flushed: the
+    /// changes attempted afterwards fail. Returns the offset it is flushed up to.
+    pub fn close(&self) -> io::Result<i64> {
+        let mut closed = self.lock()?;
+        let next_offset = self.flush_active_segment()?;
+        *closed = true;
+        Ok(next_offset)
+    }
+
+    /// Topic and partition of the log, from the name of its directory
+    pub fn topic_partition(&self) -> Option<(String, i32)> {
+        let name = self.directory.file_name()?.to_str()?;
+        let (topic_name, partition_index) = name.rsplit_once('-')?;
+        Some((topic_name.to_string(), partition_index.parse().ok()?))
+    }
+
+    fn flush_active_segment(&self) -> io::Result<i64> {
+        if let Some(active_segment) = self.segments()?.last() {
+            active_segment.flush()?;
+        }
+        self.next_offset()
+    }
+
+    /// Locks the log for a change, which fails once the log is closed
+    fn lock(&self) -> io::Result<MutexGuard<'_, bool>> {
+        let closed = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+        if *closed {
+            return Err(io::Error::other(format!(
+                "log {} is closed",
+                self.directory.display()
+            )));
+        }
+        Ok(closed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+
+    use super::*;
+    use crate::byte_parsable::ByteParsable;
+    use crate::test_fixtures::{TempDir, RECORD_BATCH};
+
+    /// A log in a directory removed along with the returned guard
+    fn temp_log() -> (TempDir, Log) {
+        let directory = TempDir::new("log");
+        let log = Log::in_directory(directory.path().to_path_buf());
+        (directory, log)
+    }
+
+    /// The record batch, with its timestamps moved to the given one
+    fn record_batch_at(timestamp: i64) -> RecordBatch {
+        let mut bytes = RECORD_BATCH;
+        bytes[27..35].copy_from_slice(&timestamp.to_be_bytes());
+        bytes[35..43].copy_from_slice(&timestamp.to_be_bytes());
+        RecordBatch::parse(&bytes, 0).unwrap()
+    }
+
+    /// The batches read from the offset, without a limit on their bytes
+    fn read(log: &Log, offset: i64) -> Vec<RecordBatch> {
+        RecordBatch::from_bytes(&log.read(offset, usize::MAX, true).unwrap()).unwrap()
+    }
+
+    fn base_offsets(record_batches: Vec<RecordBatch>) -> Vec<i64> {
+        record_batches
+            .iter()
+            .map(|record_batch| record_batch.base_offset)
+            .collect()
+    }
+
+    fn segment_base_offsets(log: &Log) -> Vec<i64> {
+        log.segments()
+            .unwrap()
+            .iter()
+            .map(LogSegment::base_offset)
+            .collect()
+    }
+
+    #[test]
+    fn empty_log_starts_at_offset_zero() {
+        let (_directory, log) = temp_log();
+
+        assert!(log.record_batches().unwrap().is_empty());
+        assert_eq!(0, log.log_start_offset().unwrap());
+        assert_eq!(0, log.next_offset().unwrap());
+    }
+
+    #[test]
+    fn assigns_consecutive_offsets_to_appended_record_batches() {
+        let (_directory, log) = temp_log();
+
+        let config = LogConfig::default();
+
+        let first_base_offset =
+            log.append(vec![RecordBatch::parse(&RECORD_BATCH, 0).unwrap()], &config);
+        let second_base_offset = log.append(
+            vec![
+                RecordBatch::parse(&RECORD_BATCH, 0).unwrap(),
+                RecordBatch::parse(&RECORD_BATCH, 0).unwrap(),
+            ],
+            &config,
+        );
+
+        assert_eq!(0, first_base_offset.unwrap().base_offset);
+        assert_eq!(1, second_base_offset.unwrap().base_offset);
+        let base_offsets = log
+            .record_batches()
+            .unwrap()
+            .iter()
+            .map(|record_batch| record_batch.base_offset)
+            .collect::<Vec<i64>>();
+        assert_eq!(vec![0, 1, 2], base_offsets);
+        assert_eq!(0, log.log_start_offset().unwrap());
+        assert_eq!(3, log.next_offset().unwrap());
+    }
+
+    #[test]
+    fn serialises_appends_through_handles_of_the_same_partition() {
+        let directory = TempDir::new("log");
+
+        let appenders = (0..4)
+            .map(|_| {
+                let log = Log::in_directory(directory.path().to_path_buf());
+                thread::spawn(move || {
+                    for _ in 0..5 {
+                        log.append(
+                            vec![RecordBatch::parse(&RECORD_BATCH, 0).unwrap()],
+                            &LogConfig::default(),
+                        )
+                        .unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        appenders
+            .into_iter()
+            .for_each(|appender| appender.join().unwrap());
+
+        let log = Log::in_directory(directory.path().to_path_buf());
+        assert_eq!(
+            (0..20).collect::<Vec<i64>>(),
+            base_offsets(log.record_batches().unwrap())
+        );
+    }
+
+    #[test]
+    fn locks_each_partition_separately() {
+        let (_directory, log) = temp_log();
+        let (_other_directory, other_log) = temp_log();
+
+        let _guard = log.lock().unwrap();
+
+        assert!(other_log.lock.try_lock().is_ok());
+        assert!(Log::in_directory(log.directory.clone())
+            .lock
+            .try_lock()
+            .is_err());
+    }
+
+    #[test]
+    fn reports_corrupt_segment_as_invalid_data() {
+        let (_directory, log) = temp_log();
+        fs::create_dir_all(&log.directory).unwrap();
+        fs::write(
+            LogSegment::new(&log.directory, 0).log_path(),
+            &RECORD_BATCH[..RECORD_BATCH.len() - 1],
+        )
+        .unwrap();
+
+        let error = log.record_batches().unwrap_err();
+
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+        assert!(log.append(Vec::new(), &LogConfig::default()).is_err());
+    }
+
+    #[test]
+    fn rolls_segment_past_segment_bytes() {
+        let (_directory, log) = temp_log();
+        let config = LogConfig {
+            segment_bytes: 2 * RECORD_BATCH.len() as u64,
+            ..LogConfig::default()
+        };
+
+        for _ in 0..5 {
+            log.append(vec![RecordBatch::parse(&RECORD_BATCH, 0).unwrap()], &config)
+                .unwrap();
+        }
+
+        assert_eq!(vec![0, 2, 4], segment_base_offsets(&log));
+        assert!(log.directory.join("00000000000000000002.log").exists());
+        assert_eq!(
+            vec![0, 1, 2, 3, 4],
+            base_offsets(log.record_batches().unwrap())
+        );
+        assert_eq!(5, log.next_offset().unwrap());
+    }
+
+    #[test]
+    fn rolls_segment_past_segment_ms() {
+        let (_directory, log) = temp_log();
+        let config = LogConfig {
+            segment_ms: 1000,
+            ..LogConfig::default()
+        };
+
+        for timestamp in [0, 1000, 1001, 1500, 2002] {
+            log.append(vec![record_batch_at(timestamp)], &config)
+                .unwrap();
+        }
+
+        assert_eq!(vec![0, 2, 4], segment_base_offsets(&log));
+    }
+
+    #[test]
+    fn reads_from_segment_holding_offset() {
+        let (_directory, log) = temp_log();
+        let config = LogConfig {
+            segment_bytes: 2 * RECORD_BATCH.len() as u64,
+            ..LogConfig::default()
+        };
+        for timestamp in 0..5 {
+            log.append(vec![record_batch_at(1000 * timestamp)], &config)
+                .unwrap();
+        }
+
+        assert_eq!(vec![0, 1], base_offsets(read(&log, 0)));
+        assert_eq!(vec![3], base_offsets(read(&log, 3)));
+        assert_eq!(vec![4], base_offsets(read(&log, 4)));
+        assert!(log.read(5, usize::MAX, true).unwrap().is_empty());
+        assert_eq!(
+            Some(2),
+            log.offset_for_timestamp(1500)
+                .unwrap()
+                .map(|timestamp_and_offset| timestamp_and_offset.offset)
+        );
+        assert_eq!(
+            Some(4),
+            log.max_timestamp_and_offset()
+                .unwrap()
+                .map(|timestamp_and_offset| timestamp_and_offset.offset)
+        );
+    }
+
+    #[test]
+    fn deletes_segments_past_retention_bytes() {
+        let (_directory, log) = temp_log();
+        let config = LogConfig {
+            segment_bytes: 2 * RECORD_BATCH.len() as u64,
+            retention_bytes: 2 * RECORD_BATCH.len() as i64,
+            ..LogConfig::default()
+        };
+        for _ in 0..5 {
+            log.append(vec![RecordBatch::parse(&RECORD_BATCH, 0).unwrap()], &config)
+                .unwrap();
+        }
+
+        let deleted = log.delete_old_segments(&config, 0).unwrap();
+
+        // Without segment 0, segments 2 and 4 are still 3 batches, but without segment 2 too
+        // they'd be less than the retention
+        assert_eq!(1, deleted);
+        assert_eq!(vec![2, 4], segment_base_offsets(&log));
+        assert_eq!(2, log.log_start_offset().unwrap());
+        assert!(!log.directory.join("00000000000000000000.index").exists());
+    }
+
+    #[test]
+    fn deletes_segments_past_retention_ms() {
+        let (_directory, log) = temp_log();
+        let config = LogConfig {
+            segment_bytes: 2 * RECORD_BATCH.len() as u64,
+            retention_ms: 1000,
+            ..LogConfig::default()
+        };
+        for timestamp in 0..5 {
+            log.append(vec![record_batch_at(1000 * timestamp)], &config)
+                .unwrap();
+        }
+
+        assert_eq!(0, log.delete_old_segments(&config, 2000).unwrap());
+        assert_eq!(1, log.delete_old_segments(&config, 2001).unwrap());
+        assert_eq!(vec![2, 4], segment_base_offsets(&log));
+        assert_eq!(2, log.delete_old_segments(&config, 10_000).unwrap());
+
+        // An empty segment takes over, keeping the log end offset
+        assert_eq!(vec![5], segment_base_offsets(&log));
+        assert_eq!(5, log.log_start_offset().unwrap());
+        assert_eq!(5, log.next_offset().unwrap());
+        assert_eq!(0, log.delete_old_segments(&config, 10_000).unwrap());
+        assert_eq!(
+            5,
+            log.append(vec![record_batch_at(20_000)], &config)
+                .unwrap()
+                .base_offset
+        );
+        assert_eq!(vec![5], base_offsets(read(&log, 0)));
+    }
+
+    #[test]
+    fn stamps_batches_with_log_append_time() {
+        let (_directory, log) = temp_log();
+        let config = LogConfig {
+            message_timestamp_type: TimestampType::LogAppendTime,
+            ..LogConfig::default()
+        };
+
+        let log_append_info = log.append(vec![record_batch_at(1000)], &config).unwrap();
+
+        assert!(log_append_info.log_append_time > 1000);
+        let record_batch = &log.record_batches().unwrap()[0];
+        assert_eq!(log_append_info.log_append_time, record_batch.max_timestamp);
+        assert!(record_batch.validate_crc().is_ok());
+    }
+
+    #[test]
+    fn recovers_from_recovery_point() {
+        let (_directory, log) = temp_log();
+        let config = LogConfig {
+            segment_bytes: 2 * RECORD_BATCH.len() as u64,
+            ..LogConfig::default()
+        };
+        for _ in 0..5 {
+            log.append(vec![RecordBatch::parse(&RECORD_BATCH, 0).unwrap()], &config)
+                .unwrap();
+        }
+        // Corrupts a value byte of the second batch of a segment
+        let corrupt = |base_offset| {
+            let path = LogSegment::new(&log.directory, base_offset)
+                .log_path()
+                .to_path_buf();
+            let mut bytes = fs::read(&path).unwrap();
+            bytes[RECORD_BATCH.len() + 80] ^= 0x01;
+            fs::write(path, bytes).unwrap();
+        };
+        corrupt(0);
+        corrupt(2);
+
+        log.recover(2).unwrap();
+
+        // Segment 0 is before the recovery point, so it isn't scanned
+        assert_eq!(vec![0, 2], segment_base_offsets(&log));
+        assert_eq!(vec![0, 1, 2], base_offsets(log.record_batches().unwrap()));
+        assert_eq!(3, log.next_offset().unwrap());
+        assert_eq!(3, log.flush().unwrap());
+    }
+}
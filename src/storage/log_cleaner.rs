@@ -0,0 +1,280 @@
+//! Compaction of the logs of topics with the `compact` cleanup policy, as Kafka's log cleaner.
+//!
+//! A pass over a log maps each key to the offset of its latest record in the closed segments,
+//! then rewrites those segments keeping only the records at the mapped offsets. The active
+//! segment is left alone, so a key's latest record there doesn't remove older ones until the
+//! segment is rolled. Control batches, such as transaction markers, aren't keyed records and
+//! are kept as they are.
+
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+
+use crate::records::record_batch::Record;
+use crate::storage::log_config::LogConfig;
+use crate::storage::log_segment::LogSegment;
+
+/// Cleans the closed segments of a log, as Kafka's Cleaner
+#[derive(Debug)]
+pub struct Cleaner {
+    // Offset of the latest record of each key in the segments being cleaned
+    offset_map: HashMap<Vec<u8>, i64>,
+    // Tombstones in segments whose largest timestamp is before this are removed
+    delete_horizon_ms: i64,
+}
+
+impl Cleaner {
+    /// Builds the offset map of the segments, which are in offset order
+    pub fn new(segments: &[LogSegment], config: &LogConfig, now_ms: i64) -> io::Result<Self> {
+        let mut offset_map = HashMap::new();
+        for segment in segments {
+            for record_batch in segment.record_batches()? {
+                if record_batch.is_control() {
+                    continue;
+                }
+                for record in record_batch
+                    .records()
+                    .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?
+                {
+                    if let Some(key) = &record.key {
+                        offset_map.insert(key.clone(), record_batch.record_offset(&record));
+                    }
+                }
+            }
+        }
+        Ok(Self {
+            offset_map,
+            delete_horizon_ms: now_ms.saturating_sub(config.delete_retention_ms),
+        })
+    }
+
+    /// Writes the records to keep of the segment to a cleaned segment alongside it, to be
+    /// swapped in with [`LogSegment::replace_with`]. Returns the cleaned segment with the
+    /// number of records removed, or `None` if none are. Batches left without records are
+    /// dropped, and a segment left without batches becomes an empty file so its base offset
+    /// still marks where the log resumes.
+    pub fn clean_segment(&self, segment: &LogSegment) -> io::Result<Option<(LogSegment, usize)>> {
+        let retain_tombstones = segment.largest_timestamp()? >= self.delete_horizon_ms;
+        let mut removed = 0;
+        let mut record_batches = segment.record_batches()?;
+        for record_batch in record_batches
+            .iter_mut()
+            .filter(|record_batch| !record_batch.is_control())
+        {
+            record_batch.retain_records(|offset, record| {
+                let retained = self.should_retain(offset, record, retain_tombstones);
+                if !retained {
+                    removed += 1;
+                }
+                retained
+            })?;
+        }
+        if removed == 0 {
+            return Ok(None);
+        }
+        record_batches.retain(|record_batch| record_batch.records_count > 0);
+        Ok(Some((segment.write_cleaned(&record_batches)?, removed)))
+    }
+
+    /// A record is kept if it is the latest of its key, unless it is a tombstone past the
+    /// delete horizon. Records without a key can't be compacted, so Kafka removes them too.
+    fn should_retain(&self, offset: i64, record: &Record, retain_tombstones: bool) -> bool {
+        let Some(key) = &record.key else {
+            return false;
+        };
+        let is_latest = self.offset_map.get(key) == Some(&offset);
+        is_latest && (record.value.is_some() || retain_tombstones)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+
+    use super::*;
+    use crate::byte_parsable::ByteParsable;
+    use crate::records::record_batch::RecordBatch;
+    use crate::records::record_batch_builder::RecordBatchBuilder;
+    use crate::serializable::Serializable;
+    use crate::storage::log::Log;
+    use crate::test_fixtures::TempDir;
+
+    /// Batch with a record for each key and value, a millisecond apart from the timestamp
+    fn keyed_batch(base_offset: i64, timestamp: i64, records: &[(&str, Option<&str>)]) -> Vec<u8> {
+        records
+            .iter()
+            .zip(timestamp..)
+            .fold(
+                RecordBatchBuilder::new(base_offset),
+                |builder, ((key, value), timestamp)| {
+                    builder.record(
+                        timestamp,
+                        Some(key.as_bytes()),
+                        value.map(str::as_bytes),
+                        Vec::new(),
+                    )
+                },
+            )
+            .build()
+            .unwrap()
+            .to_be_bytes()
+    }
+
+    /// Writes a segment of the batches at the base offset of the first
+    fn write_segment(directory: &Path, record_batches: &[Vec<u8>]) -> LogSegment {
+        let record_batches: Vec<RecordBatch> = record_batches
+            .iter()
+            .map(|bytes| RecordBatch::parse(bytes, 0).unwrap())
+            .collect();
+        let segment = LogSegment::new(directory, record_batches[0].base_offset);
+        segment.append(&record_batches).unwrap();
+        segment
+    }
+
+    /// Offset, key and value of each record in the segment
+    fn records(segment: &LogSegment) -> Vec<(i64, String, Option<String>)> {
+        let text = |bytes: &[u8]| String::from_utf8(bytes.to_vec()).unwrap();
+        segment
+            .record_batches()
+            .unwrap()
+            .iter()
+            .flat_map(|record_batch| {
+                record_batch.records().unwrap().into_iter().map(|record| {
+                    (
+                        record_batch.record_offset(&record),
+                        text(record.key.as_deref().unwrap()),
+                        record.value.as_deref().map(text),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    fn entry(offset: i64, key: &str, value: Option<&str>) -> (i64, String, Option<String>) {
+        (offset, key.to_string(), value.map(str::to_string))
+    }
+
+    #[test]
+    fn keeps_latest_record_of_each_key_in_closed_segments() {
+        let temp_dir = TempDir::new("log-cleaner");
+        let directory = temp_dir.path();
+        let closed = write_segment(
+            directory,
+            &[
+                keyed_batch(0, 1000, &[("a", Some("1")), ("b", Some("1"))]),
+                keyed_batch(2, 1002, &[("b", Some("2")), ("c", Some("1"))]),
+            ],
+        );
+        let active = write_segment(directory, &[keyed_batch(4, 1004, &[("a", Some("2"))])]);
+
+        let removed = Log::in_directory(directory.to_path_buf())
+            .compact(&LogConfig::default(), 2000)
+            .unwrap();
+
+        assert_eq!(1, removed);
+        assert_eq!(
+            vec![
+                entry(0, "a", Some("1")),
+                entry(2, "b", Some("2")),
+                entry(3, "c", Some("1"))
+            ],
+            records(&closed)
+        );
+        assert_eq!(vec![entry(4, "a", Some("2"))], records(&active));
+        let record_batches = closed.record_batches().unwrap();
+        let rewritten = &record_batches[0];
+        assert_eq!(0, rewritten.last_offset_delta);
+        assert_eq!(1000, rewritten.max_timestamp);
+        assert_eq!(
+            crc32c::crc32c(&rewritten.to_be_bytes()[21..]),
+            rewritten.crc
+        );
+        assert_eq!(Some(4), closed.next_offset().unwrap());
+    }
+
+    #[test]
+    fn removes_tombstones_past_delete_retention() {
+        let temp_dir = TempDir::new("log-cleaner");
+        let directory = temp_dir.path();
+        let closed = write_segment(
+            directory,
+            &[
+                keyed_batch(0, 1000, &[("a", Some("1")), ("b", Some("1"))]),
+                keyed_batch(2, 2000, &[("a", None)]),
+            ],
+        );
+        write_segment(directory, &[keyed_batch(3, 3000, &[("c", Some("1"))])]);
+        let log = Log::in_directory(directory.to_path_buf());
+        let config = LogConfig {
+            delete_retention_ms: 1000,
+            ..LogConfig::default()
+        };
+
+        assert_eq!(1, log.compact(&config, 2500).unwrap());
+        assert_eq!(
+            vec![entry(1, "b", Some("1")), entry(2, "a", None)],
+            records(&closed)
+        );
+
+        assert_eq!(1, log.compact(&config, 3500).unwrap());
+        assert_eq!(vec![entry(1, "b", Some("1"))], records(&closed));
+        assert_eq!(1, closed.record_batches().unwrap().len());
+        assert_eq!(0, log.compact(&config, 3500).unwrap());
+    }
+
+    #[test]
+    fn keeps_control_batches_out_of_the_offset_map() {
+        let temp_dir = TempDir::new("log-cleaner");
+        let directory = temp_dir.path();
+        // A transaction marker's key is its version and type, here those of an abort marker
+        let marker_key = [0x00, 0x00, 0x00, 0x00];
+        let marker = RecordBatchBuilder::new(1)
+            .control()
+            .record(1001, Some(&marker_key), Some(&[0x00; 6]), Vec::new())
+            .build()
+            .unwrap()
+            .to_be_bytes();
+        let closed = write_segment(
+            directory,
+            &[
+                keyed_batch(0, 1000, &[("\0\0\0\0", Some("1"))]),
+                marker,
+                keyed_batch(2, 1002, &[("b", Some("1")), ("b", Some("2"))]),
+            ],
+        );
+        write_segment(directory, &[keyed_batch(4, 1004, &[("c", Some("1"))])]);
+
+        let removed = Log::in_directory(directory.to_path_buf())
+            .compact(&LogConfig::default(), 2000)
+            .unwrap();
+
+        assert_eq!(1, removed);
+        let record_batches = closed.record_batches().unwrap();
+        assert_eq!(
+            vec![(0, false), (1, true), (2, false)],
+            record_batches
+                .iter()
+                .map(|record_batch| (record_batch.base_offset, record_batch.is_control()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn drops_cleaned_segment_of_segment_deleted_meanwhile() {
+        let temp_dir = TempDir::new("log-cleaner");
+        let directory = temp_dir.path();
+        let closed = write_segment(
+            directory,
+            &[keyed_batch(0, 1000, &[("a", Some("1")), ("a", Some("2"))])],
+        );
+        let cleaner =
+            Cleaner::new(std::slice::from_ref(&closed), &LogConfig::default(), 2000).unwrap();
+        let (cleaned, removed) = cleaner.clean_segment(&closed).unwrap().unwrap();
+        assert_eq!(1, removed);
+
+        closed.delete().unwrap();
+
+        assert!(!closed.replace_with(&cleaned).unwrap());
+        assert_eq!(0, fs::read_dir(directory).unwrap().count());
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::records::compression::Compression;
+use crate::utils::config::ServerConfig;
+
+/// Settings of the partition logs, from the broker's `log.*` properties as Kafka's LogConfig
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogConfig {
+    /// Size past which the active segment is rolled
+    pub segment_bytes: u64,
+    /// Span of record timestamps in the active segment past which it is rolled
+    pub segment_ms: i64,
+    /// Age of a segment's newest record past which the segment is deleted, or -1 to keep
+    /// segments regardless of age
+    pub retention_ms: i64,
+    /// Size a log is trimmed to by deleting its oldest segments, or -1 for no limit
+    pub retention_bytes: i64,
+    /// Whether old segments are deleted by retention, compacted, or both
+    pub cleanup_policy: CleanupPolicy,
+    /// Time a tombstone is kept in a compacted log, so consumers get to see the deletion
+    pub delete_retention_ms: i64,
+    /// Whether records keep the timestamps they were produced with or get the append time
+    pub message_timestamp_type: TimestampType,
+    /// Codec produced batches are stored with
+    pub compression_type: CompressionType,
+}
+
+impl LogConfig {
+    /// The broker's settings with the configs set on a topic, by their Kafka names, taking
+    /// precedence. Invalid topic configs are ignored.
+    pub fn for_topic(
+        server_config: &ServerConfig,
+        topic_configs: Option<&HashMap<String, String>>,
+    ) -> Self {
+        let mut config = Self::from(server_config);
+        for (name, value) in topic_configs.into_iter().flatten() {
+            if let Err(reason) = config.set(name, value) {
+                warn!("Ignoring topic config {name}={value}: {reason}");
+            }
+        }
+        config
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        match name {
+            "segment.bytes" => self.segment_bytes = parse_at_least(value, 1)?,
+            "segment.ms" => self.segment_ms = parse_at_least(value, 1)?,
+            "retention.ms" => self.retention_ms = parse_at_least(value, -1)?,
+            "retention.bytes" => self.retention_bytes = parse_at_least(value, -1)?,
+            "cleanup.policy" => self.cleanup_policy = value.parse()?,
+            "delete.retention.ms" => self.delete_retention_ms = parse_at_least(value, 0)?,
+            "message.timestamp.type" => self.message_timestamp_type = value.parse()?,
+            "compression.type" => self.compression_type = value.parse()?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl From<&ServerConfig> for LogConfig {
+    fn from(server_config: &ServerConfig) -> Self {
+        Self {
+            segment_bytes: server_config.log_segment_bytes,
+            segment_ms: server_config.segment_ms(),
+            retention_ms: server_config.retention_ms(),
+            retention_bytes: server_config.log_retention_bytes,
+            cleanup_policy: server_config.log_cleanup_policy,
+            delete_retention_ms: server_config.log_cleaner_delete_retention_ms,
+            message_timestamp_type: server_config.log_message_timestamp_type,
+            compression_type: server_config.compression_type,
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self::from(&ServerConfig::default())
+    }
+}
+
+/// Kafka's `cleanup.policy`, a list of `delete` and `compact`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct CleanupPolicy {
+    pub delete: bool,
+    pub compact: bool,
+}
+
+impl Default for CleanupPolicy {
+    fn default() -> Self {
+        Self {
+            delete: true,
+            compact: false,
+        }
+    }
+}
+
+impl FromStr for CleanupPolicy {
+    type Err = String;
+
+    fn from_str(policies: &str) -> Result<Self, Self::Err> {
+        let mut cleanup_policy = Self {
+            delete: false,
+            compact: false,
+        };
+        for policy in policies.split(',').map(str::trim) {
+            match policy.to_ascii_lowercase().as_str() {
+                "delete" => cleanup_policy.delete = true,
+                "compact" => cleanup_policy.compact = true,
+                _ => return Err(format!("unknown cleanup policy {policy:?}")),
+            }
+        }
+        Ok(cleanup_policy)
+    }
+}
+
+impl TryFrom<String> for CleanupPolicy {
+    type Error = String;
+
+    fn try_from(policies: String) -> Result<Self, Self::Error> {
+        policies.parse()
+    }
+}
+
+impl fmt::Display for CleanupPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.delete, self.compact) {
+            (true, true) => write!(f, "compact,delete"),
+            (false, true) => write!(f, "compact"),
+            _ => write!(f, "delete"),
+        }
+    }
+}
+
+/// Kafka's `message.timestamp.type`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum TimestampType {
+    /// Records keep the timestamps the producer set
+    #[default]
+    CreateTime,
+    /// The broker sets the time it appends a batch as the timestamp of its records
+    LogAppendTime,
+}
+
+impl FromStr for TimestampType {
+    type Err = String;
+
+    fn from_str(timestamp_type: &str) -> Result<Self, Self::Err> {
+        match timestamp_type {
+            "CreateTime" => Ok(Self::CreateTime),
+            "LogAppendTime" => Ok(Self::LogAppendTime),
+            _ => Err(format!("unknown timestamp type {timestamp_type:?}")),
+        }
+    }
+}
+
+impl TryFrom<String> for TimestampType {
+    type Error = String;
+
+    fn try_from(timestamp_type: String) -> Result<Self, Self::Error> {
+        timestamp_type.parse()
+    }
+}
+
+/// Kafka's `compression.type`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum CompressionType {
+    /// Batches keep the codec the producer compressed them with
+    #[default]
+    Producer,
+    /// Batches are recompressed with the codec
+    Codec(Compression),
+}
+
+impl CompressionType {
+    /// Codec a produced batch is stored with, given the one it was produced with
+    pub fn target(self, produced: Compression) -> Compression {
+        match self {
+            Self::Producer => produced,
+            Self::Codec(compression) => compression,
+        }
+    }
+}
+
+impl FromStr for CompressionType {
+    type Err = String;
+
+    fn from_str(compression_type: &str) -> Result<Self, Self::Err> {
+        match compression_type.to_ascii_lowercase().as_str() {
+            "producer" => Ok(Self::Producer),
+            "uncompressed" => Ok(Self::Codec(Compression::None)),
+            "gzip" => Ok(Self::Codec(Compression::Gzip)),
+            "snappy" => Ok(Self::Codec(Compression::Snappy)),
+            "lz4" => Ok(Self::Codec(Compression::Lz4)),
+            "zstd" => Ok(Self::Codec(Compression::Zstd)),
+            _ => Err(format!("unknown compression type {compression_type:?}")),
+        }
+    }
+}
+
+impl TryFrom<String> for CompressionType {
+    type Error = String;
+
+    fn try_from(compression_type: String) -> Result<Self, Self::Error> {
+        compression_type.parse()
+    }
+}
+
+fn parse_at_least<T>(value: &str, min: T) -> Result<T, String>
+where
+    T: FromStr + PartialOrd + fmt::Display,
+    T::Err: fmt::Display,
+{
+    let parsed: T = value.parse().map_err(|e: T::Err| e.to_string())?;
+    if parsed < min {
+        return Err(format!("must be at least {min}"));
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn topic_configs_override_broker_settings() {
+        let topic_configs = HashMap::from([
+            ("cleanup.policy".to_string(), "compact".to_string()),
+            ("delete.retention.ms".to_string(), "1000".to_string()),
+            ("segment.bytes".to_string(), "0".to_string()),
+            ("compression.type".to_string(), "zstd".to_string()),
+        ]);
+
+        let config = LogConfig::for_topic(&ServerConfig::default(), Some(&topic_configs));
+
+        assert_eq!(
+            CleanupPolicy {
+                delete: false,
+                compact: true
+            },
+            config.cleanup_policy
+        );
+        assert_eq!(1000, config.delete_retention_ms);
+        assert_eq!(
+            Compression::Zstd,
+            config.compression_type.target(Compression::Gzip)
+        );
+        assert_eq!(LogConfig::default().segment_bytes, config.segment_bytes);
+    }
+
+    #[test]
+    fn parses_cleanup_policy_lists() {
+        let parse = |policies: &str| policies.parse::<CleanupPolicy>();
+
+        assert_eq!(Ok(CleanupPolicy::default()), parse("delete"));
+        assert_eq!(
+            "compact,delete",
+            parse("Compact, delete").unwrap().to_string()
+        );
+        assert!(parse("compact,archive").is_err());
+    }
+}
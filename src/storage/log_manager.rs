@@ -0,0 +1,337 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::sync::PoisonError;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tracing::{error, info, warn};
+
+use crate::metadata_image::{MetadataImage, SharedMetadataImage};
+use crate::storage::log::Log;
+use crate::storage::log_config::LogConfig;
+use crate::storage::offset_checkpoint::OffsetCheckpointFile;
+use crate::utils::config::ServerConfig;
+
+// The cluster metadata log is kept whole, as Kafka trims it by snapshots rather than retention
+const CLUSTER_METADATA_DIRECTORY: &str = "__cluster_metadata-0";
+/// File of each log directory holding the recovery points of its logs
+const RECOVERY_POINT_CHECKPOINT_FILE: &str = "recovery-point-offset-checkpoint";
+/// File written to each log directory once its logs are flushed and closed on an orderly
+/// shutdown, so that they are not recovered at the next startup
+const CLEAN_SHUTDOWN_FILE: &str = ".kafka_cleanshutdown";
+
+/// The partition logs in the log directories, as Kafka's LogManager
+#[derive(Debug, Clone)]
+pub struct LogManager {
+    server_config: ServerConfig,
+}
+
+impl LogManager {
+    pub fn new(server_config: &ServerConfig) -> Self {
+        Self {
+            server_config: server_config.clone(),
+        }
+    }
+
+    /// The logs of the partition directories in the log directories
+    pub fn logs(&self) -> io::Result<Vec<Log>> {
+        let mut logs = Vec::new();
+        for log_dir in self.log_dirs() {
+            logs.extend(logs_in(log_dir)?);
+        }
+        Ok(logs)
+    }
+
+    /// Loads the logs at startup, recovering each from the recovery point in the checkpoint of
+    /// its log directory, then checkpoints the recovery points of the recovered logs. Logs
+    /// missing from the checkpoint are recovered from the start. The logs of a log directory
+    /// that was shut down cleanly are fully flushed, so they are loaded without recovery.
+    /// Each log is locked on its own, so the recovery of the cluster metadata log doesn't
+    /// wait on that of the partition logs.
+    pub fn load(&self) -> io::Result<()> {
+        for log_dir in self.log_dirs() {
+            let clean_shutdown_file = log_dir.join(CLEAN_SHUTDOWN_FILE);
+            if clean_shutdown_file.exists() {
+                info!(
+                    "Skipping recovery of {} after a clean shutdown",
+                    log_dir.display()
+                );
+                logs_in(log_dir)?.iter().try_for_each(Log::load)?;
+                // Removed so that a crash of this run makes the next startup recover the logs
+                fs::remove_file(&clean_shutdown_file)?;
+                continue;
+            }
+            let checkpoint =
+                OffsetCheckpointFile::new(log_dir.join(RECOVERY_POINT_CHECKPOINT_FILE));
+            let recovery_points = checkpoint.read().unwrap_or_else(|e| {
+                warn!("Recovering all logs of {}: {e}", log_dir.display());
+                Default::default()
+            });
+            for log in logs_in(log_dir)? {
+                let recovery_point = log
+                    .topic_partition()
+                    .and_then(|topic_partition| recovery_points.get(&topic_partition))
+                    .copied()
+                    .unwrap_or(0);
+                log.recover(recovery_point)?;
+            }
+            checkpoint_recovery_points(log_dir, &checkpoint)?;
+        }
+        Ok(())
+    }
+
+    /// Closes the logs on an orderly shutdown, checkpointing the offsets they are flushed up
+    /// to, then marks each log directory as shut down cleanly
+    pub fn shutdown(&self) -> io::Result<()> {
+        for log_dir in self.log_dirs() {
+            let mut recovery_points = Vec::new();
+            for log in logs_in(log_dir)? {
+                let next_offset = log.close()?;
+                if let Some((topic_name, partition_index)) = log.topic_partition() {
+                    recovery_points.push((topic_name, partition_index, next_offset));
+                }
+            }
+            recovery_points.sort();
+            OffsetCheckpointFile::new(log_dir.join(RECOVERY_POINT_CHECKPOINT_FILE))
+                .write(&recovery_points)?;
+            File::create(log_dir.join(CLEAN_SHUTDOWN_FILE))?.sync_all()?;
+        }
+        Ok(())
+    }
+
+    /// Starts the background task deleting the segments past the retention limits of the logs,
+    /// every `log.retention.check.interval.ms`
+    pub fn spawn_retention_task(
+        &self,
+        metadata_image: SharedMetadataImage,
+    ) -> io::Result<thread::JoinHandle<()>> {
+        let log_manager = self.clone();
+        let interval = Duration::from_millis(self.server_config.log_retention_check_interval_ms);
+        thread::Builder::new()
+            .name("log-retention".to_string())
+            .spawn(move || loop {
+                thread::sleep(interval);
+                if let Err(e) = log_manager.delete_old_segments(&metadata_image) {
+                    error!("Failed to enforce log retention: {e}");
+                }
+            })
+    }
+
+    /// Starts the background task compacting the logs of topics with the compact cleanup
+    /// policy, every `log.cleaner.backoff.ms`, unless `log.cleaner.enable` is off
+    pub fn spawn_cleaner_task(
+        &self,
+        metadata_image: SharedMetadataImage,
+    ) -> io::Result<Option<thread::JoinHandle<()>>> {
+        if !self.server_config.log_cleaner_enable {
+            return Ok(None);
+        }
+        let log_manager = self.clone();
+        let interval = Duration::from_millis(self.server_config.log_cleaner_backoff_ms);
+        thread::Builder::new()
+            .name("log-cleaner".to_string())
+            .spawn(move || loop {
+                thread::sleep(interval);
+                if let Err(e) = log_manager.compact_logs(&metadata_image) {
+                    error!("Failed to compact logs: {e}");
+                }
+            })
+            .map(Some)
+    }
+
+    /// Starts the background task flushing the logs and checkpointing their recovery points,
+    /// every `log.flush.offset.checkpoint.interval.ms`, so a restart only recovers what was
+    /// appended since
+    pub fn spawn_recovery_point_checkpoint_task(&self) -> io::Result<thread::JoinHandle<()>> {
+        let log_manager = self.clone();
+        let interval =
+            Duration::from_millis(self.server_config.log_flush_offset_checkpoint_interval_ms);
+        thread::Builder::new()
+            .name("log-recovery-point-checkpoint".to_string())
+            .spawn(move || loop {
+                thread::sleep(interval);
+                for log_dir in log_manager.log_dirs() {
+                    let checkpoint =
+                        OffsetCheckpointFile::new(log_dir.join(RECOVERY_POINT_CHECKPOINT_FILE));
+                    if let Err(e) = checkpoint_recovery_points(log_dir, &checkpoint) {
+                        error!(
+                            "Failed to checkpoint recovery points of {}: {e}",
+                            log_dir.display()
+                        );
+                    }
+                }
+            })
+    }
+
+    fn log_dirs(&self) -> impl Iterator<Item = &Path> {
+        self.server_config
+            .log_dirs
+            .iter()
+            .map(|log_dir| log_dir.as_path())
+            .filter(|log_dir| log_dir.is_dir())
+    }
+
+    /// Settings of the log: the broker's, with those set on its topic taking precedence
+    fn log_config(&self, log: &Log, metadata_image: &MetadataImage) -> LogConfig {
+        let topic_configs = log
+            .topic_partition()
+            .and_then(|(topic_name, _)| metadata_image.topic_configs(topic_name.as_bytes()));
+        LogConfig::for_topic(&self.server_config, topic_configs)
+    }
+
+    fn delete_old_segments(&self, metadata_image: &SharedMetadataImage) -> io::Result<()> {
+        let now_ms = now_ms();
+        for log in self.logs()? {
+            if log.directory().ends_with(CLUSTER_METADATA_DIRECTORY) {
+                continue;
+            }
+            let config = self.log_config(
+                &log,
+                &metadata_image
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner),
+            );
+            match log.delete_old_segments(&config, now_ms) {
+                Ok(0) => {}
+                Ok(deleted) => info!(
+                    "Deleted {deleted} segment(s) of {} past retention, log start offset is now {}",
+                    log.directory().display(),
+                    log.log_start_offset()?
+                ),
+                Err(e) => error!(
+                    "Failed to delete old segments of {}: {e}",
+                    log.directory().display()
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    fn compact_logs(&self, metadata_image: &SharedMetadataImage) -> io::Result<()> {
+        let now_ms = now_ms();
+        for log in self.logs()? {
+            let config = self.log_config(
+                &log,
+                &metadata_image
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner),
+            );
+            if log.directory().ends_with(CLUSTER_METADATA_DIRECTORY)
+                || !config.cleanup_policy.compact
+            {
+                continue;
+            }
+            match log.compact(&config, now_ms) {
+                Ok(0) => {}
+                Ok(removed) => info!(
+                    "Compacted {}, removing {removed} record(s)",
+                    log.directory().display()
+                ),
+                Err(e) => error!("Failed to compact {}: {e}", log.directory().display()),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The logs of the partition directories in a log directory
+fn logs_in(log_dir: &Path) -> io::Result<Vec<Log>> {
+    let mut logs = Vec::new();
+    for entry in fs::read_dir(log_dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            logs.push(Log::in_directory(path));
+        }
+    }
+    Ok(logs)
+}
+
+/// Flushes the logs of the log directory and writes the offsets they are flushed up to as
+/// their recovery points
+fn checkpoint_recovery_points(log_dir: &Path, checkpoint: &OffsetCheckpointFile) -> io::Result<()> {
+    let mut recovery_points = Vec::new();
+    for log in logs_in(log_dir)? {
+        if let Some((topic_name, partition_index)) = log.topic_partition() {
+            recovery_points.push((topic_name, partition_index, log.flush()?));
+        }
+    }
+    recovery_points.sort();
+    checkpoint.write(&recovery_points)
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_millis() as i64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::records::record_batch_builder::RecordBatchBuilder;
+    use crate::test_fixtures::TempDir;
+
+    fn temp_log_manager() -> (TempDir, LogManager) {
+        let log_dir = TempDir::new("log-manager");
+        let log_manager = LogManager::new(&ServerConfig {
+            log_dirs: vec![log_dir.path().to_path_buf()],
+            ..ServerConfig::default()
+        });
+        (log_dir, log_manager)
+    }
+
+    /// Log of partition 0 of "topic", holding 2 batches of a record
+    fn log_with_2_batches(log_manager: &LogManager) -> Log {
+        let log = Log::new(&log_manager.server_config.log_dirs, "topic", 0);
+        for timestamp in [1000, 2000] {
+            let record_batch = RecordBatchBuilder::new(0)
+                .record(timestamp, None, Some(b"value"), Vec::new())
+                .build()
+                .unwrap();
+            log.append(vec![record_batch], &LogConfig::default())
+                .unwrap();
+        }
+        log
+    }
+
+    #[test]
+    fn shutdown_closes_logs_and_marks_log_dirs_clean() {
+        let (_log_dir, log_manager) = temp_log_manager();
+        let log_dir = &log_manager.server_config.log_dirs[0];
+        let log = log_with_2_batches(&log_manager);
+
+        log_manager.shutdown().unwrap();
+
+        assert!(log_dir.join(CLEAN_SHUTDOWN_FILE).exists());
+        let recovery_points =
+            OffsetCheckpointFile::new(log_dir.join(RECOVERY_POINT_CHECKPOINT_FILE))
+                .read()
+                .unwrap();
+        assert_eq!(Some(&2), recovery_points.get(&("topic".to_string(), 0)));
+        assert!(log.append(Vec::new(), &LogConfig::default()).is_err());
+    }
+
+    #[test]
+    fn skips_recovery_after_clean_shutdown_only() {
+        let (_log_dir, log_manager) = temp_log_manager();
+        let log_dir = &log_manager.server_config.log_dirs[0];
+        let log = log_with_2_batches(&log_manager);
+        let segment_path = log.segments().unwrap()[0].log_path().to_path_buf();
+        let mut bytes = fs::read(&segment_path).unwrap();
+        // Corrupts the value of the last batch, which a crash could leave unflushed
+        let last = bytes.len() - 2;
+        bytes[last] ^= 0x01;
+        fs::write(&segment_path, &bytes).unwrap();
+        File::create(log_dir.join(CLEAN_SHUTDOWN_FILE)).unwrap();
+
+        log_manager.load().unwrap();
+
+        assert_eq!(2, log.next_offset().unwrap());
+        assert!(!log_dir.join(CLEAN_SHUTDOWN_FILE).exists());
+
+        log_manager.load().unwrap();
+
+        assert_eq!(1, log.next_offset().unwrap());
+    }
+}
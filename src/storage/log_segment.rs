@@ -0,0 +1,804 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use tracing::warn;
+
+use crate::byte_parsable::ByteParsable;
+use crate::records::record_batch::{Record, RecordBatch, SPAN_HEADER_SIZE};
+use crate::serializable::Serializable;
+use crate::size::Size;
+use crate::storage::index::{OffsetIndex, OffsetPosition, TimeIndex, TimestampOffset};
+use crate::storage::log_config::LogConfig;
+
+/// Bytes of batches appended between offset index entries, Kafka's `index.interval.bytes` default
+const INDEX_INTERVAL_BYTES: u64 = 4096;
+const NO_TIMESTAMP: i64 = -1;
+
+/// A record's timestamp and offset, with the leader epoch of its batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimestampAndOffset {
+    pub timestamp: i64,
+    pub offset: i64,
+    pub leader_epoch: i32,
+}
+
+impl TimestampAndOffset {
+    fn of(record_batch: &RecordBatch, record: &Record) -> Self {
+        Self {
+            timestamp: record_batch.record_timestamp(record),
+            offset: record_batch.record_offset(record),
+            leader_epoch: record_batch.partition_leader_epoch,
+        }
+    }
+}
+
+/// Segment file of a partition log starting at a base offset, named after it like Kafka's
+/// `00000000000000000000.log`, with its `.index` and `.timeindex` alongside
+#[derive(Debug, Clone)]
+pub struct LogSegment {
+    log_path: PathBuf,
+    offset_index: OffsetIndex,
+    time_index: TimeIndex,
+    base_offset: i64,
+}
+
+impl LogSegment {
+    pub fn new(directory: &Path, base_offset: i64) -> Self {
+        let path = |extension| directory.join(format!("{base_offset:020}.{extension}"));
+        Self {
+            log_path: path("log"),
+            offset_index: OffsetIndex::new(path("index"), base_offset),
+            time_index: TimeIndex::new(path("timeindex"), base_offset),
+            base_offset,
+        }
+    }
+
+    pub fn log_path(&self) -> &Path {
+        &self.log_path
+    }
+
+    pub fn base_offset(&self) -> i64 {
+        self.base_offset
+    }
+
+    /// Base offset of the segment named by the file name, if it is a segment file
+    pub fn parse_base_offset(file_name: &str) -> Option<i64> {
+        file_name
+            .strip_suffix(".log")
+            .filter(|stem| stem.len() == 20 && stem.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|stem| stem.parse().ok())
+    }
+
+    /// Size of the segment file, which is empty if it doesn't exist yet
+    pub fn size(&self) -> io::Result<u64> {
+        match self.log_path.metadata() {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn record_batches(&self) -> io::Result<Vec<RecordBatch>> {
+        self.record_batches_from(0)
+    }
+
+    /// Reads the batches from the one holding the offset, as many whole batches as fit in
+    /// `max_bytes`, or the first one whatever its size if `min_one_batch`. Only the batch
+    /// headers are read to find where the batches start and end, and the batches are returned
+    /// as the bytes they are stored as. Returns `None` if no batch of the segment holds the
+    /// offset or a later one.
+    pub fn read(
+        &self,
+        offset: i64,
+        max_bytes: usize,
+        min_one_batch: bool,
+    ) -> io::Result<Option<Vec<u8>>> {
+        let mut file = match File::open(&self.log_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let size = file.metadata()?.len();
+        let mut start = self
+            .offset_index
+            .lookup(offset)?
+            .map_or(0, |entry| entry.position as u64);
+        loop {
+            match batch_span(&mut file, start, size)? {
+                Some((last_offset, end)) if last_offset < offset => start = end,
+                Some(_) => break,
+                None => return Ok(None),
+            }
+        }
+        let mut end = start;
+        while let Some((_, batch_end)) = batch_span(&mut file, end, size)? {
+            if batch_end - start > max_bytes as u64 && !(end == start && min_one_batch) {
+                break;
+            }
+            end = batch_end;
+        }
+        let mut bytes = Vec::new();
+        file.seek(SeekFrom::Start(start))?;
+        file.take(end - start).read_to_end(&mut bytes)?;
+        Ok(Some(bytes))
+    }
+
+    /// The batches from the one holding the offset to the end of the segment
+    fn record_batches_from_offset(&self, offset: i64) -> io::Result<Vec<RecordBatch>> {
+        let bytes = self.read(offset, usize::MAX, true)?.unwrap_or_default();
+        RecordBatch::from_bytes(&bytes).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+    }
+
+    /// Whether the batches to append belong in a new segment: this one isn't empty, and
+    /// appending would take it past `segment.bytes`, the span of its timestamps past
+    /// `segment.ms` counting from its first batch, or its offsets past what the index can hold
+    pub fn should_roll(
+        &self,
+        config: &LogConfig,
+        record_batches: &[RecordBatch],
+    ) -> io::Result<bool> {
+        let Some(first_batch) = self.first_batch()? else {
+            return Ok(false);
+        };
+        let bytes: u64 = record_batches
+            .iter()
+            .map(|record_batch| record_batch.size() as u64)
+            .sum();
+        let max_timestamp = record_batches
+            .iter()
+            .map(|record_batch| record_batch.max_timestamp)
+            .max()
+            .unwrap_or(NO_TIMESTAMP);
+        let last_offset = record_batches
+            .last()
+            .map_or(self.base_offset, RecordBatch::last_offset);
+        Ok(self.size()? + bytes > config.segment_bytes
+            || (first_batch.max_timestamp >= 0
+                && max_timestamp - first_batch.max_timestamp > config.segment_ms)
+            || last_offset - self.base_offset > i32::MAX as i64)
+    }
+
+    /// Largest timestamp of the segment's batches, or the time it was last modified if they
+    /// have none, as Kafka's retention goes by
+    pub fn largest_timestamp(&self) -> io::Result<i64> {
+        match self.resume_indexing()?.max_timestamp {
+            Some(max_timestamp) if max_timestamp.timestamp >= 0 => Ok(max_timestamp.timestamp),
+            _ => {
+                let modified = self.log_path.metadata()?.modified()?;
+                Ok(modified
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |since_epoch| since_epoch.as_millis() as i64))
+            }
+        }
+    }
+
+    /// Creates the files of an empty segment
+    pub fn create(&self) -> io::Result<()> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.log_path)?;
+        self.offset_index.append(&[])?;
+        self.time_index.append(&[])
+    }
+
+    /// Deletes the segment and its indexes
+    pub fn delete(&self) -> io::Result<()> {
+        for path in [
+            self.log_path.as_path(),
+            self.offset_index.path(),
+            self.time_index.path(),
+        ] {
+            match fs::remove_file(path) {
+                Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Indexes the largest timestamp of the segment once it is no longer appended to, so that
+    /// the time index covers all of it, and flushes it so recovery can start after it
+    pub fn on_become_inactive(&self) -> io::Result<()> {
+        let indexer = self.resume_indexing()?;
+        if let Some(max_timestamp) = indexer
+            .max_timestamp
+            .filter(|max_timestamp| max_timestamp.timestamp > indexer.last_entry_timestamp)
+        {
+            self.time_index.append(&[max_timestamp])?;
+        }
+        self.flush()
+    }
+
+    /// Offset that follows the last batch, if the segment has any. Only the batches after the
+    /// last offset index entry are read.
+    pub fn next_offset(&self) -> io::Result<Option<i64>> {
+        let position = self
+            .offset_index
+            .last_entry()?
+            .map_or(0, |entry| entry.position);
+        Ok(self
+            .record_batches_from(position)?
+            .last()
+            .map(RecordBatch::next_offset))
+    }
+
+    /// The first record with a timestamp at or after the given one. The time index gives an
+    /// offset whose batch and the ones before it are all older, and the batches from there
+    /// whose `max_timestamp` reaches the timestamp have their records looked at.
+    pub fn find_offset_by_timestamp(
+        &self,
+        timestamp: i64,
+    ) -> io::Result<Option<TimestampAndOffset>> {
+        let start_offset = self
+            .time_index
+            .lookup_before(timestamp)?
+            .map_or(self.base_offset, |entry| entry.offset);
+        for record_batch in self.record_batches_from_offset(start_offset)? {
+            if record_batch.max_timestamp < timestamp {
+                continue;
+            }
+            if let Some(timestamp_and_offset) = records(&record_batch)?
+                .iter()
+                .map(|record| TimestampAndOffset::of(&record_batch, record))
+                .find(|timestamp_and_offset| timestamp_and_offset.timestamp >= timestamp)
+            {
+                return Ok(Some(timestamp_and_offset));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The record with the largest timestamp, the first of them if several share it. Batches
+    /// before the one of the last time index entry all have smaller timestamps.
+    pub fn max_timestamp_and_offset(&self) -> io::Result<Option<TimestampAndOffset>> {
+        let start_offset = self
+            .time_index
+            .last_entry()?
+            .map_or(self.base_offset, |entry| entry.offset);
+        let mut max: Option<TimestampAndOffset> = None;
+        for record_batch in self.record_batches_from_offset(start_offset)? {
+            if max.is_some_and(|max| record_batch.max_timestamp <= max.timestamp) {
+                continue;
+            }
+            for record in &records(&record_batch)? {
+                let timestamp_and_offset = TimestampAndOffset::of(&record_batch, record);
+                if max.is_none_or(|max| timestamp_and_offset.timestamp > max.timestamp) {
+                    max = Some(timestamp_and_offset);
+                }
+            }
+        }
+        Ok(max)
+    }
+
+    /// Appends record batches, whose offsets are already assigned, and indexes them
+    pub fn append(&self, record_batches: &[RecordBatch]) -> io::Result<()> {
+        let mut indexer = self.resume_indexing()?;
+        let mut bytes = Vec::new();
+        for record_batch in record_batches {
+            indexer.add(record_batch);
+            bytes.extend_from_slice(&record_batch.to_be_bytes());
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.log_path)?
+            .write_all(&bytes)?;
+        // The segment is written first, so a failed index write leaves an index that is
+        // sparser than it should be rather than one pointing past the segment
+        self.offset_index.append(&indexer.offset_entries)?;
+        self.time_index.append(&indexer.time_entries)
+    }
+
+    /// Writes the batches the cleaner keeps of the segment to a segment alongside it, with
+    /// `.cleaned` added to the names of its files, indexed and flushed so that swapping it in
+    /// with [`LogSegment::replace_with`] only renames them
+    pub fn write_cleaned(&self, record_batches: &[RecordBatch]) -> io::Result<LogSegment> {
+        let cleaned_path = |path: &Path| {
+            let mut cleaned_path = path.as_os_str().to_owned();
+            cleaned_path.push(".cleaned");
+            PathBuf::from(cleaned_path)
+        };
+        let cleaned = Self {
+            log_path: cleaned_path(&self.log_path),
+            offset_index: OffsetIndex::new(
+                cleaned_path(self.offset_index.path()),
+                self.base_offset,
+            ),
+            time_index: TimeIndex::new(cleaned_path(self.time_index.path()), self.base_offset),
+            base_offset: self.base_offset,
+        };
+        // Leftovers of a pass that didn't get to swap its segment in are written over
+        cleaned.delete()?;
+        cleaned.create()?;
+        cleaned.append(record_batches)?;
+        cleaned.on_become_inactive()?;
+        Ok(cleaned)
+    }
+
+    /// Swaps in the cleaned segment, renaming its files over the ones of this segment. The old
+    /// indexes are removed first, so that at any point the indexes either match the segment or
+    /// are missing and rebuilt when loaded. A segment deleted meanwhile, by retention, stays
+    /// deleted and the cleaned one is dropped. Returns whether the segment was replaced.
+    pub fn replace_with(&self, cleaned: &LogSegment) -> io::Result<bool> {
+        if !self.log_path.exists() {
+            cleaned.delete()?;
+            return Ok(false);
+        }
+        for index_path in [self.offset_index.path(), self.time_index.path()] {
+            match fs::remove_file(index_path) {
+                Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        fs::rename(&cleaned.log_path, &self.log_path)?;
+        fs::rename(cleaned.offset_index.path(), self.offset_index.path())?;
+        fs::rename(cleaned.time_index.path(), self.time_index.path())?;
+        Ok(true)
+    }
+
+    /// Checks the indexes of the segment, rebuilding them if they are missing or corrupt
+    pub fn load(&self) -> io::Result<()> {
+        if !self.log_path.exists() {
+            return Ok(());
+        }
+        match self.sanity_check() {
+            Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::InvalidData) => {
+                warn!(
+                    "Rebuilding indexes of segment {}: {e}",
+                    self.log_path.display()
+                );
+                self.rebuild_indexes()
+            }
+            result => result,
+        }
+    }
+
+    /// Validates the batches of a segment that may not have been fully written to disk,
+    /// truncating it at the first batch that is partial, can't be decoded, fails its CRC or
+    /// goes back in offsets, and rebuilds its indexes. Returns whether it was truncated.
+    pub fn recover(&self) -> io::Result<bool> {
+        let bytes = match fs::read(&self.log_path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        let mut position = 0;
+        let mut next_offset = self.base_offset;
+        let mut invalid = None;
+        while position < bytes.len() {
+            let record_batch = RecordBatch::parse(&bytes, position)
+                .and_then(|record_batch| {
+                    record_batch.validate_crc()?;
+                    Ok(record_batch)
+                })
+                .map_err(|e| e.to_string())
+                .and_then(|record_batch| {
+                    if record_batch.base_offset < next_offset {
+                        return Err(format!(
+                            "offset {} is before the expected offset {next_offset}",
+                            record_batch.base_offset
+                        ));
+                    }
+                    Ok(record_batch)
+                });
+            match record_batch {
+                Ok(record_batch) => {
+                    position += record_batch.size();
+                    next_offset = record_batch.next_offset();
+                }
+                Err(reason) => {
+                    invalid = Some(reason);
+                    break;
+                }
+            }
+        }
+        if let Some(reason) = &invalid {
+            warn!(
+                "Truncating segment {} at position {position}, dropping {} byte(s): {reason}",
+                self.log_path.display(),
+                bytes.len() - position
+            );
+            let file = OpenOptions::new().write(true).open(&self.log_path)?;
+            file.set_len(position as u64)?;
+            file.sync_all()?;
+        }
+        self.rebuild_indexes()?;
+        Ok(invalid.is_some())
+    }
+
+    /// Forces the segment file to disk
+    pub fn flush(&self) -> io::Result<()> {
+        match File::open(&self.log_path) {
+            Ok(file) => file.sync_all(),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn sanity_check(&self) -> io::Result<()> {
+        self.offset_index.sanity_check(self.size()?)?;
+        let next_offset = self.next_offset()?.unwrap_or(self.base_offset);
+        self.time_index.sanity_check(next_offset)
+    }
+
+    pub fn rebuild_indexes(&self) -> io::Result<()> {
+        let mut indexer = Indexer::new(0, 0, NO_TIMESTAMP);
+        for record_batch in self.record_batches()? {
+            indexer.add(&record_batch);
+        }
+        self.offset_index.reset(&indexer.offset_entries)?;
+        self.time_index.reset(&indexer.time_entries)
+    }
+
+    /// Indexer continuing from the end of the segment. The largest timestamp so far is the
+    /// one of the last time index entry, unless a batch after the last offset index entry has
+    /// a larger one.
+    fn resume_indexing(&self) -> io::Result<Indexer> {
+        let last_entry_position = self
+            .offset_index
+            .last_entry()?
+            .map_or(0, |entry| entry.position as u64);
+        let last_time_entry = self.time_index.last_entry()?;
+        let mut indexer = Indexer::new(
+            self.size()?,
+            last_entry_position,
+            last_time_entry.map_or(NO_TIMESTAMP, |entry| entry.timestamp),
+        );
+        indexer.max_timestamp = last_time_entry;
+        for record_batch in self.record_batches_from(last_entry_position as u32)? {
+            indexer.track_max_timestamp(&record_batch);
+        }
+        Ok(indexer)
+    }
+
+    fn first_batch(&self) -> io::Result<Option<RecordBatch>> {
+        let mut file = match File::open(&self.log_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let mut header = [0; 12];
+        match file.read_exact(&mut header) {
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof && self.size()? == 0 => return Ok(None),
+            result => result?,
+        }
+        let batch_length = i32::from_be_bytes(header[8..].try_into().unwrap());
+        let mut bytes = header.to_vec();
+        file.take(batch_length.max(0) as u64)
+            .read_to_end(&mut bytes)?;
+        RecordBatch::parse(&bytes, 0)
+            .map(Some)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+    }
+
+    fn record_batches_from(&self, position: u32) -> io::Result<Vec<RecordBatch>> {
+        let mut file = match File::open(&self.log_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        file.seek(SeekFrom::Start(position as u64))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        RecordBatch::from_bytes(&bytes).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+    }
+}
+
+/// The last offset of the batch at the position of the segment file and the position after it,
+/// `None` at the end of the file or of its last whole batch
+fn batch_span(file: &mut File, position: u64, size: u64) -> io::Result<Option<(i64, u64)>> {
+    if position + SPAN_HEADER_SIZE as u64 > size {
+        return Ok(None);
+    }
+    let mut header = [0; SPAN_HEADER_SIZE];
+    file.seek(SeekFrom::Start(position))?;
+    file.read_exact(&mut header)?;
+    let (last_offset, batch_size) =
+        RecordBatch::span(&header).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+    let end = position + batch_size as u64;
+    Ok((end <= size).then_some((last_offset, end)))
+}
+
+/// The records of a batch read from a segment, which only fail to decode if it is corrupt
+fn records(record_batch: &RecordBatch) -> io::Result<Vec<Record>> {
+    record_batch
+        .records()
+        .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Works out the index entries of batches appended to a segment, as Kafka's LogSegment: an
+/// offset index entry for the first batch after more than `INDEX_INTERVAL_BYTES` since the
+/// last one, with a time index entry for the largest timestamp so far if it grew since the last
+/// time index entry
+struct Indexer {
+    position: u64,
+    last_entry_position: u64,
+    last_entry_timestamp: i64,
+    max_timestamp: Option<TimestampOffset>,
+    offset_entries: Vec<OffsetPosition>,
+    time_entries: Vec<TimestampOffset>,
+}
+
+impl Indexer {
+    fn new(position: u64, last_entry_position: u64, last_entry_timestamp: i64) -> Self {
+        Self {
+            position,
+            last_entry_position,
+            last_entry_timestamp,
+            max_timestamp: None,
+            offset_entries: Vec::new(),
+            time_entries: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, record_batch: &RecordBatch) {
+        self.track_max_timestamp(record_batch);
+        if self.position - self.last_entry_position > INDEX_INTERVAL_BYTES {
+            self.offset_entries.push(OffsetPosition {
+                offset: record_batch.last_offset(),
+                position: self.position as u32,
+            });
+            if let Some(max_timestamp) = self
+                .max_timestamp
+                .filter(|max_timestamp| max_timestamp.timestamp > self.last_entry_timestamp)
+            {
+                self.time_entries.push(max_timestamp);
+                self.last_entry_timestamp = max_timestamp.timestamp;
+            }
+            self.last_entry_position = self.position;
+        }
+        self.position += record_batch.size() as u64;
+    }
+
+    fn track_max_timestamp(&mut self, record_batch: &RecordBatch) {
+        if self
+            .max_timestamp
+            .is_none_or(|max_timestamp| record_batch.max_timestamp > max_timestamp.timestamp)
+        {
+            self.max_timestamp = Some(TimestampOffset {
+                timestamp: record_batch.max_timestamp,
+                offset: record_batch.last_offset(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::*;
+    use crate::test_fixtures::TempDir;
+    use crate::types::unsigned_varint::UnsignedVarint;
+
+    /// Signed varint of a non-negative value
+    fn zig_zag(value: usize) -> Vec<u8> {
+        UnsignedVarint::new((value as u32) << 1).to_be_bytes()
+    }
+
+    /// Batch with a record for each timestamp delta, each with a null key and a value of the
+    /// given size
+    fn record_batch(
+        base_offset: i64,
+        base_timestamp: i64,
+        timestamp_deltas: &[usize],
+        value_size: usize,
+    ) -> RecordBatch {
+        let mut records = Vec::new();
+        for (offset_delta, &timestamp_delta) in timestamp_deltas.iter().enumerate() {
+            let mut record = vec![0x00]; // attributes
+            record.extend(zig_zag(timestamp_delta));
+            record.extend(zig_zag(offset_delta));
+            record.push(0x01); // key_length: -1
+            record.extend(zig_zag(value_size));
+            record.extend(vec![0x61; value_size]);
+            record.push(0x00); // headers_count: 0
+            records.extend(zig_zag(record.len()));
+            records.extend(record);
+        }
+        let max_timestamp = base_timestamp + *timestamp_deltas.iter().max().unwrap() as i64;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&base_offset.to_be_bytes());
+        bytes.extend_from_slice(&(49 + records.len() as i32).to_be_bytes()); // batch_length
+        bytes.extend_from_slice(&5i32.to_be_bytes()); // partition_leader_epoch
+        bytes.push(2); // magic
+        bytes.extend_from_slice(&0u32.to_be_bytes()); // crc, computed once the batch is built
+        bytes.extend_from_slice(&0i16.to_be_bytes()); // attributes
+        bytes.extend_from_slice(&(timestamp_deltas.len() as i32 - 1).to_be_bytes()); // last_offset_delta
+        bytes.extend_from_slice(&base_timestamp.to_be_bytes());
+        bytes.extend_from_slice(&max_timestamp.to_be_bytes());
+        bytes.extend_from_slice(&(-1i64).to_be_bytes()); // producer_id
+        bytes.extend_from_slice(&(-1i16).to_be_bytes()); // producer_epoch
+        bytes.extend_from_slice(&(-1i32).to_be_bytes()); // base_sequence
+        bytes.extend_from_slice(&(timestamp_deltas.len() as i32).to_be_bytes()); // records_count
+        bytes.extend_from_slice(&records);
+        let crc = crc32c::crc32c(&bytes[21..]);
+        bytes[17..21].copy_from_slice(&crc.to_be_bytes());
+        RecordBatch::parse(&bytes, 0).unwrap()
+    }
+
+    fn temp_segment() -> (TempDir, LogSegment) {
+        let directory = TempDir::new("log-segment");
+        let segment = LogSegment::new(directory.path(), 0);
+        (directory, segment)
+    }
+
+    /// Ten batches of a 1 KiB record each, a second apart
+    fn append_batches(segment: &LogSegment) {
+        for offset in 0..10 {
+            segment
+                .append(&[record_batch(offset, 1000 * offset, &[0], 1024)])
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn finds_first_record_at_or_after_timestamp() {
+        let (_directory, segment) = temp_segment();
+        segment
+            .append(&[
+                record_batch(0, 1000, &[0, 10, 5], 0),
+                record_batch(3, 1020, &[0, 30], 0),
+            ])
+            .unwrap();
+
+        let at = |timestamp, offset| {
+            Some(TimestampAndOffset {
+                timestamp,
+                offset,
+                leader_epoch: 5,
+            })
+        };
+        let find = |timestamp| segment.find_offset_by_timestamp(timestamp).unwrap();
+        assert_eq!(at(1000, 0), find(0));
+        assert_eq!(at(1010, 1), find(1001));
+        assert_eq!(at(1010, 1), find(1010));
+        assert_eq!(at(1020, 3), find(1011));
+        assert_eq!(at(1050, 4), find(1021));
+        assert_eq!(None, find(1051));
+        assert_eq!(at(1050, 4), segment.max_timestamp_and_offset().unwrap());
+    }
+
+    #[test]
+    fn empty_segment_has_no_offsets() {
+        let (_directory, segment) = temp_segment();
+
+        assert_eq!(None, segment.next_offset().unwrap());
+        assert_eq!(None, segment.find_offset_by_timestamp(0).unwrap());
+        assert_eq!(None, segment.max_timestamp_and_offset().unwrap());
+    }
+
+    #[test]
+    fn indexes_a_batch_every_index_interval() {
+        let (_directory, segment) = temp_segment();
+        let batch_size = record_batch(0, 0, &[0], 1024).size() as u32;
+
+        append_batches(&segment);
+
+        // Batch 4 is the first to start more than 4096 bytes after batch 0, and batch 8 after it
+        assert_eq!(
+            vec![
+                OffsetPosition {
+                    offset: 4,
+                    position: 4 * batch_size
+                },
+                OffsetPosition {
+                    offset: 8,
+                    position: 8 * batch_size
+                },
+            ],
+            segment.offset_index.entries().unwrap()
+        );
+        assert_eq!(
+            vec![
+                TimestampOffset {
+                    timestamp: 4000,
+                    offset: 4
+                },
+                TimestampOffset {
+                    timestamp: 8000,
+                    offset: 8
+                },
+            ],
+            segment.time_index.entries().unwrap()
+        );
+        assert_eq!(Some(10), segment.next_offset().unwrap());
+    }
+
+    #[test]
+    fn reads_from_batch_holding_offset() {
+        let (_directory, segment) = temp_segment();
+        append_batches(&segment);
+
+        let base_offsets = |offset, max_bytes, min_one_batch| {
+            segment
+                .read(offset, max_bytes, min_one_batch)
+                .unwrap()
+                .map(|bytes| {
+                    RecordBatch::from_bytes(&bytes)
+                        .unwrap()
+                        .iter()
+                        .map(|record_batch| record_batch.base_offset)
+                        .collect::<Vec<i64>>()
+                })
+        };
+        let batch_size = segment.record_batches().unwrap()[0].size();
+
+        assert_eq!(Some((0..10).collect()), base_offsets(0, usize::MAX, true));
+        assert_eq!(Some(vec![5, 6, 7, 8, 9]), base_offsets(5, usize::MAX, true));
+        assert_eq!(Some(vec![9]), base_offsets(9, usize::MAX, true));
+        assert_eq!(None, base_offsets(10, usize::MAX, true));
+        // Only whole batches within the max bytes, unless the first is read whatever its size
+        assert_eq!(Some(vec![5, 6]), base_offsets(5, 3 * batch_size - 1, false));
+        assert_eq!(Some(vec![]), base_offsets(5, batch_size - 1, false));
+        assert_eq!(Some(vec![5]), base_offsets(5, batch_size - 1, true));
+        assert_eq!(
+            Some(6),
+            segment
+                .find_offset_by_timestamp(5500)
+                .unwrap()
+                .map(|timestamp_and_offset| timestamp_and_offset.offset)
+        );
+    }
+
+    #[test]
+    fn rebuilds_missing_or_corrupt_indexes_on_load() {
+        let (_directory, segment) = temp_segment();
+        append_batches(&segment);
+        let offset_entries = segment.offset_index.entries().unwrap();
+        let time_entries = segment.time_index.entries().unwrap();
+
+        fs::remove_file(segment.offset_index.path()).unwrap();
+        fs::write(segment.time_index.path(), [0xff; 30]).unwrap();
+        segment.load().unwrap();
+
+        assert_eq!(offset_entries, segment.offset_index.entries().unwrap());
+        assert_eq!(time_entries, segment.time_index.entries().unwrap());
+    }
+
+    #[test]
+    fn recovery_truncates_at_batch_with_bad_crc() {
+        let (_directory, segment) = temp_segment();
+        append_batches(&segment);
+        let batch_size = record_batch(0, 0, &[0], 1024).size();
+        let mut bytes = fs::read(segment.log_path()).unwrap();
+        // A byte of the value of batch 7, so the batch still decodes
+        bytes[8 * batch_size - 10] ^= 0x01;
+        fs::write(segment.log_path(), bytes).unwrap();
+
+        assert!(segment.recover().unwrap());
+
+        assert_eq!(7 * batch_size as u64, segment.size().unwrap());
+        assert_eq!(Some(7), segment.next_offset().unwrap());
+        assert_eq!(
+            vec![OffsetPosition {
+                offset: 4,
+                position: 4 * batch_size as u32
+            }],
+            segment.offset_index.entries().unwrap()
+        );
+        assert!(!segment.recover().unwrap());
+    }
+
+    #[test]
+    fn recovery_truncates_partial_batch() {
+        let (_directory, segment) = temp_segment();
+        append_batches(&segment);
+        let size = segment.size().unwrap();
+        let partial = record_batch(10, 10_000, &[0], 1024).to_be_bytes();
+        OpenOptions::new()
+            .append(true)
+            .open(segment.log_path())
+            .unwrap()
+            .write_all(&partial[..100])
+            .unwrap();
+
+        assert!(segment.recover().unwrap());
+
+        assert_eq!(size, segment.size().unwrap());
+        assert_eq!(Some(10), segment.next_offset().unwrap());
+    }
+}
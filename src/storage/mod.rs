@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+
+pub mod index;
+pub mod log;
+pub mod log_cleaner;
+pub mod log_config;
+pub mod log_manager;
+pub mod log_segment;
+pub mod offset_checkpoint;
+
+/// Directory of a partition's log: the log directory already holding it, or else the first one
+pub fn partition_directory(
+    log_dirs: &[PathBuf],
+    topic_name: &str,
+    partition_index: i32,
+) -> PathBuf {
+    let name = format!("{topic_name}-{partition_index}");
+    log_dirs
+        .iter()
+        .map(|log_dir| log_dir.join(&name))
+        .find(|directory| directory.is_dir())
+        .unwrap_or_else(|| {
+            log_dirs
+                .first()
+                .map_or_else(|| PathBuf::from(&name), |log_dir| log_dir.join(&name))
+        })
+}
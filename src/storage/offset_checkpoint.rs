@@ -0,0 +1,132 @@
+//! Offset checkpoint files of a log directory, in the format of Kafka's
+//! `recovery-point-offset-checkpoint`: a version line, a line with the number of entries, then
+//! a `<topic> <partition> <offset>` line for each partition.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, ErrorKind, Write};
+use std::path::PathBuf;
+
+const VERSION: i32 = 0;
+
+#[derive(Debug, Clone)]
+pub struct OffsetCheckpointFile {
+    path: PathBuf,
+}
+
+impl OffsetCheckpointFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Reads the offset of each topic partition. A missing file has none.
+    pub fn read(&self) -> io::Result<HashMap<(String, i32), i64>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(e) => return Err(e),
+        };
+        let mut lines = contents.lines();
+        let version = lines
+            .next()
+            .and_then(|line| line.trim().parse::<i32>().ok());
+        if version != Some(VERSION) {
+            return Err(self.malformed("unsupported version"));
+        }
+        let count = lines
+            .next()
+            .and_then(|line| line.trim().parse::<usize>().ok())
+            .ok_or_else(|| self.malformed("missing entry count"))?;
+        let mut offsets = HashMap::new();
+        for line in lines.by_ref().take(count) {
+            let entry = match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [topic, partition, offset] => partition
+                    .parse()
+                    .ok()
+                    .zip(offset.parse().ok())
+                    .map(|(partition, offset)| ((topic.to_string(), partition), offset)),
+                _ => None,
+            };
+            let (topic_partition, offset) =
+                entry.ok_or_else(|| self.malformed(&format!("malformed line {line:?}")))?;
+            offsets.insert(topic_partition, offset);
+        }
+        if offsets.len() != count {
+            return Err(self.malformed(&format!(
+                "expected {count} entries but found {}",
+                offsets.len()
+            )));
+        }
+        Ok(offsets)
+    }
+
+    /// Replaces the checkpoint with the offsets, writing them to a temporary file first and
+    /// renaming it over the checkpoint so a crash leaves either the old or the new one
+    pub fn write(&self, offsets: &[(String, i32, i64)]) -> io::Result<()> {
+        let mut contents = format!("{VERSION}\n{}\n", offsets.len());
+        for (topic, partition, offset) in offsets {
+            contents.push_str(&format!("{topic} {partition} {offset}\n"));
+        }
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(".tmp");
+        let mut file = File::create(&temp_path)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temp_path, &self.path)
+    }
+
+    fn malformed(&self, reason: &str) -> io::Error {
+        io::Error::new(
+            ErrorKind::InvalidData,
+            format!("malformed checkpoint {}: {reason}", self.path.display()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_fixtures::TempDir;
+
+    fn temp_checkpoint() -> (TempDir, OffsetCheckpointFile) {
+        let directory = TempDir::new("checkpoint");
+        let checkpoint =
+            OffsetCheckpointFile::new(directory.path().join("recovery-point-offset-checkpoint"));
+        (directory, checkpoint)
+    }
+
+    #[test]
+    fn writes_offsets_in_kafka_format() {
+        let (_directory, checkpoint) = temp_checkpoint();
+
+        checkpoint
+            .write(&[("mango".to_string(), 0, 42), ("my-topic".to_string(), 3, 7)])
+            .unwrap();
+
+        assert_eq!(
+            "0\n2\nmango 0 42\nmy-topic 3 7\n",
+            fs::read_to_string(&checkpoint.path).unwrap()
+        );
+        assert_eq!(
+            HashMap::from([
+                (("mango".to_string(), 0), 42),
+                (("my-topic".to_string(), 3), 7)
+            ]),
+            checkpoint.read().unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_checkpoint() {
+        let (_directory, checkpoint) = temp_checkpoint();
+        assert!(checkpoint.read().unwrap().is_empty());
+
+        for contents in ["1\n0\n", "0\n2\nmango 0 42\n", "0\n1\nmango zero 42\n"] {
+            fs::write(&checkpoint.path, contents).unwrap();
+            assert_eq!(
+                ErrorKind::InvalidData,
+                checkpoint.read().unwrap_err().kind()
+            );
+        }
+    }
+}
@@ -1,53 +1,198 @@
 use crate::{
-    byte_parsable::ByteParsable, serializable::Serializable, size::Size,
+    byte_parsable::ByteParsable,
+    decode_error::{take_bytes, DecodeError, DecodeErrorReason},
+    serializable::Serializable,
+    size::Size,
     types::unsigned_varint::UnsignedVarint,
 };
 
+// https://cwiki.apache.org/confluence/display/KAFKA/KIP-482%3A+The+Kafka+Protocol+should+Support+Optional+Tagged+Fields
+
 pub const EMPTY: usize = 1;
 
-#[derive(Debug, Clone)]
+/// The tagged fields at the end of a flexible version of a message or struct.
+/// The section starts with the number of fields as an UNSIGNED_VARINT. Each field follows as its
+/// tag and the size of its data, both UNSIGNED_VARINTs, and then the data itself.
+/// Fields are kept in ascending tag order, which is the order they must be serialized in.
+/// Fields with unknown tags are kept as is, so that they round-trip.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct TaggedFieldsSection {
-    number_of_tagged_fields: UnsignedVarint,
+    fields: Vec<TaggedField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaggedField {
+    pub tag: u32,
+    pub data: Vec<u8>,
 }
 
 impl TaggedFieldsSection {
     pub fn empty() -> Self {
-        Self {
-            number_of_tagged_fields: UnsignedVarint::new(0),
-            // TODO: add field for tagged fields
+        Self { fields: Vec::new() }
+    }
+
+    /// Decodes the data of the field with the given tag, if the section has one. The broker
+    /// reads none of the tagged fields it receives, so only tests check the fields it sends.
+    #[cfg(test)]
+    pub fn get<T: ByteParsable<T>>(&self, tag: u32) -> Result<Option<T>, DecodeError> {
+        self.fields
+            .iter()
+            .find(|field| field.tag == tag)
+            .map(|field| T::parse(&field.data, 0))
+            .transpose()
+    }
+
+    /// Sets the field with the given tag to the serialized value, replacing any previous value
+    pub fn set<T: Serializable>(&mut self, tag: u32, value: &T) {
+        let data = value.to_be_bytes();
+        match self.fields.binary_search_by_key(&tag, |field| field.tag) {
+            Ok(index) => self.fields[index].data = data,
+            Err(index) => self.fields.insert(index, TaggedField { tag, data }),
         }
     }
 }
 
-impl PartialEq for TaggedFieldsSection {
-    fn eq(&self, other: &Self) -> bool {
-        self.number_of_tagged_fields.value == other.number_of_tagged_fields.value
-        // TODO: compare tagged fields
+impl TaggedField {
+    fn header(&self) -> (UnsignedVarint, UnsignedVarint) {
+        (
+            UnsignedVarint::new(self.tag),
+            UnsignedVarint::new(self.data.len() as u32),
+        )
     }
 }
 
 impl Size for TaggedFieldsSection {
     fn size(&self) -> usize {
-        self.number_of_tagged_fields.size()
-        // TODO: add sizes for tagged fields also
+        UnsignedVarint::new(self.fields.len() as u32).size()
+            + self
+                .fields
+                .iter()
+                .map(|field| {
+                    let (tag, size) = field.header();
+                    tag.size() + size.size() + field.data.len()
+                })
+                .sum::<usize>()
     }
 }
 
 impl Serializable for TaggedFieldsSection {
     fn to_be_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        bytes.extend_from_slice(&self.number_of_tagged_fields.to_be_bytes());
-        // TODO: serialize tagged fields also
+        let mut bytes = Vec::with_capacity(self.size());
+        bytes.extend_from_slice(&UnsignedVarint::new(self.fields.len() as u32).to_be_bytes());
+        for field in &self.fields {
+            let (tag, size) = field.header();
+            bytes.extend_from_slice(&tag.to_be_bytes());
+            bytes.extend_from_slice(&size.to_be_bytes());
+            bytes.extend_from_slice(&field.data);
+        }
         bytes
     }
 }
 
 impl ByteParsable<TaggedFieldsSection> for TaggedFieldsSection {
-    fn parse(bytes: &[u8], offset: usize) -> TaggedFieldsSection {
-        let number_of_tagged_fields = UnsignedVarint::parse(bytes, offset);
-        // TODO: parse tagged fields also
-        TaggedFieldsSection {
-            number_of_tagged_fields,
+    fn parse(bytes: &[u8], offset: usize) -> Result<TaggedFieldsSection, DecodeError> {
+        let mut offset = offset;
+        let number_of_tagged_fields = UnsignedVarint::parse(bytes, offset)?;
+        offset += number_of_tagged_fields.size();
+        let mut fields: Vec<TaggedField> = Vec::new();
+        for _ in 0..number_of_tagged_fields.value {
+            let tag = UnsignedVarint::parse(bytes, offset)?;
+            if fields.last().is_some_and(|last| last.tag >= tag.value) {
+                return Err(DecodeError::new::<TaggedField>(
+                    offset,
+                    DecodeErrorReason::UnorderedTag(tag.value),
+                ));
+            }
+            offset += tag.size();
+            let size = UnsignedVarint::parse(bytes, offset)?;
+            offset += size.size();
+            let data = take_bytes::<TaggedField>(bytes, offset, size.value as usize)?.to_vec();
+            offset += data.len();
+            fields.push(TaggedField {
+                tag: tag.value,
+                data,
+            });
         }
+        Ok(TaggedFieldsSection { fields })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trips_unknown_tags() {
+        let bytes = [
+            0x02, // number of tagged fields
+            0x00, 0x02, 0x00, 0x07, // tag 0, 2 bytes
+            0x05, 0x01, 0x01, // tag 5, 1 byte
+            0xff, // trailing byte of the enclosing message
+        ];
+
+        let section = TaggedFieldsSection::parse(&bytes, 0).unwrap();
+
+        assert_eq!(
+            &[
+                TaggedField {
+                    tag: 0,
+                    data: vec![0x00, 0x07]
+                },
+                TaggedField {
+                    tag: 5,
+                    data: vec![0x01]
+                },
+            ],
+            section.fields.as_slice()
+        );
+        assert_eq!(bytes.len() - 1, section.size());
+        assert_eq!(bytes[..bytes.len() - 1].to_vec(), section.to_be_bytes());
+    }
+
+    #[test]
+    fn sets_typed_fields_in_tag_order() {
+        let mut section = TaggedFieldsSection::empty();
+        section.set(3, &true);
+        section.set(1, &-1i64);
+        section.set(3, &false);
+
+        assert_eq!(
+            vec![1, 3],
+            section.fields.iter().map(|f| f.tag).collect::<Vec<_>>()
+        );
+        assert_eq!(Ok(Some(-1i64)), section.get::<i64>(1));
+        assert_eq!(Ok(Some(false)), section.get::<bool>(3));
+        assert_eq!(Ok(None), section.get::<bool>(2));
+        assert_eq!(
+            vec![
+                0x02, 0x01, 0x08, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x03, 0x01, 0x00
+            ],
+            section.to_be_bytes()
+        );
+    }
+
+    #[test]
+    fn fails_on_unordered_tags() {
+        let bytes = [0x02, 0x05, 0x00, 0x01, 0x00];
+
+        let error = TaggedFieldsSection::parse(&bytes, 0).unwrap_err();
+
+        assert_eq!(3, error.offset);
+        assert_eq!(DecodeErrorReason::UnorderedTag(1), error.reason);
+    }
+
+    #[test]
+    fn fails_on_truncated_field_data() {
+        let bytes = [0x01, 0x00, 0x04, 0x00];
+
+        let error = TaggedFieldsSection::parse(&bytes, 0).unwrap_err();
+
+        assert_eq!(
+            DecodeErrorReason::UnexpectedEof {
+                needed: 4,
+                available: 1
+            },
+            error.reason
+        );
     }
 }
@@ -0,0 +1,96 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use uuid::Uuid;
+
+use crate::metadata_image::MetadataImage;
+use crate::records::partition_record::PartitionRecord;
+use crate::records::record_batch::RecordValue;
+use crate::records::topic_record::TopicRecord;
+use crate::types::compact_array::CompactArray;
+use crate::types::{compact_string::CompactString, unsigned_varint::UnsignedVarint};
+
+// FeatureLevelRecord batch (1 record) taken from a __cluster_metadata log
+pub const RECORD_BATCH: [u8; 91] = [
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x4f, 0x00, 0x00, 0x00, 0x01,
+    0x02, 0xb0, 0x69, 0x45, 0x7c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x91, 0xe0,
+    0x5a, 0xf8, 0x18, 0x00, 0x00, 0x01, 0x91, 0xe0, 0x5a, 0xf8, 0x18, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x01, 0x3a, 0x00, 0x00,
+    0x00, 0x01, 0x2e, 0x01, 0x0c, 0x00, 0x11, 0x6d, 0x65, 0x74, 0x61, 0x64, 0x61, 0x74, 0x61, 0x2e,
+    0x76, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x00, 0x14, 0x00, 0x00,
+];
+
+/// Directory created under the system temp directory, removed with everything below it when
+/// dropped, so also when the test panics
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new(prefix: &str) -> TempDir {
+        let path = env::temp_dir().join(format!("{prefix}-{}", Uuid::new_v4()));
+        fs::create_dir_all(&path).unwrap();
+        TempDir { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+pub fn topic_record(topic_name: &str, topic_id: Uuid) -> TopicRecord {
+    TopicRecord {
+        frame_version: 1,
+        _type: 2,
+        version: 0,
+        topic_name: CompactString::from(topic_name),
+        topic_uuid: topic_id,
+        tagged_fields_count: UnsignedVarint::new(0),
+    }
+}
+
+pub fn partition_record(topic_id: Uuid, partition_id: i32, leader_epoch: i32) -> PartitionRecord {
+    PartitionRecord {
+        frame_version: 1,
+        _type: 3,
+        version: 1,
+        partition_id,
+        topic_uuid: topic_id,
+        replica_array: vec![1].into(),
+        in_sync_replica_array: vec![1].into(),
+        removing_replica_array: CompactArray::empty(),
+        adding_replica_array: CompactArray::empty(),
+        leader: 1,
+        leader_epoch,
+        partition_epoch: 0,
+        directories_array: CompactArray::empty(),
+        tagged_fields_count: UnsignedVarint::new(0),
+    }
+}
+
+/// Image holding the topic with partitions 0 to `partitions_count - 1`, each led by broker 1 in
+/// the leader epoch
+pub fn metadata_image(
+    topic_name: &str,
+    topic_id: Uuid,
+    partitions_count: i32,
+    leader_epoch: i32,
+) -> MetadataImage {
+    let mut metadata_image = MetadataImage::default();
+    metadata_image.apply(RecordValue::Topic(topic_record(topic_name, topic_id)));
+    for partition_id in 0..partitions_count {
+        metadata_image.apply(RecordValue::Partition(partition_record(
+            topic_id,
+            partition_id,
+            leader_epoch,
+        )));
+    }
+    metadata_image
+}
@@ -0,0 +1,141 @@
+use crate::byte_parsable::ByteParsable;
+use crate::decode_error::{DecodeError, DecodeErrorReason};
+use crate::serializable::Serializable;
+use crate::size::Size;
+use std::iter;
+use std::slice::Iter;
+
+// https://kafka.apache.org/27/protocol.html#protocol_types
+
+/// Represents a sequence of objects of a given type T, as used by the non-flexible versions of
+/// the messages. First the length N is given as an INT32, then N instances of type T follow.
+/// A null array is represented with a length of -1.
+#[derive(Debug, Clone)]
+pub struct Array<T: Serializable + Size + ByteParsable<T> + Clone> {
+    elements: Option<Vec<T>>,
+}
+
+#[allow(dead_code)]
+impl<T: Serializable + Size + ByteParsable<T> + Clone> Array<T> {
+    pub fn new(elements: Vec<T>) -> Self {
+        Self {
+            elements: Some(elements),
+        }
+    }
+
+    pub fn empty() -> Self {
+        Self::new(Vec::new())
+    }
+
+    pub fn null() -> Self {
+        Self { elements: None }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.elements.is_none()
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.as_ref().map(|v| v.len()).unwrap_or(0)
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.elements.as_deref().unwrap_or(&[]).iter()
+    }
+
+    pub fn into_iter(self) -> std::vec::IntoIter<T> {
+        self.elements
+            .map(|v| v.into_iter())
+            .unwrap_or_else(|| vec![].into_iter())
+    }
+}
+
+/// An empty array, the default of the array fields of a message's versions that don't have them
+impl<T: Serializable + Size + ByteParsable<T> + Clone> Default for Array<T> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<T: Serializable + Size + ByteParsable<T> + Clone> Size for Array<T> {
+    fn size(&self) -> usize {
+        size_of::<i32>() + self.elements.size()
+    }
+}
+
+impl<T: Serializable + Size + ByteParsable<T> + Clone> ByteParsable<Array<T>> for Array<T> {
+    fn parse(bytes: &[u8], offset: usize) -> Result<Array<T>, DecodeError> {
+        let mut offset = offset;
+        let length = i32::parse(bytes, offset)?;
+        let elements = match length {
+            -1 => None,
+            0.. => {
+                offset += length.size();
+                let mut elements = Vec::new();
+                for _ in 0..length {
+                    let element = T::parse(bytes, offset)?;
+                    offset += element.size();
+                    elements.push(element);
+                }
+                Some(elements)
+            }
+            _ => {
+                return Err(DecodeError::new::<Self>(
+                    offset,
+                    DecodeErrorReason::InvalidLength(length.into()),
+                ))
+            }
+        };
+        Ok(Array { elements })
+    }
+}
+
+impl<T: Serializable + Size + ByteParsable<T> + Clone> Serializable for Array<T> {
+    fn to_be_bytes(&self) -> Vec<u8> {
+        let length = self.elements.as_ref().map_or(-1, |v| v.len() as i32);
+        iter::once(length.to_be_bytes().to_vec())
+            .chain(self.iter().map(|x| x.to_be_bytes()))
+            .flatten()
+            .collect()
+    }
+}
+
+impl<T: Serializable + Size + ByteParsable<T> + Clone> std::ops::Index<usize> for Array<T> {
+    type Output = T;
+
+    fn index(&self, offset: usize) -> &Self::Output {
+        &self.elements.as_ref().expect("elements is None")[offset]
+    }
+}
+
+impl<T: Serializable + Size + ByteParsable<T> + Clone> From<Vec<T>> for Array<T> {
+    fn from(val: Vec<T>) -> Self {
+        Array::new(val)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trips_array_with_int32_length() {
+        let bytes = [0x00, 0x00, 0x00, 0x02, 0x00, 0x01, 0x00, 0x02];
+
+        let array = Array::<i16>::parse(&bytes, 0).unwrap();
+
+        assert_eq!(vec![1, 2], array.iter().copied().collect::<Vec<_>>());
+        assert_eq!(bytes.len(), array.size());
+        assert_eq!(bytes.to_vec(), array.to_be_bytes());
+    }
+
+    #[test]
+    fn round_trips_null_array() {
+        let bytes = [0xff, 0xff, 0xff, 0xff];
+
+        let array = Array::<i16>::parse(&bytes, 0).unwrap();
+
+        assert!(array.is_null());
+        assert_eq!(bytes.to_vec(), array.to_be_bytes());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::byte_parsable::ByteParsable;
+use crate::decode_error::DecodeError;
 use crate::serializable::Serializable;
 use crate::size::Size;
 use crate::types::unsigned_varint::UnsignedVarint;
@@ -34,6 +35,10 @@ impl<T: Serializable + Size + ByteParsable<T> + Clone> CompactArray<T> {
         }
     }
 
+    pub fn is_null(&self) -> bool {
+        self.elements.is_none()
+    }
+
     pub fn len(&self) -> usize {
         self.elements.as_ref().map(|v| v.len()).unwrap_or(0)
     }
@@ -67,9 +72,9 @@ impl<T: Serializable + Size + ByteParsable<T> + Clone> Size for CompactArray<T>
 impl<T: Serializable + Size + ByteParsable<T> + Clone> ByteParsable<CompactArray<T>>
     for CompactArray<T>
 {
-    fn parse(bytes: &[u8], offset: usize) -> CompactArray<T> {
+    fn parse(bytes: &[u8], offset: usize) -> Result<CompactArray<T>, DecodeError> {
         let mut offset = offset;
-        let length = UnsignedVarint::parse(bytes, offset);
+        let length = UnsignedVarint::parse(bytes, offset)?;
         offset += length.size();
         let elements = match length.value {
             0 => None,
@@ -77,14 +82,14 @@ impl<T: Serializable + Size + ByteParsable<T> + Clone> ByteParsable<CompactArray
             _ => {
                 let mut elements = Vec::new();
                 for _ in 0..(length.value - 1) {
-                    let element = T::parse(bytes, offset);
+                    let element = T::parse(bytes, offset)?;
                     offset += element.size();
                     elements.push(element);
                 }
                 Some(elements)
             }
         };
-        CompactArray { length, elements }
+        Ok(CompactArray { length, elements })
     }
 }
 
@@ -1,5 +1,6 @@
 use crate::{
     byte_parsable::ByteParsable,
+    decode_error::DecodeError,
     serializable::Serializable,
     size::Size,
     types::{compact_nullable::CompactNullable, unsigned_varint::UnsignedVarint},
@@ -29,9 +30,9 @@ impl Size for CompactNullableBytes {
 impl CompactNullable<CompactNullableBytes> for CompactNullableBytes {}
 
 impl ByteParsable<CompactNullableBytes> for CompactNullableBytes {
-    fn parse(bytes: &[u8], offset: usize) -> Self {
-        let (length, bytes) = Self::parse_length_and_bytes(bytes, offset);
-        Self { length, bytes }
+    fn parse(bytes: &[u8], offset: usize) -> Result<Self, DecodeError> {
+        let (length, bytes) = Self::parse_length_and_bytes(bytes, offset)?;
+        Ok(Self { length, bytes })
     }
 }
 
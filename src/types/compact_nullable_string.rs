@@ -1,6 +1,7 @@
 use std::fmt::{Debug, Display};
 
 use crate::byte_parsable::ByteParsable;
+use crate::decode_error::DecodeError;
 use crate::serializable::Serializable;
 use crate::size::Size;
 use crate::types::compact_nullable::CompactNullable;
@@ -32,9 +33,9 @@ impl Size for CompactNullableString {
 impl CompactNullable<CompactNullableString> for CompactNullableString {}
 
 impl ByteParsable<CompactNullableString> for CompactNullableString {
-    fn parse(bytes: &[u8], offset: usize) -> Self {
-        let (length, bytes) = Self::parse_length_and_bytes(bytes, offset);
-        Self { length, bytes }
+    fn parse(bytes: &[u8], offset: usize) -> Result<Self, DecodeError> {
+        let (length, bytes) = Self::parse_length_and_bytes(bytes, offset)?;
+        Ok(Self { length, bytes })
     }
 }
 
@@ -1,12 +1,9 @@
-use crate::byte_parsable::ByteParsable;
-use crate::records::record_batch::RecordBatch;
-use crate::serializable::Serializable;
-use crate::size::Size;
-use crate::tagged_fields_section::TaggedFieldsSection;
+use kafka_derive::{ByteParsable, Serializable, Size};
+
 use crate::types::compact_nullable_bytes::CompactNullableBytes;
 use crate::types::unsigned_varint::UnsignedVarint;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, ByteParsable, Serializable, Size)]
 pub struct CompactRecords {
     records: CompactNullableBytes,
 }
@@ -18,23 +15,15 @@ impl CompactRecords {
         }
     }
 
-    pub fn from_record_batches(record_batches: &[RecordBatch]) -> Self {
-        let record_batches_bytes = record_batches
-            .iter()
-            .flat_map(|record_batch| record_batch._parsed_bytes.clone())
-            .collect();
-        let unsigned_varint_value = record_batches
-            .iter()
-            .map(|record_batch| record_batch.expected_length() as u32)
-            .sum::<u32>()
-            + 1;
-        let length = UnsignedVarint::new(unsigned_varint_value);
-        let bytes = if record_batches.is_empty() {
-            None
-        } else {
-            Some(record_batches_bytes)
-        };
-        Self::new(length, bytes)
+    /// Records of the bytes of record batches, as they are stored
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        let length = UnsignedVarint::new(bytes.len() as u32 + 1);
+        Self::new(length, (!bytes.is_empty()).then_some(bytes))
+    }
+
+    /// The bytes of the records, `None` if they are null
+    pub fn bytes(&self) -> Option<&[u8]> {
+        self.records.bytes.as_deref()
     }
 
     fn new(length: UnsignedVarint, bytes: Option<Vec<u8>>) -> Self {
@@ -43,22 +32,3 @@ impl CompactRecords {
         }
     }
 }
-
-impl Size for CompactRecords {
-    fn size(&self) -> usize {
-        self.records.size()
-    }
-}
-
-impl ByteParsable<CompactRecords> for CompactRecords {
-    fn parse(bytes: &[u8], offset: usize) -> Self {
-        let records = CompactNullableBytes::parse(bytes, offset);
-        Self { records }
-    }
-}
-
-impl Serializable for CompactRecords {
-    fn to_be_bytes(&self) -> Vec<u8> {
-        self.records.to_be_bytes()
-    }
-}
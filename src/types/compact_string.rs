@@ -1,6 +1,7 @@
 use std::fmt::{Debug, Display};
 
 use crate::byte_parsable::ByteParsable;
+use crate::decode_error::{take_bytes, DecodeError, DecodeErrorReason};
 use crate::serializable::Serializable;
 use crate::size::Size;
 use crate::types::compact_nullable_string::CompactNullableString;
@@ -15,7 +16,6 @@ pub struct CompactString {
 }
 
 impl CompactString {
-    #[allow(dead_code)]
     pub fn to_compact_nullable_string(&self) -> CompactNullableString {
         CompactNullableString {
             length: self.length.clone(),
@@ -31,6 +31,22 @@ impl CompactString {
     }
 }
 
+impl From<&str> for CompactString {
+    fn from(s: &str) -> Self {
+        let bytes = s.as_bytes().to_vec();
+        Self {
+            length: UnsignedVarint::new(bytes.len() as u32 + 1),
+            bytes,
+        }
+    }
+}
+
+impl Default for CompactString {
+    fn default() -> Self {
+        Self::from("")
+    }
+}
+
 impl Size for CompactString {
     fn size(&self) -> usize {
         self.length.size() + self.bytes.size()
@@ -38,15 +54,21 @@ impl Size for CompactString {
 }
 
 impl ByteParsable<CompactString> for CompactString {
-    fn parse(bytes: &[u8], offset: usize) -> CompactString {
+    fn parse(bytes: &[u8], offset: usize) -> Result<CompactString, DecodeError> {
         let mut offset = offset;
-        let length = UnsignedVarint::parse(bytes, offset);
+        let length = UnsignedVarint::parse(bytes, offset)?;
         offset += length.size();
         let bytes = match length.value {
+            0 => {
+                return Err(DecodeError::new::<Self>(
+                    offset,
+                    DecodeErrorReason::InvalidLength(-1),
+                ))
+            }
             1 => Vec::new(),
-            _ => bytes[offset..offset + (length.value - 1) as usize].into(),
+            _ => take_bytes::<Self>(bytes, offset, (length.value - 1) as usize)?.into(),
         };
-        Self { length, bytes }
+        Ok(Self { length, bytes })
     }
 }
 
@@ -1,10 +1,13 @@
+pub mod array;
 pub mod compact_array;
 pub mod compact_nullable;
 pub mod compact_nullable_bytes;
 pub mod compact_nullable_string;
 pub mod compact_records;
 pub mod compact_string;
+pub mod nullable_bytes;
 pub mod nullable_string;
+pub mod nullable_struct;
 pub mod signed_varint;
 pub mod unsigned_varint;
 mod variable_integer;
@@ -0,0 +1,59 @@
+use crate::byte_parsable::ByteParsable;
+use crate::decode_error::{take_bytes, DecodeError, DecodeErrorReason};
+use crate::serializable::Serializable;
+use crate::size::Size;
+
+// https://kafka.apache.org/27/protocol.html#protocol_types
+
+/// Represents a raw sequence of bytes or null.
+/// For non-null values, first the length N is given as an INT32. Then N bytes follow.
+/// A null value is encoded with length of -1 and there are no following bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NullableBytes {
+    pub bytes: Option<Vec<u8>>,
+}
+
+#[allow(dead_code)]
+impl NullableBytes {
+    pub fn null() -> Self {
+        Self { bytes: None }
+    }
+}
+
+impl Size for NullableBytes {
+    fn size(&self) -> usize {
+        size_of::<i32>() + self.bytes.size()
+    }
+}
+
+impl ByteParsable<NullableBytes> for NullableBytes {
+    fn parse(bytes: &[u8], offset: usize) -> Result<Self, DecodeError> {
+        let length = i32::parse(bytes, offset)?;
+        let bytes = match length {
+            -1 => None,
+            0.. => {
+                Some(take_bytes::<Self>(bytes, offset + length.size(), length as usize)?.to_vec())
+            }
+            _ => {
+                return Err(DecodeError::new::<Self>(
+                    offset,
+                    DecodeErrorReason::InvalidLength(length.into()),
+                ))
+            }
+        };
+        Ok(Self { bytes })
+    }
+}
+
+impl Serializable for NullableBytes {
+    fn to_be_bytes(&self) -> Vec<u8> {
+        match &self.bytes {
+            Some(bytes) => {
+                let mut serialized = (bytes.len() as i32).to_be_bytes().to_vec();
+                serialized.extend_from_slice(bytes);
+                serialized
+            }
+            None => (-1i32).to_be_bytes().to_vec(),
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::byte_parsable::ByteParsable;
+use crate::decode_error::{take_bytes, DecodeError, DecodeErrorReason};
 use crate::serializable::Serializable;
 use crate::size::Size;
 
@@ -11,7 +12,7 @@ pub const LENGTH: usize = 2;
 /// Then N bytes follow which are the UTF-8 encoding of the character sequence.
 /// A null value is encoded with length of -1 and there are no following bytes.
 #[allow(dead_code)]
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct NullableString {
     bytes: Option<Vec<u8>>,
 }
@@ -27,6 +28,11 @@ impl NullableString {
             bytes: Some(string.as_bytes().to_vec()),
         }
     }
+
+    /// The UTF-8 bytes of the string, or `None` if it's null
+    pub fn bytes(&self) -> Option<&[u8]> {
+        self.bytes.as_deref()
+    }
 }
 
 impl Size for NullableString {
@@ -36,15 +42,35 @@ impl Size for NullableString {
 }
 
 impl ByteParsable<NullableString> for NullableString {
-    fn parse(bytes: &[u8], offset: usize) -> Self {
+    fn parse(bytes: &[u8], offset: usize) -> Result<Self, DecodeError> {
         let mut offset = offset;
-        let length = i16::parse(bytes, offset);
-        let bytes = if length == -1 {
-            None
-        } else {
-            offset += length.size();
-            Some(bytes[offset..offset + length as usize].to_vec())
+        let length = i16::parse(bytes, offset)?;
+        let bytes = match length {
+            -1 => None,
+            0.. => {
+                offset += length.size();
+                Some(take_bytes::<Self>(bytes, offset, length as usize)?.to_vec())
+            }
+            _ => {
+                return Err(DecodeError::new::<Self>(
+                    offset,
+                    DecodeErrorReason::InvalidLength(length.into()),
+                ))
+            }
         };
-        Self { bytes }
+        Ok(Self { bytes })
+    }
+}
+
+impl Serializable for NullableString {
+    fn to_be_bytes(&self) -> Vec<u8> {
+        match &self.bytes {
+            Some(bytes) => {
+                let mut serialized = (bytes.len() as i16).to_be_bytes().to_vec();
+                serialized.extend_from_slice(bytes);
+                serialized
+            }
+            None => (-1i16).to_be_bytes().to_vec(),
+        }
     }
 }
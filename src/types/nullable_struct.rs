@@ -0,0 +1,55 @@
+use crate::byte_parsable::ByteParsable;
+use crate::decode_error::DecodeError;
+use crate::serializable::Serializable;
+use crate::size::Size;
+
+/// A struct field which can be null, such as the cursor of DescribeTopicPartitions.
+/// It's preceded by an INT8 marker, which is -1 for null and 1 when the struct follows.
+#[derive(Debug, Clone)]
+pub struct NullableStruct<T: Serializable + Size + ByteParsable<T> + Clone> {
+    pub value: Option<T>,
+}
+
+#[allow(dead_code)]
+impl<T: Serializable + Size + ByteParsable<T> + Clone> NullableStruct<T> {
+    pub fn null() -> Self {
+        Self { value: None }
+    }
+
+    pub fn new(value: T) -> Self {
+        Self { value: Some(value) }
+    }
+}
+
+impl<T: Serializable + Size + ByteParsable<T> + Clone> Size for NullableStruct<T> {
+    fn size(&self) -> usize {
+        size_of::<i8>() + self.value.as_ref().map_or(0, |value| value.size())
+    }
+}
+
+impl<T: Serializable + Size + ByteParsable<T> + Clone> ByteParsable<NullableStruct<T>>
+    for NullableStruct<T>
+{
+    fn parse(bytes: &[u8], offset: usize) -> Result<Self, DecodeError> {
+        let marker = i8::parse(bytes, offset)?;
+        let value = if marker < 0 {
+            None
+        } else {
+            Some(T::parse(bytes, offset + marker.size())?)
+        };
+        Ok(Self { value })
+    }
+}
+
+impl<T: Serializable + Size + ByteParsable<T> + Clone> Serializable for NullableStruct<T> {
+    fn to_be_bytes(&self) -> Vec<u8> {
+        match &self.value {
+            Some(value) => {
+                let mut bytes = vec![1];
+                bytes.extend_from_slice(&value.to_be_bytes());
+                bytes
+            }
+            None => vec![0xff],
+        }
+    }
+}
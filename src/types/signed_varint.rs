@@ -1,15 +1,31 @@
 use crate::{
     byte_parsable::ByteParsable,
+    decode_error::{DecodeError, DecodeErrorReason},
+    serializable::Serializable,
     size::Size,
-    types::{variable_integer::parse, zig_zag_decoder::ZigZagDecoder},
+    types::{
+        variable_integer::{parse, serialize, size_of},
+        zig_zag_decoder::ZigZagDecoder,
+    },
 };
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct SignedVarint {
     pub value: i32,
     pub byte_count: usize,
 }
 
+impl SignedVarint {
+    pub fn new(value: i32) -> Self {
+        let byte_count = size_of(Self::zig_zag_encode(value)) as usize;
+        Self { value, byte_count }
+    }
+
+    fn zig_zag_encode(value: i32) -> u64 {
+        ((value << 1) ^ (value >> 31)) as u32 as u64
+    }
+}
+
 impl ZigZagDecoder for SignedVarint {
     type Int = i32;
 }
@@ -21,10 +37,18 @@ impl Size for SignedVarint {
 }
 
 impl ByteParsable<SignedVarint> for SignedVarint {
-    fn parse(bytes: &[u8], offset: usize) -> Self {
-        let (value, byte_count) = parse(bytes, offset);
-        let value = SignedVarint::zig_zag_decode(value);
-        Self { value, byte_count }
+    fn parse(bytes: &[u8], offset: usize) -> Result<Self, DecodeError> {
+        let (encoded_value, byte_count) = parse::<Self>(bytes, offset)?;
+        let value = SignedVarint::zig_zag_decode(encoded_value).ok_or_else(|| {
+            DecodeError::new::<Self>(offset, DecodeErrorReason::OutOfRange(encoded_value as i128))
+        })?;
+        Ok(Self { value, byte_count })
+    }
+}
+
+impl Serializable for SignedVarint {
+    fn to_be_bytes(&self) -> Vec<u8> {
+        serialize(Self::zig_zag_encode(self.value))
     }
 }
 
@@ -42,9 +66,9 @@ mod test {
         // 00000010010110           // Concatenate.
         // 128 + 16 + 4 + 2 = 150   // Interpret as an unsigned 64-bit integer.
         let varint_encoded_bytes: [u8; 2] = [0x96, 0x01];
-        let expected_parsed_value = SignedVarint::zig_zag_decode(150u64);
+        let expected_parsed_value = SignedVarint::zig_zag_decode(150u64).unwrap();
 
-        let varint = SignedVarint::parse(&varint_encoded_bytes, 0);
+        let varint = SignedVarint::parse(&varint_encoded_bytes, 0).unwrap();
 
         assert_eq!(varint.value, expected_parsed_value);
         assert_eq!(varint.byte_count, varint_encoded_bytes.len());
@@ -61,13 +85,32 @@ mod test {
     fn zig_zag_decodes(input: u64, expected: i32) {
         let res = SignedVarint::zig_zag_decode(input);
 
-        assert_eq!(res, expected);
+        assert_eq!(res, Some(expected));
+    }
+
+    #[parameterized(value = { 0, -1, 1, 75, -8193, i32::MAX, i32::MIN })]
+    fn serializes_what_it_parses(value: i32) {
+        let varint = SignedVarint::new(value);
+        let bytes = varint.to_be_bytes();
+
+        let parsed = SignedVarint::parse(&bytes, 0).unwrap();
+
+        assert_eq!(value, parsed.value);
+        assert_eq!(bytes.len(), varint.size());
     }
 
     #[test]
-    #[should_panic = "Invalid return type: expected i32"]
-    fn panics_on_zig_zag_decoding_overflow() {
+    fn fails_on_zig_zag_decoding_overflow() {
         let input: u64 = 0xffffffffff;
-        let _ = SignedVarint::zig_zag_decode(input);
+        assert_eq!(SignedVarint::zig_zag_decode(input), None);
+    }
+
+    #[test]
+    fn fails_to_parse_varint_out_of_range_for_i32() {
+        let varint_encoded_bytes: [u8; 5] = [0xff, 0xff, 0xff, 0xff, 0x1f];
+
+        let error = SignedVarint::parse(&varint_encoded_bytes, 0).unwrap_err();
+
+        assert_eq!(DecodeErrorReason::OutOfRange(0x1_ffff_ffff), error.reason);
     }
 }
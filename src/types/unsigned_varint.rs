@@ -1,5 +1,4 @@
-use std::any::type_name;
-
+use crate::decode_error::{DecodeError, DecodeErrorReason};
 use crate::serializable::Serializable;
 use crate::types::variable_integer::{parse, serialize, size_of};
 use crate::{byte_parsable::ByteParsable, size::Size, types::zig_zag_decoder::ZigZagDecoder};
@@ -12,7 +11,7 @@ pub struct UnsignedVarint {
 
 impl UnsignedVarint {
     pub fn new(value: u32) -> Self {
-        let byte_count = size_of(value) as usize;
+        let byte_count = size_of(value.into()) as usize;
         Self { value, byte_count }
     }
 }
@@ -24,22 +23,18 @@ impl Size for UnsignedVarint {
 }
 
 impl ByteParsable<UnsignedVarint> for UnsignedVarint {
-    fn parse(bytes: &[u8], offset: usize) -> Self {
-        let (value, byte_count) = parse(bytes, offset);
-        let value = value.try_into().unwrap_or_else(|_| {
-            panic!(
-                "Invalid return type: expected {} but was out of range {}",
-                type_name::<u32>(),
-                value
-            )
-        });
-        Self { value, byte_count }
+    fn parse(bytes: &[u8], offset: usize) -> Result<Self, DecodeError> {
+        let (value, byte_count) = parse::<Self>(bytes, offset)?;
+        let value = value.try_into().map_err(|_| {
+            DecodeError::new::<Self>(offset, DecodeErrorReason::OutOfRange(value as i128))
+        })?;
+        Ok(Self { value, byte_count })
     }
 }
 
 impl Serializable for UnsignedVarint {
     fn to_be_bytes(&self) -> Vec<u8> {
-        serialize(self.value)
+        serialize(self.value.into())
     }
 }
 
@@ -52,6 +47,7 @@ mod test {
     #[rstest]
     #[case(&[0x00], 0)]
     #[case(&[0x96, 0x01], 150)]
+    #[case(&[0xAC, 0x02], 300)]
     fn parses_varint_encoded_bytes(#[case] bytes: &[u8], #[case] expected: u32) {
         // 150, encoded as `9601`
         // 10010110 00000001        // Original inputs.
@@ -60,7 +56,7 @@ mod test {
         // 00000010010110           // Concatenate.
         // 128 + 16 + 4 + 2 = 150   // Interpret as an unsigned 64-bit integer.
 
-        let varint = UnsignedVarint::parse(bytes, 0);
+        let varint = UnsignedVarint::parse(bytes, 0).unwrap();
 
         assert_eq!(varint.value, expected);
         assert_eq!(varint.byte_count, bytes.len());
@@ -2,9 +2,14 @@ use itertools::join;
 use tracing::{debug, field, trace};
 use tracing_subscriber::field::debug;
 
+use crate::decode_error::{take_bytes, DecodeError, DecodeErrorReason};
 use crate::{lazy_debug, lazy_trace};
 
-pub fn parse(varint_encoded_bytes: &[u8], offset: usize) -> (u64, usize) {
+// A u64 needs at most ten groups of seven bits
+const MAX_VARINT_LENGTH: usize = 10;
+
+/// Decodes a varint, naming `T` (the type being parsed) in any error
+pub fn parse<T>(varint_encoded_bytes: &[u8], offset: usize) -> Result<(u64, usize), DecodeError> {
     // https://protobuf.dev/programming-guides/encoding/#varints
     let mut value = 0u64;
     let mut i = offset;
@@ -13,17 +18,23 @@ pub fn parse(varint_encoded_bytes: &[u8], offset: usize) -> (u64, usize) {
     let mut byte_count: usize = 0;
     // varint_encoded_bytes should come in little-endian order
     while continuation_bit_set {
-        trace!("bytes[{i}]: {:08b}", varint_encoded_bytes[i]);
-        let continuation_bit = (varint_encoded_bytes[i] >> 7) & 0x01;
+        if byte_count == MAX_VARINT_LENGTH {
+            return Err(DecodeError::new::<T>(
+                offset,
+                DecodeErrorReason::VarintTooLong(MAX_VARINT_LENGTH),
+            ));
+        }
+        let byte = take_bytes::<T>(varint_encoded_bytes, i, 1)?[0];
+        trace!("bytes[{i}]: {:08b}", byte);
+        let continuation_bit = (byte >> 7) & 0x01;
         continuation_bit_set = continuation_bit == 1;
         trace!(
             "continuation bit: {continuation_bit}, continuation_bit_set: {continuation_bit_set}"
         );
-        let byte_with_8th_bit_cleared = varint_encoded_bytes[i] & 0x7F;
-        assert_eq!(get_bit_value(byte_with_8th_bit_cleared, 7), 0);
+        let byte_with_8th_bit_cleared = byte & 0x7F;
         trace!("Drop continuation bit: {:07b}", byte_with_8th_bit_cleared);
         // Concatenate bytes in opposite order (big-endian)
-        value |= (byte_with_8th_bit_cleared << shift) as u64;
+        value |= (byte_with_8th_bit_cleared as u64) << shift;
         trace!("concatenated value: {:b}\n", value);
         byte_count += 1;
         if continuation_bit_set {
@@ -37,10 +48,10 @@ pub fn parse(varint_encoded_bytes: &[u8], offset: usize) -> (u64, usize) {
         value,
         width = byte_count * 7
     );
-    (value, byte_count)
+    Ok((value, byte_count))
 }
 
-pub fn serialize(number: u32) -> Vec<u8> {
+pub fn serialize(number: u64) -> Vec<u8> {
     // https://cwiki.apache.org/confluence/pages/viewpage.action?pageId=120722234#KIP482:TheKafkaProtocolshouldSupportOptionalTaggedFields-UnsignedVarints
     // 1. Break up number into groups of seven bits
     // 2. Set high bit (bit 8) of the group if it's NOT the last one and clear bit if it is the last group
@@ -56,22 +67,15 @@ pub fn serialize(number: u32) -> Vec<u8> {
     let mut continuation_bit_needed = true;
     while continuation_bit_needed {
         // let mut current_byte = remaining_bits & 0x00_00_00_00_00_00_00_7F;
-        let mut byte = (remaining_bits & 0x00_00_00_7F) as u8; // takes lowest seven bits
+        let mut byte = (remaining_bits & 0x7F) as u8; // takes lowest seven bits
         assert!(byte < 255);
         trace!(
             "Lowest 7 bits from remaining (before continuation bit added): {:07b}",
             byte
         );
 
-        let shift = GROUP + byte_index;
-        let byte_shifted = remaining_bits >> shift;
-        trace!(
-            "Remaining bits shifted by {} ({} + {}): {:08b}",
-            shift,
-            GROUP,
-            byte_index,
-            byte_shifted
-        );
+        let byte_shifted = remaining_bits >> GROUP;
+        trace!("Remaining bits shifted by {}: {:08b}", GROUP, byte_shifted);
         continuation_bit_needed = byte_shifted != 0;
         if continuation_bit_needed {
             byte |= 0x80; // sets eighth bit to 1 while the rest are unchanged
@@ -93,15 +97,14 @@ pub fn serialize(number: u32) -> Vec<u8> {
 }
 
 /// Counts the bytes needed to encode varint based on serialize function above.
-pub fn size_of(number: u32) -> u8 {
+pub fn size_of(number: u64) -> u8 {
     let mut byte_index = 0u8;
 
     const GROUP: u8 = 7u8;
     let mut remaining_bits = number;
     let mut continuation_bit_needed = true;
     while continuation_bit_needed {
-        let shift = GROUP + byte_index;
-        let byte_shifted = remaining_bits >> shift;
+        let byte_shifted = remaining_bits >> GROUP;
         continuation_bit_needed = byte_shifted != 0;
         if continuation_bit_needed {
             byte_index += 1;
@@ -114,10 +117,6 @@ pub fn size_of(number: u32) -> u8 {
     byte_count
 }
 
-fn get_bit_value(byte: u8, index: u8) -> u8 {
-    (byte >> index) & 0x01
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
@@ -135,19 +134,39 @@ mod test {
         let varint_encoded_bytes: [u8; 2] = [0x96, 0x01];
         let expected_parsed_value = 150u64;
 
-        let (value, byte_count) = parse(&varint_encoded_bytes, 0);
+        let (value, byte_count) = parse::<u64>(&varint_encoded_bytes, 0).unwrap();
 
         assert_eq!(value, expected_parsed_value);
         assert_eq!(byte_count, varint_encoded_bytes.len());
     }
 
+    #[test]
+    fn fails_on_varint_missing_final_byte() {
+        let varint_encoded_bytes: [u8; 2] = [0x96, 0x81];
+
+        let error = parse::<u64>(&varint_encoded_bytes, 0).unwrap_err();
+
+        assert_eq!(2, error.offset);
+    }
+
+    #[test]
+    fn fails_on_varint_longer_than_u64() {
+        let varint_encoded_bytes = [0xff; 11];
+
+        let error = parse::<u64>(&varint_encoded_bytes, 0).unwrap_err();
+
+        assert_eq!(DecodeErrorReason::VarintTooLong(10), error.reason);
+    }
+
     #[test_log::test]
     #[rstest]
     #[case(0, &[0x00])]
     #[case(300, &[0xAC, 0x02])]
     #[case(150, &[0x96, 0x01])]
+    #[case(16384, &[0x80, 0x80, 0x01])]
+    #[case(u64::MAX, &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01])]
     #[serial]
-    fn serializes_to_varint_encoded_bytes(#[case] number: u32, #[case] expected: &[u8]) {
+    fn serializes_to_varint_encoded_bytes(#[case] number: u64, #[case] expected: &[u8]) {
         let serialized = serialize(number);
         assert_eq!(serialized, expected);
     }
@@ -157,8 +176,10 @@ mod test {
     #[case(0, 1)]
     #[case(150, 2)]
     #[case(300, 2)]
+    #[case(16384, 3)]
+    #[case(u64::MAX, 10)]
     #[serial]
-    fn counts_bytes_needed_to_encode_varint(#[case] number: u32, #[case] expected: u8) {
+    fn counts_bytes_needed_to_encode_varint(#[case] number: u64, #[case] expected: u8) {
         let byte_count = size_of(number);
         assert_eq!(byte_count, expected);
     }
@@ -1,16 +1,31 @@
 use crate::{
     byte_parsable::ByteParsable,
+    decode_error::{DecodeError, DecodeErrorReason},
+    serializable::Serializable,
     size::Size,
-    types::{variable_integer::parse, zig_zag_decoder::ZigZagDecoder},
+    types::{
+        variable_integer::{parse, serialize, size_of},
+        zig_zag_decoder::ZigZagDecoder,
+    },
 };
 
 #[derive(Clone, Debug)]
 pub struct Varlong {
-    #[allow(dead_code)]
     pub value: i64,
     pub byte_count: usize,
 }
 
+impl Varlong {
+    pub fn new(value: i64) -> Self {
+        let byte_count = size_of(Self::zig_zag_encode(value)) as usize;
+        Self { value, byte_count }
+    }
+
+    fn zig_zag_encode(value: i64) -> u64 {
+        ((value << 1) ^ (value >> 63)) as u64
+    }
+}
+
 impl ZigZagDecoder for Varlong {
     type Int = i64;
 }
@@ -22,10 +37,18 @@ impl Size for Varlong {
 }
 
 impl ByteParsable<Varlong> for Varlong {
-    fn parse(bytes: &[u8], offset: usize) -> Self {
-        let (value, byte_count) = parse(bytes, offset);
-        let value = Varlong::zig_zag_decode(value);
-        Self { value, byte_count }
+    fn parse(bytes: &[u8], offset: usize) -> Result<Self, DecodeError> {
+        let (encoded_value, byte_count) = parse::<Self>(bytes, offset)?;
+        let value = Varlong::zig_zag_decode(encoded_value).ok_or_else(|| {
+            DecodeError::new::<Self>(offset, DecodeErrorReason::OutOfRange(encoded_value as i128))
+        })?;
+        Ok(Self { value, byte_count })
+    }
+}
+
+impl Serializable for Varlong {
+    fn to_be_bytes(&self) -> Vec<u8> {
+        serialize(Self::zig_zag_encode(self.value))
     }
 }
 
@@ -43,9 +66,9 @@ mod test {
         // 00000010010110           // Concatenate.
         // 128 + 16 + 4 + 2 = 150   // Interpret as an unsigned 64-bit integer.
         let varint_encoded_bytes: [u8; 2] = [0x96, 0x01];
-        let expected_parsed_value = Varlong::zig_zag_decode(150u64);
+        let expected_parsed_value = Varlong::zig_zag_decode(150u64).unwrap();
 
-        let varint = Varlong::parse(&varint_encoded_bytes, 0);
+        let varint = Varlong::parse(&varint_encoded_bytes, 0).unwrap();
 
         assert_eq!(varint.value, expected_parsed_value);
         assert_eq!(varint.byte_count, varint_encoded_bytes.len());
@@ -62,6 +85,17 @@ mod test {
     fn zig_zag_decodes(input: u64, expected: i64) {
         let res = Varlong::zig_zag_decode(input);
 
-        assert_eq!(res, expected);
+        assert_eq!(res, Some(expected));
+    }
+
+    #[parameterized(value = { 0, -1, 1, 1_700_000_000_000, i64::MAX, i64::MIN })]
+    fn serializes_what_it_parses(value: i64) {
+        let varlong = Varlong::new(value);
+        let bytes = varlong.to_be_bytes();
+
+        let parsed = Varlong::parse(&bytes, 0).unwrap();
+
+        assert_eq!(value, parsed.value);
+        assert_eq!(bytes.len(), varlong.size());
     }
 }
@@ -1,12 +1,87 @@
 use serde::Deserialize;
+use std::fmt;
 use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-#[derive(Deserialize)]
+use thiserror::Error;
+
+use crate::storage::log_config::{CleanupPolicy, CompressionType, TimestampType};
+use crate::utils::properties::{self, PropertiesError};
+
+/// Config file read when no path is given on the command line
+pub const DEFAULT_CONFIG_PATH: &str = "src/config.toml";
+/// Prefix of the environment variables overriding broker properties, such as `KAFKA_NODE_ID`
+/// for `node.id`
+const ENV_PREFIX: &str = "KAFKA_";
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("unable to read config file {path}: {source}")]
+    Read { path: PathBuf, source: io::Error },
+    #[error("invalid config file {path}: {source}")]
+    Toml {
+        path: PathBuf,
+        source: Box<toml::de::Error>,
+    },
+    #[error("invalid config file {path}: {source}")]
+    Properties {
+        path: PathBuf,
+        source: PropertiesError,
+    },
+    #[error("{path}:{line}: {source}")]
+    InvalidProperty {
+        path: PathBuf,
+        line: usize,
+        source: Box<ConfigError>,
+    },
+    #[error("invalid value {value:?} for {key}: {reason}")]
+    InvalidValue {
+        key: String,
+        value: String,
+        reason: String,
+    },
+}
+
+#[derive(Debug, Default, Deserialize)]
 pub struct Config {
+    #[serde(default)]
     pub log: LogConfig,
+    #[serde(default)]
+    pub server: ServerConfig,
+    /// Keys of the properties file the broker doesn't support, to warn about once logging is
+    /// set up
+    #[serde(skip)]
+    pub unknown_properties: Vec<String>,
+}
+
+impl Config {
+    /// Reads the config from the properties of a Kafka `server.properties` file
+    fn from_properties(path: &Path, contents: &str) -> Result<Self, ConfigError> {
+        let properties = properties::parse(contents).map_err(|source| ConfigError::Properties {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let mut config = Config::default();
+        for property in properties {
+            let supported = config
+                .server
+                .set(&property.key, &property.value)
+                .map_err(|e| ConfigError::InvalidProperty {
+                    path: path.to_path_buf(),
+                    line: property.line,
+                    source: Box::new(e),
+                })?;
+            if !supported {
+                config.unknown_properties.push(property.key);
+            }
+        }
+        Ok(config)
+    }
 }
 
-#[derive(Deserialize)]
+#[derive(Debug, Deserialize)]
 #[serde(default)]
 pub struct LogConfig {
     pub level: String,
@@ -20,10 +95,586 @@ impl Default for LogConfig {
     }
 }
 
-pub fn load_config() -> Config {
-    let filename = "src/config.toml";
-    let contents =
-        fs::read_to_string(filename).unwrap_or_else(|_| panic!("Problem reading {filename} file"));
-    let config: Config = toml::from_str(&contents).expect("Failed to parse TOML");
-    config
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub node_id: i32,
+    /// Addresses the broker listens on, as Kafka's listeners (`PLAINTEXT://127.0.0.1:9092`)
+    pub listeners: Vec<Listener>,
+    /// Addresses clients are told to connect to in Metadata responses, as Kafka's
+    /// advertised.listeners. The listeners are advertised when empty.
+    pub advertised_listeners: Vec<Listener>,
+    /// Directories holding the partition logs, as Kafka's log.dirs
+    pub log_dirs: Vec<PathBuf>,
+    /// Largest request (excluding its 4-byte size prefix) accepted before the connection is
+    /// closed, as Kafka's socket.request.max.bytes
+    pub socket_request_max_bytes: usize,
+    /// Threads multiplexing the client connections, as Kafka's num.network.threads
+    pub num_network_threads: usize,
+    /// Threads handling the requests, as Kafka's num.io.threads
+    pub num_io_threads: usize,
+    /// Requests waiting for a handler thread before the network threads stop reading more, as
+    /// Kafka's queued.max.requests
+    pub queued_max_requests: usize,
+    /// Partitions of automatically created topics, as Kafka's num.partitions
+    pub num_partitions: i32,
+    /// Whether producing to or fetching from an unknown topic creates it, as Kafka's
+    /// auto.create.topics.enable
+    pub auto_create_topics_enable: bool,
+    /// Size a log segment grows to before a new one is rolled, as Kafka's log.segment.bytes
+    pub log_segment_bytes: u64,
+    /// Kafka's log.roll.ms, which takes precedence over log.roll.hours
+    pub log_roll_ms: Option<i64>,
+    pub log_roll_hours: i64,
+    /// Kafka's log.retention.ms, which takes precedence over log.retention.minutes and
+    /// log.retention.hours. -1 keeps the logs forever.
+    pub log_retention_ms: Option<i64>,
+    pub log_retention_minutes: Option<i64>,
+    pub log_retention_hours: i64,
+    /// Size a partition's log is trimmed to, as Kafka's log.retention.bytes. -1 means no limit.
+    pub log_retention_bytes: i64,
+    /// Interval between checks for segments past the retention limits, as Kafka's
+    /// log.retention.check.interval.ms
+    pub log_retention_check_interval_ms: u64,
+    /// Default cleanup.policy of the topics, as Kafka's log.cleanup.policy
+    pub log_cleanup_policy: CleanupPolicy,
+    /// Whether the cleaner compacts the logs of topics with the compact policy, as Kafka's
+    /// log.cleaner.enable
+    pub log_cleaner_enable: bool,
+    /// Default delete.retention.ms of the topics, as Kafka's log.cleaner.delete.retention.ms
+    pub log_cleaner_delete_retention_ms: i64,
+    /// Interval between passes of the cleaner over the logs, as Kafka's log.cleaner.backoff.ms
+    pub log_cleaner_backoff_ms: u64,
+    /// Default message.timestamp.type of the topics, as Kafka's log.message.timestamp.type
+    pub log_message_timestamp_type: TimestampType,
+    /// Interval between writes of the logs' recovery points to the
+    /// recovery-point-offset-checkpoint files, as Kafka's
+    /// log.flush.offset.checkpoint.interval.ms
+    pub log_flush_offset_checkpoint_interval_ms: u64,
+    /// Default compression.type of the topics, as Kafka's compression.type
+    pub compression_type: CompressionType,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            node_id: 1,
+            listeners: vec![Listener {
+                name: "PLAINTEXT".to_string(),
+                host: "127.0.0.1".to_string(),
+                port: 9092,
+            }],
+            advertised_listeners: Vec::new(),
+            log_dirs: vec![PathBuf::from("/tmp/kraft-combined-logs")],
+            socket_request_max_bytes: 100 * 1024 * 1024,
+            num_network_threads: 3,
+            num_io_threads: 8,
+            queued_max_requests: 500,
+            num_partitions: 1,
+            auto_create_topics_enable: true,
+            log_segment_bytes: 1024 * 1024 * 1024,
+            log_roll_ms: None,
+            log_roll_hours: 24 * 7,
+            log_retention_ms: None,
+            log_retention_minutes: None,
+            log_retention_hours: 24 * 7,
+            log_retention_bytes: -1,
+            log_retention_check_interval_ms: 5 * 60 * 1000,
+            log_cleanup_policy: CleanupPolicy::default(),
+            log_cleaner_enable: true,
+            log_cleaner_delete_retention_ms: 24 * 60 * 60 * 1000,
+            log_cleaner_backoff_ms: 15 * 1000,
+            log_message_timestamp_type: TimestampType::default(),
+            log_flush_offset_checkpoint_interval_ms: 60 * 1000,
+            compression_type: CompressionType::default(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Kafka names of the properties that can be set with `set`
+    pub const PROPERTIES: [&'static str; 26] = [
+        "node.id",
+        "listeners",
+        "advertised.listeners",
+        "log.dirs",
+        "log.dir",
+        "socket.request.max.bytes",
+        "num.network.threads",
+        "num.io.threads",
+        "queued.max.requests",
+        "num.partitions",
+        "auto.create.topics.enable",
+        "log.segment.bytes",
+        "log.roll.ms",
+        "log.roll.hours",
+        "log.retention.ms",
+        "log.retention.minutes",
+        "log.retention.hours",
+        "log.retention.bytes",
+        "log.retention.check.interval.ms",
+        "log.cleanup.policy",
+        "log.cleaner.enable",
+        "log.cleaner.delete.retention.ms",
+        "log.cleaner.backoff.ms",
+        "log.message.timestamp.type",
+        "log.flush.offset.checkpoint.interval.ms",
+        "compression.type",
+    ];
+
+    /// Sets a property by its Kafka name. Returns `false` if the property isn't supported.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<bool, ConfigError> {
+        let value = value.trim();
+        match key {
+            "node.id" => self.node_id = parse_value(key, value)?,
+            "listeners" => self.listeners = parse_listeners(key, value)?,
+            "advertised.listeners" => self.advertised_listeners = parse_listeners(key, value)?,
+            "log.dirs" | "log.dir" => self.log_dirs = parse_log_dirs(key, value)?,
+            "socket.request.max.bytes" => self.socket_request_max_bytes = parse_value(key, value)?,
+            "num.network.threads" => self.num_network_threads = parse_value(key, value)?,
+            "num.io.threads" => self.num_io_threads = parse_value(key, value)?,
+            "queued.max.requests" => self.queued_max_requests = parse_value(key, value)?,
+            "num.partitions" => self.num_partitions = parse_positive(key, value)?,
+            "auto.create.topics.enable" => self.auto_create_topics_enable = parse_bool(key, value)?,
+            "log.segment.bytes" => self.log_segment_bytes = parse_positive(key, value)?,
+            "log.roll.ms" => self.log_roll_ms = Some(parse_positive(key, value)?),
+            "log.roll.hours" => self.log_roll_hours = parse_positive(key, value)?,
+            "log.retention.ms" => self.log_retention_ms = Some(parse_retention(key, value)?),
+            "log.retention.minutes" => {
+                self.log_retention_minutes = Some(parse_retention(key, value)?)
+            }
+            "log.retention.hours" => self.log_retention_hours = parse_retention(key, value)?,
+            "log.retention.bytes" => self.log_retention_bytes = parse_retention(key, value)?,
+            "log.retention.check.interval.ms" => {
+                self.log_retention_check_interval_ms = parse_positive(key, value)?
+            }
+            "log.cleanup.policy" => self.log_cleanup_policy = parse_value(key, value)?,
+            "log.cleaner.enable" => self.log_cleaner_enable = parse_bool(key, value)?,
+            "log.cleaner.delete.retention.ms" => {
+                self.log_cleaner_delete_retention_ms = parse_non_negative(key, value)?
+            }
+            "log.cleaner.backoff.ms" => self.log_cleaner_backoff_ms = parse_positive(key, value)?,
+            "log.message.timestamp.type" => {
+                self.log_message_timestamp_type = parse_value(key, value)?
+            }
+            "log.flush.offset.checkpoint.interval.ms" => {
+                self.log_flush_offset_checkpoint_interval_ms = parse_positive(key, value)?
+            }
+            "compression.type" => self.compression_type = parse_value(key, value)?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Time after which a new log segment is rolled
+    pub fn segment_ms(&self) -> i64 {
+        self.log_roll_ms
+            .unwrap_or(self.log_roll_hours * 60 * 60 * 1000)
+    }
+
+    /// Time log segments are kept for, or -1 to keep them forever
+    pub fn retention_ms(&self) -> i64 {
+        let retention_ms = self.log_retention_ms.unwrap_or_else(|| {
+            self.log_retention_minutes
+                .unwrap_or(self.log_retention_hours.saturating_mul(60))
+                .saturating_mul(60 * 1000)
+        });
+        if retention_ms < 0 {
+            -1
+        } else {
+            retention_ms
+        }
+    }
+
+    /// The listener advertised to clients
+    pub fn advertised_listener(&self) -> Option<&Listener> {
+        self.advertised_listeners
+            .first()
+            .or_else(|| self.listeners.first())
+    }
+
+    /// Applies the `KAFKA_`-prefixed variables overriding properties: the property name in
+    /// upper case with dots replaced by underscores, as the Kafka container images do
+    fn apply_env_overrides(
+        &mut self,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<(), ConfigError> {
+        for (name, value) in vars {
+            let Some(property) = name.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            if let Some(key) = Self::PROPERTIES
+                .iter()
+                .find(|key| key.to_uppercase().replace('.', "_") == property)
+            {
+                self.set(key, &value)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.listeners.is_empty() {
+            return Err(invalid_value(
+                "listeners",
+                "",
+                "at least one listener is needed",
+            ));
+        }
+        if self.log_dirs.is_empty() {
+            return Err(invalid_value(
+                "log.dirs",
+                "",
+                "at least one directory is needed",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A listener, such as `PLAINTEXT://127.0.0.1:9092`. An empty host means all interfaces.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Listener {
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+}
+
+impl Listener {
+    /// Address to bind the listener to
+    pub fn bind_address(&self) -> (&str, u16) {
+        let host = if self.host.is_empty() {
+            "0.0.0.0"
+        } else {
+            self.host.as_str()
+        };
+        (host, self.port)
+    }
+}
+
+impl FromStr for Listener {
+    type Err = String;
+
+    fn from_str(listener: &str) -> Result<Self, Self::Err> {
+        let (name, address) = listener
+            .split_once("://")
+            .ok_or("expected NAME://host:port")?;
+        let (host, port) = address.rsplit_once(':').ok_or("missing port")?;
+        let port = port
+            .parse::<u16>()
+            .map_err(|_| format!("invalid port {port:?}"))?;
+        // IPv6 addresses are bracketed, as in PLAINTEXT://[::1]:9092
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        Ok(Self {
+            name: name.to_string(),
+            host: host.to_string(),
+            port,
+        })
+    }
+}
+
+impl TryFrom<String> for Listener {
+    type Error = String;
+
+    fn try_from(listener: String) -> Result<Self, Self::Error> {
+        listener.parse()
+    }
+}
+
+impl fmt::Display for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "{}://[{}]:{}", self.name, self.host, self.port)
+        } else {
+            write!(f, "{}://{}:{}", self.name, self.host, self.port)
+        }
+    }
+}
+
+/// Loads the config from the file given as the first command line argument, or else from
+/// `DEFAULT_CONFIG_PATH` if it exists, and then applies the environment variable overrides.
+/// Without a config file, the defaults are used. A file without the `.toml` extension is read as
+/// a Kafka `server.properties` file.
+pub fn load_config() -> Result<Config, ConfigError> {
+    let mut config = match std::env::args_os().nth(1) {
+        Some(path) => load_config_file(Path::new(&path))?,
+        None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+            load_config_file(Path::new(DEFAULT_CONFIG_PATH))?
+        }
+        None => Config::default(),
+    };
+    config.server.apply_env_overrides(std::env::vars())?;
+    config.server.validate()?;
+    Ok(config)
+}
+
+fn load_config_file(path: &Path) -> Result<Config, ConfigError> {
+    let contents = fs::read_to_string(path).map_err(|source| ConfigError::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    if path
+        .extension()
+        .is_some_and(|extension| extension == "toml")
+    {
+        toml::from_str(&contents).map_err(|source| ConfigError::Toml {
+            path: path.to_path_buf(),
+            source: Box::new(source),
+        })
+    } else {
+        Config::from_properties(path, &contents)
+    }
+}
+
+fn parse_value<T: FromStr>(key: &str, value: &str) -> Result<T, ConfigError>
+where
+    T::Err: fmt::Display,
+{
+    value
+        .parse()
+        .map_err(|e: T::Err| invalid_value(key, value, &e.to_string()))
+}
+
+/// Parses a boolean as Kafka does, accepting `true` and `false` in any case
+fn parse_bool(key: &str, value: &str) -> Result<bool, ConfigError> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(invalid_value(key, value, "expected true or false")),
+    }
+}
+
+fn parse_positive<T>(key: &str, value: &str) -> Result<T, ConfigError>
+where
+    T: FromStr + PartialOrd + Default,
+    T::Err: fmt::Display,
+{
+    let parsed: T = parse_value(key, value)?;
+    if parsed <= T::default() {
+        return Err(invalid_value(key, value, "must be positive"));
+    }
+    Ok(parsed)
+}
+
+fn parse_non_negative(key: &str, value: &str) -> Result<i64, ConfigError> {
+    let parsed: i64 = parse_value(key, value)?;
+    if parsed < 0 {
+        return Err(invalid_value(key, value, "must not be negative"));
+    }
+    Ok(parsed)
+}
+
+/// Parses a retention limit, which is either -1 for no limit or at least 0
+fn parse_retention(key: &str, value: &str) -> Result<i64, ConfigError> {
+    let parsed: i64 = parse_value(key, value)?;
+    if parsed < -1 {
+        return Err(invalid_value(
+            key,
+            value,
+            "must be -1 (no limit) or at least 0",
+        ));
+    }
+    Ok(parsed)
+}
+
+fn parse_listeners(key: &str, value: &str) -> Result<Vec<Listener>, ConfigError> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|listener| !listener.is_empty())
+        .map(|listener| {
+            listener
+                .parse()
+                .map_err(|reason: String| invalid_value(key, value, &reason))
+        })
+        .collect()
+}
+
+fn parse_log_dirs(key: &str, value: &str) -> Result<Vec<PathBuf>, ConfigError> {
+    let log_dirs: Vec<PathBuf> = value
+        .split(',')
+        .map(str::trim)
+        .filter(|log_dir| !log_dir.is_empty())
+        .map(PathBuf::from)
+        .collect();
+    if log_dirs.is_empty() {
+        return Err(invalid_value(
+            key,
+            value,
+            "at least one directory is needed",
+        ));
+    }
+    Ok(log_dirs)
+}
+
+fn invalid_value(key: &str, value: &str, reason: &str) -> ConfigError {
+    ConfigError::InvalidValue {
+        key: key.to_string(),
+        value: value.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_toml_config() {
+        let config: Config = toml::from_str(
+            r#"
+            [server]
+            node_id = 2
+            listeners = ["PLAINTEXT://:9093"]
+            advertised_listeners = ["PLAINTEXT://broker-2:9093"]
+            log_dirs = ["/var/lib/kafka/a", "/var/lib/kafka/b"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(2, config.server.node_id);
+        assert_eq!(("0.0.0.0", 9093), config.server.listeners[0].bind_address());
+        assert_eq!(
+            "PLAINTEXT://broker-2:9093",
+            config.server.advertised_listener().unwrap().to_string()
+        );
+        assert_eq!(2, config.server.log_dirs.len());
+        assert_eq!("info", config.log.level);
+        assert_eq!(8, config.server.num_io_threads);
+    }
+
+    #[test]
+    fn parses_server_properties() {
+        let config = Config::from_properties(
+            Path::new("server.properties"),
+            "# Stock KRaft config
+process.roles=broker,controller
+node.id=1
+controller.quorum.voters=1@localhost:9093
+listeners=PLAINTEXT://:9092,CONTROLLER://:9093
+log.dirs=/tmp/kraft-combined-logs
+num.partitions=3
+auto.create.topics.enable=False
+log.retention.hours=168
+log.retention.minutes=60
+log.segment.bytes=1073741824
+",
+        )
+        .unwrap();
+
+        assert_eq!(1, config.server.node_id);
+        assert_eq!(2, config.server.listeners.len());
+        assert_eq!(3, config.server.num_partitions);
+        assert!(!config.server.auto_create_topics_enable);
+        assert_eq!(60 * 60 * 1000, config.server.retention_ms());
+        assert_eq!(1024 * 1024 * 1024, config.server.log_segment_bytes);
+        assert_eq!(
+            vec!["process.roles", "controller.quorum.voters"],
+            config.unknown_properties
+        );
+    }
+
+    #[test]
+    fn reports_line_of_invalid_property() {
+        let error = Config::from_properties(
+            Path::new("server.properties"),
+            "node.id=1\nnum.partitions=0\n",
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            "server.properties:2: invalid value \"0\" for num.partitions: must be positive",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn takes_most_precise_retention_time() {
+        let mut server_config = ServerConfig::default();
+        assert_eq!(7 * 24 * 60 * 60 * 1000, server_config.retention_ms());
+
+        server_config.set("log.retention.ms", "1000").unwrap();
+        server_config.set("log.retention.hours", "1").unwrap();
+        assert_eq!(1000, server_config.retention_ms());
+
+        server_config.set("log.retention.ms", "-1").unwrap();
+        assert_eq!(-1, server_config.retention_ms());
+        assert!(server_config.set("log.retention.ms", "-2").is_err());
+    }
+
+    #[test]
+    fn advertises_listeners_by_default() {
+        let server_config = ServerConfig::default();
+
+        assert_eq!(
+            "PLAINTEXT://127.0.0.1:9092",
+            server_config.advertised_listener().unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn sets_properties_by_kafka_name() {
+        let mut server_config = ServerConfig::default();
+
+        assert!(server_config.set("node.id", "3").unwrap());
+        assert!(server_config
+            .set("listeners", "PLAINTEXT://[::1]:9092, CONTROLLER://:9093")
+            .unwrap());
+        assert!(server_config.set("log.dirs", "/a,/b").unwrap());
+        assert!(!server_config.set("unknown.property", "1").unwrap());
+
+        assert_eq!(3, server_config.node_id);
+        assert_eq!(
+            vec!["PLAINTEXT://[::1]:9092", "CONTROLLER://:9093"],
+            server_config
+                .listeners
+                .iter()
+                .map(Listener::to_string)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![PathBuf::from("/a"), PathBuf::from("/b")],
+            server_config.log_dirs
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        let mut server_config = ServerConfig::default();
+
+        let error = server_config.set("node.id", "one").unwrap_err();
+        assert_eq!(
+            "invalid value \"one\" for node.id: invalid digit found in string",
+            error.to_string()
+        );
+        assert!(server_config.set("listeners", "127.0.0.1:9092").is_err());
+        assert!(server_config
+            .set("listeners", "PLAINTEXT://host:http")
+            .is_err());
+        assert!(server_config.set("log.dirs", " , ").is_err());
+    }
+
+    #[test]
+    fn applies_env_overrides() {
+        let mut server_config = ServerConfig::default();
+
+        server_config
+            .apply_env_overrides([
+                ("KAFKA_NODE_ID".to_string(), "4".to_string()),
+                (
+                    "KAFKA_ADVERTISED_LISTENERS".to_string(),
+                    "PLAINTEXT://broker-4:9092".to_string(),
+                ),
+                ("KAFKA_UNKNOWN".to_string(), "x".to_string()),
+                ("NODE_ID".to_string(), "5".to_string()),
+            ])
+            .unwrap();
+
+        assert_eq!(4, server_config.node_id);
+        assert_eq!(
+            "broker-4",
+            server_config.advertised_listener().unwrap().host
+        );
+    }
 }
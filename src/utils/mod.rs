@@ -1,4 +1,5 @@
 pub mod config;
-pub mod file;
 pub mod logging;
+pub mod properties;
+pub mod signals;
 pub mod uuid;
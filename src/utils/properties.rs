@@ -0,0 +1,183 @@
+//! Parser for the Java properties format of Kafka's `server.properties`, following
+//! `java.util.Properties.load`.
+
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("line {line}: {reason}")]
+pub struct PropertiesError {
+    pub line: usize,
+    pub reason: String,
+}
+
+/// A key and value, with the line the property starts on
+#[derive(Debug, PartialEq, Eq)]
+pub struct Property {
+    pub line: usize,
+    pub key: String,
+    pub value: String,
+}
+
+/// Parses the properties in file order.
+///
+/// Lines whose first non-blank character is `#` or `!` are comments. The key ends at the first
+/// unescaped `=`, `:` or whitespace, and the value is the rest of the line after the separator.
+/// A line ending in an odd number of backslashes continues on the next line. Keys and values
+/// can contain the escapes `\t`, `\n`, `\r`, `\f` and `\uXXXX`; a backslash before any other
+/// character stands for that character.
+pub fn parse(contents: &str) -> Result<Vec<Property>, PropertiesError> {
+    let mut properties = Vec::new();
+    let mut lines = contents.lines().enumerate();
+    while let Some((index, line)) = lines.next() {
+        let line_number = index + 1;
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+            continue;
+        }
+        let mut logical_line = line.to_string();
+        while ends_with_continuation(&logical_line) {
+            logical_line.pop();
+            match lines.next() {
+                Some((_, next_line)) => logical_line.push_str(next_line.trim_start()),
+                None => break,
+            }
+        }
+        let (key, value) = split_key_value(&logical_line);
+        let error = |reason: String| PropertiesError {
+            line: line_number,
+            reason,
+        };
+        properties.push(Property {
+            line: line_number,
+            key: unescape(key).map_err(error)?,
+            value: unescape(value).map_err(error)?,
+        });
+    }
+    Ok(properties)
+}
+
+fn ends_with_continuation(line: &str) -> bool {
+    line.chars().rev().take_while(|&c| c == '\\').count() % 2 == 1
+}
+
+/// Splits a logical line into its raw (still escaped) key and value
+fn split_key_value(line: &str) -> (&str, &str) {
+    let mut escaped = false;
+    let key_end = line
+        .char_indices()
+        .find(|&(_, c)| {
+            let is_separator = !escaped && (c == '=' || c == ':' || c.is_whitespace());
+            escaped = !escaped && c == '\\';
+            is_separator
+        })
+        .map_or(line.len(), |(index, _)| index);
+    let key = &line[..key_end];
+    let rest = line[key_end..].trim_start();
+    let value = rest.strip_prefix(['=', ':']).map_or(rest, str::trim_start);
+    (key, value)
+}
+
+fn unescape(escaped: &str) -> Result<String, String> {
+    let mut unescaped = String::with_capacity(escaped.len());
+    let mut chars = escaped.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('f') => unescaped.push('\u{c}'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                let c = u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .filter(|_| hex.len() == 4)
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| format!("malformed \\uxxxx escape \\u{hex}"))?;
+                unescaped.push(c);
+            }
+            Some(c) => unescaped.push(c),
+            None => {}
+        }
+    }
+    Ok(unescaped)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key_values(contents: &str) -> Vec<(String, String)> {
+        parse(contents)
+            .unwrap()
+            .into_iter()
+            .map(|property| (property.key, property.value))
+            .collect()
+    }
+
+    fn pair(key: &str, value: &str) -> (String, String) {
+        (key.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let properties = parse("# comment\n\n   ! also a comment\nnode.id=1\n").unwrap();
+
+        assert_eq!(
+            vec![Property {
+                line: 4,
+                key: "node.id".to_string(),
+                value: "1".to_string()
+            }],
+            properties
+        );
+    }
+
+    #[test]
+    fn splits_on_any_separator() {
+        assert_eq!(
+            vec![
+                pair("a", "1"),
+                pair("b", "2"),
+                pair("c", "3"),
+                pair("d", "4 = four"),
+                pair("e", ""),
+                pair("f", "=6"),
+            ],
+            key_values("a=1\nb : 2\n  c 3\nd = 4 = four\ne\nf==6")
+        );
+    }
+
+    #[test]
+    fn joins_continued_lines() {
+        assert_eq!(
+            vec![
+                pair("listeners", "PLAINTEXT://:9092,CONTROLLER://:9093"),
+                pair("path", "C:\\\\"),
+            ],
+            key_values("listeners=PLAINTEXT://:9092,\\\n    CONTROLLER://:9093\npath=C:\\\\\\\\")
+        );
+    }
+
+    #[test]
+    fn unescapes_keys_and_values() {
+        assert_eq!(
+            vec![pair("a key", "tab\there \u{e9}:=")],
+            key_values("a\\ key=tab\\there \\u00e9\\:\\=")
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_unicode_escape() {
+        assert_eq!(
+            Err(PropertiesError {
+                line: 2,
+                reason: "malformed \\uxxxx escape \\u12".to_string()
+            }),
+            parse("a=1\nb=\\u12")
+        );
+    }
+}
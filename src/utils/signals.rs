@@ -0,0 +1,37 @@
+use std::io;
+use std::mem::MaybeUninit;
+use std::ptr;
+
+/// The signals asking the broker to shut down: SIGTERM, as sent by kafka-server-stop, and SIGINT
+fn shutdown_signals() -> libc::sigset_t {
+    let mut signals = MaybeUninit::<libc::sigset_t>::uninit();
+    // SAFETY: sigemptyset initialises the set, which sigaddset then adds valid signals to
+    unsafe {
+        libc::sigemptyset(signals.as_mut_ptr());
+        libc::sigaddset(signals.as_mut_ptr(), libc::SIGTERM);
+        libc::sigaddset(signals.as_mut_ptr(), libc::SIGINT);
+        signals.assume_init()
+    }
+}
+
+/// Blocks the shutdown signals in the calling thread and the threads it spawns afterwards, so
+/// they are left pending for [`wait_for_shutdown_signal`] rather than killing the process
+pub fn block_shutdown_signals() -> io::Result<()> {
+    let signals = shutdown_signals();
+    // SAFETY: the set is initialised and the old mask is not asked for
+    match unsafe { libc::pthread_sigmask(libc::SIG_BLOCK, &signals, ptr::null_mut()) } {
+        0 => Ok(()),
+        errno => Err(io::Error::from_raw_os_error(errno)),
+    }
+}
+
+/// Waits for one of the shutdown signals, returning it
+pub fn wait_for_shutdown_signal() -> io::Result<i32> {
+    let signals = shutdown_signals();
+    let mut signal = 0;
+    // SAFETY: the set is initialised and the signal is written to a valid i32
+    match unsafe { libc::sigwait(&signals, &mut signal) } {
+        0 => Ok(signal),
+        errno => Err(io::Error::from_raw_os_error(errno)),
+    }
+}
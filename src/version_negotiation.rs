@@ -0,0 +1,55 @@
+use thiserror::Error;
+
+use crate::api_handler::ApiHandler;
+
+#[derive(Debug, Error)]
+pub enum RequestError {
+    #[error("unknown API key {0}")]
+    UnknownApiKey(i16),
+    #[error(transparent)]
+    Decode(#[from] crate::decode_error::DecodeError),
+}
+
+/// Outcome of checking a request's version against the versions the broker supports for its API
+#[derive(Debug, PartialEq, Eq)]
+pub enum Negotiation {
+    Supported,
+    UnsupportedVersion,
+}
+
+pub fn negotiate(handler: &dyn ApiHandler, api_version: i16) -> Negotiation {
+    if handler.versions().contains(&api_version) {
+        Negotiation::Supported
+    } else {
+        Negotiation::UnsupportedVersion
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api_versions::api_versions_api::ApiVersionsApi;
+    use crate::fetch::{self, fetch_api::FetchApi};
+    use parameterized::parameterized;
+
+    #[parameterized(
+        version = {
+            0, 1, 2, 3, 4
+        }
+    )]
+    fn checks_supported_version(version: i16) {
+        assert_eq!(Negotiation::Supported, negotiate(&ApiVersionsApi, version));
+    }
+
+    #[test]
+    fn checks_unsupported_version() {
+        assert_eq!(
+            Negotiation::UnsupportedVersion,
+            negotiate(&ApiVersionsApi, 6)
+        );
+        assert_eq!(
+            Negotiation::UnsupportedVersion,
+            negotiate(&FetchApi, fetch::MAX_VERSION + 1)
+        );
+    }
+}